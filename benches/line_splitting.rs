@@ -0,0 +1,49 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use process_events_streaming::LineReader;
+use std::io::{BufRead, BufReader};
+
+/// 64 MiB of output lines of various lengths
+fn output() -> Vec<u8> {
+    let mut output = vec![];
+    let mut index = 0;
+    while output.len() < 64 * 1024 * 1024 {
+        output.extend_from_slice(format!("{} {}\n", index, "data ".repeat(index % 40)).as_bytes());
+        index += 1;
+    }
+    output
+}
+
+fn line_splitting(criterion: &mut Criterion) {
+    let output = output();
+    let mut group = criterion.benchmark_group("line_splitting");
+    group.throughput(Throughput::Bytes(output.len() as u64));
+    group.sample_size(10);
+    group.bench_function("buf_reader_read_line", |bencher| {
+        bencher.iter_batched_ref(
+            || BufReader::new(output.as_slice()),
+            |reader| {
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 0 {
+                    line.clear();
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("line_reader", |bencher| {
+        bencher.iter_batched_ref(
+            || LineReader::new(output.as_slice()),
+            |reader| {
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 0 {
+                    line.clear();
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, line_splitting);
+criterion_main!(benches);
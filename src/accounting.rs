@@ -0,0 +1,281 @@
+use duct::Expression;
+use std::io;
+use std::time::Duration;
+
+/// Resource usage of the whole process tree (the process & all its descendants) over the execution, see
+/// [`crate::ProcessRequest::job_accounting`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JobAccounting {
+    /// Total CPU time (user & system) of all the processes
+    pub cpu_time: Duration,
+    /// Bytes read by all the processes, None if not accounted (e.g. the `io` cgroup controller is not enabled)
+    pub io_read_bytes: Option<u64>,
+    /// Bytes written by all the processes, None if not accounted
+    pub io_write_bytes: Option<u64>,
+    /// Peak memory of all the processes together, None if not accounted (e.g. the `memory` cgroup controller is not
+    /// enabled)
+    pub peak_memory_bytes: Option<u64>,
+}
+
+/// Group of the processes of an execution accounted together: a cgroup (v2) on Linux, a Job Object on Windows
+#[cfg(target_os = "linux")]
+pub(crate) struct JobAccount {
+    dir: std::path::PathBuf,
+    procs: std::sync::Arc<std::fs::File>,
+}
+
+#[cfg(target_os = "linux")]
+impl JobAccount {
+    /// create a cgroup under the cgroup of this process, None if it can't be created (e.g. no writable cgroup v2
+    /// hierarchy)
+    pub(crate) fn create(enabled: bool) -> Option<JobAccount> {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        if !enabled {
+            return None;
+        }
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let dir = own_cgroup_dir()?.join(format!("pes-{}-{}", std::process::id(), id));
+        std::fs::create_dir(&dir).ok()?;
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .open(dir.join("cgroup.procs"))
+        {
+            Ok(procs) => Some(JobAccount {
+                dir,
+                procs: std::sync::Arc::new(procs),
+            }),
+            Err(_) => {
+                _ = std::fs::remove_dir(&dir);
+                None
+            }
+        }
+    }
+
+    /// move all the commands of the expression into the cgroup before exec, ahead of the other spawn options as
+    /// switching the user may revoke the access to the cgroup
+    pub(crate) fn attach(&self, expression: Expression) -> Expression {
+        use std::os::fd::AsRawFd;
+        use std::os::unix::process::CommandExt;
+
+        let procs = std::sync::Arc::clone(&self.procs);
+        expression.before_spawn(move |command| {
+            let fd = procs.as_raw_fd();
+            unsafe {
+                // 0 is the writing process, runs in the child after fork so must not allocate
+                command.pre_exec(move || {
+                    if libc::write(fd, b"0".as_ptr() as *const libc::c_void, 1) != 1 {
+                        return Err(io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
+            Ok(())
+        })
+    }
+
+    /// the processes are already in the cgroup
+    pub(crate) fn assign(&self, _pids: &[u32]) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// read the usage accounted so far, None if the cgroup can't be read
+    pub(crate) fn read(&self) -> Option<JobAccounting> {
+        let read = |name: &str| std::fs::read_to_string(self.dir.join(name)).ok();
+        let usage_usec = read("cpu.stat")?
+            .lines()
+            .find_map(|line| line.strip_prefix("usage_usec "))
+            .and_then(|value| value.trim().parse().ok())?;
+        let io = read("io.stat").map(|stat| {
+            let mut bytes = (0, 0);
+            for field in stat.split_whitespace() {
+                let (name, value) = field.split_once('=').unwrap_or_default();
+                let value: u64 = value.parse().unwrap_or_default();
+                match name {
+                    "rbytes" => bytes.0 += value,
+                    "wbytes" => bytes.1 += value,
+                    _ => {}
+                }
+            }
+            bytes
+        });
+        Some(JobAccounting {
+            cpu_time: Duration::from_micros(usage_usec),
+            io_read_bytes: io.map(|(read_bytes, _)| read_bytes),
+            io_write_bytes: io.map(|(_, written_bytes)| written_bytes),
+            peak_memory_bytes: read("memory.peak").and_then(|peak| peak.trim().parse().ok()),
+        })
+    }
+}
+
+/// the cgroup is removed unless some processes are still running in it
+#[cfg(target_os = "linux")]
+impl Drop for JobAccount {
+    fn drop(&mut self) {
+        _ = std::fs::remove_dir(&self.dir);
+    }
+}
+
+/// directory of the cgroup (v2) of this process
+#[cfg(target_os = "linux")]
+fn own_cgroup_dir() -> Option<std::path::PathBuf> {
+    let cgroups = std::fs::read_to_string("/proc/self/cgroup").ok()?;
+    let path = cgroups.lines().find_map(|line| line.strip_prefix("0::"))?;
+    let mounts = std::fs::read_to_string("/proc/self/mountinfo").ok()?;
+    // the mount point is the 5th field, the filesystem type follows the " - " separator
+    let mount_point = mounts.lines().find_map(|line| {
+        let (fields, filesystem) = line.split_once(" - ")?;
+        filesystem
+            .starts_with("cgroup2 ")
+            .then(|| fields.split(' ').nth(4))
+            .flatten()
+    })?;
+    Some(std::path::Path::new(mount_point).join(path.trim_start_matches('/')))
+}
+
+#[cfg(windows)]
+pub(crate) struct JobAccount {
+    job: windows_sys::Win32::Foundation::HANDLE,
+}
+
+#[cfg(windows)]
+impl JobAccount {
+    /// create an anonymous Job Object, None if it can't be created
+    pub(crate) fn create(enabled: bool) -> Option<JobAccount> {
+        use windows_sys::Win32::System::JobObjects::CreateJobObjectW;
+
+        if !enabled {
+            return None;
+        }
+        let job = unsafe { CreateJobObjectW(std::ptr::null(), std::ptr::null()) };
+        (!job.is_null()).then_some(JobAccount { job })
+    }
+
+    /// the processes are assigned to the job once spawned, see [`JobAccount::assign`]
+    pub(crate) fn attach(&self, expression: Expression) -> Expression {
+        expression
+    }
+
+    /// assign the spawned processes to the job, their children started from now on are in the job too
+    pub(crate) fn assign(&self, pids: &[u32]) -> io::Result<()> {
+        use windows_sys::Win32::Foundation::CloseHandle;
+        use windows_sys::Win32::System::JobObjects::AssignProcessToJobObject;
+        use windows_sys::Win32::System::Threading::{
+            OpenProcess, PROCESS_SET_QUOTA, PROCESS_TERMINATE,
+        };
+
+        for pid in pids {
+            unsafe {
+                let handle = OpenProcess(PROCESS_SET_QUOTA | PROCESS_TERMINATE, 0, *pid);
+                if handle.is_null() {
+                    return Err(io::Error::last_os_error());
+                }
+                let result = AssignProcessToJobObject(self.job, handle);
+                CloseHandle(handle);
+                if result == 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// read the usage accounted so far, None if the job can't be queried
+    pub(crate) fn read(&self) -> Option<JobAccounting> {
+        use windows_sys::Win32::System::JobObjects::{
+            JobObjectBasicAndIoAccountingInformation, JobObjectExtendedLimitInformation,
+            QueryInformationJobObject, JOBOBJECT_BASIC_AND_IO_ACCOUNTING_INFORMATION,
+            JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+        };
+
+        unsafe {
+            let mut accounting: JOBOBJECT_BASIC_AND_IO_ACCOUNTING_INFORMATION = std::mem::zeroed();
+            let mut limits: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+            if QueryInformationJobObject(
+                self.job,
+                JobObjectBasicAndIoAccountingInformation,
+                &mut accounting as *mut _ as *mut std::ffi::c_void,
+                std::mem::size_of_val(&accounting) as u32,
+                std::ptr::null_mut(),
+            ) == 0
+                || QueryInformationJobObject(
+                    self.job,
+                    JobObjectExtendedLimitInformation,
+                    &mut limits as *mut _ as *mut std::ffi::c_void,
+                    std::mem::size_of_val(&limits) as u32,
+                    std::ptr::null_mut(),
+                ) == 0
+            {
+                return None;
+            }
+            let basic = accounting.BasicInfo;
+            // in 100 nanoseconds unit
+            let cpu_ticks = (basic.TotalUserTime + basic.TotalKernelTime).max(0) as u64;
+            Some(JobAccounting {
+                cpu_time: Duration::from_nanos(cpu_ticks * 100),
+                io_read_bytes: Some(accounting.IoInfo.ReadTransferCount),
+                io_write_bytes: Some(accounting.IoInfo.WriteTransferCount),
+                peak_memory_bytes: Some(limits.PeakJobMemoryUsed as u64),
+            })
+        }
+    }
+}
+
+#[cfg(windows)]
+impl Drop for JobAccount {
+    fn drop(&mut self) {
+        unsafe { windows_sys::Win32::Foundation::CloseHandle(self.job) };
+    }
+}
+
+/// Job accounting is not available on this platform
+#[cfg(not(any(target_os = "linux", windows)))]
+pub(crate) struct JobAccount;
+
+#[cfg(not(any(target_os = "linux", windows)))]
+impl JobAccount {
+    pub(crate) fn create(_enabled: bool) -> Option<JobAccount> {
+        None
+    }
+
+    pub(crate) fn attach(&self, expression: Expression) -> Expression {
+        expression
+    }
+
+    pub(crate) fn assign(&self, _pids: &[u32]) -> io::Result<()> {
+        Ok(())
+    }
+
+    pub(crate) fn read(&self) -> Option<JobAccounting> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ProcessRequest;
+    use std::time::Duration;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    pub fn test_job_accounting() {
+        // the CPU time is spent by a grandchild, a subshell in the background
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 277,
+            use_shell: true,
+            cmd_line: vec![vec![String::from(
+                "(i=0; while [ $i -lt 100000 ]; do i=$((i+1)); done) & wait",
+            )]],
+            job_accounting: true,
+            ..Default::default()
+        });
+        assert_eq!(result.exit_code, Some(0));
+        // a writable cgroup v2 hierarchy is needed
+        let Some(accounting) = result.job_accounting else {
+            return;
+        };
+        assert!(accounting.cpu_time > Duration::from_millis(20));
+    }
+}
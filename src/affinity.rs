@@ -0,0 +1,111 @@
+use duct::Expression;
+use std::io;
+
+/// Pin all the commands of the expression to the given CPU cores, applied in the child before exec
+#[cfg(target_os = "linux")]
+pub(crate) fn apply_cpu_affinity(
+    expression: Expression,
+    cores: Option<&[usize]>,
+) -> io::Result<Expression> {
+    use std::os::unix::process::CommandExt;
+
+    let Some(cores) = cores else {
+        return Ok(expression);
+    };
+    let mut cpu_set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    for core in cores {
+        if *core >= libc::CPU_SETSIZE as usize {
+            return Err(invalid_core(*core));
+        }
+        unsafe { libc::CPU_SET(*core, &mut cpu_set) };
+    }
+    Ok(expression.before_spawn(move |command| {
+        unsafe {
+            command.pre_exec(move || {
+                let size = std::mem::size_of::<libc::cpu_set_t>();
+                if libc::sched_setaffinity(0, size, &cpu_set) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        Ok(())
+    }))
+}
+
+/// On Windows the affinity is set once the processes are spawned, see [`pin_spawned_processes`]
+#[cfg(windows)]
+pub(crate) fn apply_cpu_affinity(
+    expression: Expression,
+    _cores: Option<&[usize]>,
+) -> io::Result<Expression> {
+    Ok(expression)
+}
+
+/// CPU affinity is not available on this platform
+#[cfg(not(any(target_os = "linux", windows)))]
+pub(crate) fn apply_cpu_affinity(
+    expression: Expression,
+    cores: Option<&[usize]>,
+) -> io::Result<Expression> {
+    match cores {
+        None => Ok(expression),
+        Some(_) => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "CPU affinity is not supported on this platform",
+        )),
+    }
+}
+
+/// Pin the already spawned processes to the given CPU cores using SetProcessAffinityMask
+#[cfg(windows)]
+pub(crate) fn pin_spawned_processes(pids: &[u32], cores: Option<&[usize]>) -> io::Result<()> {
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, SetProcessAffinityMask, PROCESS_QUERY_LIMITED_INFORMATION,
+        PROCESS_SET_INFORMATION,
+    };
+
+    let Some(cores) = cores else {
+        return Ok(());
+    };
+    let mut mask: usize = 0;
+    for core in cores {
+        if *core >= usize::BITS as usize {
+            return Err(invalid_core(*core));
+        }
+        mask |= 1 << core;
+    }
+    for pid in pids {
+        unsafe {
+            let handle = OpenProcess(
+                PROCESS_SET_INFORMATION | PROCESS_QUERY_LIMITED_INFORMATION,
+                0,
+                *pid,
+            );
+            if handle.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            let result = SetProcessAffinityMask(handle, mask);
+            CloseHandle(handle);
+            if result == 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Affinity is already applied before exec on this platform
+#[cfg(not(windows))]
+pub(crate) fn pin_spawned_processes(_pids: &[u32], _cores: Option<&[usize]>) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(any(target_os = "linux", windows))]
+fn invalid_core(core: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("CPU core {} is out of the supported range", core),
+    )
+}
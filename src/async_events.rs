@@ -0,0 +1,261 @@
+use crate::{ProcessData, ProcessEvent, QueuedEvent};
+use futures_core::Stream;
+use futures_sink::Sink;
+use std::collections::VecDeque;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+
+struct ChannelState {
+    events: VecDeque<QueuedEvent>,
+    /// number of the live sinks, the stream ends once it's 0 & the events are taken
+    senders: usize,
+    receiver_alive: bool,
+    stream_waker: Option<Waker>,
+    sink_wakers: Vec<Waker>,
+}
+
+struct Channel {
+    capacity: usize,
+    state: Mutex<ChannelState>,
+    /// signals the blocked process threads once there is room
+    changed: Condvar,
+}
+
+impl Channel {
+    /// wake the producers waiting for room
+    fn wake_senders(&self, state: &mut ChannelState) {
+        self.changed.notify_all();
+        for waker in state.sink_wakers.drain(..) {
+            waker.wake();
+        }
+    }
+
+    fn queue(&self, state: &mut ChannelState, event: QueuedEvent) {
+        state.events.push_back(event);
+        if let Some(waker) = state.stream_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Bounded channel bridging the events of the requests to an async consumer: set the sink as
+/// [`crate::ProcessRequest::async_events`] & consume the stream in an async task, e.g. forwarding the lines to a
+/// websocket. A full channel blocks the output reading of the process (backpressure) instead of buffering without a
+/// bound. The stream ends once all the sinks are dropped, i.e. the requests holding them are done
+pub fn async_event_channel(capacity: usize) -> (AsyncEventSink, AsyncEventStream) {
+    let channel = Arc::new(Channel {
+        capacity: capacity.max(1),
+        state: Mutex::new(ChannelState {
+            events: VecDeque::new(),
+            senders: 1,
+            receiver_alive: true,
+            stream_waker: None,
+            sink_wakers: vec![],
+        }),
+        changed: Condvar::new(),
+    });
+    (
+        AsyncEventSink {
+            channel: Arc::clone(&channel),
+        },
+        AsyncEventStream { channel },
+    )
+}
+
+/// Sending half of the [`async_event_channel`], clone it to share the channel between requests. It's also a
+/// [`Sink`] of the events to feed the channel from the async code
+pub struct AsyncEventSink {
+    channel: Arc<Channel>,
+}
+
+impl AsyncEventSink {
+    /// queue the event, waits while the channel is full. False if the stream was dropped
+    pub(crate) fn push(&self, event: &ProcessEvent, data: &ProcessData) -> bool {
+        let channel = &self.channel;
+        let state = channel.state.lock().unwrap();
+        let mut state = channel
+            .changed
+            .wait_while(state, |state| {
+                state.receiver_alive && state.events.len() >= channel.capacity
+            })
+            .unwrap();
+        if !state.receiver_alive {
+            return false;
+        }
+        let event = QueuedEvent::Event {
+            event: *event,
+            request_id: data
+                .request
+                .as_ref()
+                .map_or(0, |request| request.request_id),
+            line_number: data.line_number,
+            line: data.line_to_owned(),
+            timestamp: data.timestamp(),
+        };
+        channel.queue(&mut state, event);
+        true
+    }
+}
+
+impl Clone for AsyncEventSink {
+    fn clone(&self) -> Self {
+        self.channel.state.lock().unwrap().senders += 1;
+        Self {
+            channel: Arc::clone(&self.channel),
+        }
+    }
+}
+
+impl Drop for AsyncEventSink {
+    fn drop(&mut self) {
+        let mut state = self.channel.state.lock().unwrap();
+        state.senders -= 1;
+        if state.senders == 0 {
+            if let Some(waker) = state.stream_waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl Sink<QueuedEvent> for AsyncEventSink {
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut state = self.channel.state.lock().unwrap();
+        if !state.receiver_alive {
+            return Poll::Ready(Err(stream_dropped()));
+        }
+        if state.events.len() >= self.channel.capacity {
+            state.sink_wakers.push(context.waker().clone());
+            return Poll::Pending;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, event: QueuedEvent) -> io::Result<()> {
+        let mut state = self.channel.state.lock().unwrap();
+        if !state.receiver_alive {
+            return Err(stream_dropped());
+        }
+        self.channel.queue(&mut state, event);
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _context: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _context: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Receiving half of the [`async_event_channel`], a [`Stream`] of the events
+pub struct AsyncEventStream {
+    channel: Arc<Channel>,
+}
+
+impl Stream for AsyncEventStream {
+    type Item = QueuedEvent;
+
+    fn poll_next(self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Option<QueuedEvent>> {
+        let mut state = self.channel.state.lock().unwrap();
+        if let Some(event) = state.events.pop_front() {
+            self.channel.wake_senders(&mut state);
+            return Poll::Ready(Some(event));
+        }
+        if state.senders == 0 {
+            return Poll::Ready(None);
+        }
+        state.stream_waker = Some(context.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for AsyncEventStream {
+    fn drop(&mut self) {
+        let mut state = self.channel.state.lock().unwrap();
+        state.receiver_alive = false;
+        state.events.clear();
+        self.channel.wake_senders(&mut state);
+    }
+}
+
+fn stream_dropped() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "Event stream was dropped")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{async_event_channel, ProcessEvent, ProcessRequest, QueuedEvent};
+    use futures_core::Stream;
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+    use std::thread::{self, Thread};
+    use std::time::Duration;
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// poll the stream to its end on this thread
+    fn collect(mut stream: Pin<&mut impl Stream<Item = QueuedEvent>>) -> Vec<QueuedEvent> {
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut context = Context::from_waker(&waker);
+        let mut events = vec![];
+        loop {
+            match stream.as_mut().poll_next(&mut context) {
+                Poll::Ready(Some(event)) => {
+                    // a slow consumer, the process waits meanwhile
+                    thread::sleep(Duration::from_millis(20));
+                    events.push(event);
+                }
+                Poll::Ready(None) => return events,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_async_event_channel() {
+        let (sink, mut stream) = async_event_channel(2);
+        ProcessRequest::start(ProcessRequest {
+            request_id: 294,
+            non_blocking_mode: true,
+            use_shell: true,
+            cmd_line: vec![vec![String::from("for i in 1 2 3 4 5 6; do echo $i; done")]],
+            async_events: Some(sink),
+            ..Default::default()
+        });
+        let events = collect(Pin::new(&mut stream));
+        let lines: Vec<&str> = events
+            .iter()
+            .filter_map(|event| match event {
+                QueuedEvent::Event {
+                    event: ProcessEvent::IOData,
+                    line,
+                    ..
+                } => Some(line.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(lines, ["1", "2", "3", "4", "5", "6"]);
+        assert!(matches!(
+            events.last(),
+            Some(QueuedEvent::Event {
+                event: ProcessEvent::Exited,
+                request_id: 294,
+                ..
+            })
+        ));
+    }
+}
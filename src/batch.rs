@@ -0,0 +1,175 @@
+use crate::report::{self, ReportCollector};
+use crate::retry::start_process_with_retry;
+use crate::{BatchReport, ProcessCallback, ProcessRequest, ProcessResult};
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Options of a batch execution, see [`ProcessRequest::start_batch_with`]
+#[derive(Clone, Default)]
+pub struct BatchOptions {
+    /// Max requests to run in parallel, for no limit use None
+    pub max_parallel: Option<usize>,
+    /// Shared callback for the events of all the requests (use `data.request` to know the request_id),
+    /// it replaces the callbacks of the requests. To use the callback of each request use None
+    pub callback: Option<Arc<ProcessCallback>>,
+}
+
+/// Outcome of a single request of the batch
+#[derive(Debug)]
+pub struct BatchItem {
+    /// Id of the request
+    pub request_id: u32,
+    /// Exit code of the process, see [`ProcessResult::exit_code`]
+    pub exit_code: Option<i32>,
+    /// Time taken to run the request including the retries
+    pub duration: Duration,
+    /// Full result of the request including the data set by the callback
+    pub result: ProcessResult,
+}
+
+impl BatchItem {
+    /// Process execution was successful, see [`ProcessResult::success`]
+    pub fn succeeded(&self) -> bool {
+        self.result.success.as_ref().is_ok_and(|success| *success)
+    }
+}
+
+/// Combined result of a batch execution
+#[derive(Debug)]
+pub struct BatchSummary {
+    /// Outcome of each request in the submission order
+    pub items: Vec<BatchItem>,
+    /// Total time taken by the batch
+    pub duration: Duration,
+    /// Execution time percentiles, failure rates by the command & the peak concurrency of the batch
+    pub report: BatchReport,
+}
+
+impl BatchSummary {
+    /// Requests which failed to start or failed
+    pub fn failures(&self) -> impl Iterator<Item = &BatchItem> {
+        self.items.iter().filter(|item| !item.succeeded())
+    }
+
+    /// All the requests were successful
+    pub fn all_succeeded(&self) -> bool {
+        self.failures().next().is_none()
+    }
+}
+
+/// Handle of a running batch
+pub struct BatchHandle {
+    join_handle: JoinHandle<BatchSummary>,
+}
+
+impl BatchHandle {
+    /// All the requests are completed
+    pub fn is_finished(&self) -> bool {
+        self.join_handle.is_finished()
+    }
+
+    /// Wait for all the requests to complete and return the summary
+    pub fn wait(self) -> thread::Result<BatchSummary> {
+        self.join_handle.join()
+    }
+}
+
+/// start running the requests in a batch thread
+pub(crate) fn start_batch(
+    requests: Vec<ProcessRequest>,
+    options: BatchOptions,
+) -> io::Result<BatchHandle> {
+    let requests: Vec<Arc<ProcessRequest>> = requests
+        .into_iter()
+        .map(|mut request| {
+            if options.callback.is_some() {
+                request.callback = options.callback.clone();
+            }
+            Arc::new(request)
+        })
+        .collect();
+    let max_parallel = options.max_parallel;
+    let join_handle = thread::Builder::new()
+        .name(String::from("pes_batch"))
+        .spawn(move || run_batch(requests, max_parallel))?;
+    Ok(BatchHandle { join_handle })
+}
+
+/// run the requests on the worker threads and collect the summary
+fn run_batch(requests: Vec<Arc<ProcessRequest>>, max_parallel: Option<usize>) -> BatchSummary {
+    let started = Instant::now();
+    let workers = max_parallel
+        .unwrap_or(requests.len())
+        .clamp(1, requests.len().max(1));
+    let next = AtomicUsize::new(0);
+    let items: Mutex<Vec<Option<BatchItem>>> = Mutex::new(requests.iter().map(|_| None).collect());
+    let collector = ReportCollector::default();
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(request) = requests.get(index) else {
+                    return;
+                };
+                let request_started = Instant::now();
+                let running = collector.begin();
+                let result = start_process_with_retry(Arc::clone(request), None);
+                drop(running);
+                let duration = request_started.elapsed();
+                collector.record(&report::command_line(request), duration, &result);
+                items.lock().unwrap()[index] = Some(BatchItem {
+                    request_id: request.request_id,
+                    exit_code: result.exit_code,
+                    duration,
+                    result,
+                });
+            });
+        }
+    });
+    BatchSummary {
+        items: items.into_inner().unwrap().into_iter().flatten().collect(),
+        duration: started.elapsed(),
+        report: collector.report(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BatchOptions, ProcessRequest};
+
+    #[test]
+    #[cfg(unix)]
+    pub fn test_batch_summary() {
+        let requests = (0..4)
+            .map(|index| ProcessRequest {
+                request_id: 341 + index,
+                use_shell: true,
+                cmd_line: vec![vec![format!("exit {}", index % 2)]],
+                ..Default::default()
+            })
+            .collect();
+        let summary = ProcessRequest::start_batch_with(
+            requests,
+            BatchOptions {
+                max_parallel: Some(2),
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+        assert_eq!(summary.items.len(), 4);
+        assert_eq!(summary.items[1].request_id, 342);
+        assert_eq!(summary.failures().count(), 2);
+        assert!(!summary.all_succeeded());
+        let report = &summary.report;
+        assert_eq!(report.runtimes.unwrap().count, 4);
+        assert!((1..=2).contains(&report.max_concurrency));
+        assert_eq!(report.commands["exit 0"].failure_rate(), 0.0);
+        assert_eq!(report.commands["exit 1"].runs, 2);
+        assert_eq!(report.commands["exit 1"].failure_rate(), 1.0);
+    }
+}
@@ -0,0 +1,187 @@
+//! `pes` - run a command, a pipeline or the jobs of a config file & print the process events
+use process_events_streaming::{
+    BatchOptions, JsonEventSink, ProcessData, ProcessEvent, ProcessRequest, ProcessResult,
+};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+
+const USAGE: &str = "Usage:
+  pes [OPTIONS] -- COMMAND [ARGS...] ['|' COMMAND [ARGS...]...]
+  pes [OPTIONS] --job FILE
+  pes [OPTIONS] --jobs FILE
+
+Options:
+  --job FILE            Run the job of a TOML/YAML config file
+  --jobs FILE           Run all the jobs of a TOML/YAML config file in parallel
+  --shell               Run the commands using the shell
+  --json                Print the events as JSON lines
+  --timeout SECS        Kill the process if it doesn't complete in time
+  --idle-timeout SECS   Kill the process if there is no output line in time
+  --cwd DIR             Working directory of the process
+  --env NAME=VALUE      Set an environment variable, can be repeated
+  --dry-run             Print the resolved command line without running it
+  -h, --help            Print this help";
+
+/// command line options
+#[derive(Default)]
+struct Options {
+    job: Option<PathBuf>,
+    jobs: Option<PathBuf>,
+    json: bool,
+    request: ProcessRequest,
+}
+
+fn main() -> ExitCode {
+    let help = std::env::args()
+        .take_while(|arg| arg != "--")
+        .any(|arg| arg == "-h" || arg == "--help");
+    if help {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("pes: {}\n\n{}", error, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    match run(options) {
+        Ok(exit_code) => exit_code,
+        Err(error) => {
+            eprintln!("pes: {}", error);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    let value = |args: &mut dyn Iterator<Item = String>, name: &str| {
+        args.next()
+            .ok_or_else(|| format!("Missing value of {}", name))
+    };
+    let secs = |value: String| {
+        value
+            .parse::<f64>()
+            .ok()
+            .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+            .ok_or_else(|| format!("Invalid seconds {}", value))
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--job" => options.job = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "--jobs" => options.jobs = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "--shell" => options.request.use_shell = true,
+            "--json" => options.json = true,
+            "--dry-run" => options.request.dry_run = true,
+            "--timeout" => options.request.timeout = Some(secs(value(&mut args, &arg)?)?),
+            "--idle-timeout" => options.request.idle_timeout = Some(secs(value(&mut args, &arg)?)?),
+            "--cwd" => options.request.working_dir = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "--env" => {
+                let env = value(&mut args, &arg)?;
+                let (name, value) = env
+                    .split_once('=')
+                    .ok_or_else(|| format!("Invalid environment variable {}", env))?;
+                options
+                    .request
+                    .env
+                    .insert(String::from(name), String::from(value));
+            }
+            "--" => {
+                options.request.cmd_line = args.by_ref().fold(vec![vec![]], |mut stages, arg| {
+                    if arg == "|" {
+                        stages.push(vec![]);
+                    } else {
+                        stages.last_mut().unwrap().push(arg);
+                    }
+                    stages
+                });
+            }
+            _ => return Err(format!("Unknown option {}", arg)),
+        }
+    }
+    let sources = [
+        options.job.is_some(),
+        options.jobs.is_some(),
+        !options.request.cmd_line.is_empty(),
+    ];
+    if sources.iter().filter(|source| **source).count() != 1 {
+        return Err(String::from(
+            "Use exactly one of a command, --job or --jobs",
+        ));
+    }
+    if options.request.cmd_line.iter().any(Vec::is_empty) {
+        return Err(String::from("Empty command in the pipeline"));
+    }
+    Ok(options)
+}
+
+/// apply the command line options on the request loaded from a job file
+fn override_request(mut request: ProcessRequest, options: &ProcessRequest) -> ProcessRequest {
+    request.use_shell |= options.use_shell;
+    request.dry_run |= options.dry_run;
+    request.timeout = options.timeout.or(request.timeout);
+    request.idle_timeout = options.idle_timeout.or(request.idle_timeout);
+    if options.working_dir.is_some() {
+        request.working_dir.clone_from(&options.working_dir);
+    }
+    request.env.extend(options.env.clone());
+    request
+}
+
+/// print the event in the human readable form
+fn print_event(event: &ProcessEvent, data: &ProcessData) -> ProcessResult {
+    let request_id = data
+        .request
+        .as_ref()
+        .map_or(0, |request| request.request_id);
+    match event {
+        ProcessEvent::IOData => print!("[{}] {}", request_id, data.line),
+        event if data.line.is_empty() => println!("[{}] {:?}", request_id, event),
+        event => println!("[{}] {:?}: {}", request_id, event, data.line),
+    }
+    ProcessResult::new()
+}
+
+fn run(options: Options) -> std::io::Result<ExitCode> {
+    let mut requests = match (&options.job, &options.jobs) {
+        (Some(job), _) => vec![ProcessRequest::from_config_file(job)?],
+        (_, Some(jobs)) => ProcessRequest::from_config_file_jobs(jobs)?,
+        _ => vec![options.request.clone()],
+    };
+    let json_events = options.json.then(|| Arc::new(JsonEventSink::stdout()));
+    requests = requests
+        .into_iter()
+        .map(|request| {
+            let mut request = override_request(request, &options.request);
+            request.non_blocking_mode = false;
+            request.json_events.clone_from(&json_events);
+            request
+        })
+        .collect();
+    let callback = (!options.json)
+        .then(|| Arc::new(print_event) as Arc<process_events_streaming::ProcessCallback>);
+    let summary = ProcessRequest::start_batch_with(
+        requests,
+        BatchOptions {
+            callback,
+            ..Default::default()
+        },
+    )?
+    .wait()
+    .map_err(|_| std::io::Error::other("Batch thread panicked"))?;
+    if options.request.dry_run {
+        return Ok(ExitCode::SUCCESS);
+    }
+    Ok(match summary.items.as_slice() {
+        [item] => match item.exit_code {
+            Some(exit_code) => ExitCode::from(exit_code.clamp(0, 255) as u8),
+            None => ExitCode::FAILURE,
+        },
+        _ if summary.all_succeeded() => ExitCode::SUCCESS,
+        _ => ExitCode::FAILURE,
+    })
+}
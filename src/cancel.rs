@@ -0,0 +1,121 @@
+use crate::{check_and_trigger_callback, ProcessData, ProcessEvent, ProcessRequest, ProcessResult};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Flag to cancel an execution from any thread without holding its handle, see [`crate::ProcessRequest::cancellation`].
+/// Once cancelled the running process is killed with the [`crate::ProcessEvent::ExitRequested`] event & it's not retried,
+/// a request cancelled before its spawn is not run at all. Clones share the same flag
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    flag: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Token which is not cancelled yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Token sharing an existing flag, the execution is cancelled once it's set to true
+    pub fn from_flag(flag: Arc<AtomicBool>) -> Self {
+        Self { flag }
+    }
+
+    /// Cancel the executions of the token
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::SeqCst);
+    }
+
+    /// The token is cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::SeqCst)
+    }
+}
+
+impl From<Arc<AtomicBool>> for CancellationToken {
+    fn from(flag: Arc<AtomicBool>) -> Self {
+        Self::from_flag(flag)
+    }
+}
+
+/// the cancellation token of the request is cancelled
+pub(crate) fn is_cancelled(request: &ProcessRequest) -> bool {
+    request
+        .cancellation
+        .as_ref()
+        .is_some_and(CancellationToken::is_cancelled)
+}
+
+/// trigger the exit requested event of the request cancelled before its process is spawned, returns its failed result
+pub(crate) fn cancelled_before_spawn(request: &Arc<ProcessRequest>) -> ProcessResult {
+    let mut process_data = ProcessData::new();
+    process_data.request = Some(Arc::clone(request));
+    process_data.line.push_str("Cancelled before the spawn");
+    check_and_trigger_callback(request, &ProcessEvent::ExitRequested, &process_data);
+    let mut result = ProcessResult::new();
+    result.success = Ok(false);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CancellationToken, ProcessData, ProcessEvent, ProcessRequest, ProcessResult};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_cancellation_token() {
+        static EXIT_REQUESTED: AtomicBool = AtomicBool::new(false);
+        let callback = |event: &ProcessEvent, _data: &ProcessData| -> ProcessResult {
+            if *event == ProcessEvent::ExitRequested {
+                EXIT_REQUESTED.store(true, Ordering::SeqCst);
+            }
+            ProcessResult::new()
+        };
+        let flag = Arc::new(AtomicBool::new(false));
+        let token = CancellationToken::from(Arc::clone(&flag));
+        assert!(!token.is_cancelled());
+        let started = Instant::now();
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(300));
+            flag.store(true, Ordering::SeqCst);
+        });
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 328,
+            cmd_line: vec![vec![String::from("sleep"), String::from("5")]],
+            cancellation: Some(token.clone()),
+            callback: Some(Arc::new(callback)),
+            ..Default::default()
+        });
+        canceller.join().unwrap();
+        assert!(token.is_cancelled());
+        assert!(started.elapsed() < Duration::from_secs(4));
+        assert!(!result.success.unwrap());
+        assert!(EXIT_REQUESTED.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    pub fn test_cancelled_before_spawn() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let recorded = Arc::clone(&events);
+        let callback = move |event: &ProcessEvent, _data: &ProcessData| -> ProcessResult {
+            recorded.lock().unwrap().push(*event);
+            ProcessResult::new()
+        };
+        let token = CancellationToken::new();
+        token.cancel();
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 503,
+            cmd_line: vec![vec![String::from("echo"), String::from("never")]],
+            cancellation: Some(token),
+            callback: Some(Arc::new(callback)),
+            ..Default::default()
+        });
+        assert!(!result.success.unwrap());
+        assert!(!result.spawned);
+        assert_eq!(result.attempts, 0);
+        assert_eq!(*events.lock().unwrap(), [ProcessEvent::ExitRequested]);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::{check_and_trigger_callback, ProcessData, ProcessEvent, ProcessRequest, WarningKind};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Position in the output of an execution of a request, see [`ProcessRequest::checkpointer`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Checkpoint {
+    /// Number of the last processed output line, see [`ProcessData::line_number`]
+    pub line_number: i64,
+    /// Bytes of the output lines up to & including the last processed one, after the decoding of the output
+    pub byte_offset: u64,
+}
+
+/// Storage of the checkpoints by the request id, see [`ProcessRequest::checkpointer`]. Implement it to persist the
+/// checkpoints elsewhere, e.g. in a database
+pub trait Checkpointer: Send + Sync {
+    /// Checkpoint saved for the request, None if there is none
+    fn load(&self, request_id: u32) -> io::Result<Option<Checkpoint>>;
+
+    /// Save the checkpoint of the request, replacing the previous one
+    fn save(&self, request_id: u32, checkpoint: &Checkpoint) -> io::Result<()>;
+}
+
+/// In-memory checkpoints, they survive the restarts of the process (e.g. by the [`crate::Supervisor`]) but not of
+/// this program
+#[derive(Debug, Default)]
+pub struct MemoryCheckpointer {
+    checkpoints: Mutex<HashMap<u32, Checkpoint>>,
+}
+
+impl MemoryCheckpointer {
+    /// No checkpoints
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Checkpointer for MemoryCheckpointer {
+    fn load(&self, request_id: u32) -> io::Result<Option<Checkpoint>> {
+        Ok(self.checkpoints.lock().unwrap().get(&request_id).copied())
+    }
+
+    fn save(&self, request_id: u32, checkpoint: &Checkpoint) -> io::Result<()> {
+        self.checkpoints
+            .lock()
+            .unwrap()
+            .insert(request_id, *checkpoint);
+        Ok(())
+    }
+}
+
+/// Checkpoints persisted as the files `<request id>.checkpoint` in a directory, replaced atomically on every save
+#[derive(Debug, Clone)]
+pub struct FileCheckpointer {
+    dir: PathBuf,
+}
+
+impl FileCheckpointer {
+    /// Keep the checkpoints in the directory, it's created if missing
+    pub fn new(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, request_id: u32, extension: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", request_id, extension))
+    }
+}
+
+impl Checkpointer for FileCheckpointer {
+    fn load(&self, request_id: u32) -> io::Result<Option<Checkpoint>> {
+        let content = match fs::read_to_string(self.path(request_id, "checkpoint")) {
+            Ok(content) => content,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error),
+        };
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Invalid checkpoint file");
+        let (line_number, byte_offset) = content.trim().split_once(' ').ok_or_else(invalid)?;
+        Ok(Some(Checkpoint {
+            line_number: line_number.parse().map_err(|_| invalid())?,
+            byte_offset: byte_offset.parse().map_err(|_| invalid())?,
+        }))
+    }
+
+    fn save(&self, request_id: u32, checkpoint: &Checkpoint) -> io::Result<()> {
+        let temporary = self.path(request_id, "checkpoint.tmp");
+        fs::write(
+            &temporary,
+            format!("{} {}\n", checkpoint.line_number, checkpoint.byte_offset),
+        )?;
+        fs::rename(temporary, self.path(request_id, "checkpoint"))
+    }
+}
+
+/// load the checkpoint of the previous execution of the request, one which can't be loaded is ignored
+pub(crate) fn load(request: &ProcessRequest) -> Option<Checkpoint> {
+    let checkpointer = request.checkpointer.as_ref()?;
+    checkpointer.load(request.request_id).ok().flatten()
+}
+
+/// save the position of the last processed line, a failure is reported as a warning
+pub(crate) fn save(request: &Arc<ProcessRequest>, position: &Checkpoint, data: &ProcessData) {
+    let Some(checkpointer) = request.checkpointer.as_ref() else {
+        return;
+    };
+    if checkpointer.save(request.request_id, position).is_err() {
+        check_and_trigger_callback(
+            request,
+            &ProcessEvent::Warning(WarningKind::SinkWriteFailed),
+            data,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Checkpoint, Checkpointer, FileCheckpointer, MemoryCheckpointer, ProcessData, ProcessEvent,
+        ProcessRequest, ProcessResult,
+    };
+    use std::sync::{Arc, Mutex};
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_checkpoint_resume() {
+        let checkpointer = Arc::new(MemoryCheckpointer::new());
+        let lines = Arc::new(Mutex::new(vec![]));
+        let run = |stop_at: Option<&'static str>| {
+            let captured = Arc::clone(&lines);
+            let callback = move |event: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+                let mut result = ProcessResult::new();
+                if *event == ProcessEvent::IOData {
+                    captured.lock().unwrap().push(data.line_to_owned());
+                    if stop_at == Some(data.line_str()) {
+                        result.set_exit_flag_and_success(true, Ok(true));
+                    }
+                }
+                result
+            };
+            ProcessRequest::start(ProcessRequest {
+                request_id: 290,
+                callback: Some(Arc::new(callback)),
+                cmd_line: vec![vec![
+                    String::from("printf"),
+                    String::from("a\\nb\\nc\\nd\\n"),
+                ]],
+                checkpointer: Some(checkpointer.clone()),
+                resume_from_checkpoint: true,
+                ..Default::default()
+            });
+        };
+        // the consumer stops at "b", the next run skips the processed lines
+        run(Some("b"));
+        assert_eq!(
+            checkpointer.load(290).unwrap(),
+            Some(Checkpoint {
+                line_number: 2,
+                byte_offset: 4
+            })
+        );
+        run(None);
+        assert_eq!(*lines.lock().unwrap(), ["a", "b", "c", "d"]);
+        assert_eq!(checkpointer.load(290).unwrap().unwrap().line_number, 4);
+    }
+
+    #[test]
+    pub fn test_file_checkpointer() {
+        let dir = std::env::temp_dir().join("pes_test_checkpoints");
+        _ = std::fs::remove_dir_all(&dir);
+        let checkpointer = FileCheckpointer::new(&dir).unwrap();
+        assert_eq!(checkpointer.load(291).unwrap(), None);
+        let checkpoint = Checkpoint {
+            line_number: 7,
+            byte_offset: 120,
+        };
+        checkpointer.save(291, &checkpoint).unwrap();
+        let reopened = FileCheckpointer::new(&dir).unwrap();
+        assert_eq!(reopened.load(291).unwrap(), Some(checkpoint));
+        _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,139 @@
+use regex::Regex;
+use std::io;
+
+/// Class of an output line as per the [`LineClassifier`] of the request, see [`crate::ProcessData::line_class`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LineClass {
+    /// Error or failure
+    Error,
+    /// Warning
+    Warning,
+    /// Informational
+    Info,
+    /// Debugging or tracing details
+    Debug,
+    /// Class of a custom classifier
+    Custom(String),
+}
+
+/// Classifies every output line (e.g. by its severity) for the colored UIs & the error counts, see
+/// [`crate::ProcessRequest::line_classifier`]. Implement it for a custom model, a closure returning the class works too
+pub trait LineClassifier: Send + Sync {
+    /// Class of the output line (without its line break), None if it has none
+    fn classify(&self, line: &str) -> Option<LineClass>;
+}
+
+impl<F> LineClassifier for F
+where
+    F: Fn(&str) -> Option<LineClass> + Send + Sync,
+{
+    fn classify(&self, line: &str) -> Option<LineClass> {
+        self(line)
+    }
+}
+
+/// Classifies the lines by the regular expressions, the first matching rule wins
+#[derive(Debug, Clone, Default)]
+pub struct RegexClassifier {
+    rules: Vec<(Regex, LineClass)>,
+}
+
+impl RegexClassifier {
+    /// Classifier without any rule
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Classifier of the common severity words (case insensitive): `error`, `fatal`, `panic`, `exception` & `failed`
+    /// as [`LineClass::Error`], `warn` & `warning` as [`LineClass::Warning`], `info` as [`LineClass::Info`], `debug`
+    /// & `trace` as [`LineClass::Debug`]
+    pub fn severity() -> Self {
+        let rules = [
+            (
+                r"(?i)\b(error|fatal|panic|exception|failed)\b",
+                LineClass::Error,
+            ),
+            (r"(?i)\b(warn|warning)\b", LineClass::Warning),
+            (r"(?i)\binfo\b", LineClass::Info),
+            (r"(?i)\b(debug|trace)\b", LineClass::Debug),
+        ];
+        Self {
+            rules: rules
+                .into_iter()
+                .map(|(pattern, class)| (Regex::new(pattern).unwrap(), class))
+                .collect(),
+        }
+    }
+
+    /// Add a rule classifying the lines matching the regular expression, fails if it's not valid
+    pub fn rule(mut self, pattern: &str, class: LineClass) -> io::Result<Self> {
+        let regex = Regex::new(pattern)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error.to_string()))?;
+        self.rules.push((regex, class));
+        Ok(self)
+    }
+}
+
+impl LineClassifier for RegexClassifier {
+    fn classify(&self, line: &str) -> Option<LineClass> {
+        self.rules
+            .iter()
+            .find(|(regex, _)| regex.is_match(line))
+            .map(|(_, class)| class.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        LineClass, LineClassifier, ProcessData, ProcessEvent, ProcessRequest, ProcessResult,
+        RegexClassifier,
+    };
+    use std::sync::{Arc, Mutex};
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_line_classifier() {
+        let classes = Arc::new(Mutex::new(vec![]));
+        let recorded = Arc::clone(&classes);
+        let callback = move |event: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            if *event == ProcessEvent::IOData {
+                recorded.lock().unwrap().push(data.line_class.clone());
+            }
+            ProcessResult::new()
+        };
+        let classifier = RegexClassifier::new()
+            .rule("^slow", LineClass::Custom(String::from("perf")))
+            .unwrap();
+        let severity = RegexClassifier::severity();
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 325,
+            use_shell: true,
+            cmd_line: vec![vec![String::from(
+                "printf 'INFO ready\\nslow query\\nWARNING disk\\nerror: boom\\nplain\\nError again\\n'",
+            )]],
+            line_classifier: Some(Arc::new(move |line: &str| {
+                classifier
+                    .classify(line)
+                    .or_else(|| severity.classify(line))
+            })),
+            callback: Some(Arc::new(callback)),
+            ..Default::default()
+        });
+        assert_eq!(
+            *classes.lock().unwrap(),
+            [
+                Some(LineClass::Info),
+                Some(LineClass::Custom(String::from("perf"))),
+                Some(LineClass::Warning),
+                Some(LineClass::Error),
+                None,
+                Some(LineClass::Error),
+            ]
+        );
+        assert_eq!(result.line_classes[&LineClass::Error], 2);
+        assert_eq!(result.line_classes[&LineClass::Warning], 1);
+        assert!(!result.line_classes.contains_key(&LineClass::Debug));
+    }
+}
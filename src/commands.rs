@@ -0,0 +1,99 @@
+//! Platform aware requests of the common commands, resolved to the right executable & flags on Windows, Linux & macOS.
+//! Set the other fields (e.g. the callback) of the returned request before starting it, e.g.
+//! `ProcessRequest { callback, ..commands::ping_host("example.com", 3) }`
+
+use crate::ProcessRequest;
+use std::path::Path;
+
+/// List the names of the entries of the directory, one per line, including the hidden ones
+pub fn list_directory(path: impl AsRef<Path>) -> ProcessRequest {
+    let path = path_string(path.as_ref());
+    if cfg!(windows) {
+        request(["cmd", "/C", "dir", "/B", "/A", &path])
+    } else {
+        request(["ls", "-1", "-A", &path])
+    }
+}
+
+/// Ping the host `count` times
+pub fn ping_host(host: &str, count: u32) -> ProcessRequest {
+    let count = count.to_string();
+    if cfg!(windows) {
+        request(["ping", "-n", &count, host])
+    } else {
+        request(["ping", "-c", &count, host])
+    }
+}
+
+/// Output the last `lines` lines of the file, then keep following it if `follow` is set (across the rotations where
+/// supported) till the process is killed
+pub fn tail_file(path: impl AsRef<Path>, lines: u32, follow: bool) -> ProcessRequest {
+    let path = path_string(path.as_ref());
+    let lines = lines.to_string();
+    if cfg!(windows) {
+        let mut script = format!(
+            "Get-Content -LiteralPath '{}' -Tail {}",
+            path.replace('\'', "''"),
+            lines
+        );
+        if follow {
+            script.push_str(" -Wait");
+        }
+        request([
+            "powershell",
+            "-NoProfile",
+            "-NonInteractive",
+            "-Command",
+            &script,
+        ])
+    } else if follow {
+        request(["tail", "-n", &lines, "-F", &path])
+    } else {
+        request(["tail", "-n", &lines, &path])
+    }
+}
+
+/// Query the status of the system service: `sc query` on Windows, `launchctl list` on macOS & `systemctl status` on
+/// Linux. The exit code is non-zero if the service is not running (Linux) or not found
+pub fn service_status(name: &str) -> ProcessRequest {
+    if cfg!(windows) {
+        request(["sc", "query", name])
+    } else if cfg!(target_os = "macos") {
+        request(["launchctl", "list", name])
+    } else {
+        request(["systemctl", "status", "--no-pager", name])
+    }
+}
+
+fn request<const N: usize>(argv: [&str; N]) -> ProcessRequest {
+    ProcessRequest {
+        cmd_line: vec![argv.iter().map(|arg| arg.to_string()).collect()],
+        ..Default::default()
+    }
+}
+
+fn path_string(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::commands;
+    use crate::run::run_request;
+
+    #[test]
+    pub fn test_commands() {
+        let dir = std::env::temp_dir().join("pes_test_commands");
+        _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("it's.log");
+        std::fs::write(&file, "one\ntwo\nthree\n").unwrap();
+
+        let listing = run_request(commands::list_directory(&dir)).unwrap();
+        assert!(listing.success());
+        assert_eq!(listing.lines, ["it's.log"]);
+        let tail = run_request(commands::tail_file(&file, 2, false)).unwrap();
+        assert_eq!(tail.lines, ["two", "three"]);
+        _ = std::fs::remove_dir_all(&dir);
+    }
+}
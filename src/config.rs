@@ -0,0 +1,275 @@
+use crate::{
+    EnvInheritance, LineTransform, OutputExpectation, OutputLimitAction, OutputThrottle,
+    PipelineStage, PriorityInheritance, ProcessPriority, ProcessRequest, RecordFormat,
+    ResourceLimit, RestrictedToken, RetryPolicy, ShellKind, TraceContext, UserSpec,
+};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Declarative definition of a process (job) in a TOML/YAML config file.
+/// Either `cmd_line` (a single command), `pipeline` (commands piped one to the next) or `stages` (pipeline commands
+/// each with its own shell mode) should be set
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct JobConfig {
+    request_id: u32,
+    cmd_line: Vec<String>,
+    pipeline: Vec<Vec<String>>,
+    stages: Vec<PipelineStage>,
+    use_shell: bool,
+    shell: ShellKind,
+    non_blocking_mode: bool,
+    env: HashMap<String, String>,
+    env_file: Option<PathBuf>,
+    env_inheritance: EnvInheritance,
+    report_env_diff: bool,
+    trace_context: Option<TraceContext>,
+    working_dir: Option<PathBuf>,
+    timeout_secs: Option<f64>,
+    idle_timeout_secs: Option<f64>,
+    read_timeout_secs: Option<f64>,
+    kill_on_read_stall: bool,
+    max_output_bytes: Option<u64>,
+    output_limit_action: OutputLimitAction,
+    output_throttle: Option<OutputThrottle>,
+    tag_output_streams: bool,
+    heartbeat_interval_secs: Option<f64>,
+    line_transforms: Vec<LineTransform>,
+    patterns: Vec<String>,
+    expectations: Vec<OutputExpectation>,
+    kill_on_expectation_failure: bool,
+    fail_on_patterns: Vec<String>,
+    kill_on_fail_pattern: bool,
+    succeed_on_patterns: Vec<String>,
+    golden_file: Option<PathBuf>,
+    record_format: Option<RecordFormat>,
+    start_after_secs: Option<f64>,
+    start_jitter_secs: Option<f64>,
+    resource_sample_interval_secs: Option<f64>,
+    resource_limits: Vec<ResourceLimit>,
+    job_accounting: bool,
+    priority: Option<ProcessPriority>,
+    inherit_priority: Option<PriorityInheritance>,
+    cpu_affinity: Option<Vec<usize>>,
+    run_as: Option<UserSpec>,
+    deny_network: bool,
+    restricted_token: Option<RestrictedToken>,
+    success_exit_codes: Option<Vec<i32>>,
+    unchecked_exit_status: bool,
+    exit_categories: HashMap<ExitCodeKey, String>,
+    retry: Option<RetryPolicy>,
+    tag: Option<String>,
+}
+
+/// Config file with multiple jobs
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JobsConfig {
+    jobs: Vec<JobConfig>,
+}
+
+impl JobConfig {
+    fn into_request(self) -> io::Result<ProcessRequest> {
+        let cmd_line = match (
+            self.cmd_line.is_empty(),
+            self.pipeline.is_empty(),
+            self.stages.is_empty(),
+        ) {
+            (false, true, true) => vec![self.cmd_line],
+            (true, false, true) => self.pipeline,
+            (true, true, false) => vec![],
+            _ => {
+                return Err(invalid_data(format!(
+                    "Job {} should have either cmd_line, pipeline or stages",
+                    self.request_id
+                )))
+            }
+        };
+        Ok(ProcessRequest {
+            request_id: self.request_id,
+            use_shell: self.use_shell,
+            shell: self.shell,
+            non_blocking_mode: self.non_blocking_mode,
+            cmd_line,
+            stages: self.stages,
+            env: self.env,
+            env_file: self.env_file,
+            env_inheritance: self.env_inheritance,
+            report_env_diff: self.report_env_diff,
+            trace_context: self.trace_context,
+            working_dir: self.working_dir,
+            timeout: secs_to_duration(self.timeout_secs)?,
+            idle_timeout: secs_to_duration(self.idle_timeout_secs)?,
+            read_timeout: secs_to_duration(self.read_timeout_secs)?,
+            kill_on_read_stall: self.kill_on_read_stall,
+            max_output_bytes: self.max_output_bytes,
+            output_limit_action: self.output_limit_action,
+            output_throttle: self.output_throttle,
+            tag_output_streams: self.tag_output_streams,
+            heartbeat_interval: secs_to_duration(self.heartbeat_interval_secs)?,
+            line_transforms: self.line_transforms,
+            patterns: self.patterns,
+            expectations: self.expectations,
+            kill_on_expectation_failure: self.kill_on_expectation_failure,
+            fail_on_patterns: self.fail_on_patterns,
+            kill_on_fail_pattern: self.kill_on_fail_pattern,
+            succeed_on_patterns: self.succeed_on_patterns,
+            golden_file: self.golden_file,
+            record_format: self.record_format,
+            start_after: secs_to_duration(self.start_after_secs)?,
+            start_jitter: secs_to_duration(self.start_jitter_secs)?,
+            resource_sample_interval: secs_to_duration(self.resource_sample_interval_secs)?,
+            resource_limits: self.resource_limits,
+            job_accounting: self.job_accounting,
+            priority: self.priority,
+            inherit_priority: self.inherit_priority,
+            cpu_affinity: self.cpu_affinity,
+            run_as: self.run_as,
+            deny_network: self.deny_network,
+            restricted_token: self.restricted_token,
+            success_exit_codes: self.success_exit_codes,
+            unchecked_exit_status: self.unchecked_exit_status,
+            exit_categories: exit_categories(self.exit_categories)?,
+            retry: self.retry,
+            tag: self.tag,
+            ..Default::default()
+        })
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// exit code as a map key, a TOML key is always a string
+#[derive(Debug, Deserialize, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+enum ExitCodeKey {
+    Code(i32),
+    Text(String),
+}
+
+fn exit_categories(categories: HashMap<ExitCodeKey, String>) -> io::Result<BTreeMap<i32, String>> {
+    categories
+        .into_iter()
+        .map(|(key, category)| match key {
+            ExitCodeKey::Code(code) => Ok((code, category)),
+            ExitCodeKey::Text(text) => text
+                .parse()
+                .map(|code| (code, category))
+                .map_err(|_| invalid_data(format!("Invalid exit code {:?}", text))),
+        })
+        .collect()
+}
+
+fn secs_to_duration(secs: Option<f64>) -> io::Result<Option<Duration>> {
+    secs.map(|secs| {
+        Duration::try_from_secs_f64(secs).map_err(|error| invalid_data(error.to_string()))
+    })
+    .transpose()
+}
+
+/// parse the config file as per its extension (.toml, .yaml or .yml)
+fn parse_config_file<T: for<'de> Deserialize<'de>>(path: &Path) -> io::Result<T> {
+    let content = std::fs::read_to_string(path)?;
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("toml") => toml::from_str(&content).map_err(|error| invalid_data(error.to_string())),
+        Some("yaml" | "yml") => {
+            serde_yaml::from_str(&content).map_err(|error| invalid_data(error.to_string()))
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Unsupported config file {:?}, use .toml, .yaml or .yml",
+                path
+            ),
+        )),
+    }
+}
+
+impl ProcessRequest {
+    /// Load a single job (process definition) from a TOML/YAML config file, the format is chosen by the file extension.
+    /// The callback is not part of the config, set it on the returned request
+    pub fn from_config_file(path: impl AsRef<Path>) -> io::Result<ProcessRequest> {
+        parse_config_file::<JobConfig>(path.as_ref())?.into_request()
+    }
+
+    /// Load all the jobs listed under `jobs` from a TOML/YAML config file, the format is chosen by the file extension
+    pub fn from_config_file_jobs(path: impl AsRef<Path>) -> io::Result<Vec<ProcessRequest>> {
+        parse_config_file::<JobsConfig>(path.as_ref())?
+            .jobs
+            .into_iter()
+            .map(JobConfig::into_request)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{PipelineStage, ProcessRequest, ShellKind};
+    use std::time::Duration;
+
+    #[test]
+    pub fn test_config_files() {
+        let dir = std::env::temp_dir();
+        let toml_path = dir.join("pes_test_config_job.toml");
+        std::fs::write(
+            &toml_path,
+            r#"
+request_id = 371
+use_shell = true
+shell = "Bash"
+cmd_line = ["echo $GREETING"]
+timeout_secs = 1.5
+env = { GREETING = "hello" }
+exit_categories = { 2 = "usage error" }
+"#,
+        )
+        .unwrap();
+        let request = ProcessRequest::from_config_file(&toml_path).unwrap();
+        assert_eq!(request.request_id, 371);
+        assert_eq!(request.shell, ShellKind::Bash);
+        assert_eq!(request.timeout, Some(Duration::from_millis(1500)));
+        assert_eq!(request.env["GREETING"], "hello");
+        assert_eq!(request.exit_categories[&2], "usage error");
+
+        let yaml_path = dir.join("pes_test_config_jobs.yaml");
+        std::fs::write(
+            &yaml_path,
+            r#"
+jobs:
+  - request_id: 372
+    pipeline: [["echo", "a"], ["cat"]]
+    working_dir: /tmp
+  - request_id: 373
+"#,
+        )
+        .unwrap();
+        assert!(ProcessRequest::from_config_file_jobs(&yaml_path).is_err());
+        std::fs::write(
+            &yaml_path,
+            r#"
+jobs:
+  - request_id: 372
+    pipeline: [["echo", "a"], ["cat"]]
+    priority: BelowNormal
+    exit_categories: { 137: OOM killed }
+  - request_id: 374
+    stages:
+      - argv: ["echo", "a"]
+      - argv: ["tr a-z A-Z"]
+        shell: true
+"#,
+        )
+        .unwrap();
+        let requests = ProcessRequest::from_config_file_jobs(&yaml_path).unwrap();
+        assert_eq!(requests[0].cmd_line.len(), 2);
+        assert_eq!(requests[0].exit_categories[&137], "OOM killed");
+        assert_eq!(requests[1].stages[1], PipelineStage::shell("tr a-z A-Z"));
+        _ = std::fs::remove_file(toml_path);
+        _ = std::fs::remove_file(yaml_path);
+    }
+}
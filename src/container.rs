@@ -0,0 +1,228 @@
+use crate::shell_script;
+use crate::ProcessRequest;
+use std::ffi::OsString;
+use std::io;
+use std::path::PathBuf;
+
+/// Exit code of docker/podman when the runtime itself fails (e.g. unknown image or container)
+const RUNTIME_ERROR_EXIT_CODE: i32 = 125;
+
+/// Container runtime CLI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ContainerRuntime {
+    /// `docker`
+    #[default]
+    Docker,
+    /// `podman`
+    Podman,
+}
+
+/// Where the command runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ContainerSource {
+    /// `run --rm` a new container of this image
+    Image(String),
+    /// `exec` in this running container (name or id)
+    Running(String),
+}
+
+impl Default for ContainerSource {
+    fn default() -> Self {
+        ContainerSource::Image(String::new())
+    }
+}
+
+/// Bind mount of a host path into a new container
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ContainerMount {
+    /// Path on the host
+    pub host_path: PathBuf,
+    /// Path in the container
+    pub container_path: PathBuf,
+    /// Mount as read only
+    pub read_only: bool,
+}
+
+/// Container to run the request in using the docker/podman CLI, see [`ProcessRequest::container`].
+/// The env & working directory of the request are applied in the container, a pipeline or a shell command line runs
+/// in the container's `sh`. The output of the container is streamed as the [`crate::ProcessEvent::IOData`] events
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ContainerTarget {
+    /// Runtime CLI to use
+    pub runtime: ContainerRuntime,
+    /// New container of an image or a running container
+    pub source: ContainerSource,
+    /// Bind mounts, used only for a new container
+    pub mounts: Vec<ContainerMount>,
+}
+
+/// argv of the container runtime CLI to run the request in the container. An image or container name starting with
+/// `-` is rejected, as it would be taken as an option & `run` doesn't accept `--` before the image
+pub(crate) fn container_argv(
+    container: &ContainerTarget,
+    request: &ProcessRequest,
+) -> io::Result<Vec<OsString>> {
+    let name = match &container.source {
+        ContainerSource::Image(image) => image,
+        ContainerSource::Running(name) => name,
+    };
+    if name.starts_with('-') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid image or container name: {:?}", name),
+        ));
+    }
+    let mut argv: Vec<OsString> = vec![match container.runtime {
+        ContainerRuntime::Docker => "docker".into(),
+        ContainerRuntime::Podman => "podman".into(),
+    }];
+    match &container.source {
+        ContainerSource::Image(_) => {
+            argv.extend(["run".into(), "--rm".into(), "-i".into()]);
+            for mount in &container.mounts {
+                let mut volume = mount.host_path.clone().into_os_string();
+                volume.push(":");
+                volume.push(&mount.container_path);
+                if mount.read_only {
+                    volume.push(":ro");
+                }
+                argv.push("-v".into());
+                argv.push(volume);
+            }
+        }
+        ContainerSource::Running(_) => argv.extend(["exec".into(), "-i".into()]),
+    }
+    let mut env: Vec<(&String, &String)> = request.env.iter().collect();
+    env.sort();
+    for (name, value) in env {
+        argv.push("-e".into());
+        argv.push(format!("{}={}", name, value).into());
+    }
+    if let Some(working_dir) = request.working_dir.as_ref() {
+        argv.push("-w".into());
+        argv.push(working_dir.into());
+    }
+    argv.push(name.into());
+    match request.pipeline_stages().as_slice() {
+        [stage] if !stage.shell => argv.extend(stage.argv.iter().map(OsString::from)),
+        _ => argv.extend([
+            "sh".into(),
+            "-c".into(),
+            shell_script::pipeline(request).into(),
+        ]),
+    }
+    Ok(argv)
+}
+
+/// Detail of a non-zero exit code of the container runtime CLI, None for an exit code of the command itself
+pub(crate) fn exit_code_detail(exit_code: i32) -> Option<String> {
+    match exit_code {
+        RUNTIME_ERROR_EXIT_CODE => {
+            Some(String::from("container runtime failed to run the command"))
+        }
+        126 => Some(String::from("command cannot be invoked in the container")),
+        127 => Some(String::from("command not found in the container")),
+        129..=192 => Some(format!(
+            "command in the container terminated by signal {}",
+            exit_code - 128
+        )),
+        _ => None,
+    }
+}
+
+/// true if the command never ran as the container runtime itself failed
+pub(crate) fn is_runtime_error(exit_code: Option<i32>) -> bool {
+    exit_code == Some(RUNTIME_ERROR_EXIT_CODE)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::container::{container_argv, exit_code_detail};
+    use crate::{
+        ContainerMount, ContainerRuntime, ContainerSource, ContainerTarget, ProcessRequest,
+    };
+    use std::io;
+
+    #[test]
+    pub fn test_container_argv() {
+        let request = ProcessRequest {
+            cmd_line: vec![vec![String::from("ls"), String::from("-l")]],
+            env: [(String::from("LANG"), String::from("C"))].into(),
+            working_dir: Some("/data".into()),
+            ..Default::default()
+        };
+        let container = ContainerTarget {
+            source: ContainerSource::Image(String::from("alpine:3")),
+            mounts: vec![ContainerMount {
+                host_path: "/srv/data".into(),
+                container_path: "/data".into(),
+                read_only: true,
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            container_argv(&container, &request).unwrap(),
+            [
+                "docker",
+                "run",
+                "--rm",
+                "-i",
+                "-v",
+                "/srv/data:/data:ro",
+                "-e",
+                "LANG=C",
+                "-w",
+                "/data",
+                "alpine:3",
+                "ls",
+                "-l"
+            ]
+        );
+
+        let request = ProcessRequest {
+            cmd_line: vec![
+                vec![String::from("cat"), String::from("a b")],
+                vec![String::from("wc"), String::from("-l")],
+            ],
+            ..Default::default()
+        };
+        let container = ContainerTarget {
+            runtime: ContainerRuntime::Podman,
+            source: ContainerSource::Running(String::from("web")),
+            ..Default::default()
+        };
+        assert_eq!(
+            container_argv(&container, &request).unwrap(),
+            [
+                "podman",
+                "exec",
+                "-i",
+                "web",
+                "sh",
+                "-c",
+                "cat 'a b' | wc -l"
+            ]
+        );
+        for source in [
+            ContainerSource::Image(String::from("--privileged")),
+            ContainerSource::Running(String::from("-u=root")),
+        ] {
+            let container = ContainerTarget {
+                source,
+                ..Default::default()
+            };
+            assert_eq!(
+                container_argv(&container, &request).unwrap_err().kind(),
+                io::ErrorKind::InvalidInput
+            );
+        }
+        assert!(exit_code_detail(137).unwrap().ends_with("signal 9"));
+        assert_eq!(exit_code_detail(3), None);
+    }
+}
@@ -0,0 +1,179 @@
+use std::io;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Parsed cron expression with 5 fields: `minute hour day-of-month month day-of-week`, evaluated in UTC.
+/// Each field supports `*`, numbers, ranges `a-b`, steps `*/n` & `a-b/n` and lists `a,b,c`.
+/// Day of week is 0-7 where both 0 & 7 are Sunday.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    expression: String,
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days_of_month: Vec<bool>,
+    months: Vec<bool>,
+    days_of_week: Vec<bool>,
+    /// day of month field is not `*`
+    day_of_month_restricted: bool,
+    /// day of week field is not `*`
+    day_of_week_restricted: bool,
+}
+
+impl CronSchedule {
+    /// Parse the cron expression e.g. `*/5 * * * *` for every 5 minutes
+    pub fn parse(expression: &str) -> io::Result<Self> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(invalid_expression(expression, "expected 5 fields"));
+        }
+        let mut days_of_week =
+            parse_field(fields[4], 0, 7).map_err(|error| invalid_expression(expression, &error))?;
+        // 7 is an alias of Sunday
+        if days_of_week[7] {
+            days_of_week[0] = true;
+        }
+        days_of_week.truncate(7);
+        Ok(Self {
+            expression: expression.to_string(),
+            minutes: parse_field(fields[0], 0, 59)
+                .map_err(|error| invalid_expression(expression, &error))?,
+            hours: parse_field(fields[1], 0, 23)
+                .map_err(|error| invalid_expression(expression, &error))?,
+            days_of_month: parse_field(fields[2], 1, 31)
+                .map_err(|error| invalid_expression(expression, &error))?,
+            months: parse_field(fields[3], 1, 12)
+                .map_err(|error| invalid_expression(expression, &error))?,
+            days_of_week,
+            day_of_month_restricted: fields[2] != "*",
+            day_of_week_restricted: fields[4] != "*",
+        })
+    }
+
+    /// The original expression
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// Next matching time strictly after the given time, None if nothing matches within 5 years
+    pub fn next_after(&self, time: SystemTime) -> Option<SystemTime> {
+        let seconds = time.duration_since(UNIX_EPOCH).ok()?.as_secs();
+        let start_minute = seconds / 60 + 1;
+        let start_day = start_minute / (24 * 60);
+        for day in start_day..start_day + 366 * 5 {
+            if !self.matches_day(day) {
+                continue;
+            }
+            for hour in (0..24).filter(|hour| self.hours[*hour]) {
+                for minute in (0..60).filter(|minute| self.minutes[*minute]) {
+                    let candidate = (day * 24 + hour as u64) * 60 + minute as u64;
+                    if candidate >= start_minute {
+                        return Some(UNIX_EPOCH + Duration::from_secs(candidate * 60));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// check the month, day of month & day of week of the day since epoch
+    fn matches_day(&self, day: u64) -> bool {
+        let (_, month, day_of_month) = civil_from_days(day as i64);
+        // 1970-01-01 was a Thursday
+        let day_of_week = ((day + 4) % 7) as usize;
+        if !self.months[month as usize] {
+            return false;
+        }
+        let day_of_month_match = self.days_of_month[day_of_month as usize];
+        let day_of_week_match = self.days_of_week[day_of_week];
+        // as per cron, when both are restricted either of them should match
+        match (self.day_of_month_restricted, self.day_of_week_restricted) {
+            (true, true) => day_of_month_match || day_of_week_match,
+            _ => day_of_month_match && day_of_week_match,
+        }
+    }
+}
+
+/// parse a single field into a lookup table indexed by the value
+fn parse_field(field: &str, min: usize, max: usize) -> Result<Vec<bool>, String> {
+    let mut values = vec![false; max + 1];
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<usize>()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or_else(|| format!("invalid step in '{}'", part))?,
+            ),
+            None => (part, 1),
+        };
+        let parse_value = |value: &str| -> Result<usize, String> {
+            value
+                .parse::<usize>()
+                .ok()
+                .filter(|value| (min..=max).contains(value))
+                .ok_or_else(|| format!("'{}' is not within {}-{}", value, min, max))
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((start, end)) => (parse_value(start)?, parse_value(end)?),
+                // "a/n" means from a till the max
+                None if step > 1 => (parse_value(range)?, max),
+                None => (parse_value(range)?, parse_value(range)?),
+            },
+        };
+        if start > end {
+            return Err(format!("invalid range '{}'", range));
+        }
+        for value in (start..=end).step_by(step) {
+            values[value] = true;
+        }
+    }
+    Ok(values)
+}
+
+fn invalid_expression(expression: &str, error: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("Invalid cron expression '{}': {}", expression, error),
+    )
+}
+
+/// (year, month, day) from the days since epoch, as per Howard Hinnant's algorithm
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::CronSchedule;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    pub fn test_cron_next_after() {
+        // 2024-01-01T00:00:30Z, a Monday
+        let time = UNIX_EPOCH + Duration::from_secs(1704067230);
+        let every_5_minutes = CronSchedule::parse("*/5 * * * *").unwrap();
+        assert_eq!(
+            every_5_minutes.next_after(time),
+            Some(UNIX_EPOCH + Duration::from_secs(1704067200 + 5 * 60))
+        );
+        // 09:30 on Fridays => 2024-01-05T09:30:00Z
+        let friday = CronSchedule::parse("30 9 * * 5").unwrap();
+        assert_eq!(
+            friday.next_after(time),
+            Some(UNIX_EPOCH + Duration::from_secs(1704447000))
+        );
+        assert!(CronSchedule::parse("61 * * * *").is_err());
+        assert!(CronSchedule::parse("* * *").is_err());
+    }
+}
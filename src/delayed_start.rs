@@ -0,0 +1,117 @@
+use crate::latch::Latch;
+use crate::{check_and_trigger_callback, ProcessData, ProcessEvent, ProcessRequest};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// State of a delayed start, shared between the caller's handle and the scheduled thread
+#[derive(Debug, Default)]
+pub(crate) struct StartGate {
+    /// (started, cancelled)
+    state: Mutex<(bool, bool)>,
+    condvar: Condvar,
+}
+
+impl StartGate {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// cancel the start, returns false if the process is already started
+    pub(crate) fn cancel(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.0 {
+            return false;
+        }
+        state.1 = true;
+        self.condvar.notify_all();
+        true
+    }
+
+    /// wait for the delay and mark as started, returns false if cancelled in between
+    fn wait_and_start(&self, delay: Duration) -> bool {
+        let state = self.state.lock().unwrap();
+        let (mut state, _) = self
+            .condvar
+            .wait_timeout_while(state, delay, |state| !state.1)
+            .unwrap();
+        if state.1 {
+            return false;
+        }
+        state.0 = true;
+        true
+    }
+}
+
+/// Delay before starting the request, the later of `start_after` & `start_at` is used
+pub(crate) fn start_delay(request: &ProcessRequest) -> Option<Duration> {
+    let after = request.start_after;
+    let at = request.start_at.map(|start_at| {
+        start_at
+            .duration_since(SystemTime::now())
+            .unwrap_or_default()
+    });
+    match (after, at) {
+        (None, None) => None,
+        (after, at) => Some(after.unwrap_or_default().max(at.unwrap_or_default())),
+    }
+}
+
+/// trigger the scheduled event for the request
+pub(crate) fn notify_scheduled(request: &Arc<ProcessRequest>, delay: Duration) {
+    let mut process_data = ProcessData::new();
+    process_data.request = Some(Arc::clone(request));
+    process_data
+        .line
+        .push_str(format!("Starting in {} ms", delay.as_millis()).as_str());
+    check_and_trigger_callback(request, &ProcessEvent::Scheduled, &process_data);
+}
+
+/// wait for the scheduled start, returns false & triggers the cancelled event if cancelled before start
+pub(crate) fn wait_for_start(
+    request: &Arc<ProcessRequest>,
+    delay: Duration,
+    gate: Option<&StartGate>,
+) -> bool {
+    match gate {
+        Some(gate) if !gate.wait_and_start(delay) => {
+            let mut process_data = ProcessData::new();
+            process_data.request = Some(Arc::clone(request));
+            check_and_trigger_callback(request, &ProcessEvent::ScheduleCancelled, &process_data);
+            false
+        }
+        Some(_) => true,
+        None => {
+            thread::sleep(delay);
+            true
+        }
+    }
+}
+
+/// random delay up to the start jitter of the request, None if it has no jitter
+pub(crate) fn jitter_delay(request: &ProcessRequest) -> Option<Duration> {
+    let max_nanos = request.start_jitter?.as_nanos().min(u64::MAX as u128) as u64;
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(request.request_id);
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    Some(Duration::from_nanos(
+        hasher.finish() % max_nanos.saturating_add(1),
+    ))
+}
+
+/// wait for the start jitter of the request, returns the delay. The wait ends early once the stop latch is set
+pub(crate) fn wait_for_jitter(request: &ProcessRequest, stop: Option<&Latch>) -> Option<Duration> {
+    let delay = jitter_delay(request)?;
+    match stop {
+        Some(stop) => _ = stop.wait_timeout(delay),
+        None => thread::sleep(delay),
+    }
+    Some(delay)
+}
@@ -0,0 +1,75 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// threads of the executions running now
+static ACTIVE_THREADS: AtomicUsize = AtomicUsize::new(0);
+/// child processes still running after their execution completed
+static LEAKED_CHILDREN: Mutex<Vec<u32>> = Mutex::new(vec![]);
+/// panic once an execution leaks a child process
+static LEAK_CHECK: AtomicBool = AtomicBool::new(false);
+
+/// Counts a thread of an execution as active till it's dropped, created first thing in the thread
+pub(crate) struct ThreadGuard(());
+
+impl Drop for ThreadGuard {
+    fn drop(&mut self) {
+        ACTIVE_THREADS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// count the current thread as active till the guard is dropped
+pub(crate) fn track_thread() -> ThreadGuard {
+    ACTIVE_THREADS.fetch_add(1, Ordering::SeqCst);
+    ThreadGuard(())
+}
+
+/// Number of the threads running for the executions of this library: the workers of the non-blocking mode, the
+/// watchdogs & samplers (joined before the execution completes) & the output readers (detached, they end with the
+/// output of the process). Once all the executions are completed it drops to 0
+pub fn active_threads() -> usize {
+    ACTIVE_THREADS.load(Ordering::SeqCst)
+}
+
+/// Processes spawned by this library still running (or not reaped) after their execution completed, e.g. kept alive
+/// by a leaked handle. A process which has ended meanwhile is removed. The grandchildren are not tracked. Always
+/// empty on Windows
+pub fn leaked_children() -> Vec<u32> {
+    let mut leaked = LEAKED_CHILDREN.lock().unwrap();
+    leaked.retain(|pid| is_running(*pid));
+    leaked.clone()
+}
+
+/// Check the child processes of every execution once it completes & panic if any of them is still running, to catch
+/// the leaks in the tests. Off by default
+pub fn set_leak_check(enabled: bool) {
+    LEAK_CHECK.store(enabled, Ordering::SeqCst);
+}
+
+/// check the child processes of the completed execution are gone, its reader has been dropped
+pub(crate) fn execution_completed(pids: &[u32]) {
+    let running: Vec<u32> = pids
+        .iter()
+        .copied()
+        .filter(|pid| is_running(*pid))
+        .collect();
+    if running.is_empty() {
+        return;
+    }
+    LEAKED_CHILDREN.lock().unwrap().extend(&running);
+    if LEAK_CHECK.load(Ordering::SeqCst) {
+        panic!("Execution leaked the child processes {:?}", running);
+    }
+}
+
+/// the process exists, including a zombie not yet reaped
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// no check of the processes on Windows
+#[cfg(not(unix))]
+fn is_running(_pid: u32) -> bool {
+    false
+}
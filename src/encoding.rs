@@ -0,0 +1,150 @@
+use std::io::{self, Read};
+
+/// Size of the chunk read from the process output
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Code page 437, the characters of the bytes 0x80..=0xFF
+const CP437: [char; 128] = [
+    '\u{c7}', '\u{fc}', '\u{e9}', '\u{e2}', '\u{e4}', '\u{e0}', '\u{e5}', '\u{e7}', '\u{ea}',
+    '\u{eb}', '\u{e8}', '\u{ef}', '\u{ee}', '\u{ec}', '\u{c4}', '\u{c5}', '\u{c9}', '\u{e6}',
+    '\u{c6}', '\u{f4}', '\u{f6}', '\u{f2}', '\u{fb}', '\u{f9}', '\u{ff}', '\u{d6}', '\u{dc}',
+    '\u{a2}', '\u{a3}', '\u{a5}', '\u{20a7}', '\u{192}', '\u{e1}', '\u{ed}', '\u{f3}', '\u{fa}',
+    '\u{f1}', '\u{d1}', '\u{aa}', '\u{ba}', '\u{bf}', '\u{2310}', '\u{ac}', '\u{bd}', '\u{bc}',
+    '\u{a1}', '\u{ab}', '\u{bb}', '\u{2591}', '\u{2592}', '\u{2593}', '\u{2502}', '\u{2524}',
+    '\u{2561}', '\u{2562}', '\u{2556}', '\u{2555}', '\u{2563}', '\u{2551}', '\u{2557}', '\u{255d}',
+    '\u{255c}', '\u{255b}', '\u{2510}', '\u{2514}', '\u{2534}', '\u{252c}', '\u{251c}', '\u{2500}',
+    '\u{253c}', '\u{255e}', '\u{255f}', '\u{255a}', '\u{2554}', '\u{2569}', '\u{2566}', '\u{2560}',
+    '\u{2550}', '\u{256c}', '\u{2567}', '\u{2568}', '\u{2564}', '\u{2565}', '\u{2559}', '\u{2558}',
+    '\u{2552}', '\u{2553}', '\u{256b}', '\u{256a}', '\u{2518}', '\u{250c}', '\u{2588}', '\u{2584}',
+    '\u{258c}', '\u{2590}', '\u{2580}', '\u{3b1}', '\u{df}', '\u{393}', '\u{3c0}', '\u{3a3}',
+    '\u{3c3}', '\u{b5}', '\u{3c4}', '\u{3a6}', '\u{398}', '\u{3a9}', '\u{3b4}', '\u{221e}',
+    '\u{3c6}', '\u{3b5}', '\u{2229}', '\u{2261}', '\u{b1}', '\u{2265}', '\u{2264}', '\u{2320}',
+    '\u{2321}', '\u{f7}', '\u{2248}', '\u{b0}', '\u{2219}', '\u{b7}', '\u{221a}', '\u{207f}',
+    '\u{b2}', '\u{25a0}', '\u{a0}',
+];
+
+/// Code page 850, the characters of the bytes 0x80..=0xFF
+const CP850: [char; 128] = [
+    '\u{c7}', '\u{fc}', '\u{e9}', '\u{e2}', '\u{e4}', '\u{e0}', '\u{e5}', '\u{e7}', '\u{ea}',
+    '\u{eb}', '\u{e8}', '\u{ef}', '\u{ee}', '\u{ec}', '\u{c4}', '\u{c5}', '\u{c9}', '\u{e6}',
+    '\u{c6}', '\u{f4}', '\u{f6}', '\u{f2}', '\u{fb}', '\u{f9}', '\u{ff}', '\u{d6}', '\u{dc}',
+    '\u{f8}', '\u{a3}', '\u{d8}', '\u{d7}', '\u{192}', '\u{e1}', '\u{ed}', '\u{f3}', '\u{fa}',
+    '\u{f1}', '\u{d1}', '\u{aa}', '\u{ba}', '\u{bf}', '\u{ae}', '\u{ac}', '\u{bd}', '\u{bc}',
+    '\u{a1}', '\u{ab}', '\u{bb}', '\u{2591}', '\u{2592}', '\u{2593}', '\u{2502}', '\u{2524}',
+    '\u{c1}', '\u{c2}', '\u{c0}', '\u{a9}', '\u{2563}', '\u{2551}', '\u{2557}', '\u{255d}',
+    '\u{a2}', '\u{a5}', '\u{2510}', '\u{2514}', '\u{2534}', '\u{252c}', '\u{251c}', '\u{2500}',
+    '\u{253c}', '\u{e3}', '\u{c3}', '\u{255a}', '\u{2554}', '\u{2569}', '\u{2566}', '\u{2560}',
+    '\u{2550}', '\u{256c}', '\u{a4}', '\u{f0}', '\u{d0}', '\u{ca}', '\u{cb}', '\u{c8}', '\u{131}',
+    '\u{cd}', '\u{ce}', '\u{cf}', '\u{2518}', '\u{250c}', '\u{2588}', '\u{2584}', '\u{a6}',
+    '\u{cc}', '\u{2580}', '\u{d3}', '\u{df}', '\u{d4}', '\u{d2}', '\u{f5}', '\u{d5}', '\u{b5}',
+    '\u{fe}', '\u{de}', '\u{da}', '\u{db}', '\u{d9}', '\u{fd}', '\u{dd}', '\u{af}', '\u{b4}',
+    '\u{ad}', '\u{b1}', '\u{2017}', '\u{be}', '\u{b6}', '\u{a7}', '\u{f7}', '\u{b8}', '\u{b0}',
+    '\u{a8}', '\u{b7}', '\u{b9}', '\u{b3}', '\u{b2}', '\u{25a0}', '\u{a0}',
+];
+
+/// Decoder of a legacy encoding to UTF-8
+pub(crate) enum Transcoder {
+    /// DOS code page (not supported by encoding_rs), ASCII is as is
+    CodePage(&'static [char; 128]),
+    /// Any encoding of the Encoding Standard
+    Decoder(encoding_rs::Decoder),
+}
+
+impl Transcoder {
+    /// Transcoder of the encoding label, e.g. `cp850`, `cp437`, `ibm866`, `windows-1252` or `shift_jis`
+    pub(crate) fn for_label(label: &str) -> io::Result<Self> {
+        match label.trim().to_ascii_lowercase().as_str() {
+            "cp437" | "ibm437" | "437" => Ok(Transcoder::CodePage(&CP437)),
+            "cp850" | "ibm850" | "850" => Ok(Transcoder::CodePage(&CP850)),
+            label => encoding_rs::Encoding::for_label(label.as_bytes())
+                .map(|encoding| Transcoder::Decoder(encoding.new_decoder_without_bom_handling()))
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("Unknown output encoding {}", label),
+                    )
+                }),
+        }
+    }
+
+    /// decode the bytes, the last call flushes any incomplete sequence
+    fn decode(&mut self, bytes: &[u8], last: bool, output: &mut String) {
+        match self {
+            Transcoder::CodePage(table) => output.extend(bytes.iter().map(|byte| match byte {
+                0x00..=0x7F => *byte as char,
+                _ => table[(*byte - 0x80) as usize],
+            })),
+            Transcoder::Decoder(decoder) => {
+                if let Some(length) = decoder.max_utf8_buffer_length(bytes.len()) {
+                    output.reserve(length);
+                }
+                _ = decoder.decode_to_string(bytes, output, last);
+            }
+        }
+    }
+}
+
+/// Reader transcoding the output of the process to UTF-8, before it's split into the lines
+pub(crate) struct TranscodingReader<R> {
+    inner: R,
+    transcoder: Transcoder,
+    decoded: String,
+    position: usize,
+    done: bool,
+}
+
+impl<R: Read> TranscodingReader<R> {
+    pub(crate) fn new(inner: R, transcoder: Transcoder) -> Self {
+        Self {
+            inner,
+            transcoder,
+            decoded: String::new(),
+            position: 0,
+            done: false,
+        }
+    }
+}
+
+impl<R: Read> Read for TranscodingReader<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.decoded.len() && !self.done {
+            self.decoded.clear();
+            self.position = 0;
+            let mut chunk = [0; CHUNK_SIZE];
+            let read = self.inner.read(&mut chunk)?;
+            self.done = read == 0;
+            self.transcoder
+                .decode(&chunk[..read], self.done, &mut self.decoded);
+        }
+        let available = &self.decoded.as_bytes()[self.position..];
+        let length = available.len().min(buffer.len());
+        buffer[..length].copy_from_slice(&available[..length]);
+        self.position += length;
+        Ok(length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::encoding::{Transcoder, TranscodingReader};
+    use std::io::Read;
+
+    #[test]
+    pub fn test_transcoding() {
+        let decode = |label: &str, bytes: &[u8]| {
+            let mut decoded = String::new();
+            TranscodingReader::new(bytes, Transcoder::for_label(label).unwrap())
+                .read_to_string(&mut decoded)
+                .unwrap();
+            decoded
+        };
+        assert_eq!(
+            decode("cp850", b"Datei \x81ber \x9d\r\n"),
+            "Datei über Ø\r\n"
+        );
+        assert_eq!(decode("CP437", b"\xc9\xcd\xbb 50\xf8"), "╔═╗ 50°");
+        assert_eq!(decode("windows-1252", b"caf\xe9 \x80"), "café €");
+        assert_eq!(decode("ibm866", b"\x8f\xe0\xa8\xa2\xa5\xe2"), "Привет");
+        assert!(Transcoder::for_label("klingon").is_err());
+    }
+}
@@ -0,0 +1,335 @@
+use crate::ProcessRequest;
+use duct::Expression;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsString;
+use std::io;
+use std::path::Path;
+
+/// Placeholder of a secret value in the [`EnvDiff`]
+const MASKED: &str = "***";
+
+/// Parts of the variable names which mark a secret value (case insensitive)
+const SECRET_NAME_PARTS: [&str; 8] = [
+    "SECRET",
+    "PASSWORD",
+    "PASSWD",
+    "TOKEN",
+    "KEY",
+    "CREDENTIAL",
+    "AUTH",
+    "PRIVATE",
+];
+
+/// How the environment of the process differs from the environment of the parent, see
+/// [`ProcessRequest::report_env_diff`]. The values of the variables named like a secret (e.g. `DB_PASSWORD`,
+/// `API_TOKEN`, `AWS_SECRET_ACCESS_KEY`) are masked as `***`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnvDiff {
+    /// Variables not set in the parent, with their values
+    pub added: BTreeMap<String, String>,
+    /// Variables set in the parent with a different value, with their new values
+    pub overridden: BTreeMap<String, String>,
+    /// Variables of the parent not inherited
+    pub removed: Vec<String>,
+}
+
+impl EnvDiff {
+    /// The environment is the same as the parent's
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.overridden.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Which environment variables of the parent the process inherits, see [`ProcessRequest::env_inheritance`]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EnvInheritance {
+    /// Inherit all the variables
+    #[default]
+    All,
+    /// Start with an empty environment, only the [`ProcessRequest::env`] is set.
+    /// On Windows most programs need at least `SystemRoot`, consider an allowlist instead
+    Clean,
+    /// Inherit only these variables (case insensitive on Windows) if set in the parent
+    Allowlist(Vec<String>),
+}
+
+impl EnvInheritance {
+    fn inherits(&self, name: &OsString) -> bool {
+        match self {
+            EnvInheritance::All => true,
+            EnvInheritance::Clean => false,
+            EnvInheritance::Allowlist(names) => names.iter().any(|allowed| {
+                if cfg!(windows) {
+                    name.eq_ignore_ascii_case(allowed)
+                } else {
+                    name == allowed.as_str()
+                }
+            }),
+        }
+    }
+}
+
+/// Apply the environment variables of the request (the env file, overridden by the explicit ones) on top of the
+/// inherited ones
+pub(crate) fn apply_env(
+    mut expression: Expression,
+    request: &ProcessRequest,
+) -> io::Result<Expression> {
+    let request_env = request_env(request)?;
+    if request.env_inheritance == EnvInheritance::All {
+        for (name, value) in &request_env {
+            expression = expression.env(name, value);
+        }
+        return Ok(expression);
+    }
+    let mut env: HashMap<OsString, OsString> = std::env::vars_os()
+        .filter(|(name, _)| request.env_inheritance.inherits(name))
+        .collect();
+    env.extend(
+        request_env
+            .into_iter()
+            .map(|(name, value)| (name.into(), value.into())),
+    );
+    Ok(expression.full_env(env))
+}
+
+/// variables set by the request, the env file overridden by the explicit ones
+fn request_env(request: &ProcessRequest) -> io::Result<HashMap<String, String>> {
+    let mut request_env = match request.env_file.as_ref() {
+        Some(env_file) => read_env_file(env_file)?,
+        None => HashMap::new(),
+    };
+    request_env.extend(request.env.clone());
+    Ok(request_env)
+}
+
+/// difference of the environment of the process from the environment of this process, with the secrets masked
+pub(crate) fn env_diff(request: &ProcessRequest) -> io::Result<EnvDiff> {
+    let parent: HashMap<String, String> = std::env::vars_os()
+        .map(|(name, value)| {
+            (
+                name.to_string_lossy().into_owned(),
+                value.to_string_lossy().into_owned(),
+            )
+        })
+        .collect();
+    let request_env = request_env(request)?;
+    let mut diff = EnvDiff::default();
+    for (name, value) in &request_env {
+        let value = if is_secret(name) {
+            String::from(MASKED)
+        } else {
+            value.clone()
+        };
+        match parent.get(name) {
+            None => _ = diff.added.insert(name.clone(), value),
+            Some(parent_value) if parent_value != &request_env[name] => {
+                _ = diff.overridden.insert(name.clone(), value)
+            }
+            Some(_) => {}
+        }
+    }
+    diff.removed = parent
+        .keys()
+        .filter(|name| {
+            !request_env.contains_key(*name)
+                && !request.env_inheritance.inherits(&OsString::from(name))
+        })
+        .cloned()
+        .collect();
+    diff.removed.sort();
+    Ok(diff)
+}
+
+/// the variable name marks a secret value
+fn is_secret(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    SECRET_NAME_PARTS.iter().any(|part| name.contains(part))
+}
+
+/// Read the variables of a dotenv file: `NAME=value` lines with an optional `export ` prefix, `#` comments, single
+/// quoted (literal) & double quoted (with `\n`, `\"` & `\\` escapes) values. Variables are not expanded
+pub(crate) fn read_env_file(path: &Path) -> io::Result<HashMap<String, String>> {
+    let content = std::fs::read_to_string(path)?;
+    let mut env = HashMap::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = |reason: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}:{} {}", path.display(), index + 1, reason),
+            )
+        };
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (name, value) = line
+            .split_once('=')
+            .ok_or_else(|| invalid("expected NAME=value"))?;
+        let name = name.trim();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(invalid("invalid variable name"));
+        }
+        let value = parse_value(value.trim()).ok_or_else(|| invalid("unterminated quote"))?;
+        env.insert(name.to_string(), value);
+    }
+    Ok(env)
+}
+
+/// unquote the value, None if a quote is unterminated
+fn parse_value(value: &str) -> Option<String> {
+    if let Some(quoted) = value.strip_prefix('\'') {
+        return quoted.find('\'').map(|end| quoted[..end].to_string());
+    }
+    if let Some(quoted) = value.strip_prefix('"') {
+        let mut unquoted = String::new();
+        let mut characters = quoted.chars();
+        while let Some(character) = characters.next() {
+            match character {
+                '"' => return Some(unquoted),
+                '\\' => match characters.next()? {
+                    'n' => unquoted.push('\n'),
+                    'r' => unquoted.push('\r'),
+                    't' => unquoted.push('\t'),
+                    escaped => unquoted.push(escaped),
+                },
+                character => unquoted.push(character),
+            }
+        }
+        return None;
+    }
+    // an unquoted value ends at an inline comment
+    let value = match value.find(" #") {
+        Some(comment) => &value[..comment],
+        None => value,
+    };
+    Some(value.trim_end().to_string())
+}
+
+/// check the env variable name is `[A-Za-z_][A-Za-z0-9_]*`, as it's put in the remote command line unquoted
+#[cfg(any(feature = "ssh", feature = "kubernetes", feature = "winrm"))]
+pub(crate) fn check_name(name: &str) -> io::Result<()> {
+    let mut characters = name.chars();
+    let valid = characters
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && characters.all(|character| character.is_ascii_alphanumeric() || character == '_');
+    if !valid {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid environment variable name: {:?}", name),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::env::read_env_file;
+    use crate::{
+        EnvDiff, EnvInheritance, ProcessData, ProcessEvent, ProcessRequest, ProcessResult,
+    };
+    use std::sync::{Arc, Mutex};
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_env_inheritance() {
+        let run = |env_inheritance: EnvInheritance| {
+            let lines = Arc::new(Mutex::new(vec![]));
+            let output = Arc::clone(&lines);
+            let request = ProcessRequest {
+                request_id: 231,
+                cmd_line: vec![vec![String::from("/usr/bin/env")]],
+                env: [(String::from("PES_TEST"), String::from("1"))].into(),
+                env_inheritance,
+                callback: Some(Arc::new(move |event: &ProcessEvent, data: &ProcessData| {
+                    if *event == ProcessEvent::IOData {
+                        output
+                            .lock()
+                            .unwrap()
+                            .push(data.line.trim_end().to_string());
+                    }
+                    ProcessResult::new()
+                })),
+                ..Default::default()
+            };
+            ProcessRequest::start(request);
+            let mut lines = lines.lock().unwrap().clone();
+            lines.sort();
+            lines
+        };
+        assert_eq!(run(EnvInheritance::Clean), ["PES_TEST=1"]);
+        let path = format!("PATH={}", std::env::var("PATH").unwrap());
+        assert_eq!(
+            run(EnvInheritance::Allowlist(vec![String::from("PATH")])),
+            [path.clone(), String::from("PES_TEST=1")]
+        );
+        assert!(run(EnvInheritance::All).len() > 2);
+    }
+
+    #[test]
+    pub fn test_env_file() {
+        let path = std::env::temp_dir().join(format!("pes_test_{}.env", std::process::id()));
+        std::fs::write(
+            &path,
+            "# comment\nexport NAME=value # inline\nSINGLE='a $b \\n'\nDOUBLE=\"x\\ny \\\"z\\\"\"\n\nEMPTY=\n",
+        )
+        .unwrap();
+        let env = read_env_file(&path).unwrap();
+        assert_eq!(env["NAME"], "value");
+        assert_eq!(env["SINGLE"], "a $b \\n");
+        assert_eq!(env["DOUBLE"], "x\ny \"z\"");
+        assert_eq!(env["EMPTY"], "");
+        std::fs::write(&path, "VALID=1\nINVALID\n").unwrap();
+        assert!(read_env_file(&path)
+            .unwrap_err()
+            .to_string()
+            .ends_with(":2 expected NAME=value"));
+        _ = std::fs::remove_file(path);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_env_diff() {
+        let diffs = Arc::new(Mutex::new(vec![]));
+        let recorded = Arc::clone(&diffs);
+        let callback = move |event: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            if *event == ProcessEvent::EnvironmentDiff {
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push(data.env_diff.clone().unwrap());
+            }
+            ProcessResult::new()
+        };
+        ProcessRequest::start(ProcessRequest {
+            request_id: 323,
+            cmd_line: vec![vec![String::from("env")]],
+            env: [
+                (String::from("PES_DIFF_ADDED"), String::from("1")),
+                (String::from("PES_DB_PASSWORD"), String::from("hunter2")),
+                (String::from("PATH"), String::from("/pes/bin")),
+            ]
+            .into(),
+            env_inheritance: EnvInheritance::Allowlist(vec![String::from("PATH")]),
+            report_env_diff: true,
+            dry_run: true,
+            callback: Some(Arc::new(callback)),
+            ..Default::default()
+        });
+        let diffs = diffs.lock().unwrap();
+        let diff: &EnvDiff = &diffs[0];
+        assert_eq!(diff.added["PES_DIFF_ADDED"], "1");
+        assert_eq!(diff.added["PES_DB_PASSWORD"], "***");
+        assert_eq!(diff.overridden["PATH"], "/pes/bin");
+        let parent_names: Vec<String> = std::env::vars_os()
+            .map(|(name, _)| name.to_string_lossy().into_owned())
+            .filter(|name| name != "PATH")
+            .collect();
+        assert_eq!(diff.removed.len(), parent_names.len());
+        assert!(!diff.removed.contains(&String::from("PATH")));
+    }
+}
@@ -0,0 +1,36 @@
+use crate::ProcessResult;
+use std::fmt;
+use std::io;
+
+/// Failure to get the result of a non-blocking mode execution, see [`ProcessResult::wait`]
+#[derive(Debug)]
+pub enum ProcessError {
+    /// The thread running the request could not be created
+    Spawn(io::Error),
+    /// The thread running the request panicked (e.g. in the callback)
+    Panicked,
+    /// The execution didn't complete within the timeout of [`ProcessResult::wait_timeout`], it's still running.
+    /// The pending result is returned to wait again
+    TimedOut(Box<ProcessResult>),
+}
+
+impl fmt::Display for ProcessError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProcessError::Spawn(error) => {
+                write!(formatter, "Failed to start the thread: {}", error)
+            }
+            ProcessError::Panicked => write!(formatter, "The thread running the request panicked"),
+            ProcessError::TimedOut(_) => write!(formatter, "Not completed within the timeout"),
+        }
+    }
+}
+
+impl std::error::Error for ProcessError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ProcessError::Spawn(error) => Some(error),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use crate::{ProcessData, ProcessEvent};
+use std::collections::VecDeque;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// What to do with a new event when the [`EventQueue`] is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OverflowPolicy {
+    /// Wait till the consumer makes room, the process output is not read meanwhile
+    #[default]
+    Block,
+    /// Drop the oldest queued event to make room for the new one
+    DropOldest,
+    /// Drop the new event
+    DropNewest,
+    /// Drop the new event and kill the process which emitted it
+    KillProcess,
+}
+
+/// An event taken from the [`EventQueue`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueuedEvent {
+    /// An event of a request along with its data
+    Event {
+        /// The event
+        event: ProcessEvent,
+        /// Id of the request, see [`crate::ProcessRequest::request_id`]
+        request_id: u32,
+        /// Line number of the output line, see [`ProcessData::line_number`]
+        line_number: i64,
+        /// Output line or the event details, see [`ProcessData::line`]
+        line: String,
+        /// Time of the event, see [`ProcessData::timestamp`]
+        timestamp: SystemTime,
+    },
+    /// Events were dropped at this position of the queue as per the [`OverflowPolicy`]
+    EventsDropped {
+        /// Number of the dropped events
+        count: u64,
+    },
+}
+
+#[derive(Default)]
+struct QueueState {
+    events: VecDeque<QueuedEvent>,
+    /// events dropped from the front, reported before the queued events
+    dropped_front: u64,
+    /// events dropped at the back, reported before the next queued event
+    dropped_back: u64,
+    /// total number of the dropped events
+    dropped_total: u64,
+}
+
+/// Bounded queue decoupling a slow consumer from the process output, set it as [`crate::ProcessRequest::event_queue`]
+/// (share it between requests using [`std::sync::Arc`]) & take the events from another thread.
+/// The [`QueuedEvent::EventsDropped`] notification is not counted in the capacity
+pub struct EventQueue {
+    capacity: usize,
+    policy: OverflowPolicy,
+    state: Mutex<QueueState>,
+    changed: Condvar,
+}
+
+impl EventQueue {
+    /// Create a queue of at most `capacity` events (at least one) with the overflow policy
+    pub fn new(capacity: usize, policy: OverflowPolicy) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            policy,
+            state: Mutex::new(QueueState::default()),
+            changed: Condvar::new(),
+        }
+    }
+
+    /// Overflow policy of the queue
+    pub fn policy(&self) -> OverflowPolicy {
+        self.policy
+    }
+
+    /// Number of the queued events
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().events.len()
+    }
+
+    /// No events are queued
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Total number of the events dropped so far
+    pub fn dropped(&self) -> u64 {
+        self.state.lock().unwrap().dropped_total
+    }
+
+    /// queue the event as per the overflow policy, true if an event (the new or an older one) was dropped
+    pub(crate) fn push(&self, event: &ProcessEvent, data: &ProcessData) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let mut dropped = false;
+        if state.events.len() >= self.capacity {
+            match self.policy {
+                OverflowPolicy::Block => {
+                    state = self
+                        .changed
+                        .wait_while(state, |state| state.events.len() >= self.capacity)
+                        .unwrap();
+                }
+                OverflowPolicy::DropOldest => {
+                    dropped = true;
+                    while state.events.len() >= self.capacity {
+                        match state.events.pop_front() {
+                            Some(QueuedEvent::EventsDropped { count }) => {
+                                state.dropped_front += count
+                            }
+                            _ => {
+                                state.dropped_front += 1;
+                                state.dropped_total += 1;
+                            }
+                        }
+                    }
+                }
+                OverflowPolicy::DropNewest | OverflowPolicy::KillProcess => {
+                    state.dropped_back += 1;
+                    state.dropped_total += 1;
+                    if self.policy == OverflowPolicy::KillProcess {
+                        if let (Some(reader), Some(kill_record)) = (data.reader, data.kill_record) {
+                            kill_record.record(false);
+                            _ = reader.kill();
+                        }
+                    }
+                    return true;
+                }
+            }
+        }
+        if state.dropped_back > 0 {
+            let count = std::mem::take(&mut state.dropped_back);
+            state.events.push_back(QueuedEvent::EventsDropped { count });
+        }
+        state.events.push_back(QueuedEvent::Event {
+            event: *event,
+            request_id: data
+                .request
+                .as_ref()
+                .map_or(0, |request| request.request_id),
+            line_number: data.line_number,
+            line: data.line_to_owned(),
+            timestamp: data.timestamp(),
+        });
+        self.changed.notify_all();
+        dropped
+    }
+
+    /// take the next event from the state, if any
+    fn take(&self, state: &mut QueueState) -> Option<QueuedEvent> {
+        if state.dropped_front > 0 {
+            let count = std::mem::take(&mut state.dropped_front);
+            return Some(QueuedEvent::EventsDropped { count });
+        }
+        let event = state.events.pop_front()?;
+        self.changed.notify_all();
+        Some(event)
+    }
+
+    /// Take the next event, waits till an event is available
+    pub fn recv(&self) -> QueuedEvent {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(event) = self.take(&mut state) {
+                return event;
+            }
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+
+    /// Take the next event, waits till the timeout for an event. None if no event is available
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<QueuedEvent> {
+        let state = self.state.lock().unwrap();
+        let (mut state, _) = self
+            .changed
+            .wait_timeout_while(state, timeout, |state| {
+                state.dropped_front == 0 && state.events.is_empty()
+            })
+            .unwrap();
+        self.take(&mut state)
+    }
+
+    /// Take the next event if available
+    pub fn try_recv(&self) -> Option<QueuedEvent> {
+        self.take(&mut self.state.lock().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{EventQueue, OverflowPolicy, ProcessEvent, ProcessRequest, QueuedEvent};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn run(request_id: u32, queue: &Arc<EventQueue>) {
+        ProcessRequest::start(ProcessRequest {
+            request_id,
+            use_shell: true,
+            cmd_line: vec![vec![String::from("for i in 1 2 3 4 5 6; do echo $i; done")]],
+            event_queue: Some(Arc::clone(queue)),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    #[cfg(unix)]
+    pub fn test_event_queue_drop_newest() {
+        let queue = Arc::new(EventQueue::new(3, OverflowPolicy::DropNewest));
+        run(391, &queue);
+        // Starting, Started & the first line are queued, the rest is dropped
+        assert_eq!(queue.len(), 3);
+        assert!(queue.dropped() > 0);
+        let mut events = vec![];
+        while let Some(event) = queue.try_recv() {
+            events.push(event);
+        }
+        assert!(matches!(
+            events[2],
+            QueuedEvent::Event {
+                event: ProcessEvent::IOData,
+                line_number: 1,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    pub fn test_event_queue_drop_oldest() {
+        let queue = Arc::new(EventQueue::new(2, OverflowPolicy::DropOldest));
+        run(392, &queue);
+        let dropped = queue.dropped();
+        assert!(matches!(
+            queue.try_recv(),
+            Some(QueuedEvent::EventsDropped { count }) if count == dropped
+        ));
+        assert!(matches!(
+            queue.recv_timeout(Duration::from_millis(10)),
+            Some(QueuedEvent::Event { .. })
+        ));
+        assert!(matches!(
+            queue.recv(),
+            QueuedEvent::Event {
+                event: ProcessEvent::Exited,
+                ..
+            }
+        ));
+        assert!(queue.is_empty());
+    }
+}
@@ -0,0 +1,162 @@
+use crate::{check_and_trigger_callback, ProcessData, ProcessEvent, ProcessRequest, ProcessResult};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Runs a single attempt of a request & emits its events through the callback of the request,
+/// see [`ProcessRequest::executor`]. Retries, delayed start, scheduling etc. are handled by the crate
+pub trait ProcessExecutor: Send + Sync {
+    /// Run the request till it completes, use [`ProcessResult::set_exited`] to report the exit code
+    fn execute(&self, request: Arc<ProcessRequest>) -> ProcessResult;
+}
+
+/// Default executor spawning the real processes using duct
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DuctExecutor;
+
+impl ProcessExecutor for DuctExecutor {
+    fn execute(&self, request: Arc<ProcessRequest>) -> ProcessResult {
+        crate::spawn_process(request, None)
+    }
+}
+
+/// Executor feeding the scripted output lines & exit code instead of spawning a process,
+/// to unit-test the callbacks
+#[derive(Debug, Clone, Default)]
+pub struct MockExecutor {
+    lines: Vec<String>,
+    exit_code: i32,
+    line_delay: Duration,
+    start_error: Option<String>,
+}
+
+impl MockExecutor {
+    /// Mock a process with no output which exits with exit code 0
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an output line, the line feed is appended as the real output lines have it
+    pub fn line(mut self, line: impl Into<String>) -> Self {
+        let mut line = line.into();
+        line.push('\n');
+        self.lines.push(line);
+        self
+    }
+
+    /// Add the output lines
+    pub fn lines<S: Into<String>>(self, lines: impl IntoIterator<Item = S>) -> Self {
+        lines.into_iter().fold(self, |mock, line| mock.line(line))
+    }
+
+    /// Exit with this exit code once the lines are emitted
+    pub fn exit_code(mut self, exit_code: i32) -> Self {
+        self.exit_code = exit_code;
+        self
+    }
+
+    /// Wait before emitting every line
+    pub fn line_delay(mut self, line_delay: Duration) -> Self {
+        self.line_delay = line_delay;
+        self
+    }
+
+    /// Fail to start with this error instead of emitting the lines
+    pub fn start_error(mut self, error: impl Into<String>) -> Self {
+        self.start_error = Some(error.into());
+        self
+    }
+}
+
+impl ProcessExecutor for MockExecutor {
+    fn execute(&self, request: Arc<ProcessRequest>) -> ProcessResult {
+        let mut process_data = ProcessData::new();
+        process_data.request = Some(Arc::clone(&request));
+        process_data.line.push_str("Mock executor");
+        let mut process_result =
+            check_and_trigger_callback(&request, &ProcessEvent::Starting, &process_data);
+        if let Some(error) = self.start_error.as_ref() {
+            process_data.line.clone_from(error);
+            let mut process_result =
+                check_and_trigger_callback(&request, &ProcessEvent::StartError, &process_data);
+            process_result.set_spawn_failed();
+            return process_result;
+        }
+        process_data.line.clear();
+        check_and_trigger_callback(&request, &ProcessEvent::Started, &process_data);
+        let mut exit_code = Some(self.exit_code);
+        for line in &self.lines {
+            thread::sleep(self.line_delay);
+            process_data.line.clone_from(line);
+            process_data.line_number += 1;
+            process_result =
+                check_and_trigger_callback(&request, &ProcessEvent::IOData, &process_data);
+            if process_result.should_exit == Some(true) {
+                check_and_trigger_callback(&request, &ProcessEvent::ExitRequested, &process_data);
+                // as if the process was killed
+                exit_code = None;
+                break;
+            }
+        }
+        process_data.line.clear();
+        match exit_code {
+            Some(0) => {
+                check_and_trigger_callback(&request, &ProcessEvent::IOEof, &process_data);
+            }
+            Some(exit_code) => {
+                process_data.line.push_str(&format!(
+                    "command {:?} exited with code {}",
+                    request.cmd_line, exit_code
+                ));
+                check_and_trigger_callback(&request, &ProcessEvent::IOError, &process_data);
+                process_data.line.clear();
+            }
+            None => {}
+        }
+        check_and_trigger_callback(&request, &ProcessEvent::Exited, &process_data);
+        process_result.set_exited(exit_code);
+        process_result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Backoff, MockExecutor, ProcessData, ProcessEvent, ProcessRequest, ProcessResult,
+        RetryPolicy,
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    pub fn test_mock_executor() {
+        static LINES: AtomicUsize = AtomicUsize::new(0);
+        let callback = |status: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            if let ProcessEvent::IOData = status {
+                assert!(data.line.starts_with("mock line"));
+                LINES.fetch_add(1, Ordering::SeqCst);
+            }
+            ProcessResult::new()
+        };
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 431,
+            callback: Some(Arc::new(callback)),
+            cmd_line: vec![vec![String::from("not-a-real-command")]],
+            executor: Some(Arc::new(
+                MockExecutor::new()
+                    .lines(["mock line 1", "mock line 2"])
+                    .exit_code(3),
+            )),
+            retry: Some(RetryPolicy {
+                max_attempts: 2,
+                backoff: Backoff::Fixed(Duration::ZERO),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        assert_eq!(result.exit_code, Some(3));
+        assert_eq!(result.attempts, 2);
+        assert_eq!(LINES.load(Ordering::SeqCst), 4);
+    }
+}
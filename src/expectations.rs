@@ -0,0 +1,176 @@
+use regex::Regex;
+use std::io;
+
+/// A declarative expectation on the output of a process, see [`crate::ProcessRequest::expectations`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OutputExpectation {
+    /// At least one output line should match the regular expression
+    Contains(String),
+    /// No output line should match the regular expression
+    NotContains(String),
+    /// Number of the output lines should be within `min..=max`, for no upper bound use None as `max`
+    LineCount {
+        /// Min number of the lines
+        min: u64,
+        /// Max number of the lines
+        max: Option<u64>,
+    },
+}
+
+/// compiled expectation along with its state
+enum Rule {
+    Contains { regex: Regex, matched: bool },
+    NotContains(Regex),
+    LineCount { min: u64, max: Option<u64> },
+}
+
+/// Checks the output of an execution against the expectations of the request, each expectation fails at most once
+pub(crate) struct ExpectationChecker {
+    rules: Vec<(Rule, bool)>,
+}
+
+impl ExpectationChecker {
+    /// compile the expectations of the request, None if there are none
+    pub(crate) fn new(expectations: &[OutputExpectation]) -> io::Result<Option<Self>> {
+        if expectations.is_empty() {
+            return Ok(None);
+        }
+        let compile = |pattern: &str| {
+            Regex::new(pattern)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error.to_string()))
+        };
+        let mut rules = vec![];
+        for expectation in expectations {
+            let rule = match expectation {
+                OutputExpectation::Contains(pattern) => Rule::Contains {
+                    regex: compile(pattern)?,
+                    matched: false,
+                },
+                OutputExpectation::NotContains(pattern) => Rule::NotContains(compile(pattern)?),
+                OutputExpectation::LineCount { min, max } => Rule::LineCount {
+                    min: *min,
+                    max: *max,
+                },
+            };
+            rules.push((rule, false));
+        }
+        Ok(Some(Self { rules }))
+    }
+
+    /// check the output line, returns the indexes of the expectations failed by it
+    pub(crate) fn check_line(&mut self, line: &str, line_number: i64) -> Vec<usize> {
+        let mut failed = vec![];
+        for (index, (rule, rule_failed)) in self.rules.iter_mut().enumerate() {
+            if *rule_failed {
+                continue;
+            }
+            *rule_failed = match rule {
+                Rule::Contains { regex, matched } => {
+                    if !*matched {
+                        *matched = regex.is_match(line);
+                    }
+                    false
+                }
+                Rule::NotContains(regex) => regex.is_match(line),
+                Rule::LineCount { max, .. } => max.is_some_and(|max| line_number as u64 > max),
+            };
+            if *rule_failed {
+                failed.push(index);
+            }
+        }
+        failed
+    }
+
+    /// check at the end of the output, returns the failed expectations along with the reason
+    pub(crate) fn check_end(&mut self, line_count: i64) -> Vec<(usize, String)> {
+        let mut failed = vec![];
+        for (index, (rule, rule_failed)) in self.rules.iter_mut().enumerate() {
+            if *rule_failed {
+                continue;
+            }
+            let reason = match rule {
+                Rule::Contains { regex, matched } if !*matched => {
+                    format!("No output line matches {}", regex.as_str())
+                }
+                Rule::LineCount { min, .. } if (line_count as u64) < *min => {
+                    format!(
+                        "Only {} output lines, expected at least {}",
+                        line_count, min
+                    )
+                }
+                _ => continue,
+            };
+            *rule_failed = true;
+            failed.push((index, reason));
+        }
+        failed
+    }
+
+    /// any of the expectations failed
+    pub(crate) fn failed(&self) -> bool {
+        self.rules.iter().any(|(_, failed)| *failed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{OutputExpectation, ProcessData, ProcessEvent, ProcessRequest, ProcessResult};
+    use std::sync::{Arc, Mutex};
+
+    fn run(
+        request_id: u32,
+        command: &str,
+        expectations: Vec<OutputExpectation>,
+        kill: bool,
+    ) -> (ProcessResult, Vec<(usize, String)>) {
+        let failures = Arc::new(Mutex::new(vec![]));
+        let recorded = Arc::clone(&failures);
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id,
+            use_shell: true,
+            cmd_line: vec![vec![String::from(command)]],
+            expectations,
+            kill_on_expectation_failure: kill,
+            callback: Some(Arc::new(move |event: &ProcessEvent, data: &ProcessData| {
+                if *event == ProcessEvent::ExpectationFailed {
+                    recorded
+                        .lock()
+                        .unwrap()
+                        .push((data.expectation_index.unwrap(), data.line_to_owned()));
+                }
+                ProcessResult::new()
+            })),
+            ..Default::default()
+        });
+        let failures = failures.lock().unwrap().clone();
+        (result, failures)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_expectations() {
+        let expectations = vec![
+            OutputExpectation::Contains(String::from("^done$")),
+            OutputExpectation::NotContains(String::from("ERROR")),
+            OutputExpectation::LineCount {
+                min: 2,
+                max: Some(3),
+            },
+        ];
+        let (result, failures) = run(401, "echo working; echo done", expectations.clone(), false);
+        assert!(result.success.unwrap());
+        assert!(failures.is_empty());
+
+        let (result, failures) = run(402, "echo ERROR: disk", expectations.clone(), false);
+        assert!(!result.success.unwrap());
+        assert_eq!(failures[0], (1, String::from("ERROR: disk")));
+        assert_eq!(failures[1].0, 0);
+        assert_eq!(failures[2].0, 2);
+
+        let (result, failures) = run(403, "echo ERROR; exec sleep 10", expectations, true);
+        assert!(!result.success.unwrap());
+        assert_eq!(failures.len(), 1);
+        assert!(result.duration.unwrap().as_secs() < 5);
+    }
+}
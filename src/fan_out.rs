@@ -0,0 +1,176 @@
+use crate::{BatchHandle, BatchOptions, ProcessRequest, ProcessResult, RemoteTarget};
+use std::collections::BTreeMap;
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+/// Outcome of the request on a single host, see [`ProcessRequest::start_on_hosts`]
+#[derive(Debug)]
+pub struct HostOutcome {
+    /// Host the request ran on
+    pub host: String,
+    /// Exit code of the command on the host, see [`ProcessResult::exit_code`]
+    pub exit_code: Option<i32>,
+    /// Time taken on the host including the retries
+    pub duration: Duration,
+    /// Full result on the host
+    pub result: ProcessResult,
+}
+
+impl HostOutcome {
+    /// Command was successful on the host, see [`ProcessResult::success`]
+    pub fn succeeded(&self) -> bool {
+        self.result.success.as_ref().is_ok_and(|success| *success)
+    }
+}
+
+/// Combined result of running a request on many hosts
+#[derive(Debug)]
+pub struct FanOutSummary {
+    /// Outcome on each host in the order of the hosts
+    pub hosts: Vec<HostOutcome>,
+    /// Total time taken on all the hosts
+    pub duration: Duration,
+}
+
+impl FanOutSummary {
+    /// Exit code of the command by the host
+    pub fn exit_codes(&self) -> BTreeMap<&str, Option<i32>> {
+        self.hosts
+            .iter()
+            .map(|outcome| (outcome.host.as_str(), outcome.exit_code))
+            .collect()
+    }
+
+    /// Hosts where the command failed to start or failed
+    pub fn failed_hosts(&self) -> Vec<&str> {
+        self.hosts
+            .iter()
+            .filter(|outcome| !outcome.succeeded())
+            .map(|outcome| outcome.host.as_str())
+            .collect()
+    }
+
+    /// The command was successful on all the hosts
+    pub fn all_succeeded(&self) -> bool {
+        self.failed_hosts().is_empty()
+    }
+}
+
+/// Handle of a request running on many hosts
+pub struct FanOutHandle {
+    batch: BatchHandle,
+    hosts: Vec<String>,
+}
+
+impl FanOutHandle {
+    /// The request is completed on all the hosts
+    pub fn is_finished(&self) -> bool {
+        self.batch.is_finished()
+    }
+
+    /// Wait for the request to complete on all the hosts and return the summary
+    pub fn wait(self) -> thread::Result<FanOutSummary> {
+        let summary = self.batch.wait()?;
+        Ok(FanOutSummary {
+            hosts: self
+                .hosts
+                .into_iter()
+                .zip(summary.items)
+                .map(|(host, item)| HostOutcome {
+                    host,
+                    exit_code: item.exit_code,
+                    duration: item.duration,
+                    result: item.result,
+                })
+                .collect(),
+            duration: summary.duration,
+        })
+    }
+}
+
+/// run a copy of the request on every host as a batch
+pub(crate) fn start_on_hosts(
+    request: ProcessRequest,
+    hosts: Vec<RemoteTarget>,
+    options: BatchOptions,
+) -> io::Result<FanOutHandle> {
+    let host_names = hosts.iter().map(|host| host.host.clone()).collect();
+    let requests = hosts
+        .into_iter()
+        .map(|host| ProcessRequest {
+            remote: Some(host),
+            ..request.clone()
+        })
+        .collect();
+    Ok(FanOutHandle {
+        batch: crate::batch::start_batch(requests, options)?,
+        hosts: host_names,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        MockExecutor, ProcessData, ProcessEvent, ProcessExecutor, ProcessRequest, ProcessResult,
+        RemoteTarget,
+    };
+    use std::sync::{Arc, Mutex};
+
+    /// mocks the command printing the host & failing on the host `db`
+    struct HostMock;
+
+    impl ProcessExecutor for HostMock {
+        fn execute(&self, request: Arc<ProcessRequest>) -> ProcessResult {
+            let host = request.remote.as_ref().unwrap().host.clone();
+            let exit_code = if host == "db" { 2 } else { 0 };
+            MockExecutor::new()
+                .line(format!("up on {}", host))
+                .exit_code(exit_code)
+                .execute(request)
+        }
+    }
+
+    #[test]
+    pub fn test_start_on_hosts() {
+        let lines = Arc::new(Mutex::new(vec![]));
+        let captured = Arc::clone(&lines);
+        let callback = move |event: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            if *event == ProcessEvent::IOData {
+                captured.lock().unwrap().push((
+                    data.remote_host().unwrap().to_string(),
+                    data.line_to_owned(),
+                ));
+            }
+            ProcessResult::new()
+        };
+        let request = ProcessRequest {
+            request_id: 296,
+            cmd_line: vec![vec![String::from("uptime")]],
+            executor: Some(Arc::new(HostMock)),
+            callback: Some(Arc::new(callback)),
+            ..Default::default()
+        };
+        let hosts = ["web1", "db", "web2"].map(|host| RemoteTarget {
+            host: host.to_string(),
+            ..Default::default()
+        });
+        let summary = ProcessRequest::start_on_hosts(request, hosts.to_vec())
+            .unwrap()
+            .wait()
+            .unwrap();
+        assert_eq!(
+            summary.exit_codes().into_iter().collect::<Vec<_>>(),
+            [("db", Some(2)), ("web1", Some(0)), ("web2", Some(0))]
+        );
+        assert_eq!(summary.hosts[1].host, "db");
+        assert_eq!(summary.failed_hosts(), ["db"]);
+        assert!(!summary.all_succeeded());
+
+        let lines = lines.lock().unwrap();
+        assert_eq!(lines.len(), 3);
+        for (host, line) in lines.iter() {
+            assert_eq!(*line, format!("up on {}", host));
+        }
+    }
+}
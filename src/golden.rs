@@ -0,0 +1,120 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Compares the output of an execution line by line with the expected output of the golden file, see
+/// [`crate::ProcessRequest::golden_file`]
+pub(crate) struct GoldenComparer {
+    expected: Vec<String>,
+    mismatches: u64,
+}
+
+impl GoldenComparer {
+    /// load the golden file of the request, None if there is none
+    pub(crate) fn new(golden_file: Option<&Path>) -> io::Result<Option<Self>> {
+        let Some(golden_file) = golden_file else {
+            return Ok(None);
+        };
+        let content = fs::read_to_string(golden_file).map_err(|error| {
+            io::Error::new(
+                error.kind(),
+                format!("Golden file {}: {}", golden_file.display(), error),
+            )
+        })?;
+        Ok(Some(Self {
+            expected: content.lines().map(String::from).collect(),
+            mismatches: 0,
+        }))
+    }
+
+    /// compare the output line with the line of the golden file at its line number, returns the mismatch
+    pub(crate) fn check_line(&mut self, line: &str, line_number: i64) -> Option<String> {
+        let index = usize::try_from(line_number - 1).ok()?;
+        let mismatch = match self.expected.get(index) {
+            Some(expected) if expected == line => return None,
+            Some(expected) => format!(
+                "Line {}: expected {:?}, got {:?}",
+                line_number, expected, line
+            ),
+            None => format!(
+                "Line {}: unexpected {:?}, the golden file has {} lines",
+                line_number,
+                line,
+                self.expected.len()
+            ),
+        };
+        self.mismatches += 1;
+        Some(mismatch)
+    }
+
+    /// check the output had all the lines of the golden file, returns the mismatch
+    pub(crate) fn check_end(&mut self, line_count: i64) -> Option<String> {
+        let line_count = usize::try_from(line_count).unwrap_or(0);
+        let first_missing = self.expected.get(line_count)?;
+        self.mismatches += 1;
+        Some(format!(
+            "Output ended after {} lines, the golden file has {} lines, first missing {:?}",
+            line_count,
+            self.expected.len(),
+            first_missing
+        ))
+    }
+
+    /// the output differed from the golden file
+    pub(crate) fn mismatched(&self) -> bool {
+        self.mismatches > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ProcessData, ProcessEvent, ProcessRequest, ProcessResult};
+    use std::sync::{Arc, Mutex};
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_golden_file() {
+        let dir = std::env::temp_dir().join("pes_test_golden");
+        _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let golden_file = dir.join("expected.txt");
+        std::fs::write(&golden_file, "one\ntwo\nthree\n").unwrap();
+        let run = |request_id: u32, command: &str| {
+            let mismatches = Arc::new(Mutex::new(vec![]));
+            let recorded = Arc::clone(&mismatches);
+            let callback = move |event: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+                if *event == ProcessEvent::GoldenMismatch {
+                    recorded.lock().unwrap().push(data.line_to_owned());
+                }
+                ProcessResult::new()
+            };
+            let result = ProcessRequest::start(ProcessRequest {
+                request_id,
+                use_shell: true,
+                cmd_line: vec![vec![String::from(command)]],
+                golden_file: Some(golden_file.clone()),
+                callback: Some(Arc::new(callback)),
+                ..Default::default()
+            });
+            let mismatches = mismatches.lock().unwrap().clone();
+            (result, mismatches)
+        };
+
+        let (result, mismatches) = run(297, "printf 'one\\ntwo\\nthree\\n'");
+        assert_eq!(result.golden_matched, Some(true));
+        assert!(result.success.unwrap());
+        assert!(mismatches.is_empty());
+
+        let (result, mismatches) = run(298, "printf 'one\\n2\\n'");
+        assert_eq!(result.golden_matched, Some(false));
+        assert!(!result.success.unwrap());
+        assert_eq!(
+            mismatches,
+            [
+                "Line 2: expected \"two\", got \"2\"",
+                "Output ended after 2 lines, the golden file has 3 lines, first missing \"three\""
+            ]
+        );
+        _ = std::fs::remove_dir_all(&dir);
+    }
+}
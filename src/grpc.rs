@@ -0,0 +1,218 @@
+use crate::remote::RemoteProcesses;
+use crate::{ProcessData, ProcessEvent, ProcessRequest};
+use proto::process_service_server::ProcessService;
+use proto::{
+    CommandLine, KillRequest, KillResponse, ListRunningRequest, ListRunningResponse,
+    ProcessEventMessage, ProcessStatus, StartProcessRequest, StartProcessResponse,
+    StreamEventsRequest,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+/// Generated gRPC messages, client & server of `proto/pes.proto`
+pub mod proto {
+    tonic::include_proto!("pes");
+}
+
+/// gRPC [`ProcessService`] running the processes on this host, add it to a tonic server using
+/// `proto::process_service_server::ProcessServiceServer::new(ProcessGrpcService::new())`
+#[derive(Clone)]
+pub struct ProcessGrpcService {
+    processes: Arc<RemoteProcesses<ProcessEventMessage>>,
+}
+
+impl Default for ProcessGrpcService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProcessGrpcService {
+    /// Create the service with no processes
+    pub fn new() -> Self {
+        Self {
+            processes: Arc::new(RemoteProcesses::new(to_event_message)),
+        }
+    }
+}
+
+/// convert the process event to the gRPC message
+fn to_event_message(event: &ProcessEvent, data: &ProcessData) -> ProcessEventMessage {
+    ProcessEventMessage {
+        event: format!("{:?}", event),
+        request_id: data
+            .request
+            .as_ref()
+            .map_or(0, |request| request.request_id),
+        line_number: data.line_number,
+        line: data.line_to_owned(),
+        timestamp_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64,
+        pids: data.child_pids(),
+    }
+}
+
+/// convert the gRPC request to the process request
+fn to_process_request(request: StartProcessRequest) -> ProcessRequest {
+    ProcessRequest {
+        request_id: request.request_id,
+        use_shell: request.use_shell,
+        cmd_line: request
+            .cmd_line
+            .into_iter()
+            .map(|command: CommandLine| command.args)
+            .collect(),
+        env: request.env.into_iter().collect(),
+        working_dir: request.working_dir.map(PathBuf::from),
+        timeout: request.timeout_ms.map(Duration::from_millis),
+        idle_timeout: request.idle_timeout_ms.map(Duration::from_millis),
+        start_after: request.start_after_ms.map(Duration::from_millis),
+        ..Default::default()
+    }
+}
+
+#[tonic::async_trait]
+impl ProcessService for ProcessGrpcService {
+    async fn start_process(
+        &self,
+        request: Request<StartProcessRequest>,
+    ) -> Result<Response<StartProcessResponse>, Status> {
+        let request = to_process_request(request.into_inner());
+        let request_id = request.request_id;
+        match self.processes.start(request) {
+            Ok(()) => Ok(Response::new(StartProcessResponse { request_id })),
+            Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => {
+                Err(Status::already_exists(error.to_string()))
+            }
+            Err(error) => Err(Status::internal(error.to_string())),
+        }
+    }
+
+    type StreamEventsStream = ReceiverStream<Result<ProcessEventMessage, Status>>;
+
+    async fn stream_events(
+        &self,
+        request: Request<StreamEventsRequest>,
+    ) -> Result<Response<Self::StreamEventsStream>, Status> {
+        let request_id = request.into_inner().request_id;
+        let events = self
+            .processes
+            .subscribe(request_id)
+            .ok_or_else(|| Status::not_found(format!("Unknown request id {}", request_id)))?;
+        let (sender, receiver) = tokio::sync::mpsc::channel(64);
+        // forward from the blocking channel of the process events
+        tokio::task::spawn_blocking(move || {
+            for event in events {
+                if sender.blocking_send(Ok(event)).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+
+    async fn kill(&self, request: Request<KillRequest>) -> Result<Response<KillResponse>, Status> {
+        let request_id = request.into_inner().request_id;
+        if self.processes.kill(request_id) {
+            Ok(Response::new(KillResponse {}))
+        } else {
+            Err(Status::not_found(format!(
+                "Unknown request id {}",
+                request_id
+            )))
+        }
+    }
+
+    async fn list_running(
+        &self,
+        _request: Request<ListRunningRequest>,
+    ) -> Result<Response<ListRunningResponse>, Status> {
+        let processes = self
+            .processes
+            .list()
+            .into_iter()
+            .map(|(request_id, running)| ProcessStatus {
+                request_id,
+                running,
+            })
+            .collect();
+        Ok(Response::new(ListRunningResponse { processes }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::proto::process_service_client::ProcessServiceClient;
+    use super::proto::process_service_server::ProcessServiceServer;
+    use super::proto::{
+        CommandLine, KillRequest, ListRunningRequest, StartProcessRequest, StreamEventsRequest,
+    };
+    use crate::ProcessGrpcService;
+    use tokio_stream::wrappers::TcpListenerStream;
+
+    #[test]
+    pub fn test_grpc_service() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let address = listener.local_addr().unwrap();
+            tokio::spawn(
+                tonic::transport::Server::builder()
+                    .add_service(ProcessServiceServer::new(ProcessGrpcService::new()))
+                    .serve_with_incoming(TcpListenerStream::new(listener)),
+            );
+            let mut client = ProcessServiceClient::connect(format!("http://{}", address))
+                .await
+                .unwrap();
+            let command = |args: &[&str]| CommandLine {
+                args: args.iter().map(|arg| arg.to_string()).collect(),
+            };
+            client
+                .start_process(StartProcessRequest {
+                    request_id: 451,
+                    cmd_line: vec![command(&["echo", "grpc"])],
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            let mut stream = client
+                .stream_events(StreamEventsRequest { request_id: 451 })
+                .await
+                .unwrap()
+                .into_inner();
+            let mut events = vec![];
+            while let Some(event) = stream.message().await.unwrap() {
+                events.push(event);
+            }
+            assert_eq!(events[0].event, "Starting");
+            assert!(events
+                .iter()
+                .any(|event| event.event == "IOData" && event.line == "grpc"));
+            assert_eq!(events.last().unwrap().event, "Exited");
+
+            client
+                .start_process(StartProcessRequest {
+                    request_id: 452,
+                    cmd_line: vec![command(&["sleep", "10"])],
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            let running = client
+                .list_running(ListRunningRequest {})
+                .await
+                .unwrap()
+                .into_inner()
+                .processes;
+            assert_eq!(running.len(), 2);
+            assert!(running[1].running);
+            client.kill(KillRequest { request_id: 452 }).await.unwrap();
+            assert!(client.kill(KillRequest { request_id: 452 }).await.is_err());
+        });
+    }
+}
@@ -0,0 +1,119 @@
+use crate::error::ProcessError;
+use crate::latch::Latch;
+use crate::ProcessResult;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Kills the process of a non-blocking mode execution when dropped (e.g. on a panic or an early return), so the
+/// process is not left running. See [`crate::ProcessRequest::start_guarded`]
+#[derive(Debug)]
+pub struct ProcessGuard {
+    /// None once detached or waited
+    result: Option<ProcessResult>,
+    stop: Arc<Latch>,
+}
+
+impl ProcessGuard {
+    pub(crate) fn new(result: ProcessResult, stop: Arc<Latch>) -> Self {
+        Self {
+            result: Some(result),
+            stop,
+        }
+    }
+
+    /// Let the process run after the guard is gone, returns the pending result to wait on
+    pub fn detach(mut self) -> ProcessResult {
+        self.result.take().unwrap()
+    }
+
+    /// Wait for the execution to complete & return its result, see [`ProcessResult::wait`]
+    pub fn wait(mut self) -> Result<ProcessResult, ProcessError> {
+        self.result.take().unwrap().wait()
+    }
+
+    /// Wait till the timeout for the execution to complete & return its result, the process is killed on timeout
+    pub fn wait_timeout(mut self, timeout: Duration) -> Result<ProcessResult, ProcessError> {
+        match self.result.take().unwrap().wait_timeout(timeout) {
+            Err(ProcessError::TimedOut(result)) => {
+                self.result = Some(*result);
+                Ok(self.kill())
+            }
+            completed => completed,
+        }
+    }
+
+    /// Kill the process now & wait for the execution to complete, returns its result
+    pub fn kill(mut self) -> ProcessResult {
+        self.stop_and_wait().unwrap_or_default()
+    }
+
+    /// set the stop latch, cancel a delayed start & wait for the execution, None if detached or waited already
+    fn stop_and_wait(&mut self) -> Option<ProcessResult> {
+        let result = self.result.take()?;
+        self.stop.set();
+        result.cancel_before_start();
+        result.wait().ok()
+    }
+}
+
+impl Drop for ProcessGuard {
+    fn drop(&mut self) {
+        self.stop_and_wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ProcessData, ProcessEvent, ProcessRequest, ProcessResult, Termination};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    fn request(request_id: u32, command: &str) -> ProcessRequest {
+        ProcessRequest {
+            request_id,
+            use_shell: true,
+            cmd_line: vec![vec![String::from(command)]],
+            ..Default::default()
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_process_guard() {
+        let terminations = Arc::new(Mutex::new(vec![]));
+        let recorded = Arc::clone(&terminations);
+        let started = Instant::now();
+        {
+            let _guard = ProcessRequest::start_guarded(ProcessRequest {
+                callback: Some(Arc::new(move |event: &ProcessEvent, data: &ProcessData| {
+                    if *event == ProcessEvent::Exited {
+                        recorded.lock().unwrap().push(data.termination);
+                    }
+                    ProcessResult::new()
+                })),
+                ..request(263, "exec sleep 10")
+            });
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(
+            *terminations.lock().unwrap(),
+            [Some(Termination::Killed { by_request: true })]
+        );
+
+        let result = ProcessRequest::start_guarded(request(264, "exit 3"))
+            .wait()
+            .unwrap();
+        assert_eq!(result.exit_code, Some(3));
+
+        let pending = ProcessRequest::start_guarded(request(265, "sleep 0.3")).detach();
+        assert_eq!(pending.wait().unwrap().exit_code, Some(0));
+
+        let started = Instant::now();
+        let result = ProcessRequest::start_guarded(request(266, "exec sleep 10"))
+            .wait_timeout(Duration::from_millis(100))
+            .unwrap();
+        assert_eq!(result.exit_code, None);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,259 @@
+use crate::{PipelineStage, ProcessRequest, ProcessResult, Termination};
+use std::collections::VecDeque;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+/// A completed execution (all its attempts) of a request, stored in the [`crate::ProcessRequest::history`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExecutionRecord {
+    /// Id of the request, see [`ProcessRequest::request_id`]
+    pub request_id: u32,
+    /// Run sequence of the request, see [`ProcessRequest::run_sequence`]
+    pub run_sequence: u64,
+    /// Commands of the request, see [`ProcessRequest::pipeline_stages`]
+    pub stages: Vec<PipelineStage>,
+    /// Working directory of the request
+    pub working_dir: Option<PathBuf>,
+    /// Start time of the first attempt
+    pub started_at: SystemTime,
+    /// Duration of all the attempts, see [`ProcessResult::duration`]
+    pub duration: Duration,
+    /// Number of the attempts, see [`ProcessResult::attempts`]
+    pub attempts: u32,
+    /// Exit code of the last attempt, see [`ProcessResult::exit_code`]
+    pub exit_code: Option<i32>,
+    /// How the last attempt terminated, see [`ProcessResult::termination`]
+    pub termination: Option<Termination>,
+    /// Execution was successful, see [`ProcessResult::success`]
+    pub success: bool,
+}
+
+impl ExecutionRecord {
+    /// record of the execution of the request started at the given time
+    pub(crate) fn new(
+        request: &ProcessRequest,
+        started_at: SystemTime,
+        result: &ProcessResult,
+    ) -> Self {
+        Self {
+            request_id: request.request_id,
+            run_sequence: request.run_sequence,
+            stages: request.pipeline_stages(),
+            working_dir: request.working_dir.clone(),
+            started_at,
+            duration: result.duration.unwrap_or_default(),
+            attempts: result.attempts,
+            exit_code: result.exit_code,
+            termination: result.termination,
+            success: matches!(result.success, Ok(true)),
+        }
+    }
+}
+
+/// Filter of the records of a [`HistoryStore`], the unset fields match all the records
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HistoryQuery {
+    /// Records of this request only
+    pub request_id: Option<u32>,
+    /// Records of the executions started at or after this time
+    pub started_after: Option<SystemTime>,
+    /// Records of the executions started before this time
+    pub started_before: Option<SystemTime>,
+    /// Records of the successful (true) or failed (false) executions only
+    pub success: Option<bool>,
+    /// At most this many of the latest matching records
+    pub limit: Option<usize>,
+}
+
+impl HistoryQuery {
+    /// Query all the records
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records of this request only
+    pub fn request_id(mut self, request_id: u32) -> Self {
+        self.request_id = Some(request_id);
+        self
+    }
+
+    /// Records of the executions started within the time range, the end is excluded
+    pub fn started_between(mut self, from: SystemTime, to: SystemTime) -> Self {
+        self.started_after = Some(from);
+        self.started_before = Some(to);
+        self
+    }
+
+    /// Records of the successful (true) or failed (false) executions only
+    pub fn success(mut self, success: bool) -> Self {
+        self.success = Some(success);
+        self
+    }
+
+    /// At most this many of the latest matching records
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// The record matches the filter, the limit is not applied
+    pub fn matches(&self, record: &ExecutionRecord) -> bool {
+        self.request_id
+            .is_none_or(|request_id| record.request_id == request_id)
+            && self
+                .started_after
+                .is_none_or(|started_after| record.started_at >= started_after)
+            && self
+                .started_before
+                .is_none_or(|started_before| record.started_at < started_before)
+            && self.success.is_none_or(|success| record.success == success)
+    }
+}
+
+/// Storage of the completed executions, see [`ProcessRequest::history`]. Implement it to persist the history, e.g. in
+/// a database
+pub trait HistoryStore: Send + Sync {
+    /// Store the record of a completed execution, an error is reported as a [`crate::WarningKind::SinkWriteFailed`]
+    /// warning
+    fn record(&self, record: ExecutionRecord) -> io::Result<()>;
+
+    /// Records matching the query, oldest first
+    fn query(&self, query: &HistoryQuery) -> Vec<ExecutionRecord>;
+}
+
+/// In-memory history keeping the latest records, share it between requests using [`std::sync::Arc`]
+pub struct MemoryHistory {
+    capacity: usize,
+    records: Mutex<VecDeque<ExecutionRecord>>,
+}
+
+impl MemoryHistory {
+    /// Keep at most `capacity` records (at least one), the oldest ones are dropped
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            records: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Number of the stored records
+    pub fn len(&self) -> usize {
+        self.records.lock().unwrap().len()
+    }
+
+    /// No records are stored
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all the records
+    pub fn clear(&self) {
+        self.records.lock().unwrap().clear();
+    }
+}
+
+impl HistoryStore for MemoryHistory {
+    fn record(&self, record: ExecutionRecord) -> io::Result<()> {
+        let mut records = self.records.lock().unwrap();
+        if records.len() >= self.capacity {
+            records.pop_front();
+        }
+        records.push_back(record);
+        Ok(())
+    }
+
+    fn query(&self, query: &HistoryQuery) -> Vec<ExecutionRecord> {
+        let records = self.records.lock().unwrap();
+        let mut matching: Vec<ExecutionRecord> = records
+            .iter()
+            .rev()
+            .filter(|record| query.matches(record))
+            .take(query.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect();
+        matching.reverse();
+        matching
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ExecutionRecord, HistoryQuery, HistoryStore, MemoryHistory, ProcessData, ProcessEvent,
+        ProcessRequest, ProcessResult, WarningKind,
+    };
+    use std::io;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, SystemTime};
+
+    /// store failing to write the records
+    struct FailingHistory;
+
+    impl HistoryStore for FailingHistory {
+        fn record(&self, _: ExecutionRecord) -> io::Result<()> {
+            Err(io::Error::other("disk full"))
+        }
+
+        fn query(&self, _: &HistoryQuery) -> Vec<ExecutionRecord> {
+            vec![]
+        }
+    }
+
+    #[test]
+    pub fn test_memory_history() {
+        let history = Arc::new(MemoryHistory::new(3));
+        let started = SystemTime::now();
+        for (request_id, command) in [(281, "true"), (282, "false"), (281, "false"), (283, "true")]
+        {
+            ProcessRequest::start(ProcessRequest {
+                request_id,
+                cmd_line: vec![vec![String::from(command)]],
+                history: Some(history.clone()),
+                ..Default::default()
+            });
+        }
+        // the oldest record is dropped
+        assert_eq!(history.len(), 3);
+        let records = history.query(&HistoryQuery::new());
+        let ids: Vec<u32> = records.iter().map(|record| record.request_id).collect();
+        assert_eq!(ids, [282, 281, 283]);
+        assert_eq!(records[0].exit_code, Some(1));
+        assert_eq!(records[0].stages[0].argv, ["false"]);
+
+        let failed = history.query(&HistoryQuery::new().request_id(281).success(false));
+        assert_eq!(failed.len(), 1);
+        assert!(!failed[0].success);
+        let latest = history.query(&HistoryQuery::new().success(true).limit(1));
+        assert_eq!(latest[0].request_id, 283);
+        let later = started + Duration::from_secs(3600);
+        let query = HistoryQuery::new().started_between(later, later + Duration::from_secs(1));
+        assert!(history.query(&query).is_empty());
+    }
+
+    #[test]
+    pub fn test_history_write_failure() {
+        let warnings = Arc::new(Mutex::new(vec![]));
+        let recorded = Arc::clone(&warnings);
+        let callback = move |event: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            if let ProcessEvent::Warning(kind) = event {
+                recorded.lock().unwrap().push((*kind, data.line_to_owned()));
+            }
+            ProcessResult::new()
+        };
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 513,
+            cmd_line: vec![vec![String::from("true")]],
+            callback: Some(Arc::new(callback)),
+            history: Some(Arc::new(FailingHistory)),
+            ..Default::default()
+        });
+        assert!(result.success.unwrap());
+        assert_eq!(
+            *warnings.lock().unwrap(),
+            [(WarningKind::SinkWriteFailed, String::from("disk full"))]
+        );
+    }
+}
@@ -0,0 +1,289 @@
+use crate::json_events::{escape_json, event_to_json};
+use crate::remote::RemoteProcesses;
+use crate::ProcessRequest;
+use serde::Deserialize;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+/// A newline delimited JSON command of the IPC control interface
+#[derive(Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+enum IpcCommand {
+    /// start the request & stream its events on this connection
+    Start { request: Box<ProcessRequest> },
+    /// stream the events of a known request on this connection, from its start
+    Subscribe { request_id: u32 },
+    /// kill the request if running & forget it
+    Kill { request_id: u32 },
+    /// list the known requests
+    List,
+}
+
+/// Local control interface on a Unix domain socket (a named pipe on Windows). Each line sent by a client is a JSON command:
+/// - `{"command":"start","request":{...}}` starts the [`ProcessRequest`] & streams its events
+/// - `{"command":"subscribe","request_id":1}` streams the events of a known request from its start
+/// - `{"command":"kill","request_id":1}` kills the request if running & forgets it
+/// - `{"command":"list"}` lists the known requests
+///
+/// Every line sent back is a JSON object, either an event as per [`crate::JsonEventSink`] or a `response` to a command
+pub struct IpcServer {
+    path: PathBuf,
+    #[cfg(unix)]
+    listener: std::os::unix::net::UnixListener,
+    processes: Arc<RemoteProcesses<String>>,
+}
+
+impl IpcServer {
+    /// Listen on the Unix domain socket path, or on the Windows named pipe path (e.g. `\\.\pipe\pes`)
+    pub fn bind(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self {
+            path: path.as_ref().to_path_buf(),
+            #[cfg(unix)]
+            listener: std::os::unix::net::UnixListener::bind(path)?,
+            processes: Arc::new(RemoteProcesses::new(event_to_json)),
+        })
+    }
+
+    /// Path of the socket or the named pipe
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Accept & serve the connections, each in its own thread. Blocks till accepting fails
+    pub fn run(&self) -> io::Result<()> {
+        loop {
+            let (reader, writer) = self.accept()?;
+            let processes = Arc::clone(&self.processes);
+            thread::Builder::new()
+                .name(String::from("pes_ipc_conn"))
+                .spawn(move || handle_connection(reader, writer, &processes))?;
+        }
+    }
+
+    #[cfg(unix)]
+    fn accept(&self) -> io::Result<(impl Read + Send, impl Write + Send + 'static)> {
+        let (stream, _) = self.listener.accept()?;
+        Ok((stream.try_clone()?, stream))
+    }
+
+    #[cfg(windows)]
+    fn accept(&self) -> io::Result<(impl Read + Send, impl Write + Send + 'static)> {
+        let pipe = named_pipe::accept(&self.path)?;
+        Ok((pipe.try_clone()?, pipe))
+    }
+}
+
+#[cfg(unix)]
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// serve the commands of a connection, the lines to send back are written by a writer thread
+fn handle_connection(
+    reader: impl Read,
+    mut writer: impl Write + Send + 'static,
+    processes: &RemoteProcesses<String>,
+) {
+    let (sender, receiver) = mpsc::channel::<String>();
+    let writer_thread = thread::spawn(move || {
+        for line in receiver {
+            if writeln!(writer, "{}", line)
+                .and_then(|_| writer.flush())
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+    for line in BufReader::new(reader).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<IpcCommand>(&line) {
+            Ok(IpcCommand::Start { request }) => {
+                let request_id = request.request_id;
+                match processes.start(*request) {
+                    Ok(()) => {
+                        forward_events(processes, request_id, &sender);
+                        format!("{{\"response\":\"started\",\"request_id\":{}}}", request_id)
+                    }
+                    Err(error) => error_json(&error.to_string()),
+                }
+            }
+            Ok(IpcCommand::Subscribe { request_id }) => {
+                if forward_events(processes, request_id, &sender) {
+                    format!(
+                        "{{\"response\":\"subscribed\",\"request_id\":{}}}",
+                        request_id
+                    )
+                } else {
+                    error_json("Unknown request id")
+                }
+            }
+            Ok(IpcCommand::Kill { request_id }) => {
+                if processes.kill(request_id) {
+                    format!("{{\"response\":\"killed\",\"request_id\":{}}}", request_id)
+                } else {
+                    error_json("Unknown request id")
+                }
+            }
+            Ok(IpcCommand::List) => {
+                let list: Vec<String> = processes
+                    .list()
+                    .iter()
+                    .map(|(request_id, running)| {
+                        format!("{{\"request_id\":{},\"running\":{}}}", request_id, running)
+                    })
+                    .collect();
+                format!(
+                    "{{\"response\":\"list\",\"processes\":[{}]}}",
+                    list.join(",")
+                )
+            }
+            Err(error) => error_json(&error.to_string()),
+        };
+        if sender.send(response).is_err() {
+            break;
+        }
+    }
+    drop(sender);
+    _ = writer_thread.join();
+}
+
+/// forward the events of the request to the connection in a thread, false if the request id is unknown
+fn forward_events(
+    processes: &RemoteProcesses<String>,
+    request_id: u32,
+    sender: &mpsc::Sender<String>,
+) -> bool {
+    let Some(events) = processes.subscribe(request_id) else {
+        return false;
+    };
+    let sender = sender.clone();
+    thread::Builder::new()
+        .name(format!("pes_ipc_rq_{}", request_id))
+        .spawn(move || {
+            for event in events {
+                if sender.send(event).is_err() {
+                    break;
+                }
+            }
+        })
+        .is_ok()
+}
+
+fn error_json(message: &str) -> String {
+    format!(
+        "{{\"response\":\"error\",\"message\":\"{}\"}}",
+        escape_json(message)
+    )
+}
+
+#[cfg(windows)]
+mod named_pipe {
+    use std::fs::File;
+    use std::io;
+    use std::os::windows::ffi::OsStrExt;
+    use std::os::windows::io::FromRawHandle;
+    use std::path::Path;
+    use std::ptr;
+    use windows_sys::Win32::Foundation::{ERROR_PIPE_CONNECTED, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::Storage::FileSystem::PIPE_ACCESS_DUPLEX;
+    use windows_sys::Win32::System::Pipes::{
+        ConnectNamedPipe, CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_TYPE_BYTE,
+        PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+    };
+
+    /// create a pipe instance & wait for a client to connect
+    pub(super) fn accept(path: &Path) -> io::Result<File> {
+        let name: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+        // SAFETY: the name is a null terminated wide string, the handle is owned by the returned file
+        unsafe {
+            let handle = CreateNamedPipeW(
+                name.as_ptr(),
+                PIPE_ACCESS_DUPLEX,
+                PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT,
+                PIPE_UNLIMITED_INSTANCES,
+                64 * 1024,
+                64 * 1024,
+                0,
+                ptr::null(),
+            );
+            if handle == INVALID_HANDLE_VALUE {
+                return Err(io::Error::last_os_error());
+            }
+            let pipe = File::from_raw_handle(handle as _);
+            if ConnectNamedPipe(handle, ptr::null_mut()) == 0 {
+                let error = io::Error::last_os_error();
+                if error.raw_os_error() != Some(ERROR_PIPE_CONNECTED as i32) {
+                    return Err(error);
+                }
+            }
+            Ok(pipe)
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use crate::IpcServer;
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    pub fn test_ipc_control() {
+        let path = std::env::temp_dir().join(format!("pes_test_ipc_{}.sock", std::process::id()));
+        let server = Arc::new(IpcServer::bind(&path).unwrap());
+        let runner = Arc::clone(&server);
+        thread::spawn(move || runner.run());
+        let mut stream = UnixStream::connect(&path).unwrap();
+        writeln!(
+            stream,
+            r#"{{"command":"start","request":{{"request_id":461,"cmd_line":[["echo","ipc"]]}}}}"#
+        )
+        .unwrap();
+        let mut lines = BufReader::new(stream.try_clone().unwrap()).lines();
+        let mut received = vec![];
+        while !received
+            .iter()
+            .any(|line: &String| line.contains("\"event\":\"Exited\""))
+        {
+            received.push(lines.next().unwrap().unwrap());
+        }
+        assert!(received
+            .iter()
+            .any(|line| line.contains("\"response\":\"started\",\"request_id\":461")));
+        assert!(received
+            .iter()
+            .any(|line| line.contains("\"event\":\"IOData\"") && line.contains("ipc")));
+        writeln!(stream, r#"{{"command":"list"}}"#).unwrap();
+        assert!(lines
+            .next()
+            .unwrap()
+            .unwrap()
+            .starts_with(r#"{"response":"list","processes":[{"request_id":461,"running":"#));
+        writeln!(stream, r#"{{"command":"kill","request_id":462}}"#).unwrap();
+        assert!(lines
+            .next()
+            .unwrap()
+            .unwrap()
+            .contains("\"response\":\"error\""));
+    }
+
+    #[test]
+    pub fn test_ipc_error_json() {
+        let error = super::error_json("bad \u{7} 'command' \"ü\"");
+        let json: serde_json::Value = serde_json::from_str(&error).unwrap();
+        assert_eq!(json["response"], "error");
+        assert_eq!(json["message"], "bad \u{7} 'command' \"ü\"");
+    }
+}
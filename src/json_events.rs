@@ -0,0 +1,134 @@
+use crate::{ProcessData, ProcessEvent};
+use std::fmt::Write as _;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+/// Writes every event of a request as a JSON object per line (JSON Lines), see [`crate::ProcessRequest::json_events`].
+/// Fields: `event`, `request_id`, `line_number`, `line`, `timestamp` (milliseconds since the Unix epoch) and `pids`
+pub struct JsonEventSink {
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl JsonEventSink {
+    /// Write the events to the writer, share the sink between requests using [`std::sync::Arc`]
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: Mutex::new(Box::new(writer)),
+        }
+    }
+
+    /// Write the events to the STDOUT
+    pub fn stdout() -> Self {
+        Self::new(io::stdout())
+    }
+
+    /// Write the events to the STDERR
+    pub fn stderr() -> Self {
+        Self::new(io::stderr())
+    }
+
+    /// Append the events to the file, it's created if missing
+    pub fn file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file: File = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self::new(BufWriter::new(file)))
+    }
+
+    /// write the event as a JSON line & flush, a write error is reported as a warning so the process is not affected
+    pub(crate) fn write_event(&self, event: &ProcessEvent, data: &ProcessData) -> io::Result<()> {
+        let json = event_to_json(event, data);
+        let mut writer = self.writer.lock().unwrap();
+        writeln!(writer, "{}", json).and_then(|_| writer.flush())
+    }
+}
+
+/// JSON object of the event & its data
+pub(crate) fn event_to_json(event: &ProcessEvent, data: &ProcessData) -> String {
+    let timestamp = data
+        .timestamp()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let request_id = data
+        .request
+        .as_ref()
+        .map_or(String::from("null"), |request| {
+            request.request_id.to_string()
+        });
+    let pids: Vec<String> = data.child_pids().iter().map(u32::to_string).collect();
+    let traceparent = data.traceparent().map_or(String::new(), |traceparent| {
+        format!(",\"traceparent\":\"{}\"", escape_json(traceparent))
+    });
+    format!(
+        "{{\"event\":\"{:?}\",\"request_id\":{},\"line_number\":{},\"line\":\"{}\",\"timestamp\":{},\"pids\":[{}]{}}}",
+        event,
+        request_id,
+        data.line_number,
+        escape_json(data.line_str()),
+        timestamp,
+        pids.join(","),
+        traceparent
+    )
+}
+
+/// escape the string to use inside the JSON quotes
+pub(crate) fn escape_json(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for character in input.chars() {
+        match character {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            character if character.is_control() => {
+                _ = write!(escaped, "\\u{:04x}", character as u32);
+            }
+            character => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{JsonEventSink, ProcessRequest};
+    use std::io::{self, Write};
+    use std::sync::{Arc, Mutex};
+
+    /// collects the written bytes for the assertions
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    pub fn test_json_events() {
+        let buffer = SharedBuffer::default();
+        ProcessRequest::start(ProcessRequest {
+            request_id: 381,
+            cmd_line: vec![vec![String::from("echo"), String::from("say \"hi\"")]],
+            json_events: Some(Arc::new(JsonEventSink::new(buffer.clone()))),
+            ..Default::default()
+        });
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines[0].starts_with("{\"event\":\"Starting\",\"request_id\":381,"));
+        assert!(lines
+            .iter()
+            .any(|line| line.contains("\"event\":\"IOData\"")
+                && line.contains("\"line_number\":1,\"line\":\"say \\\"hi\\\"\"")));
+        assert!(lines.last().unwrap().contains("\"event\":\"Exited\""));
+    }
+}
@@ -0,0 +1,80 @@
+use crate::{ProcessData, ProcessEvent};
+use serde::de::DeserializeOwned;
+
+/// decode the output line of the JSON lines mode, a blank line is delivered as is with no value
+pub(crate) fn decode_line(data: &mut ProcessData) -> ProcessEvent {
+    data.json = None;
+    data.json_error = None;
+    let line = data.line.trim();
+    if line.is_empty() {
+        return ProcessEvent::IOData;
+    }
+    match serde_json::from_str(line) {
+        Ok(value) => {
+            data.json = Some(value);
+            ProcessEvent::IOData
+        }
+        Err(error) => {
+            data.json_error = Some(error.to_string());
+            ProcessEvent::JsonParseError
+        }
+    }
+}
+
+impl ProcessData<'_> {
+    /// Deserialize the JSON value of the output line into the user type, None if there is no value
+    pub fn json_as<T: DeserializeOwned>(&self) -> Option<serde_json::Result<T>> {
+        self.json.as_ref().map(T::deserialize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ProcessData, ProcessEvent, ProcessRequest, ProcessResult};
+    use serde::Deserialize;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Progress {
+        percent: u32,
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_json_lines() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let recorded = Arc::clone(&events);
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 291,
+            use_shell: true,
+            cmd_line: vec![vec![String::from(
+                r#"echo '{"percent": 50}'; echo 'not json'; echo '{"percent": 100}'"#,
+            )]],
+            json_lines: true,
+            callback: Some(Arc::new(move |event: &ProcessEvent, data: &ProcessData| {
+                match event {
+                    ProcessEvent::IOData => recorded
+                        .lock()
+                        .unwrap()
+                        .push(Ok(data.json_as::<Progress>().unwrap().unwrap())),
+                    ProcessEvent::JsonParseError => recorded
+                        .lock()
+                        .unwrap()
+                        .push(Err(data.json_error.clone().unwrap())),
+                    _ => {}
+                }
+                ProcessResult::new()
+            })),
+            ..Default::default()
+        });
+        assert_eq!(result.exit_code, Some(0));
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0], Ok(Progress { percent: 50 }));
+        assert!(events[1]
+            .as_ref()
+            .unwrap_err()
+            .starts_with("expected ident"));
+        assert_eq!(events[2], Ok(Progress { percent: 100 }));
+    }
+}
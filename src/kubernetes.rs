@@ -0,0 +1,577 @@
+use crate::cancel;
+use crate::latch::Latch;
+use crate::line_reader::LineReader;
+use crate::shell_script;
+use crate::shutdown;
+use crate::{
+    check_and_trigger_callback, ProcessData, ProcessEvent, ProcessRequest, ProcessResult,
+    Termination,
+};
+use k8s_openapi::api::core::v1::Pod;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Status;
+use kube::api::{AttachParams, AttachedProcess};
+use kube::config::{KubeConfigOptions, Kubeconfig};
+use kube::{Api, Client, Config};
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// How often the stop latch, the cancellation token & the timeout are checked while waiting for the output
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Pod to run the request in using the exec API of the cluster, see [`ProcessRequest::pod`]. The command line
+/// (including the pipeline, env & working directory) runs in the `sh` of the container, its STDOUT & STDERR are
+/// streamed as the [`crate::ProcessEvent::IOData`] events. A failure to reach the pod (e.g. an unknown pod) is
+/// reported as the [`crate::ProcessEvent::StartError`] event. The stop, the cancellation & the timeout of the request
+/// apply, the options of a local process (e.g. the resource limits or the idle timeout) don't. The options processing
+/// the output lines (e.g. the patterns, the expectations or the output limit) are not supported, a request using them
+/// fails with the start error
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct PodTarget {
+    /// Name of the pod
+    pub pod: String,
+    /// Namespace of the pod, for the namespace of the kubeconfig context use None
+    pub namespace: Option<String>,
+    /// Container of the pod, for the default container use None
+    pub container: Option<String>,
+    /// kubeconfig context (cluster), for the current context use None
+    pub context: Option<String>,
+    /// kubeconfig file, for the default (`KUBECONFIG`, `~/.kube/config` or the in-cluster config) use None
+    pub kubeconfig: Option<PathBuf>,
+}
+
+impl PodTarget {
+    /// Pod in the namespace of the current kubeconfig context
+    pub fn new(pod: impl Into<String>) -> Self {
+        Self {
+            pod: pod.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Pod in this namespace
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Run in this container of the pod
+    pub fn container(mut self, container: impl Into<String>) -> Self {
+        self.container = Some(container.into());
+        self
+    }
+}
+
+/// command run in the container by the exec API
+fn exec_command(request: &ProcessRequest) -> io::Result<[String; 3]> {
+    Ok([
+        String::from("sh"),
+        String::from("-c"),
+        shell_script::command_line(request)?,
+    ])
+}
+
+/// the first option of the request processing the output lines, which isn't supported in a pod
+fn unsupported_option(request: &ProcessRequest) -> Option<&'static str> {
+    #[cfg(feature = "json")]
+    let json_lines = request.json_lines;
+    #[cfg(not(feature = "json"))]
+    let json_lines = false;
+    [
+        ("patterns", !request.patterns.is_empty()),
+        ("fail_on_patterns", !request.fail_on_patterns.is_empty()),
+        (
+            "succeed_on_patterns",
+            !request.succeed_on_patterns.is_empty(),
+        ),
+        ("expectations", !request.expectations.is_empty()),
+        ("golden_file", request.golden_file.is_some()),
+        ("json_lines", json_lines),
+        ("record_format", request.record_format.is_some()),
+        ("line_transforms", !request.line_transforms.is_empty()),
+        ("line_classifier", request.line_classifier.is_some()),
+        ("checkpointer", request.checkpointer.is_some()),
+        ("max_output_bytes", request.max_output_bytes.is_some()),
+        ("output_throttle", request.output_throttle.is_some()),
+        ("tag_output_streams", request.tag_output_streams),
+        ("unchecked_exit_status", request.unchecked_exit_status),
+    ]
+    .into_iter()
+    .find_map(|(option, used)| used.then_some(option))
+}
+
+/// the pod & the command run in it, e.g. `[pod: shop/web-0 (app)] "sh" "-c" "uptime"`
+fn describe(pod: &PodTarget, request: &ProcessRequest) -> io::Result<String> {
+    let mut target = match pod.namespace.as_ref() {
+        Some(namespace) => format!("{}/{}", namespace, pod.pod),
+        None => pod.pod.clone(),
+    };
+    if let Some(container) = pod.container.as_ref() {
+        target.push_str(&format!(" ({})", container));
+    }
+    let command: Vec<String> = exec_command(request)?
+        .iter()
+        .map(|arg| format!("{:?}", arg))
+        .collect();
+    Ok(format!("[pod: {}] {}", target, command.join(" ")))
+}
+
+/// start the command of the request in the pod, attached to its STDOUT & STDERR
+async fn attach(pod: &PodTarget, request: &ProcessRequest) -> io::Result<AttachedProcess> {
+    let options = KubeConfigOptions {
+        context: pod.context.clone(),
+        ..Default::default()
+    };
+    let config = match (pod.kubeconfig.as_ref(), pod.context.as_ref()) {
+        (Some(kubeconfig), _) => {
+            let kubeconfig = Kubeconfig::read_from(kubeconfig).map_err(io::Error::other)?;
+            Config::from_custom_kubeconfig(kubeconfig, &options)
+                .await
+                .map_err(io::Error::other)?
+        }
+        (None, Some(_)) => Config::from_kubeconfig(&options)
+            .await
+            .map_err(io::Error::other)?,
+        (None, None) => Config::infer().await.map_err(io::Error::other)?,
+    };
+    let namespace = pod
+        .namespace
+        .clone()
+        .unwrap_or_else(|| config.default_namespace.clone());
+    let client = Client::try_from(config).map_err(io::Error::other)?;
+    let mut params = AttachParams::default()
+        .stdin(false)
+        .stdout(true)
+        .stderr(true);
+    if let Some(container) = pod.container.as_ref() {
+        params = params.container(container);
+    }
+    Api::<Pod>::namespaced(client, &namespace)
+        .exec(&pod.pod, exec_command(request)?, &params)
+        .await
+        .map_err(io::Error::other)
+}
+
+/// exit code of the command as per the status of the exec, a failure without the exit code (e.g. `sh` missing in the
+/// container) is reported with the exit code 1 like a failed command
+fn exit_code(status: &Status) -> i32 {
+    if status.status.as_deref() == Some("Success") {
+        return 0;
+    }
+    status
+        .details
+        .as_ref()
+        .and_then(|details| details.causes.as_ref())
+        .and_then(|causes| {
+            causes
+                .iter()
+                .find(|cause| cause.reason.as_deref() == Some("ExitCode"))
+        })
+        .and_then(|cause| cause.message.as_deref()?.parse().ok())
+        .unwrap_or(1)
+}
+
+/// forward the chunks of the stream to the reader of the output
+async fn forward(mut stream: impl AsyncRead + Unpin, chunks: Sender<Vec<u8>>) {
+    let mut buffer = vec![0; 8192];
+    while let Ok(count) = stream.read(&mut buffer).await {
+        if count == 0 || chunks.send(buffer[..count].to_vec()).is_err() {
+            break;
+        }
+    }
+}
+
+/// Blocking reader of the output of the exec, it ends once both the streams are closed or the execution is to be
+/// killed (stopped, cancelled or timed out)
+struct OutputReader<'a> {
+    chunks: Receiver<Vec<u8>>,
+    chunk: Vec<u8>,
+    position: usize,
+    request: &'a ProcessRequest,
+    stop: Option<&'a Latch>,
+    shutdown: &'a Latch,
+    deadline: Option<Instant>,
+    termination: Option<Termination>,
+}
+
+impl OutputReader<'_> {
+    /// the termination of the execution if it's to be killed now
+    fn kill_reason(&self) -> Option<Termination> {
+        if self.stop.is_some_and(Latch::is_set)
+            || self.shutdown.is_set()
+            || cancel::is_cancelled(self.request)
+        {
+            return Some(Termination::Killed { by_request: true });
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Some(Termination::Killed { by_request: false });
+        }
+        None
+    }
+}
+
+impl Read for OutputReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.chunk.len() {
+            if self.termination.is_some() {
+                return Ok(0);
+            }
+            match self.chunks.recv_timeout(POLL_INTERVAL) {
+                Ok(chunk) => {
+                    self.chunk = chunk;
+                    self.position = 0;
+                }
+                Err(RecvTimeoutError::Timeout) => self.termination = self.kill_reason(),
+                Err(RecvTimeoutError::Disconnected) => return Ok(0),
+            }
+        }
+        let count = buf.len().min(self.chunk.len() - self.position);
+        buf[..count].copy_from_slice(&self.chunk[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+/// run the request in the pod till it completes or it's stopped, its output lines are streamed as the
+/// [`ProcessEvent::IOData`] events
+pub(crate) fn exec_in_pod(
+    request: Arc<ProcessRequest>,
+    pod: &PodTarget,
+    stop: Option<&Latch>,
+) -> ProcessResult {
+    let mut process_data = ProcessData::new();
+    process_data.request = Some(Arc::clone(&request));
+    if request
+        .pipeline_stages()
+        .first()
+        .is_none_or(|stage| stage.argv.is_empty())
+    {
+        process_data
+            .line
+            .push_str(format!("{:?}", "Command line - arguments are unavailable!").as_str());
+        return check_and_trigger_callback(&request, &ProcessEvent::StartError, &process_data);
+    }
+    if let Some(option) = unsupported_option(&request) {
+        process_data
+            .line
+            .push_str(&format!("The option {} is not supported in a pod", option));
+        return check_and_trigger_callback(&request, &ProcessEvent::StartError, &process_data);
+    }
+    let description = match describe(pod, &request) {
+        Ok(description) => description,
+        Err(error) => {
+            process_data.line.push_str(&format!("{:?}", error));
+            return check_and_trigger_callback(&request, &ProcessEvent::StartError, &process_data);
+        }
+    };
+    if request.dry_run {
+        process_data.line.push_str(&description);
+        return check_and_trigger_callback(&request, &ProcessEvent::Starting, &process_data);
+    }
+    crate::rate_limit::wait_for_spawn_token(&request);
+    let started = Instant::now();
+    process_data
+        .line
+        .push_str(&format!("Executing in the pod {}", pod.pod));
+    check_and_trigger_callback(&request, &ProcessEvent::Starting, &process_data);
+    let attached = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .enable_all()
+        .build()
+        .and_then(|runtime| {
+            let attached = runtime.block_on(attach(pod, &request))?;
+            Ok((runtime, attached))
+        });
+    let (runtime, mut attached) = match attached {
+        Ok(attached) => attached,
+        Err(error) => {
+            process_data.line = error.to_string();
+            let mut process_result =
+                check_and_trigger_callback(&request, &ProcessEvent::StartError, &process_data);
+            process_result.set_spawn_failed();
+            return process_result;
+        }
+    };
+    let (sender, chunks) = mpsc::channel();
+    if let Some(stdout) = attached.stdout() {
+        runtime.spawn(forward(stdout, sender.clone()));
+    }
+    if let Some(stderr) = attached.stderr() {
+        runtime.spawn(forward(stderr, sender.clone()));
+    }
+    drop(sender);
+    let status = attached.take_status().map(|status| runtime.spawn(status));
+    let shutdown = Arc::new(Latch::new());
+    let _registration = shutdown::register_latch(&shutdown);
+    process_data.line.clear();
+    let mut process_result =
+        check_and_trigger_callback(&request, &ProcessEvent::Started, &process_data);
+
+    let mut output = OutputReader {
+        chunks,
+        chunk: vec![],
+        position: 0,
+        request: &request,
+        stop,
+        shutdown: &shutdown,
+        deadline: request.timeout.map(|timeout| started + timeout),
+        termination: None,
+    };
+    let mut line_reader = LineReader::new(&mut output).invalid_utf8(request.invalid_utf8);
+    let mut read_error = None;
+    let mut exit_requested = false;
+    loop {
+        process_data.line.clear();
+        match line_reader.read_line(&mut process_data.line) {
+            Ok(0) => break,
+            Ok(_) => {
+                process_data.line_number += 1;
+                process_result =
+                    check_and_trigger_callback(&request, &ProcessEvent::IOData, &process_data);
+                if process_result.should_exit == Some(true) {
+                    check_and_trigger_callback(
+                        &request,
+                        &ProcessEvent::ExitRequested,
+                        &process_data,
+                    );
+                    exit_requested = true;
+                    break;
+                }
+            }
+            Err(error) => {
+                read_error = Some(error);
+                break;
+            }
+        }
+    }
+    drop(line_reader);
+    let termination = match output.termination {
+        _ if exit_requested => Some(Termination::Killed { by_request: true }),
+        Some(Termination::Killed { by_request: false }) => {
+            if let Some(timeout) = request.timeout {
+                process_data.line = format!("Not completed within {} ms", timeout.as_millis());
+                check_and_trigger_callback(&request, &ProcessEvent::TimedOut, &process_data);
+            }
+            Some(Termination::Killed { by_request: false })
+        }
+        termination => termination,
+    };
+    process_data.line.clear();
+    let exit_code = match termination {
+        Some(_) => {
+            attached.abort();
+            None
+        }
+        None => {
+            let status = status.and_then(|status| runtime.block_on(status).ok().flatten());
+            match (read_error, status) {
+                (Some(error), _) => {
+                    process_data.line = error.to_string();
+                    check_and_trigger_callback(&request, &ProcessEvent::IOError, &process_data);
+                    None
+                }
+                (None, Some(status)) => {
+                    let exit_code = exit_code(&status);
+                    if exit_code == 0 {
+                        check_and_trigger_callback(&request, &ProcessEvent::IOEof, &process_data);
+                    } else {
+                        process_data.line = format!(
+                            "command {:?} exited with code {}: {}",
+                            shell_script::pipeline(&request),
+                            exit_code,
+                            status.message.unwrap_or_default()
+                        );
+                        check_and_trigger_callback(&request, &ProcessEvent::IOError, &process_data);
+                    }
+                    Some(exit_code)
+                }
+                (None, None) => {
+                    process_data.line = String::from("The exec ended without a status");
+                    check_and_trigger_callback(&request, &ProcessEvent::IOError, &process_data);
+                    None
+                }
+            }
+        }
+    };
+    let termination = termination.or(exit_code.map(|code| Termination::Exited { code }));
+    process_data.line.clear();
+    process_data.termination = termination;
+    check_and_trigger_callback(&request, &ProcessEvent::Exited, &process_data);
+    process_result.set_exited(exit_code);
+    process_result.termination = termination;
+    process_result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kubernetes::{describe, exit_code, OutputReader};
+    use crate::latch::Latch;
+    use crate::line_reader::LineReader;
+    use crate::{PodTarget, ProcessData, ProcessEvent, ProcessRequest, ProcessResult, Termination};
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::{Status, StatusCause, StatusDetails};
+    use std::io::Read;
+    use std::sync::mpsc;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    pub fn test_pod_command() {
+        let request = ProcessRequest {
+            cmd_line: vec![
+                vec![String::from("cat"), String::from("app log")],
+                vec![String::from("wc"), String::from("-l")],
+            ],
+            env: [(String::from("LANG"), String::from("C"))].into(),
+            working_dir: Some("/var/log".into()),
+            ..Default::default()
+        };
+        let pod = PodTarget::new("web-0").namespace("shop").container("app");
+        assert_eq!(
+            describe(&pod, &request).unwrap(),
+            r#"[pod: shop/web-0 (app)] "sh" "-c" "export LANG=C; cd /var/log && cat 'app log' | wc -l""#
+        );
+        for name in ["X=1; rm -rf ~;Y", "1ST", ""] {
+            let request = ProcessRequest {
+                env: [(String::from(name), String::from("1"))].into(),
+                ..request.clone()
+            };
+            assert_eq!(
+                describe(&pod, &request).unwrap_err().kind(),
+                std::io::ErrorKind::InvalidInput
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_pod_exit_code() {
+        let success = Status {
+            status: Some(String::from("Success")),
+            ..Default::default()
+        };
+        assert_eq!(exit_code(&success), 0);
+        let failure = Status {
+            status: Some(String::from("Failure")),
+            reason: Some(String::from("NonZeroExitCode")),
+            details: Some(StatusDetails {
+                causes: Some(vec![StatusCause {
+                    reason: Some(String::from("ExitCode")),
+                    message: Some(String::from("3")),
+                    ..Default::default()
+                }]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(exit_code(&failure), 3);
+        let internal_error = Status {
+            status: Some(String::from("Failure")),
+            reason: Some(String::from("InternalError")),
+            ..Default::default()
+        };
+        assert_eq!(exit_code(&internal_error), 1);
+    }
+
+    #[test]
+    pub fn test_pod_start_error() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let recorded = Arc::clone(&events);
+        let callback = move |event: &ProcessEvent, _: &ProcessData| -> ProcessResult {
+            recorded.lock().unwrap().push(*event);
+            ProcessResult::new()
+        };
+        let request = ProcessRequest {
+            request_id: 514,
+            cmd_line: vec![vec![String::from("uptime")]],
+            callback: Some(Arc::new(callback)),
+            pod: Some(PodTarget {
+                kubeconfig: Some(std::env::temp_dir().join("pes_no_such_kubeconfig")),
+                ..PodTarget::new("web-0")
+            }),
+            ..Default::default()
+        };
+        let dry_run = ProcessRequest::start(ProcessRequest {
+            dry_run: true,
+            ..request.clone()
+        });
+        assert!(!dry_run.spawned);
+        let result = ProcessRequest::start(request.clone());
+        assert!(!result.spawned);
+        // the option processing the output lines is rejected before reaching the pod
+        let unsupported = ProcessRequest::start(ProcessRequest {
+            patterns: vec![String::from("ready")],
+            ..request
+        });
+        assert!(!unsupported.spawned);
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                ProcessEvent::Starting,
+                ProcessEvent::Starting,
+                ProcessEvent::StartError,
+                ProcessEvent::StartError
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_pod_output_reader() {
+        let request = ProcessRequest::default();
+        let stop = Latch::new();
+        let shutdown = Latch::new();
+        let (sender, chunks) = mpsc::channel();
+        let mut output = OutputReader {
+            chunks,
+            chunk: vec![],
+            position: 0,
+            request: &request,
+            stop: Some(&stop),
+            shutdown: &shutdown,
+            deadline: None,
+            termination: None,
+        };
+        sender.send(b"first\nsec".to_vec()).unwrap();
+        sender.send(b"ond\n".to_vec()).unwrap();
+        let mut line_reader = LineReader::new(&mut output);
+        let mut lines = vec![];
+        for _ in 0..2 {
+            let mut line = String::new();
+            line_reader.read_line(&mut line).unwrap();
+            lines.push(line);
+        }
+        assert_eq!(lines, ["first\n", "second\n"]);
+        // the streams are still open, the stop ends the output
+        stop.set();
+        assert_eq!(line_reader.read_line(&mut String::new()).unwrap(), 0);
+        drop(line_reader);
+        assert_eq!(
+            output.termination,
+            Some(Termination::Killed { by_request: true })
+        );
+
+        // the shutdown ends the output as well
+        let (_sender, chunks) = mpsc::channel();
+        let mut output = OutputReader {
+            chunks,
+            chunk: vec![],
+            position: 0,
+            request: &request,
+            stop: None,
+            shutdown: &shutdown,
+            deadline: None,
+            termination: None,
+        };
+        shutdown.set();
+        assert_eq!(output.read(&mut [0; 16]).unwrap(), 0);
+        assert_eq!(
+            output.termination,
+            Some(Termination::Killed { by_request: true })
+        );
+    }
+}
@@ -0,0 +1,35 @@
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+/// One way flag which can be waited on, used to signal the helper threads of an execution
+#[derive(Debug, Default)]
+pub(crate) struct Latch {
+    set: Mutex<bool>,
+    condvar: Condvar,
+}
+
+impl Latch {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// set the flag and wake up all the waiting threads
+    pub(crate) fn set(&self) {
+        *self.set.lock().unwrap() = true;
+        self.condvar.notify_all();
+    }
+
+    pub(crate) fn is_set(&self) -> bool {
+        *self.set.lock().unwrap()
+    }
+
+    /// wait for the flag till the timeout, returns true if the flag is set
+    pub(crate) fn wait_timeout(&self, timeout: Duration) -> bool {
+        let set = self.set.lock().unwrap();
+        let (set, _) = self
+            .condvar
+            .wait_timeout_while(set, timeout, |set| !*set)
+            .unwrap();
+        *set
+    }
+}
@@ -1,15 +1,228 @@
 use duct::{cmd, Expression, ReaderHandle};
+use std::any::Any;
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap};
 use std::ffi::OsString;
-use std::fmt::Error;
-use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
 
-use std::sync::Arc;
+use std::sync::{mpsc, Arc};
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
 use std::{io, thread};
 
+use accounting::JobAccount;
+use delayed_start::StartGate;
+use expectations::ExpectationChecker;
+use golden::GoldenComparer;
+use latch::Latch;
+use output_limit::{LineBudget, OutputBudget};
+use records::RecordDecoder;
+use streams::{StreamMerger, StreamTags};
+use termination::KillRecord;
+use throttle::OutputPacer;
+use transform::LineTransformer;
+use watchdog::{Activity, ReadTracker};
+
+mod accounting;
+mod affinity;
+#[cfg(feature = "futures")]
+mod async_events;
+mod batch;
+mod cancel;
+mod checkpoint;
+mod classify;
+pub mod commands;
+#[cfg(feature = "config")]
+mod config;
+#[cfg(feature = "container")]
+mod container;
+mod cron;
+mod delayed_start;
+mod diagnostics;
+#[cfg(feature = "encoding")]
+mod encoding;
+mod env;
+mod error;
+mod event_queue;
+mod executor;
+mod expectations;
+#[cfg(feature = "ssh")]
+mod fan_out;
+mod golden;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod guard;
+mod history;
+#[cfg(feature = "ipc")]
+mod ipc;
+mod json_events;
+#[cfg(feature = "json")]
+mod json_lines;
+#[cfg(feature = "kubernetes")]
+mod kubernetes;
+mod latch;
+mod limits;
+mod line_reader;
+#[cfg(feature = "prometheus")]
+mod metrics;
+#[cfg(feature = "namespaces")]
+mod namespaces;
+mod network;
+mod notifier;
+mod orchestrator;
+#[cfg(feature = "opentelemetry")]
+mod otel;
+mod output_limit;
+mod patterns;
+mod pause;
+mod policy;
+mod pool;
+mod portable;
+mod priority;
+mod quota;
+mod rate_limit;
+mod records;
+mod redirect;
+mod reload;
+#[cfg(any(feature = "server", feature = "grpc", feature = "ipc"))]
+mod remote;
+mod repl;
+mod report;
+mod resource;
+mod retry;
+mod run;
+mod scheduler;
+#[cfg(feature = "seccomp")]
+mod seccomp;
+#[cfg(feature = "serde")]
+mod serde_support;
+#[cfg(feature = "server")]
+mod server;
+mod service;
+mod session;
+mod shell;
+#[cfg(any(feature = "ssh", feature = "container", feature = "kubernetes"))]
+mod shell_script;
+mod shell_session;
+mod shutdown;
+#[cfg(feature = "sqlite")]
+mod sqlite_store;
+#[cfg(feature = "ssh")]
+mod ssh;
+#[cfg(feature = "ssh")]
+mod ssh_pool;
+mod stages;
+mod status;
+mod streams;
+mod supervisor;
+mod tail;
+mod termination;
+mod throttle;
+mod token;
+mod trace_context;
+#[cfg(feature = "tracing")]
+mod tracing_support;
+mod transform;
+mod user;
+mod warning;
+mod watchdog;
+#[cfg(feature = "webhook")]
+mod webhook;
+#[cfg(feature = "winrm")]
+mod winrm;
+
+pub use accounting::JobAccounting;
+#[cfg(feature = "futures")]
+pub use async_events::{async_event_channel, AsyncEventSink, AsyncEventStream};
+pub use batch::{BatchHandle, BatchItem, BatchOptions, BatchSummary};
+pub use cancel::CancellationToken;
+pub use checkpoint::{Checkpoint, Checkpointer, FileCheckpointer, MemoryCheckpointer};
+pub use classify::{LineClass, LineClassifier, RegexClassifier};
+#[cfg(feature = "container")]
+pub use container::{ContainerMount, ContainerRuntime, ContainerSource, ContainerTarget};
+pub use cron::CronSchedule;
+pub use diagnostics::{active_threads, leaked_children, set_leak_check};
+pub use env::{EnvDiff, EnvInheritance};
+pub use error::ProcessError;
+pub use event_queue::{EventQueue, OverflowPolicy, QueuedEvent};
+pub use executor::{DuctExecutor, MockExecutor, ProcessExecutor};
+pub use expectations::OutputExpectation;
+#[cfg(feature = "ssh")]
+pub use fan_out::{FanOutHandle, FanOutSummary, HostOutcome};
+#[cfg(feature = "grpc")]
+pub use grpc::{proto, ProcessGrpcService};
+pub use guard::ProcessGuard;
+pub use history::{ExecutionRecord, HistoryQuery, HistoryStore, MemoryHistory};
+#[cfg(feature = "ipc")]
+pub use ipc::IpcServer;
+pub use json_events::JsonEventSink;
+#[cfg(feature = "kubernetes")]
+pub use kubernetes::PodTarget;
+pub use limits::ResourceLimit;
+pub use line_reader::{InvalidUtf8, LineReader};
+#[cfg(feature = "prometheus")]
+pub use metrics::ProcessMetrics;
+#[cfg(feature = "namespaces")]
+pub use namespaces::{BindMount, Namespaces};
+pub use notifier::{Notification, NotificationKind, Notifier};
+#[cfg(feature = "webhook")]
+pub use notifier::{SlackNotifier, SmtpNotifier};
+pub use orchestrator::{
+    FailurePolicy, OrchestrationSummary, Orchestrator, TaskBuilder, TaskOutcome, TaskStatus,
+};
+pub use output_limit::OutputLimitAction;
+pub use patterns::PatternWaiter;
+pub use policy::CommandPolicy;
+pub use pool::{PoolHandle, ProcessPool};
+pub use portable::{native_path, null_device, PortableCommand};
+pub use priority::{PriorityInheritance, ProcessPriority, ThreadPriority};
+pub use quota::{QuotaRegistry, TagQuota};
+pub use rate_limit::RateLimiter;
+pub use records::{FieldDelimiter, RecordFormat};
+pub use redirect::FileRedirect;
+pub use reload::ReloadAction;
+pub use repl::{ReplDriver, ReplResponse};
+pub use report::{BatchReport, CommandStats, RuntimePercentiles};
+pub use resource::ResourceUsage;
+pub use retry::{Backoff, RetryOn, RetryPolicy};
+pub use run::{run_cmd, run_shell, CommandOutput};
+pub use scheduler::{Schedule, Scheduler};
+#[cfg(feature = "seccomp")]
+pub use seccomp::{SyscallFilter, BASELINE_DENIED_SYSCALLS};
+#[cfg(feature = "server")]
+pub use server::ProcessServer;
+pub use service::ServiceManager;
+pub use session::{SessionRecorder, SessionReplayer};
+pub use shell::{build_shell_line, shell_quote, ShellKind};
+pub use shell_session::ShellSession;
+pub use shutdown::shutdown_all;
+#[cfg(feature = "sqlite")]
+pub use sqlite_store::SqliteStore;
+#[cfg(feature = "ssh")]
+pub use ssh::{RemoteTarget, SshAuth, SshMultiplex};
+#[cfg(feature = "ssh")]
+pub use ssh_pool::SshConnectionPool;
+pub use stages::{PipelineStage, StageInfo};
+pub use streams::{OutputStream, StreamOrigin};
+pub use supervisor::{Supervisor, SupervisorPolicy};
+pub use tail::{tail_file, TailExecutor};
+pub use termination::Termination;
+pub use throttle::OutputThrottle;
+pub use token::{IntegrityLevel, RestrictedToken};
+pub use trace_context::TraceContext;
+pub use transform::{LineMapper, LineTransform};
+pub use user::UserSpec;
+pub use warning::WarningKind;
+pub use watchdog::Heartbeat;
+#[cfg(feature = "webhook")]
+pub use webhook::{WebhookNotifier, WebhookTrigger};
+#[cfg(feature = "winrm")]
+pub use winrm::{WinRmAuth, WinRmTarget};
+
 /// Various events associated with process's life-cycle
 ///
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ProcessEvent {
     /// Default value placeholder
     _Unknown,
@@ -19,21 +232,99 @@ pub enum ProcessEvent {
     Started,
     /// Error occurred while starting the process itself
     StartError,
-    /// Process started but error occurred during reading the output data
+    /// Process started but error occurred during reading the output data, including a non-zero exit status unless
+    /// [`ProcessRequest::unchecked_exit_status`] is set
     IOError,
     /// Process started and output data reader reached to the EOF, means process's output data is unavailable
     IOEof,
     /// Process started and a line from the output data is available now
     IOData,
     /// Process started and during IOData reading based on the API consumer's decision the callback returned [`Some(false)`] ,
-    /// which means process's exit request is submitted
+    /// which means process's exit request is submitted, or the [`ProcessRequest::cancellation`] token is cancelled
     ExitRequested,
     /// Kill API was used to kill the process
     KillRequested,
+    /// The running process is frozen by [`ProcessData::pause`]
+    Paused,
+    /// The paused process continues running after [`ProcessData::resume`]
+    Resumed,
     /// Process which was started earlier now exited
     Exited,
     /// A error occurred while killing/stopping the process
     KillError,
+    /// Periodic resource usage sample of the running process, see [`ProcessData::resource_usage`]
+    ResourceSample,
+    /// Process was terminated because it exceeded one of the [`ProcessRequest::resource_limits`]
+    ResourceLimitExceeded,
+    /// Process failed and another attempt is scheduled as per the [`ProcessRequest::retry`] policy
+    RetryScheduled,
+    /// Scheduled attempt is starting now
+    RetryStarted,
+    /// Supervised process exited and is restarted as per the [`SupervisorPolicy`]
+    Restarted,
+    /// Supervised process exited too many times within the restart window and won't be restarted anymore
+    RestartLimitReached,
+    /// No output line within the [`ProcessRequest::idle_timeout`], the process is killed
+    IdleTimeout,
+    /// A single read of the output blocked longer than the [`ProcessRequest::read_timeout`], the process is killed if
+    /// [`ProcessRequest::kill_on_read_stall`] is set
+    ReadStalled,
+    /// Output exceeded the [`ProcessRequest::max_output_bytes`], the rest is handled as per the
+    /// [`ProcessRequest::output_limit_action`]
+    OutputLimitExceeded,
+    /// Process is queued to start later as per [`ProcessRequest::start_after`] or [`ProcessRequest::start_at`]
+    Scheduled,
+    /// Scheduled process was cancelled before it started, see [`ProcessResult::cancel_before_start`]
+    ScheduleCancelled,
+    /// Process was not run because of its dependencies or the failure policy of the [`Orchestrator`]
+    Skipped,
+    /// Spawn is delayed by the [`RateLimiter`], the process starts once a spawn token is available
+    Queued,
+    /// Process didn't complete within the [`ProcessRequest::timeout`], the process is killed
+    TimedOut,
+    /// A command of a multi command pipeline is started, see [`ProcessData::stage`]
+    StageStarted,
+    /// A command of a multi command pipeline exited, see [`ProcessData::stage`]
+    StageExited,
+    /// An output line matched one of the [`ProcessRequest::patterns`], see [`ProcessData::pattern_index`]
+    PatternMatched,
+    /// An output line of the [`ProcessRequest::json_lines`] mode is not valid JSON, see [`ProcessData::json_error`]
+    JsonParseError,
+    /// Periodic liveness signal of the running process as per [`ProcessRequest::heartbeat_interval`], see [`ProcessData::heartbeat`]
+    Heartbeat,
+    /// The output failed one of the [`ProcessRequest::expectations`], see [`ProcessData::expectation_index`]
+    ExpectationFailed,
+    /// An output line matched one of the [`ProcessRequest::fail_on_patterns`], the execution is failed (see
+    /// [`ProcessData::pattern_index`] & [`ProcessResult::fail_line`])
+    FailPatternMatched,
+    /// An output line matched one of the [`ProcessRequest::succeed_on_patterns`], the process is killed & the execution
+    /// is successful (see [`ProcessData::pattern_index`] & [`ProcessResult::success_line`])
+    SuccessPatternMatched,
+    /// The followed file was rotated (replaced or truncated), see [`tail_file`]
+    FileRotated,
+    /// Reload of the supervised process is requested, see [`Supervisor::reload`]
+    ReloadRequested,
+    /// Supervised process was asked to reload as per the [`SupervisorPolicy::reload_action`]
+    Reloaded,
+    /// An output line differs from the [`ProcessRequest::golden_file`], or the output ended early. The line describes
+    /// the difference
+    GoldenMismatch,
+    /// The remote host is unreachable, the request runs on the next failover host (see the line for both)
+    HostFailover,
+    /// Output line repeated from the earlier runs of a supervised process was suppressed as per the
+    /// [`SupervisorPolicy::dedupe_output`], emitted once per line when the supervisor finishes. See
+    /// [`ProcessData::repeat_count`]
+    RepeatedOutput,
+    /// The request violates the [`CommandPolicy`], it's rejected before it's spawned (see the line for the reason)
+    PolicyViolation,
+    /// The request is over the quota of its tag, it's rejected before it's spawned (see the line for the limit), see
+    /// [`QuotaRegistry`]
+    QuotaExceeded,
+    /// How the environment of the process differs from the environment of this process, emitted before
+    /// [`ProcessEvent::Starting`] if [`ProcessRequest::report_env_diff`] is set. See [`ProcessData::env_diff`]
+    EnvironmentDiff,
+    /// A non-fatal internal condition, e.g. an output line was truncated or an event was dropped, see [`WarningKind`]
+    Warning(WarningKind),
 }
 
 /// Various fields related to the process
@@ -43,10 +334,67 @@ pub struct ProcessData<'a> {
     pub request: Option<Arc<ProcessRequest>>,
     /// Line number from output of the Process's STDOUT & STDERR
     pub line_number: i64,
-    /// A single line data from output of the Process's STDOUT & STDERR
+    /// A single line data from output of the Process's STDOUT & STDERR. The buffer is reused for every line, use
+    /// [`ProcessData::line_str`] for a view without the line break & [`ProcessData::line_to_owned`] to keep the line
     pub line: String,
+    /// Resource usage of the process, available with the [`ProcessEvent::ResourceSample`] event
+    pub resource_usage: Option<ResourceUsage>,
+    /// Pipeline command, available with the [`ProcessEvent::StageStarted`] & [`ProcessEvent::StageExited`] events
+    pub stage: Option<StageInfo>,
+    /// How the process terminated, available with the [`ProcessEvent::Exited`] event
+    pub termination: Option<Termination>,
+    /// Liveness details, available with the [`ProcessEvent::Heartbeat`] event
+    pub heartbeat: Option<Heartbeat>,
+    /// Index of the matched pattern in [`ProcessRequest::patterns`], available with the [`ProcessEvent::PatternMatched`] event,
+    /// in [`ProcessRequest::fail_on_patterns`] with the [`ProcessEvent::FailPatternMatched`] event or in
+    /// [`ProcessRequest::succeed_on_patterns`] with the [`ProcessEvent::SuccessPatternMatched`] event
+    pub pattern_index: Option<usize>,
+    /// Index of the failed expectation in [`ProcessRequest::expectations`], available with the [`ProcessEvent::ExpectationFailed`]
+    /// event. The line is the failing output line, or the reason if the expectation failed at the end of the output
+    pub expectation_index: Option<usize>,
+    /// Fields of the output line as per the [`ProcessRequest::record_format`], available with the [`ProcessEvent::IOData`]
+    /// event. None for the header line
+    pub record: Option<Vec<String>>,
+    /// Field names from the header line as per the [`ProcessRequest::record_format`]
+    pub record_header: Option<Arc<Vec<String>>>,
+    /// Checkpoint of the previous execution loaded at the start, available from the [`ProcessEvent::Started`] event on,
+    /// see [`ProcessRequest::checkpointer`]
+    pub checkpoint: Option<Checkpoint>,
+    /// Stream & sequence of the output line in the [`ProcessRequest::tag_output_streams`] mode, available with the
+    /// [`ProcessEvent::IOData`] event
+    pub origin: Option<StreamOrigin>,
+    /// Number of times the line was suppressed, available with the [`ProcessEvent::RepeatedOutput`] event
+    pub repeat_count: Option<u64>,
+    /// Differences of the environment of the process, available with the [`ProcessEvent::EnvironmentDiff`] event
+    pub env_diff: Option<EnvDiff>,
+    /// Random delay applied before the spawn as per the [`ProcessRequest::start_jitter`], available from the
+    /// [`ProcessEvent::Starting`] event on
+    pub start_delay: Option<Duration>,
+    /// Class of the output line as per the [`ProcessRequest::line_classifier`], available with the
+    /// [`ProcessEvent::IOData`] event
+    pub line_class: Option<LineClass>,
+    /// JSON value of the output line in the [`ProcessRequest::json_lines`] mode, available with the [`ProcessEvent::IOData`] event
+    #[cfg(feature = "json")]
+    pub json: Option<serde_json::Value>,
+    /// Parse error of the output line, available with the [`ProcessEvent::JsonParseError`] event
+    #[cfg(feature = "json")]
+    pub json_error: Option<String>,
+    /// Internal time of the event, see [`ProcessData::timestamp`]
+    timestamp: Cell<SystemTime>,
+    /// Internal time elapsed since the start of the execution at the event, see [`ProcessData::elapsed`]
+    elapsed: Cell<Duration>,
+    /// Internal start of the execution
+    started: Instant,
     /// Internal reader handle for managing the process
     reader: Option<&'a ReaderHandle>,
+    /// Internal record of who killed the process
+    kill_record: Option<&'a KillRecord>,
+}
+
+impl Default for ProcessData<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ProcessData<'_> {
@@ -57,38 +405,167 @@ impl ProcessData<'_> {
             request: None,
             line_number: 0,
             line: String::new(),
+            resource_usage: None,
+            stage: None,
+            termination: None,
+            heartbeat: None,
+            pattern_index: None,
+            expectation_index: None,
+            record: None,
+            record_header: None,
+            checkpoint: None,
+            origin: None,
+            repeat_count: None,
+            env_diff: None,
+            start_delay: None,
+            line_class: None,
+            #[cfg(feature = "json")]
+            json: None,
+            #[cfg(feature = "json")]
+            json_error: None,
+            timestamp: Cell::new(SystemTime::now()),
+            elapsed: Cell::new(Duration::ZERO),
+            started: Instant::now(),
             reader: None,
+            kill_record: None,
         }
     }
     /// Kill the running process
     pub fn kill(&self) -> io::Result<()> {
-        Ok(if self.reader.is_some() {
+        if let Some(reader) = self.reader {
+            if let Some(kill_record) = self.kill_record {
+                kill_record.record(true);
+            }
             check_and_trigger_callback(
-                &self.request.as_ref().unwrap(),
+                self.request.as_ref().unwrap(),
                 &ProcessEvent::KillRequested,
-                &self,
+                self,
             );
-            return self.reader.as_ref().unwrap().kill();
-        })
+            return reader.kill();
+        }
+        Ok(())
+    }
+
+    /// Pause (freeze) all the commands of the running process, using SIGSTOP on Unix & suspending their threads on
+    /// Windows, e.g. to free the CPU for a while. Its timeouts keep counting meanwhile
+    pub fn pause(&self) -> io::Result<()> {
+        self.set_paused(true)
+    }
+
+    /// Resume the process paused by [`ProcessData::pause`]
+    pub fn resume(&self) -> io::Result<()> {
+        self.set_paused(false)
+    }
+
+    /// pause or resume the running process & trigger the event
+    fn set_paused(&self, paused: bool) -> io::Result<()> {
+        if let Some(reader) = self.reader {
+            pause::set_paused(&reader.pids(), paused)?;
+            let event = if paused {
+                ProcessEvent::Paused
+            } else {
+                ProcessEvent::Resumed
+            };
+            check_and_trigger_callback(self.request.as_ref().unwrap(), &event, self);
+        }
+        Ok(())
+    }
+
+    /// The line without its line break, a view of the reused line buffer valid for the callback
+    pub fn line_str(&self) -> &str {
+        self.line.trim_end_matches(['\r', '\n'])
+    }
+
+    /// Copy of the line without its line break, to keep it after the callback
+    pub fn line_to_owned(&self) -> String {
+        self.line_str().to_owned()
+    }
+
+    /// User context of the request if it's of the type `T`, see [`ProcessRequest::context`]
+    pub fn context<T: Any>(&self) -> Option<&T> {
+        self.request.as_ref()?.context.as_ref()?.downcast_ref::<T>()
+    }
+
+    /// Category of the exit code as per the [`ProcessRequest::exit_categories`], available with the
+    /// [`ProcessEvent::Exited`] event
+    pub fn exit_category(&self) -> Option<&str> {
+        self.request
+            .as_ref()?
+            .exit_category(self.termination.as_ref())
+    }
+
+    /// Traceparent of the execution, see [`ProcessRequest::trace_context`]. None without a trace context
+    pub fn traceparent(&self) -> Option<&str> {
+        self.request
+            .as_ref()?
+            .trace_context
+            .as_ref()?
+            .traceparent
+            .as_deref()
+    }
+
+    /// Host of the remote target the request runs on, e.g. to tell the hosts apart in the callback of
+    /// [`ProcessRequest::start_on_hosts`]. None for the local execution
+    #[cfg(feature = "ssh")]
+    pub fn remote_host(&self) -> Option<&str> {
+        Some(self.request.as_ref()?.remote.as_ref()?.host.as_str())
+    }
+
+    /// Wall-clock time of the event, the creation time of the data till an event is triggered with it
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp.get()
+    }
+
+    /// Monotonic time elapsed since the start of the execution at the event
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed.get()
+    }
+
+    /// data of a running execution started at the given time, for the threads watching it
+    pub(crate) fn for_execution<'a>(
+        request: &Arc<ProcessRequest>,
+        reader: &'a ReaderHandle,
+        kill_record: &'a KillRecord,
+        started: Instant,
+    ) -> ProcessData<'a> {
+        let mut process_data = ProcessData::new();
+        process_data.request = Some(Arc::clone(request));
+        process_data.reader = Some(reader);
+        process_data.kill_record = Some(kill_record);
+        process_data.started = started;
+        process_data
+    }
+
+    /// stamp the data with the current time of the event
+    fn stamp(&self) {
+        self.timestamp.set(SystemTime::now());
+        self.elapsed.set(self.started.elapsed());
     }
 
     /// Get the list of child pids
     pub fn child_pids(&self) -> Vec<u32> {
-        if self.reader.is_some() {
-            return self.reader.as_ref().unwrap().pids();
+        if let Some(reader) = self.reader {
+            return reader.pids();
         }
-        return vec![];
+        vec![]
     }
 }
 
 /// Resulted data received from the process execution
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct ProcessResult {
     /// In case of non-blocking mode use this to join and wait for the process to complete
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub join_handle: Option<io::Result<JoinHandle<ProcessResult>>>,
+    /// In case of non-blocking mode run on a [`ProcessPool`] use this to wait for the process to complete, instead of the `join_handle`
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub pool_handle: Option<PoolHandle>,
     /// Should exit or not the process based on the custom conditions
     pub should_exit: Option<bool>,
     /// Process execution was successful or not for the desired outcome
+    #[cfg_attr(feature = "serde", serde(with = "serde_support::io_result"))]
     pub success: Result<bool, std::io::Error>,
     /// Date as String vector
     pub data_vec_str: Option<Vec<String>>,
@@ -98,19 +575,139 @@ pub struct ProcessResult {
     pub data_num: Option<i128>,
     /// Date as f64 value
     pub data_decimal: Option<f64>,
+    /// Peak resource usage observed while sampling, see [`ProcessRequest::resource_sample_interval`]
+    pub peak_resource_usage: Option<ResourceUsage>,
+    /// Resource usage of the whole process tree, see [`ProcessRequest::job_accounting`]
+    pub job_accounting: Option<JobAccounting>,
+    /// Exit code of the process, None if it was not started, was killed or terminated by a signal
+    pub exit_code: Option<i32>,
+    /// How the process terminated, None if it was not started
+    pub termination: Option<Termination>,
+    /// Category of the exit code as per the [`ProcessRequest::exit_categories`], None if it has none
+    pub exit_category: Option<String>,
+    /// Number of the output lines by their class as per the [`ProcessRequest::line_classifier`]
+    pub line_classes: BTreeMap<LineClass, u64>,
+    /// Number of attempts made to run the process, see [`ProcessRequest::retry`]
+    pub attempts: u32,
+    /// Total wall-clock duration of the execution from the start of the first attempt, None if it was not run
+    pub duration: Option<Duration>,
+    /// Output exceeded the [`ProcessRequest::max_output_bytes`]
+    pub output_limit_exceeded: bool,
+    /// Total time the output reading was paused by the [`ProcessRequest::output_throttle`]
+    pub output_throttled: Duration,
+    /// Process was spawned successfully
+    #[cfg_attr(feature = "serde", serde(skip))]
+    spawned: bool,
+    /// Spawning the process failed, unlike the errors of the request found before the spawn it's retried
+    #[cfg_attr(feature = "serde", serde(skip))]
+    spawn_failed: bool,
+    /// Output matched the [`ProcessRequest::golden_file`], None if there is none or the output was cut short
+    pub golden_matched: Option<bool>,
+    /// First output line which matched the [`ProcessRequest::fail_on_patterns`], without its line break
+    pub fail_line: Option<String>,
+    /// Output line which matched the [`ProcessRequest::succeed_on_patterns`], without its line break
+    pub success_line: Option<String>,
+    /// Time from the start of the process till the `success_line`
+    pub time_to_success: Option<Duration>,
+    /// Output failed one of the expectations, the golden file or matched a fail pattern of the request
+    #[cfg_attr(feature = "serde", serde(skip))]
+    expectation_failed: bool,
+    /// Gate of the delayed start in non-blocking mode
+    #[cfg_attr(feature = "serde", serde(skip))]
+    start_gate: Option<Arc<StartGate>>,
+}
+
+impl Default for ProcessResult {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ProcessResult {
     pub fn new() -> Self {
         Self {
             join_handle: None,
+            pool_handle: None,
             should_exit: None,
             success: Ok(false),
             data_vec_str: None,
             data_bool: None,
             data_num: None,
             data_decimal: None,
+            peak_resource_usage: None,
+            job_accounting: None,
+            exit_code: None,
+            termination: None,
+            exit_category: None,
+            line_classes: BTreeMap::new(),
+            attempts: 0,
+            duration: None,
+            output_limit_exceeded: false,
+            output_throttled: Duration::ZERO,
+            spawned: false,
+            spawn_failed: false,
+            golden_matched: None,
+            fail_line: None,
+            success_line: None,
+            time_to_success: None,
+            expectation_failed: false,
+            start_gate: None,
+        }
+    }
+
+    /// Cancel a delayed start in non-blocking mode, see [`ProcessRequest::start_after`].
+    /// Returns false if the process is already started or the start was not delayed
+    pub fn cancel_before_start(&self) -> bool {
+        self.start_gate
+            .as_ref()
+            .is_some_and(|start_gate| start_gate.cancel())
+    }
+
+    /// Wait for the non-blocking mode execution to complete & return its result, the result of a blocking mode
+    /// execution is returned as is
+    pub fn wait(mut self) -> Result<ProcessResult, ProcessError> {
+        if let Some(pool_handle) = self.pool_handle.take() {
+            return pool_handle.wait().ok_or(ProcessError::Panicked);
+        }
+        match self.join_handle.take() {
+            Some(Ok(join_handle)) => join_handle.join().map_err(|_| ProcessError::Panicked),
+            Some(Err(error)) => Err(ProcessError::Spawn(error)),
+            None => Ok(self),
+        }
+    }
+
+    /// Wait till the timeout for the non-blocking mode execution to complete & return its result. On timeout
+    /// [`ProcessError::TimedOut`] has this pending result to wait again
+    pub fn wait_timeout(self, timeout: Duration) -> Result<ProcessResult, ProcessError> {
+        if let Some(pool_handle) = self.pool_handle.as_ref() {
+            return match pool_handle.wait_timeout(timeout) {
+                Ok(result) => Ok(result),
+                Err(mpsc::RecvTimeoutError::Timeout) => Err(ProcessError::TimedOut(Box::new(self))),
+                Err(mpsc::RecvTimeoutError::Disconnected) => Err(ProcessError::Panicked),
+            };
         }
+        if let Some(Ok(join_handle)) = self.join_handle.as_ref() {
+            let deadline = Instant::now() + timeout;
+            while !join_handle.is_finished() {
+                let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+                    return Err(ProcessError::TimedOut(Box::new(self)));
+                };
+                thread::sleep(remaining.min(WAIT_POLL_INTERVAL));
+            }
+        }
+        self.wait()
+    }
+
+    /// Mark the process as spawned & exited with the exit code (None if killed), for the custom [`ProcessExecutor`]
+    pub fn set_exited(&mut self, exit_code: Option<i32>) {
+        self.spawned = true;
+        self.exit_code = exit_code;
+        self.termination = exit_code.map(|code| Termination::Exited { code });
+    }
+
+    /// Mark the process as failed to spawn, to retry it as per the [`RetryPolicy`], for the custom [`ProcessExecutor`]
+    pub fn set_spawn_failed(&mut self) {
+        self.spawn_failed = true;
     }
 
     /// set join handle
@@ -129,24 +726,291 @@ impl ProcessResult {
     }
 }
 
-unsafe impl Sync for ProcessRequest {}
-unsafe impl Send for ProcessRequest {}
+/// How often a non-blocking mode thread is checked for completion by [`ProcessResult::wait_timeout`]
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Callback to receive the process events and data. It's called from the reading thread & from the threads watching
+/// the process (e.g. the timeouts & the resource sampler) at the same time, hence it must be thread-safe
+pub type ProcessCallback =
+    dyn Fn(&ProcessEvent, &ProcessData) -> ProcessResult + Send + Sync + 'static;
 
 /// A request structure to start a process
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct ProcessRequest {
     /// Custom unique numeric id to relate the various callbacks for a particular process execution session
     pub request_id: u32,
     /// Use shell mode or direct executable path based execution
     pub use_shell: bool,
+    /// Shell to run the shell mode commands with, it's not used for the remote backends: the SSH, container & pod ones use `sh` & the WinRM one uses PowerShell
+    pub shell: ShellKind,
     /// Use blocking or non blocking mode using internal threads
     pub non_blocking_mode: bool,
+    /// Run the non blocking mode request on the workers of this pool instead of a new thread, for the global [`ProcessPool`]
+    /// (if set) use None. The result has the `pool_handle` instead of the `join_handle`
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub worker_pool: Option<Arc<ProcessPool>>,
+    /// Run the request using this executor (e.g. [`MockExecutor`] in tests), for spawning the real process use None
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub executor: Option<Arc<dyn ProcessExecutor>>,
+    /// Only resolve the command line & emit [`ProcessEvent::Starting`] with it, nothing is spawned
+    pub dry_run: bool,
     /// (2D Array) Vector of command line along with arguments. For a single command line one vector element is enough. For the pipe line use case where output of one command to provide to the next command, use Vector of command lines.
     pub cmd_line: Vec<Vec<String>>,
+    /// Pipeline commands each with its own shell mode, when set it's used instead of `cmd_line` & `use_shell`
+    pub stages: Vec<PipelineStage>,
+    /// Run the command line on this host over SSH, for the local execution use None
+    #[cfg(feature = "ssh")]
+    pub remote: Option<RemoteTarget>,
+    /// Run the command line on this Windows host over WinRM, for the local execution use None. Ignored if `remote` is
+    /// set
+    #[cfg(feature = "winrm")]
+    pub winrm: Option<WinRmTarget>,
+    /// Run the command line in this container, for the local execution use None. Ignored if `remote` or `winrm` is set
+    #[cfg(feature = "container")]
+    pub container: Option<ContainerTarget>,
+    /// Run the command line in this Kubernetes pod, for the local execution use None. Ignored if `remote`, `winrm` or
+    /// `container` is set
+    #[cfg(feature = "kubernetes")]
+    pub pod: Option<PodTarget>,
+    /// Environment variables to set for the process (all the commands of a pipeline), in addition to the inherited ones
+    pub env: HashMap<String, String>,
+    /// Dotenv file with the environment variables to set, the variables of `env` take precedence. Only for the local execution
+    pub env_file: Option<PathBuf>,
+    /// Which environment variables of the parent the process inherits, only for the local execution
+    pub env_inheritance: EnvInheritance,
+    /// Emit the [`ProcessEvent::EnvironmentDiff`] event with the variables added, overridden & removed compared to the
+    /// environment of this process (the secrets masked), to debug the differences from a shell. Only for the local
+    /// execution
+    pub report_env_diff: bool,
+    /// Correlation id set in the environment of the process & available on every event, for no trace context use None
+    pub trace_context: Option<TraceContext>,
+    /// Working directory of the process, for the current directory use None
+    pub working_dir: Option<PathBuf>,
+    /// Read the STDIN of the pipeline from this file, for no input use None
+    pub stdin_path: Option<PathBuf>,
+    /// Write the STDOUT of the pipeline to this file instead of streaming it as the [`ProcessEvent::IOData`] events
+    pub stdout_path: Option<FileRedirect>,
+    /// Write the STDERR of the pipeline to this file instead of streaming it as the [`ProcessEvent::IOData`] events
+    pub stderr_path: Option<FileRedirect>,
     /// Register callback to get various events and process output, for no callbacks use None
-    pub callback: Option<Arc<dyn Fn(&ProcessEvent, &ProcessData) -> ProcessResult + 'static>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub callback: Option<Arc<ProcessCallback>>,
+    /// Record the executions into these Prometheus metrics (share them between requests), for no metrics use None
+    #[cfg(feature = "prometheus")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub metrics: Option<Arc<ProcessMetrics>>,
+    /// Record every event to this recorder (share it between requests) to replay later, for no recording use None
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub recorder: Option<Arc<SessionRecorder>>,
+    /// Write every event as a JSON line to this sink (share it between requests), for no JSON events use None
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub json_events: Option<Arc<JsonEventSink>>,
+    /// Queue every event to this bounded queue (share it between requests) to consume it from another thread, for no queue use None
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub event_queue: Option<Arc<EventQueue>>,
+    /// Send every event to this async channel (clone the sink to share it between requests), see
+    /// [`async_event_channel`]. For no async consumer use None
+    #[cfg(feature = "futures")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub async_events: Option<AsyncEventSink>,
+    /// Notify the webhooks on the lifecycle transitions of the process, for no notifications use None
+    #[cfg(feature = "webhook")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub webhook: Option<Arc<WebhookNotifier>>,
+    /// Store the completed executions (all the attempts) in this history (share it between requests) to query them
+    /// later, for no history use None
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub history: Option<Arc<dyn HistoryStore>>,
+    /// Save the position of the last processed output line in this checkpointer (share it between requests) by the
+    /// request id, the checkpoint of the previous execution is passed in [`ProcessData::checkpoint`]. A line counts as
+    /// processed once the callback returns. For no checkpoints use None
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub checkpointer: Option<Arc<dyn Checkpointer>>,
+    /// Skip the output lines up to the checkpoint of the previous execution, e.g. for a command replaying its output
+    /// from the beginning, see [`ProcessRequest::checkpointer`]
+    pub resume_from_checkpoint: bool,
+    /// Notify the failures of the runs of the [`Supervisor`] & the [`Scheduler`] (e.g. by email or on Slack), for no
+    /// notifications use None
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub notifier: Option<Arc<dyn Notifier>>,
+    /// Sample the CPU & memory usage of the process at this interval and emit [`ProcessEvent::ResourceSample`] events, for no sampling use None
+    pub resource_sample_interval: Option<Duration>,
+    /// Account the CPU time, IO & peak memory of the whole process tree (including the children of the process) in
+    /// [`ProcessResult::job_accounting`]. It uses a cgroup v2 on Linux & a Job Object on Windows, the result is None
+    /// where they are not available
+    pub job_accounting: bool,
+    /// Resource limits (rlimits) to apply on the process, Unix only
+    pub resource_limits: Vec<ResourceLimit>,
+    /// Scheduling priority of the process, for the default (inherited) priority use None
+    pub priority: Option<ProcessPriority>,
+    /// Adopt the priority of the calling thread (or an explicit one) in the thread running the non-blocking mode
+    /// execution & the output reader threads, and in the process unless it has its own `priority`. It includes the QoS
+    /// class on macOS. The workers of a pool keep their own priority. For the default priorities use None
+    pub inherit_priority: Option<PriorityInheritance>,
+    /// Pin the process to this set of CPU cores (zero based index), Linux & Windows only. For no pinning use None
+    pub cpu_affinity: Option<Vec<usize>>,
+    /// Run the process as this user, Unix only. A permission error of switching the user is told apart in the
+    /// [`ProcessEvent::StartError`] details. For the current user use None
+    pub run_as: Option<UserSpec>,
+    /// Run the process in a new network namespace with only the loopback interface, so it's offline. Linux only,
+    /// without the privileges it needs the user namespaces enabled
+    pub deny_network: bool,
+    /// Run the process with a restricted token (e.g. at the low integrity level), Windows only. A failure of
+    /// restricting the token is told apart in the [`ProcessEvent::StartError`] details. For the inherited token use
+    /// None
+    pub restricted_token: Option<RestrictedToken>,
+    /// Isolate the process in new Linux namespaces, for no isolation use None
+    #[cfg(feature = "namespaces")]
+    pub namespaces: Option<Namespaces>,
+    /// Seccomp filter of the syscalls installed in the process before exec, for no filter use None
+    #[cfg(feature = "seccomp")]
+    pub syscall_filter: Option<SyscallFilter>,
+    /// Exit codes meaning success, [`ProcessResult::success`] is set as per the exit code unless the callback sets it
+    /// using [`ProcessResult::set_exit_flag_and_success`]. For only the exit code 0 use None
+    pub success_exit_codes: Option<Vec<i32>>,
+    /// Report a non-zero exit status or a signal as the end of the output ([`ProcessEvent::IOEof`]) followed by the
+    /// [`ProcessEvent::Exited`] event with the exit code, instead of the [`ProcessEvent::IOError`] event (duct's
+    /// unchecked mode). For commands which legitimately exit non-zero (e.g. `grep`, `diff`)
+    pub unchecked_exit_status: bool,
+    /// Categories (or messages) of the exit codes e.g. 2 → "usage error", see [`ProcessResult::exit_category`] &
+    /// [`ProcessData::exit_category`]. A process terminated by a signal has the exit code 128 + signal like in the
+    /// shells e.g. 137 for SIGKILL
+    pub exit_categories: BTreeMap<i32, String>,
+    /// Retry policy on failures, for no retries use None
+    pub retry: Option<RetryPolicy>,
+    /// Kill the process if it doesn't complete within this duration, for no timeout use None
+    pub timeout: Option<Duration>,
+    /// Kill the process if no output line arrives within this duration, for no timeout use None
+    pub idle_timeout: Option<Duration>,
+    /// Kill the process with the [`ProcessEvent::ExitRequested`] event once the token is cancelled from any thread, for
+    /// no external cancellation use None
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub cancellation: Option<CancellationToken>,
+    /// Emit the [`ProcessEvent::ReadStalled`] event if a single read of the output blocks longer than this duration,
+    /// unlike the idle timeout the time spent in the callback is not counted. For no read timeout use None
+    pub read_timeout: Option<Duration>,
+    /// Kill the process once a read of the output stalls as per the [`ProcessRequest::read_timeout`]
+    pub kill_on_read_stall: bool,
+    /// Max total bytes of the output lines, for no limit use None
+    pub max_output_bytes: Option<u64>,
+    /// What to do once the output exceeds the [`ProcessRequest::max_output_bytes`]
+    pub output_limit_action: OutputLimitAction,
+    /// Cap the rate the output lines are consumed at, applying backpressure on the process. For no cap use None
+    pub output_throttle: Option<OutputThrottle>,
+    /// Decode every output line as JSON (NDJSON), see [`ProcessData::json`]. Invalid lines emit the
+    /// [`ProcessEvent::JsonParseError`] event instead of the [`ProcessEvent::IOData`] event
+    #[cfg(feature = "json")]
+    pub json_lines: bool,
+    /// Encoding of the output (e.g. `cp850` or `cp437` of the Windows console, `windows-1252`), it's transcoded to UTF-8
+    /// before splitting into the lines. For UTF-8 output use None
+    #[cfg(feature = "encoding")]
+    pub output_encoding: Option<String>,
+    /// Read the STDOUT & STDERR through separate pipes instead of one, tagging every output line with its stream & a
+    /// sequence across both the streams in [`ProcessData::origin`]. Only the order the lines are read in is known, a
+    /// process writing to both the streams at once may have written them in another order
+    pub tag_output_streams: bool,
+    /// What to do with an output line which is not valid UTF-8, by default the reading stops with the [`ProcessEvent::IOError`] event
+    pub invalid_utf8: InvalidUtf8,
+    /// Split every output line into the fields of [`ProcessData::record`], for the plain lines use None
+    pub record_format: Option<RecordFormat>,
+    /// Transformations applied to every output line in order before it's delivered (and matched against the patterns,
+    /// expectations & the golden file), a dropped line is not delivered but still counted in the line numbers
+    pub line_transforms: Vec<LineTransform>,
+    /// Classify every output line (after the `line_transforms`) e.g. as an error or a warning, see
+    /// [`ProcessData::line_class`] & [`ProcessResult::line_classes`]. For no classification use None
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub line_classifier: Option<Arc<dyn LineClassifier>>,
+    /// Regular expressions matched against every output line, each match emits the [`ProcessEvent::PatternMatched`] event
+    pub patterns: Vec<String>,
+    /// Expectations on the output checked while streaming, a failure emits the [`ProcessEvent::ExpectationFailed`] event &
+    /// makes the execution unsuccessful
+    pub expectations: Vec<OutputExpectation>,
+    /// Kill the process once an output line fails an expectation
+    pub kill_on_expectation_failure: bool,
+    /// Regular expressions of the fatal output (e.g. `^panic:`, `OutOfMemoryError`), the first matching line emits the
+    /// [`ProcessEvent::FailPatternMatched`] event & makes the execution unsuccessful, see [`ProcessResult::fail_line`]
+    pub fail_on_patterns: Vec<String>,
+    /// Kill the process once an output line matches one of the `fail_on_patterns`
+    pub kill_on_fail_pattern: bool,
+    /// Regular expressions of the awaited output (e.g. `Compilation finished`), the first matching line emits the
+    /// [`ProcessEvent::SuccessPatternMatched`] event, the process is killed as its remaining output is irrelevant & the
+    /// execution is successful unless it failed already, see [`ProcessResult::success_line`]
+    pub succeed_on_patterns: Vec<String>,
+    /// File with the expected output, compared line by line while streaming. A difference emits the
+    /// [`ProcessEvent::GoldenMismatch`] event & makes the execution unsuccessful, see [`ProcessResult::golden_matched`].
+    /// For no comparison use None
+    pub golden_file: Option<PathBuf>,
+    /// Feed the events to this waiter, to wait for an output pattern from another thread
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub pattern_waiter: Option<Arc<PatternWaiter>>,
+    /// Emit the [`ProcessEvent::Heartbeat`] event at this interval while the process runs, for no heartbeats use None
+    pub heartbeat_interval: Option<Duration>,
+    /// Delay the start of the process by this duration, for immediate start use None
+    pub start_after: Option<Duration>,
+    /// Start the process at this time, if both `start_after` & `start_at` are set then the later one is used
+    pub start_at: Option<SystemTime>,
+    /// Delay the spawn by a random duration up to this one, so that many similar requests (e.g. of a pool, a batch or a
+    /// schedule) don't hit a downstream service at the same instant. Applied to every attempt, the delay is available
+    /// with the [`ProcessEvent::Starting`] event, see [`ProcessData::start_delay`]. For no jitter use None
+    pub start_jitter: Option<Duration>,
+    /// Limit the spawn rate using this rate limiter (share it between requests), for the global [`RateLimiter`] (if set) use None
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    /// Requester or tenant of the request, its executions count towards the quota of the tag. For no tag use None
+    pub tag: Option<String>,
+    /// Enforce the quotas of the tags using this registry (share it between requests), for the global
+    /// [`QuotaRegistry`] (if set) use None
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub quotas: Option<Arc<QuotaRegistry>>,
+    /// Sequence number of the run, set by the [`Scheduler`] for every recurring run starting from 1. For a one-off run it's 0
+    pub run_sequence: u64,
+    /// User data passed through untouched to the callbacks (e.g. shared state or a correlation id), see
+    /// [`ProcessData::context`]. For no context use None
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub context: Option<Arc<dyn Any + Send + Sync>>,
 }
 
 impl ProcessRequest {
+    /// Commands of the pipeline to run, the `stages` if set otherwise the `cmd_line` with the `use_shell` mode
+    pub fn pipeline_stages(&self) -> Vec<PipelineStage> {
+        if !self.stages.is_empty() {
+            return self.stages.clone();
+        }
+        self.cmd_line
+            .iter()
+            .map(|argv| PipelineStage {
+                argv: argv.clone(),
+                shell: self.use_shell,
+            })
+            .collect()
+    }
+
+    /// The exit code means success as per the [`ProcessRequest::success_exit_codes`], None (not started or killed) is a failure
+    pub fn is_success_exit_code(&self, exit_code: Option<i32>) -> bool {
+        match (exit_code, self.success_exit_codes.as_ref()) {
+            (None, _) => false,
+            (Some(exit_code), Some(success_exit_codes)) => success_exit_codes.contains(&exit_code),
+            (Some(exit_code), None) => exit_code == 0,
+        }
+    }
+
+    /// Category of the termination as per the [`ProcessRequest::exit_categories`], None if the exit code has none
+    pub fn exit_category(&self, termination: Option<&Termination>) -> Option<&str> {
+        let exit_code = match termination? {
+            Termination::Exited { code } => *code,
+            Termination::Signaled { signal } => 128 + signal,
+            Termination::Killed { .. } => return None,
+        };
+        self.exit_categories.get(&exit_code).map(String::as_str)
+    }
+
+    /// Command line for the [`ProcessRequest::shell`] of the request, every argument is quoted as per [`ShellKind::quote`]
+    pub fn shell_line(&self, args: &[&str]) -> String {
+        self.shell.build_line(args)
+    }
+
     /**
      Run a process based on the provided process request which is events based multi process execution(blocking & non-blocking modes) in parallel and with data streaming
      Generates various events [`ProcessEvent`] according to the process's life-cycle, process's information and data [`ProcessData`] associated with that event
@@ -225,6 +1089,7 @@ impl ProcessRequest {
     //            String::from(">&2"),
     //        ]],
     //        non_blocking_mode: true,
+    //        ..Default::default()
     //    };
     //
     //    // non Blocking mode
@@ -260,38 +1125,237 @@ impl ProcessRequest {
     //         use_shell: true,
     //         cmd_line: vec![vec![String::from("calc")]],
     //         non_blocking_mode: false,
+    //         ..Default::default()
     //     }));
      ```
     */
     pub fn start(process_request: ProcessRequest) -> ProcessResult {
-        let request = Arc::new(process_request);
+        Self::start_with_stop(process_request, None)
+    }
+
+    /// Start the process in the non-blocking mode & get a guard which kills it when dropped, unless
+    /// [`ProcessGuard::detach`] or [`ProcessGuard::wait`] is called first
+    pub fn start_guarded(mut process_request: ProcessRequest) -> ProcessGuard {
+        process_request.non_blocking_mode = true;
+        let stop = Arc::new(Latch::new());
+        let result = Self::start_with_stop(process_request, Some(Arc::clone(&stop)));
+        ProcessGuard::new(result, stop)
+    }
+
+    /// start the process, a non-blocking mode execution is killed once the stop latch is set
+    fn start_with_stop(process_request: ProcessRequest, stop: Option<Arc<Latch>>) -> ProcessResult {
+        let request = Arc::new(priority::capture_calling_thread(process_request));
+        let start_delay = delayed_start::start_delay(&request);
+        if let Some(delay) = start_delay {
+            delayed_start::notify_scheduled(&request, delay);
+        }
         if request.non_blocking_mode {
-            let join_handle = thread::Builder::new()
-                .name(format!("pes_th_rq_{}", request.request_id).into())
-                .spawn(move || {
-                    let response = start_process(request);
-                    return response;
-                });
+            let start_gate = start_delay.map(|_| Arc::new(StartGate::new()));
+            let thread_start_gate = start_gate.clone();
+            let request_id = request.request_id;
+            let worker_pool = request.worker_pool.clone().or_else(ProcessPool::global);
+            let inherit_priority = request.inherit_priority;
+            let run = move |request: Arc<ProcessRequest>| {
+                let _thread = diagnostics::track_thread();
+                if let Some(delay) = start_delay {
+                    if !delayed_start::wait_for_start(&request, delay, thread_start_gate.as_deref())
+                    {
+                        return ProcessResult::new();
+                    }
+                }
+                retry::start_process_with_retry(request, stop.as_deref())
+            };
             let mut result = ProcessResult::new();
-            result.set_join_handle(Some(join_handle));
-            return result;
+            match worker_pool {
+                Some(worker_pool) => {
+                    // the pool runs the request with its rate limiter & quotas
+                    result.pool_handle =
+                        Some(worker_pool.submit_task(Arc::unwrap_or_clone(request), run));
+                }
+                None => {
+                    let join_handle = thread::Builder::new()
+                        .name(format!("pes_th_rq_{}", request_id))
+                        .spawn(move || {
+                            priority::adopt_inherited(inherit_priority);
+                            run(request)
+                        });
+                    result.set_join_handle(Some(join_handle));
+                }
+            }
+            result.start_gate = start_gate;
+            result
         } else {
-            return start_process(request);
+            if let Some(delay) = start_delay {
+                delayed_start::wait_for_start(&request, delay, None);
+            }
+            retry::start_process_with_retry(request, None)
         }
     }
+
+    /// Run many requests in parallel in the background and get the combined summary once all are completed.
+    /// Events of each request are delivered to its own callback, see [`ProcessRequest::start_batch_with`] for a shared callback & concurrency limit
+    pub fn start_batch(process_requests: Vec<ProcessRequest>) -> io::Result<BatchHandle> {
+        batch::start_batch(process_requests, BatchOptions::default())
+    }
+
+    /// Run many requests in the background as per the batch options and get the combined summary once all are completed
+    pub fn start_batch_with(
+        process_requests: Vec<ProcessRequest>,
+        options: BatchOptions,
+    ) -> io::Result<BatchHandle> {
+        batch::start_batch(process_requests, options)
+    }
+
+    /// Run the request on all the hosts in parallel in the background over SSH and get the exit code of every host
+    /// once all are completed. The events of all the hosts are delivered to the callback of the request, see
+    /// [`ProcessData::remote_host`]
+    #[cfg(feature = "ssh")]
+    pub fn start_on_hosts(
+        process_request: ProcessRequest,
+        hosts: Vec<RemoteTarget>,
+    ) -> io::Result<FanOutHandle> {
+        fan_out::start_on_hosts(process_request, hosts, BatchOptions::default())
+    }
+
+    /// Run the request on all the hosts in the background as per the batch options (e.g. at most so many hosts in
+    /// parallel) and get the exit code of every host once all are completed
+    #[cfg(feature = "ssh")]
+    pub fn start_on_hosts_with(
+        process_request: ProcessRequest,
+        hosts: Vec<RemoteTarget>,
+        options: BatchOptions,
+    ) -> io::Result<FanOutHandle> {
+        fan_out::start_on_hosts(process_request, hosts, options)
+    }
 }
 
-fn start_process(request: Arc<ProcessRequest>) -> ProcessResult {
-    let mut process_result = ProcessResult::new();
+fn start_process(request: Arc<ProcessRequest>, stop: Option<&Latch>) -> ProcessResult {
+    match request.executor.clone() {
+        Some(executor) => executor.execute(request),
+        #[cfg(feature = "kubernetes")]
+        None if request.pod.is_some() && backend_argv(&request).is_none() => {
+            let pod = request.pod.clone().unwrap();
+            kubernetes::exec_in_pod(request, &pod, stop)
+        }
+        None => spawn_process(request, stop),
+    }
+}
+
+/// spawn the process using duct & stream its output, till it completes or the stop latch is set
+fn spawn_process(request: Arc<ProcessRequest>, stop: Option<&Latch>) -> ProcessResult {
+    #[cfg(feature = "tracing")]
+    let _span = tracing_support::execution_span(&request).entered();
+    #[cfg(feature = "prometheus")]
+    let execution_started = std::time::Instant::now();
     let mut process_data = ProcessData::new();
     process_data.line.clear();
     process_data.request = Some(Arc::clone(&request));
-    if request.as_ref().cmd_line.len() == 0 || request.as_ref().cmd_line[0].len() == 0 {
+    if request
+        .pipeline_stages()
+        .first()
+        .is_none_or(|stage| stage.argv.is_empty())
+    {
         process_data
             .line
             .push_str(format!("{:?}", "Command line - arguments are unavailable!").as_str());
         return check_and_trigger_callback(&request, &ProcessEvent::StartError, &process_data);
     }
+    let patterns = match patterns::compile_patterns(&request.patterns) {
+        Ok(patterns) => patterns,
+        Err(error) => {
+            process_data.line.push_str(&format!("{:?}", error));
+            return check_and_trigger_callback(&request, &ProcessEvent::StartError, &process_data);
+        }
+    };
+    let fail_patterns = match patterns::compile_patterns(&request.fail_on_patterns) {
+        Ok(fail_patterns) => fail_patterns,
+        Err(error) => {
+            process_data.line.push_str(&format!("{:?}", error));
+            return check_and_trigger_callback(&request, &ProcessEvent::StartError, &process_data);
+        }
+    };
+    let success_patterns = match patterns::compile_patterns(&request.succeed_on_patterns) {
+        Ok(success_patterns) => success_patterns,
+        Err(error) => {
+            process_data.line.push_str(&format!("{:?}", error));
+            return check_and_trigger_callback(&request, &ProcessEvent::StartError, &process_data);
+        }
+    };
+    let mut fail_line = None;
+    let mut success_line = None;
+    let mut output_budget = request
+        .max_output_bytes
+        .map(|max_bytes| OutputBudget::new(max_bytes, request.output_limit_action));
+    let mut output_pacer = request.output_throttle.map(OutputPacer::new);
+    let resume_checkpoint = checkpoint::load(&request);
+    process_data.checkpoint = resume_checkpoint;
+    let mut output_position = Checkpoint::default();
+    let mut line_classes = BTreeMap::new();
+    let mut expectations = match ExpectationChecker::new(&request.expectations) {
+        Ok(expectations) => expectations,
+        Err(error) => {
+            process_data.line.push_str(&format!("{:?}", error));
+            return check_and_trigger_callback(&request, &ProcessEvent::StartError, &process_data);
+        }
+    };
+    let mut golden = match GoldenComparer::new(request.golden_file.as_deref()) {
+        Ok(golden) => golden,
+        Err(error) => {
+            process_data.line.push_str(&format!("{:?}", error));
+            return check_and_trigger_callback(&request, &ProcessEvent::StartError, &process_data);
+        }
+    };
+    let line_transformer = match LineTransformer::new(&request.line_transforms) {
+        Ok(line_transformer) => line_transformer,
+        Err(error) => {
+            process_data.line.push_str(&format!("{:?}", error));
+            return check_and_trigger_callback(&request, &ProcessEvent::StartError, &process_data);
+        }
+    };
+    #[cfg(feature = "encoding")]
+    let mut transcoder = match request
+        .output_encoding
+        .as_deref()
+        .map(encoding::Transcoder::for_label)
+        .transpose()
+    {
+        Ok(transcoder) => transcoder,
+        Err(error) => {
+            process_data.line.push_str(&format!("{:?}", error));
+            return check_and_trigger_callback(&request, &ProcessEvent::StartError, &process_data);
+        }
+    };
+    if request.report_env_diff {
+        // an unreadable env file is reported as the start error of the spawn
+        if let Ok(env_diff) = env::env_diff(&request) {
+            process_data.line.push_str(&format!(
+                "Environment: {} added, {} overridden, {} removed",
+                env_diff.added.len(),
+                env_diff.overridden.len(),
+                env_diff.removed.len()
+            ));
+            process_data.env_diff = Some(env_diff);
+            check_and_trigger_callback(&request, &ProcessEvent::EnvironmentDiff, &process_data);
+            process_data.env_diff = None;
+            process_data.line.clear();
+        }
+    }
+    if request.dry_run {
+        return match resolved_command_line(&request) {
+            Ok(command_line) => {
+                process_data.line.push_str(&command_line);
+                check_and_trigger_callback(&request, &ProcessEvent::Starting, &process_data)
+            }
+            Err(error) => {
+                process_data.line.push_str(&format!("{:?}", error));
+                check_and_trigger_callback(&request, &ProcessEvent::StartError, &process_data)
+            }
+        };
+    }
+    rate_limit::wait_for_spawn_token(&request);
+    process_data.start_delay = delayed_start::wait_for_jitter(&request, stop);
+    #[cfg(feature = "opentelemetry")]
+    let mut otel_spans = otel::ExecutionSpans::start(&request);
     process_data.line.push_str(
         format!(
             "Executing in thread-context -> id: {:?}, name: {:?}",
@@ -300,69 +1364,507 @@ fn start_process(request: Arc<ProcessRequest>) -> ProcessResult {
         )
         .as_str(),
     );
-    process_result = check_and_trigger_callback(&request, &ProcessEvent::Starting, &process_data);
+    if let Some(start_delay) = process_data.start_delay {
+        process_data
+            .line
+            .push_str(format!(", start jitter: {} ms", start_delay.as_millis()).as_str());
+    }
+    let mut process_result =
+        check_and_trigger_callback(&request, &ProcessEvent::Starting, &process_data);
+    let mut peak_resource_usage = None;
+    let mut exit_code = None;
+    let mut exit_signal = None;
+    let mut termination = None;
+    let kill_record = Arc::new(KillRecord::new());
 
     let process_req = &request;
-    let stdout_reader = handle_pipeline(&request).stderr_to_stdout().reader();
+    // stage events are only for the multi command pipelines run locally
+    let pipeline_stages = match backend_argv(&request) {
+        None if request.pipeline_stages().len() > 1 => stage_argvs(&request),
+        _ => vec![],
+    };
+    let job_account = JobAccount::create(request.job_accounting);
+    let mut stderr_pipe = None;
+    let stdout_reader = handle_pipeline(&request)
+        .map(|pipeline| match &job_account {
+            Some(job_account) => job_account.attach(pipeline),
+            None => pipeline,
+        })
+        .and_then(|pipeline| {
+            if !request.tag_output_streams {
+                return pipeline.stderr_to_stdout().reader();
+            }
+            // the write end is closed with the expression once spawned, so the pipe ends with the process
+            let (stderr_reader, stderr_writer) = io::pipe()?;
+            stderr_pipe = Some(stderr_reader);
+            pipeline.stderr_file(stderr_writer).reader()
+        })
+        .and_then(|reader| {
+            // on failure the reader is dropped, which kills the process
+            affinity::pin_spawned_processes(&reader.pids(), request.cpu_affinity.as_deref())
+                .and_then(|_| match &job_account {
+                    Some(job_account) => job_account.assign(&reader.pids()),
+                    None => Ok(()),
+                })
+                .and_then(|_| {
+                    token::restrict_spawned_processes(
+                        &reader.pids(),
+                        request.restricted_token.as_ref(),
+                    )
+                })
+                .map(|_| Arc::new(reader))
+        });
     if stdout_reader.as_ref().is_ok() {
         process_data.reader = Some(stdout_reader.as_ref().unwrap());
+        process_data.kill_record = Some(&kill_record);
     }
     match stdout_reader.as_ref() {
         Ok(stdout_reader) => {
+            let _registration = shutdown::register(stdout_reader, &kill_record);
+            let shared_reader = stdout_reader;
+            let stdout_reader: &ReaderHandle = stdout_reader;
+            #[cfg(feature = "tracing")]
+            tracing_support::record_pids(&stdout_reader.pids());
+            #[cfg(feature = "opentelemetry")]
+            otel_spans.started(&stdout_reader.pids());
             process_result =
                 check_and_trigger_callback(process_req, &ProcessEvent::Started, &process_data);
-            let mut buffer_reader = BufReader::new(stdout_reader);
-            loop {
-                process_data.line.clear();
-                let result = buffer_reader.read_line(&mut process_data.line);
-                match result {
-                    Ok(result) if result == 0 => {
-                        check_and_trigger_callback(
+            let pids = stdout_reader.pids();
+            for index in 0..pipeline_stages.len() {
+                process_data.stage = Some(StageInfo {
+                    index,
+                    pid: pids.get(index).copied(),
+                    exit_code: None,
+                });
+                check_and_trigger_callback(process_req, &ProcessEvent::StageStarted, &process_data);
+            }
+            process_data.stage = None;
+            let mut stage_exit_codes = vec![None; pipeline_stages.len()];
+            let done = Latch::new();
+            let activity = Activity::new();
+            let read_tracker = request.read_timeout.map(|_| ReadTracker::new());
+            let started = process_data.started;
+            let mut exit_requested = false;
+            thread::scope(|scope| {
+                let done = &done;
+                let kill_record: &KillRecord = &kill_record;
+                let sampler = request.resource_sample_interval.map(|interval| {
+                    scope.spawn(move || {
+                        let _thread = diagnostics::track_thread();
+                        resource::run_sampler(
                             process_req,
-                            &ProcessEvent::IOEof,
-                            &process_data,
-                        );
-                        break;
-                    }
-                    Ok(_result) => {
-                        process_data.line_number += 1;
-                        process_result = check_and_trigger_callback(
+                            stdout_reader,
+                            kill_record,
+                            started,
+                            interval,
+                            done,
+                        )
+                    })
+                });
+                if let Some(stop) = stop {
+                    scope.spawn(move || {
+                        let _thread = diagnostics::track_thread();
+                        watchdog::watch_stop(
                             process_req,
-                            &ProcessEvent::IOData,
-                            &process_data,
+                            stdout_reader,
+                            kill_record,
+                            started,
+                            stop,
+                            done,
+                        )
+                    });
+                }
+                if let Some(token) = request.cancellation.as_ref() {
+                    scope.spawn(move || {
+                        let _thread = diagnostics::track_thread();
+                        watchdog::watch_cancellation(
+                            process_req,
+                            stdout_reader,
+                            kill_record,
+                            started,
+                            token,
+                            done,
+                        )
+                    });
+                }
+                if let Some(timeout) = request.timeout {
+                    scope.spawn(move || {
+                        let _thread = diagnostics::track_thread();
+                        watchdog::watch_timeout(
+                            process_req,
+                            stdout_reader,
+                            kill_record,
+                            started,
+                            timeout,
+                            done,
+                        )
+                    });
+                }
+                if let Some(idle_timeout) = request.idle_timeout {
+                    let activity = &activity;
+                    scope.spawn(move || {
+                        let _thread = diagnostics::track_thread();
+                        watchdog::watch_idle(
+                            process_req,
+                            stdout_reader,
+                            kill_record,
+                            started,
+                            idle_timeout,
+                            activity,
+                            done,
+                        )
+                    });
+                }
+                if let (Some(read_timeout), Some(tracker)) =
+                    (request.read_timeout, read_tracker.as_ref())
+                {
+                    scope.spawn(move || {
+                        let _thread = diagnostics::track_thread();
+                        watchdog::watch_reads(
+                            process_req,
+                            stdout_reader,
+                            kill_record,
+                            started,
+                            read_timeout,
+                            tracker,
+                            done,
+                        )
+                    });
+                }
+                if let Some(interval) = request.heartbeat_interval {
+                    let activity = &activity;
+                    scope.spawn(move || {
+                        let _thread = diagnostics::track_thread();
+                        watchdog::run_heartbeat(
+                            process_req,
+                            stdout_reader,
+                            kill_record,
+                            started,
+                            interval,
+                            activity,
+                            done,
+                        )
+                    });
+                }
+                let mut record_decoder = request.record_format.as_ref().map(RecordDecoder::new);
+                let (output, stream_tags): (Box<dyn io::Read>, _) = match stderr_pipe.take() {
+                    Some(stderr_pipe) => {
+                        let (merger, tags) = StreamMerger::start(
+                            Arc::clone(shared_reader),
+                            stderr_pipe,
+                            request.inherit_priority,
                         );
-                        match &process_result {
-                            process_result
-                                if process_result.should_exit.is_some()
-                                    && process_result.should_exit.unwrap() == true =>
+                        (Box::new(merger), Some(tags))
+                    }
+                    None => (Box::new(stdout_reader), None),
+                };
+                #[cfg(feature = "encoding")]
+                let output: Box<dyn io::Read> = match transcoder.take() {
+                    Some(transcoder) => {
+                        Box::new(encoding::TranscodingReader::new(output, transcoder))
+                    }
+                    None => output,
+                };
+                let mut line_reader = LineReader::new(output).invalid_utf8(request.invalid_utf8);
+                loop {
+                    process_data.line.clear();
+                    process_data.line_class = None;
+                    if let Some(tracker) = read_tracker.as_ref() {
+                        tracker.begin();
+                    }
+                    let result = line_reader.read_line(&mut process_data.line);
+                    if let Some(tracker) = read_tracker.as_ref() {
+                        tracker.end();
+                    }
+                    match result {
+                        Ok(0) => {
+                            // reader has already waited for the process & checked the exit status
+                            exit_code = Some(0);
+                            stage_exit_codes = stages::stage_exit_codes(&pipeline_stages, Ok(()));
+                            check_and_trigger_callback(
+                                process_req,
+                                &ProcessEvent::IOEof,
+                                &process_data,
+                            );
+                            break;
+                        }
+                        Ok(_result) => {
+                            process_data.line_number += 1;
+                            process_data.origin = stream_tags.as_ref().and_then(StreamTags::next);
+                            #[cfg(feature = "opentelemetry")]
+                            otel_spans.output(_result);
+                            activity.touch();
+                            output_position.line_number = process_data.line_number;
+                            output_position.byte_offset += process_data.line.len() as u64;
+                            if request.resume_from_checkpoint
+                                && resume_checkpoint.is_some_and(|checkpoint| {
+                                    output_position.line_number <= checkpoint.line_number
+                                })
                             {
+                                continue;
+                            }
+                            if let Some(pacer) = output_pacer.as_mut() {
+                                let _throttled = pacer.pace(process_data.line.len());
+                                #[cfg(feature = "prometheus")]
+                                if let Some(metrics) = metrics::request_metrics(&request) {
+                                    metrics.observe_throttled(_throttled);
+                                }
+                            }
+                            if line_reader.replaced_invalid_utf8() {
+                                check_and_trigger_callback(
+                                    process_req,
+                                    &ProcessEvent::Warning(WarningKind::InvalidUtf8Replaced),
+                                    &process_data,
+                                );
+                            }
+                            if let Some(budget) = output_budget.as_mut() {
+                                match budget.account(&mut process_data.line) {
+                                    LineBudget::Within => {}
+                                    LineBudget::Over => continue,
+                                    LineBudget::Exceeded => {
+                                        let line = std::mem::replace(
+                                            &mut process_data.line,
+                                            budget.reason(),
+                                        );
+                                        check_and_trigger_callback(
+                                            process_req,
+                                            &ProcessEvent::OutputLimitExceeded,
+                                            &process_data,
+                                        );
+                                        process_data.line = line;
+                                        match request.output_limit_action {
+                                            OutputLimitAction::Kill => {
+                                                kill_record.record(false);
+                                                break;
+                                            }
+                                            OutputLimitAction::Truncate
+                                                if !process_data.line.is_empty() =>
+                                            {
+                                                check_and_trigger_callback(
+                                                    process_req,
+                                                    &ProcessEvent::Warning(
+                                                        WarningKind::LineTruncated,
+                                                    ),
+                                                    &process_data,
+                                                );
+                                            }
+                                            _ => continue,
+                                        }
+                                    }
+                                }
+                            }
+                            if let Some(transformer) = line_transformer.as_ref() {
+                                if !transformer.apply(&mut process_data.line) {
+                                    continue;
+                                }
+                            }
+                            if let Some(classifier) = request.line_classifier.as_ref() {
+                                process_data.line_class =
+                                    classifier.classify(process_data.line_str());
+                                if let Some(line_class) = process_data.line_class.clone() {
+                                    *line_classes.entry(line_class).or_insert(0) += 1;
+                                }
+                            }
+                            if let Some(record_decoder) = record_decoder.as_mut() {
+                                record_decoder.decode(&mut process_data);
+                            }
+                            #[cfg(feature = "json")]
+                            let event = if request.json_lines {
+                                json_lines::decode_line(&mut process_data)
+                            } else {
+                                ProcessEvent::IOData
+                            };
+                            #[cfg(not(feature = "json"))]
+                            let event = ProcessEvent::IOData;
+                            process_result =
+                                check_and_trigger_callback(process_req, &event, &process_data);
+                            checkpoint::save(process_req, &output_position, &process_data);
+                            if let Some(patterns) = patterns.as_ref() {
+                                for pattern_index in patterns.matches(process_data.line_str()) {
+                                    process_data.pattern_index = Some(pattern_index);
+                                    check_and_trigger_callback(
+                                        process_req,
+                                        &ProcessEvent::PatternMatched,
+                                        &process_data,
+                                    );
+                                }
+                                process_data.pattern_index = None;
+                            }
+                            if let Some(pattern_index) = fail_patterns
+                                .as_ref()
+                                .filter(|_| fail_line.is_none())
+                                .and_then(|fail_patterns| {
+                                    fail_patterns.matches(process_data.line_str()).iter().next()
+                                })
+                            {
+                                fail_line = Some(process_data.line_str().to_string());
+                                process_data.pattern_index = Some(pattern_index);
+                                check_and_trigger_callback(
+                                    process_req,
+                                    &ProcessEvent::FailPatternMatched,
+                                    &process_data,
+                                );
+                                process_data.pattern_index = None;
+                                if request.kill_on_fail_pattern {
+                                    kill_record.record(false);
+                                    break;
+                                }
+                            }
+                            if let Some(checker) = expectations.as_mut() {
+                                let failed = checker
+                                    .check_line(process_data.line_str(), process_data.line_number);
+                                for expectation_index in failed {
+                                    process_data.expectation_index = Some(expectation_index);
+                                    check_and_trigger_callback(
+                                        process_req,
+                                        &ProcessEvent::ExpectationFailed,
+                                        &process_data,
+                                    );
+                                }
+                                process_data.expectation_index = None;
+                                if request.kill_on_expectation_failure && checker.failed() {
+                                    kill_record.record(false);
+                                    break;
+                                }
+                            }
+                            if let Some(mismatch) = golden.as_mut().and_then(|golden| {
+                                golden.check_line(process_data.line_str(), process_data.line_number)
+                            }) {
+                                let line = std::mem::replace(&mut process_data.line, mismatch);
+                                check_and_trigger_callback(
+                                    process_req,
+                                    &ProcessEvent::GoldenMismatch,
+                                    &process_data,
+                                );
+                                process_data.line = line;
+                            }
+                            if let Some(pattern_index) =
+                                success_patterns.as_ref().and_then(|success_patterns| {
+                                    success_patterns
+                                        .matches(process_data.line_str())
+                                        .iter()
+                                        .next()
+                                })
+                            {
+                                success_line =
+                                    Some((process_data.line_str().to_string(), started.elapsed()));
+                                process_data.pattern_index = Some(pattern_index);
+                                check_and_trigger_callback(
+                                    process_req,
+                                    &ProcessEvent::SuccessPatternMatched,
+                                    &process_data,
+                                );
+                                process_data.pattern_index = None;
+                                exit_requested = true;
+                                break;
+                            }
+                            if process_result.should_exit == Some(true) {
                                 check_and_trigger_callback(
                                     process_req,
                                     &ProcessEvent::ExitRequested,
                                     &process_data,
                                 );
+                                exit_requested = true;
                                 break;
                             }
-
-                            _other => {}
+                        }
+                        Err(error) => {
+                            exit_code = status::exit_code(&error);
+                            exit_signal = status::exit_signal(&error);
+                            stage_exit_codes =
+                                stages::stage_exit_codes(&pipeline_stages, Err(&error));
+                            let event = match limits::exceeded_resource_limit(
+                                &request.resource_limits,
+                                &error,
+                            ) {
+                                Some(limit) => {
+                                    process_data.line.push_str(&limit);
+                                    ProcessEvent::ResourceLimitExceeded
+                                }
+                                None => {
+                                    process_data.line.push_str(format!("{:?}", error).as_str());
+                                    ProcessEvent::IOError
+                                }
+                            };
+                            #[cfg(feature = "container")]
+                            let event = container_exit_event(
+                                &request,
+                                event,
+                                &mut exit_code,
+                                &mut process_data.line,
+                            );
+                            let event = match event {
+                                ProcessEvent::IOError
+                                    if request.unchecked_exit_status
+                                        && (exit_code.is_some() || exit_signal.is_some()) =>
+                                {
+                                    process_data.line.clear();
+                                    ProcessEvent::IOEof
+                                }
+                                event => event,
+                            };
+                            check_and_trigger_callback(process_req, &event, &process_data);
+                            break;
                         }
                     }
-                    Err(error) => {
-                        process_data.line.push_str(format!("{:?}", error).as_str());
+                }
+                done.set();
+                // the expectations on the whole output, unless the output was cut short
+                let output_complete = exit_code.is_some() || exit_signal.is_some();
+                if let Some(checker) = expectations.as_mut().filter(|_| output_complete) {
+                    for (expectation_index, reason) in checker.check_end(process_data.line_number) {
+                        process_data.line = reason;
+                        process_data.expectation_index = Some(expectation_index);
                         check_and_trigger_callback(
                             process_req,
-                            &ProcessEvent::IOError,
+                            &ProcessEvent::ExpectationFailed,
                             &process_data,
                         );
-                        break;
                     }
+                    process_data.expectation_index = None;
                 }
-            }
+                if let Some(mismatch) = golden
+                    .as_mut()
+                    .filter(|_| output_complete)
+                    .and_then(|golden| golden.check_end(process_data.line_number))
+                {
+                    process_data.line = mismatch;
+                    check_and_trigger_callback(
+                        process_req,
+                        &ProcessEvent::GoldenMismatch,
+                        &process_data,
+                    );
+                }
+                if let Some(sampler) = sampler {
+                    peak_resource_usage = sampler.join().ok().flatten();
+                }
+            });
             process_data.line.clear();
-            let exit_result = stdout_reader.kill();
+            // the reader has already waited for an exited process, otherwise it's still running & is killed
+            let exited = exit_code.is_some() || exit_signal.is_some();
+            let exit_result = if exited && !exit_requested {
+                Ok(())
+            } else {
+                kill_record.record(exit_requested);
+                stdout_reader.kill()
+            };
+            termination = kill_record
+                .termination()
+                .or(exit_code.map(|code| Termination::Exited { code }))
+                .or(exit_signal.map(|signal| Termination::Signaled { signal }));
+            for (index, exit_code) in stage_exit_codes.into_iter().enumerate() {
+                process_data.stage = Some(StageInfo {
+                    index,
+                    pid: pids.get(index).copied(),
+                    exit_code,
+                });
+                check_and_trigger_callback(process_req, &ProcessEvent::StageExited, &process_data);
+            }
+            process_data.stage = None;
 
             match exit_result {
                 Ok(_) => {
+                    process_data.termination = termination;
                     check_and_trigger_callback(process_req, &ProcessEvent::Exited, &process_data);
                 }
                 Err(_) => {
@@ -376,43 +1878,217 @@ fn start_process(request: Arc<ProcessRequest>) -> ProcessResult {
         }
         _error => {
             let reader = stdout_reader.as_ref();
-            if reader.err().is_some() {
-                process_data
-                    .line
-                    .push_str(format!("{:?}", reader.err().unwrap()).as_str());
+            if let Err(error) = reader {
+                let detail = token::start_error_detail(error)
+                    .unwrap_or_else(|| user::start_error_detail(request.run_as.as_ref(), error));
+                process_data.line.push_str(&detail);
             }
             check_and_trigger_callback(process_req, &ProcessEvent::StartError, &process_data);
         }
     }
     process_data.request = None;
     process_data.reader = None;
+    process_data.kill_record = None;
+    process_result.termination = termination;
+    process_result.peak_resource_usage = peak_resource_usage;
+    process_result.job_accounting = job_account
+        .as_ref()
+        .filter(|_| stdout_reader.is_ok())
+        .and_then(JobAccount::read);
+    process_result.exit_code = exit_code;
+    process_result.line_classes = line_classes;
+    process_result.spawned = stdout_reader.is_ok();
+    process_result.spawn_failed = stdout_reader.is_err();
+    process_result.output_limit_exceeded =
+        output_budget.as_ref().is_some_and(OutputBudget::exceeded);
+    process_result.output_throttled = output_pacer
+        .as_ref()
+        .map_or(Duration::ZERO, OutputPacer::throttled);
+    let output_complete = exit_code.is_some() || exit_signal.is_some();
+    process_result.golden_matched = golden
+        .as_ref()
+        .map(GoldenComparer::mismatched)
+        .and_then(|mismatched| (mismatched || output_complete).then_some(!mismatched));
+    process_result.expectation_failed = expectations
+        .as_ref()
+        .is_some_and(ExpectationChecker::failed)
+        || process_result.golden_matched == Some(false)
+        || fail_line.is_some();
+    process_result.fail_line = fail_line;
+    if let Some((line, time_to_success)) = success_line {
+        process_result.success_line = Some(line);
+        process_result.time_to_success = Some(time_to_success);
+    }
+    #[cfg(feature = "opentelemetry")]
+    otel_spans.end(
+        exit_code,
+        stdout_reader.as_ref().err().map(|error| error.to_string()),
+    );
+    #[cfg(feature = "prometheus")]
+    if let Some(metrics) = metrics::request_metrics(&request).filter(|_| process_result.spawned) {
+        metrics.observe_exit(execution_started.elapsed(), exit_code);
+    }
+    let pids = stdout_reader
+        .as_ref()
+        .map_or(vec![], |reader| reader.pids());
+    drop(stdout_reader);
+    diagnostics::execution_completed(&pids);
     process_result
 }
 
-/// handle pipeline based multiple command lines
-fn handle_pipeline(request: &Arc<ProcessRequest>) -> Expression {
-    let cmd_line = &request.cmd_line;
-    let use_shell = request.use_shell;
-    let mut cmd_pipeline;
-    if use_shell {
-        cmd_pipeline = sh_vector(&cmd_line[0]);
-    } else {
-        let cli = vec_string_to_osstring(&cmd_line[0]);
-        cmd_pipeline = cmd(&cli[0], &cli[1..]);
-    }
-    if cmd_line.len() > 1 {
-        let mut cmd_itr = cmd_line.iter();
-        cmd_itr.next();
-        for command in cmd_itr {
-            if use_shell {
-                cmd_pipeline = cmd_pipeline.pipe(sh_vector(&command));
+/// translate the exit of the container runtime CLI: a runtime failure is a start error with no exit code
+/// of the command, other runtime specific exit codes are described in the event line
+#[cfg(feature = "container")]
+fn container_exit_event(
+    request: &ProcessRequest,
+    event: ProcessEvent,
+    exit_code: &mut Option<i32>,
+    line: &mut String,
+) -> ProcessEvent {
+    #[cfg(feature = "ssh")]
+    if request.remote.is_some() {
+        return event;
+    }
+    if request.container.is_none() || event != ProcessEvent::IOError {
+        return event;
+    }
+    if let Some(detail) = exit_code.and_then(container::exit_code_detail) {
+        line.push_str(" - ");
+        line.push_str(&detail);
+    }
+    if container::is_runtime_error(*exit_code) {
+        *exit_code = None;
+        return ProcessEvent::StartError;
+    }
+    event
+}
+
+/// argv of the client running the request over SSH or WinRM or in a container, None to run it locally
+fn backend_argv(_request: &ProcessRequest) -> Option<io::Result<Vec<OsString>>> {
+    #[cfg(feature = "ssh")]
+    if let Some(remote) = _request.remote.as_ref() {
+        return Some(ssh::ssh_argv(remote, _request));
+    }
+    #[cfg(feature = "winrm")]
+    if let Some(winrm) = _request.winrm.as_ref() {
+        return Some(winrm::winrm_argv(winrm, _request));
+    }
+    #[cfg(feature = "container")]
+    if let Some(container) = _request.container.as_ref() {
+        return Some(container::container_argv(container, _request));
+    }
+    None
+}
+
+/// command line as it would be executed, along with the working directory & the additional environment variables
+fn resolved_command_line(request: &ProcessRequest) -> io::Result<String> {
+    if let Some(argv) = backend_argv(request) {
+        return Ok(argv?
+            .iter()
+            .map(|arg| format!("{:?}", arg))
+            .collect::<Vec<String>>()
+            .join(" ")
+            + &redirect::describe_redirects(request));
+    }
+    let mut resolved = String::new();
+    if let Some(working_dir) = request.working_dir.as_ref() {
+        resolved.push_str(&format!("[cwd: {}] ", working_dir.display()));
+    }
+    if !request.env.is_empty() {
+        let mut env: Vec<String> = request
+            .env
+            .iter()
+            .map(|(name, value)| format!("{}={:?}", name, value))
+            .collect();
+        env.sort();
+        resolved.push_str(&format!("[env: {}] ", env.join(" ")));
+    }
+    let stages: Vec<String> = stage_argvs(request)
+        .iter()
+        .map(|argv| {
+            argv.iter()
+                .map(|arg| format!("{:?}", arg))
+                .collect::<Vec<String>>()
+                .join(" ")
+        })
+        .collect();
+    resolved.push_str(&stages.join(" | "));
+    resolved.push_str(&redirect::describe_redirects(request));
+    Ok(resolved)
+}
+
+/// argv of every command of the pipeline
+pub(crate) fn stage_argvs(request: &ProcessRequest) -> Vec<Vec<OsString>> {
+    request
+        .pipeline_stages()
+        .iter()
+        .map(|stage| {
+            if stage.shell {
+                request.shell.argv(&stage.argv)
             } else {
-                let cli = vec_string_to_osstring(&command);
-                cmd_pipeline = cmd_pipeline.pipe(cmd(&cli[0], &cli[1..]));
+                vec_string_to_osstring(&stage.argv)
             }
-        }
+        })
+        .collect()
+}
+
+/// handle pipeline based multiple command lines
+fn handle_pipeline(request: &Arc<ProcessRequest>) -> io::Result<Expression> {
+    if let Some(argv) = backend_argv(request) {
+        let argv = argv?;
+        // env & working directory are applied by the backend
+        return apply_spawn_options(cmd(&argv[0], &argv[1..]), request);
     }
-    cmd_pipeline
+    let mut cmd_pipeline: Option<Expression> = None;
+    for cli in stage_argvs(request) {
+        let command = cmd(&cli[0], &cli[1..]);
+        cmd_pipeline = Some(match cmd_pipeline {
+            Some(pipeline) => pipeline.pipe(command),
+            None => command,
+        });
+    }
+    let Some(mut cmd_pipeline) = cmd_pipeline else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Command line - arguments are unavailable!",
+        ));
+    };
+    cmd_pipeline = env::apply_env(cmd_pipeline, request)?;
+    if let Some(working_dir) = request.working_dir.as_ref() {
+        cmd_pipeline = cmd_pipeline.dir(working_dir);
+    }
+    apply_spawn_options(cmd_pipeline, request)
+}
+
+/// apply the file redirects, resource limits, restricted token, priority, CPU affinity, namespaces, network, user &
+/// syscall filter of the request
+fn apply_spawn_options(
+    cmd_pipeline: Expression,
+    request: &ProcessRequest,
+) -> io::Result<Expression> {
+    // the hooks of the outer expression run first: the syscall filter is installed right before exec, which may deny
+    // the syscalls of the other spawn options. The user is switched right before it, after the other spawn options
+    // which need the privileges
+    #[cfg(feature = "seccomp")]
+    let cmd_pipeline =
+        seccomp::apply_syscall_filter(cmd_pipeline, request.syscall_filter.as_ref())?;
+    let cmd_pipeline = user::apply_run_as(cmd_pipeline, request.run_as.as_ref())?;
+    let cmd_pipeline = redirect::apply_redirects(cmd_pipeline, request)?;
+    let cmd_pipeline = limits::apply_resource_limits(cmd_pipeline, &request.resource_limits)?;
+    // applied before the priority so its creation flags, which include the priority class, are set last: the hooks
+    // of the outer expression run first
+    let cmd_pipeline = token::apply_restricted_token(
+        cmd_pipeline,
+        request.restricted_token.as_ref(),
+        priority::process_priority(request),
+    )?;
+    let cmd_pipeline = priority::apply_priority(cmd_pipeline, priority::process_priority(request))?;
+    let cmd_pipeline = priority::apply_inherited_qos_class(cmd_pipeline, request)?;
+    let cmd_pipeline = affinity::apply_cpu_affinity(cmd_pipeline, request.cpu_affinity.as_deref())?;
+    #[cfg(feature = "namespaces")]
+    let cmd_pipeline = namespaces::apply_namespaces(cmd_pipeline, request.namespaces.as_ref())?;
+    let cmd_pipeline = network::apply_deny_network(cmd_pipeline, request.deny_network)?;
+    Ok(cmd_pipeline)
 }
 
 /// check if the callback is registered and if yes then trigger it wi the supplied data
@@ -421,46 +2097,77 @@ fn check_and_trigger_callback(
     event: &ProcessEvent,
     data: &ProcessData,
 ) -> ProcessResult {
-    if request.callback.as_ref().is_some() {
-        return request.callback.as_ref().unwrap()(event, data);
+    data.stamp();
+    #[cfg(feature = "tracing")]
+    tracing_support::trace_event(event, data);
+    #[cfg(feature = "prometheus")]
+    if let Some(metrics) = metrics::request_metrics(request) {
+        metrics.observe_event(event, data);
+    }
+    let mut warning = None;
+    if let Some(recorder) = request.recorder.as_ref() {
+        if recorder.record(event, data).is_err() {
+            warning = Some(WarningKind::SinkWriteFailed);
+        }
+    }
+    if let Some(json_events) = request.json_events.as_ref() {
+        if json_events.write_event(event, data).is_err() {
+            warning = Some(WarningKind::SinkWriteFailed);
+        }
+    }
+    if let Some(event_queue) = request.event_queue.as_ref() {
+        if event_queue.push(event, data) {
+            warning = Some(WarningKind::EventsDropped);
+        }
+    }
+    #[cfg(feature = "futures")]
+    if let Some(async_events) = request.async_events.as_ref() {
+        if !async_events.push(event, data) {
+            warning = Some(WarningKind::SinkWriteFailed);
+        }
+    }
+    if let Some(pattern_waiter) = request.pattern_waiter.as_ref() {
+        pattern_waiter.observe(event, data);
+    }
+    #[cfg(feature = "webhook")]
+    if let Some(webhook) = request.webhook.as_ref() {
+        webhook.notify(event, data);
+    }
+    let _callback_guard = shutdown::enter_callback();
+    let process_result = match request.callback.as_ref() {
+        Some(callback) => callback(event, data),
+        None => ProcessResult::new(),
     };
-    ProcessResult::new()
-}
-
-/// create and run a shell based command, using vector of cmd and arguments
-fn sh_vector(command: &Vec<String>) -> Expression {
-    let argv = shell_command_argv_vector(command.into());
-    cmd(&argv[0], &argv[1..])
-}
-
-/// create a shell based command
-#[cfg(unix)]
-fn shell_command_argv_vector(command: &Vec<String>) -> Vec<OsString> {
-    let mut cli: Vec<OsString> = vec_string_to_osstring(command);
-    let mut full_args = vec!["/bin/sh".into(), "-c".into()];
-    full_args.append(&mut cli);
-    full_args
-}
-
-/// Prepare shell based command
-#[cfg(windows)]
-fn shell_command_argv_vector(command: &Vec<String>) -> Vec<OsString> {
-    let comspec = std::env::var_os("COMSPEC").unwrap_or_else(|| "cmd.exe".into());
-    let mut cli: Vec<OsString> = vec_string_to_osstring(command);
-    let mut full_args = vec![comspec, "/C".into()];
-    full_args.append(&mut cli);
-    full_args
+    // the warning of a sink is not passed to the sinks again, it could fail the same way
+    if let Some(warning) = warning.filter(|_| !matches!(event, ProcessEvent::Warning(_))) {
+        let event = ProcessEvent::Warning(warning);
+        #[cfg(feature = "tracing")]
+        tracing_support::trace_event(&event, data);
+        if let Some(callback) = request.callback.as_ref() {
+            callback(&event, data);
+        }
+    }
+    process_result
 }
 
 /// convert vector of [`String`] to vector of [`OsString`]
-fn vec_string_to_osstring(input: &Vec<String>) -> Vec<OsString> {
+fn vec_string_to_osstring(input: &[String]) -> Vec<OsString> {
     input.iter().map(|x| x.as_str().into()).collect()
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{ProcessData, ProcessEvent, ProcessRequest, ProcessResult};
-    use std::{any::Any, fmt::Error, sync::Arc};
+    use crate::{
+        Backoff, ProcessData, ProcessError, ProcessEvent, ProcessPriority, ProcessRequest,
+        ProcessResult, ResourceLimit, RetryOn, RetryPolicy,
+    };
+    use std::{
+        sync::{
+            atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+            Arc,
+        },
+        time::{Duration, Instant, SystemTime},
+    };
 
     #[test]
     pub fn test_using_sh_output_streaming_new_version() {
@@ -528,6 +2235,7 @@ mod tests {
                 String::from(">&2"),
             ]],
             non_blocking_mode: false,
+            ..Default::default()
         };
 
         let request2 = ProcessRequest {
@@ -547,6 +2255,7 @@ mod tests {
                 String::from(">&2"),
             ]],
             non_blocking_mode: true,
+            ..Default::default()
         };
 
         // non Blocking mode
@@ -555,21 +2264,23 @@ mod tests {
 
         let mut internal_data = ProcessResult::new();
         //check & wait for the non blocking mode
-        if process_result.join_handle.is_some() {
-            if process_result.join_handle.as_ref().unwrap().is_ok() {
-                internal_data = process_result.join_handle.unwrap().unwrap().join().unwrap();
+        match process_result.join_handle {
+            Some(Ok(join_handle)) => {
+                internal_data = join_handle.join().unwrap();
                 println!("Start - join waiting over in non blocking mode");
-            } else {
-                internal_data.success = Err(process_result.join_handle.unwrap().err().unwrap());
+            }
+            Some(Err(error)) => {
+                internal_data.success = Err(error);
                 println!("Start - Error in non blocking mode");
             }
-        } else {
-            internal_data = process_result;
+            None => {
+                internal_data = process_result;
+            }
         }
         println!("result dump : {:?}", internal_data);
 
         //blocking mode
-        let result1 = ProcessRequest::start(request1);
+        let _result1 = ProcessRequest::start(request1);
         println!("Returned from Start! of blocking");
 
         println!(
@@ -581,6 +2292,7 @@ mod tests {
                 use_shell: true,
                 cmd_line: vec![vec![String::from("dir")], vec![String::from("sort")]],
                 non_blocking_mode: true,
+                ..Default::default()
             })
         );
 
@@ -590,6 +2302,7 @@ mod tests {
             use_shell: true,
             cmd_line: vec![vec![String::from(r#"echo "Sandy" "#)]],
             non_blocking_mode: true,
+            ..Default::default()
         };
         println!(
             "test_using_sh_output_streaming , demo double quotes {:?}",
@@ -603,6 +2316,7 @@ mod tests {
             use_shell: true,
             cmd_line: vec![vec![]],
             non_blocking_mode: true,
+            ..Default::default()
         };
 
         println!(
@@ -618,9 +2332,481 @@ mod tests {
                 use_shell: true,
                 cmd_line: vec![vec![String::from("calc")]],
                 non_blocking_mode: false,
+                ..Default::default()
             })
         );
 
         //
     }
+
+    #[test]
+    pub fn test_request_is_thread_safe() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ProcessRequest>();
+        assert_send_sync::<Arc<crate::ProcessCallback>>();
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    pub fn test_resource_sampling() {
+        let callback = |status: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            if let ProcessEvent::ResourceSample = status {
+                println!("Event {:?} | {:?}", status, data.resource_usage);
+                assert!(data.resource_usage.is_some());
+            }
+            ProcessResult::new()
+        };
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 201,
+            callback: Some(Arc::new(callback)),
+            use_shell: true,
+            cmd_line: vec![vec![String::from("sleep 1")]],
+            resource_sample_interval: Some(Duration::from_millis(100)),
+            ..Default::default()
+        });
+        assert!(result.peak_resource_usage.unwrap().rss_bytes > 0);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    pub fn test_resource_limits() {
+        static LIMIT_EXCEEDED: AtomicBool = AtomicBool::new(false);
+        let callback = |status: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            println!("Event {:?} | {}", status, data.line);
+            if let ProcessEvent::ResourceLimitExceeded = status {
+                LIMIT_EXCEEDED.store(true, Ordering::SeqCst);
+            }
+            ProcessResult::new()
+        };
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 211,
+            callback: Some(Arc::new(callback)),
+            use_shell: true,
+            cmd_line: vec![vec![String::from("ulimit -n; while :; do :; done")]],
+            resource_limits: vec![ResourceLimit::OpenFiles(64), ResourceLimit::CpuSeconds(1)],
+            ..Default::default()
+        });
+        assert!(result.success.is_ok());
+        assert!(LIMIT_EXCEEDED.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    pub fn test_priority() {
+        let callback = |status: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            let mut result = ProcessResult::new();
+            if let ProcessEvent::IOData = status {
+                result.data_num = data.line.trim().parse().ok();
+            }
+            result
+        };
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 221,
+            callback: Some(Arc::new(callback)),
+            cmd_line: vec![vec![String::from("nice")]],
+            priority: Some(ProcessPriority::BelowNormal),
+            ..Default::default()
+        });
+        assert_eq!(result.data_num, Some(10));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    pub fn test_inherit_priority() {
+        use crate::{PriorityInheritance, ThreadPriority};
+        use std::thread;
+
+        let callback = |status: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            let mut result = ProcessResult::new();
+            if let ProcessEvent::IOData = status {
+                result.data_num = data.line.trim().parse().ok();
+                result.data_bool =
+                    Some(ThreadPriority::current().priority == ProcessPriority::Nice(7));
+            }
+            result
+        };
+        // the nice value is per thread on Linux, so lower it only in a thread of this test
+        let result = thread::spawn(move || {
+            assert_eq!(unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, 7) }, 0);
+            ProcessRequest::start(ProcessRequest {
+                request_id: 322,
+                callback: Some(Arc::new(callback)),
+                cmd_line: vec![vec![String::from("nice")]],
+                inherit_priority: Some(PriorityInheritance::CallingThread),
+                non_blocking_mode: true,
+                ..Default::default()
+            })
+        })
+        .join()
+        .unwrap();
+        let result = result.wait().unwrap();
+        assert_eq!(result.data_num, Some(7));
+        assert_eq!(result.data_bool, Some(true));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    pub fn test_cpu_affinity() {
+        let callback = |status: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            let mut result = ProcessResult::new();
+            if let ProcessEvent::IOData = status {
+                result.data_vec_str = Some(vec![data.line.trim().to_string()]);
+            }
+            result
+        };
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 231,
+            callback: Some(Arc::new(callback)),
+            use_shell: true,
+            cmd_line: vec![vec![String::from(
+                "grep Cpus_allowed_list /proc/self/status",
+            )]],
+            cpu_affinity: Some(vec![0]),
+            ..Default::default()
+        });
+        assert_eq!(
+            result.data_vec_str,
+            Some(vec![String::from("Cpus_allowed_list:\t0")])
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    pub fn test_retry() {
+        let callback = |status: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            println!("Event {:?} | {}", status, data.line);
+            ProcessResult::new()
+        };
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 241,
+            callback: Some(Arc::new(callback)),
+            use_shell: true,
+            cmd_line: vec![vec![String::from("exit 3")]],
+            retry: Some(RetryPolicy {
+                max_attempts: 3,
+                backoff: Backoff::Fixed(Duration::from_millis(10)),
+                retry_on: RetryOn::ExitCodes(vec![3]),
+            }),
+            ..Default::default()
+        });
+        assert_eq!(result.attempts, 3);
+        assert_eq!(result.exit_code, Some(3));
+
+        let retry = Some(RetryPolicy {
+            max_attempts: 3,
+            backoff: Backoff::Fixed(Duration::from_millis(10)),
+            retry_on: RetryOn::StartError,
+        });
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 330,
+            cmd_line: vec![vec![String::from("/nonexistent/retry-test")]],
+            retry: retry.clone(),
+            ..Default::default()
+        });
+        assert_eq!(result.attempts, 3);
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 501,
+            cmd_line: vec![vec![String::from("true")]],
+            patterns: vec![String::from("(")],
+            retry: retry.clone(),
+            ..Default::default()
+        });
+        assert_eq!(result.attempts, 1);
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 502,
+            cmd_line: vec![vec![String::from("true")]],
+            dry_run: true,
+            retry,
+            ..Default::default()
+        });
+        assert_eq!(result.attempts, 1);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    pub fn test_idle_timeout() {
+        static IDLE_TIMEOUT: AtomicBool = AtomicBool::new(false);
+        let callback = |status: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            println!("Event {:?} | {}", status, data.line);
+            if let ProcessEvent::IdleTimeout = status {
+                IDLE_TIMEOUT.store(true, Ordering::SeqCst);
+            }
+            ProcessResult::new()
+        };
+        let started = Instant::now();
+        ProcessRequest::start(ProcessRequest {
+            request_id: 251,
+            callback: Some(Arc::new(callback)),
+            use_shell: true,
+            cmd_line: vec![vec![String::from("echo started; exec sleep 10")]],
+            idle_timeout: Some(Duration::from_millis(300)),
+            ..Default::default()
+        });
+        assert!(IDLE_TIMEOUT.load(Ordering::SeqCst));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    pub fn test_read_timeout() {
+        let stalls = Arc::new(AtomicU32::new(0));
+        let counted = Arc::clone(&stalls);
+        let callback = move |status: &ProcessEvent, _data: &ProcessData| -> ProcessResult {
+            if let ProcessEvent::ReadStalled = status {
+                counted.fetch_add(1, Ordering::SeqCst);
+            }
+            ProcessResult::new()
+        };
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 257,
+            callback: Some(Arc::new(callback.clone())),
+            use_shell: true,
+            cmd_line: vec![vec![String::from("echo started; sleep 0.6; echo done")]],
+            read_timeout: Some(Duration::from_millis(200)),
+            ..Default::default()
+        });
+        assert_eq!(stalls.load(Ordering::SeqCst), 1);
+        assert_eq!(result.exit_code, Some(0));
+
+        let started = Instant::now();
+        ProcessRequest::start(ProcessRequest {
+            request_id: 258,
+            callback: Some(Arc::new(callback)),
+            use_shell: true,
+            cmd_line: vec![vec![String::from("echo started; exec sleep 10")]],
+            read_timeout: Some(Duration::from_millis(200)),
+            kill_on_read_stall: true,
+            ..Default::default()
+        });
+        assert_eq!(stalls.load(Ordering::SeqCst), 2);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    pub fn test_heartbeat() {
+        static HEARTBEATS: AtomicU32 = AtomicU32::new(0);
+        let callback = |status: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            if let ProcessEvent::Heartbeat = status {
+                let heartbeat = data.heartbeat.unwrap();
+                assert!(heartbeat.elapsed >= Duration::from_millis(100));
+                assert!(heartbeat.last_activity <= SystemTime::now());
+                HEARTBEATS.fetch_add(1, Ordering::SeqCst);
+            }
+            ProcessResult::new()
+        };
+        ProcessRequest::start(ProcessRequest {
+            request_id: 252,
+            callback: Some(Arc::new(callback)),
+            cmd_line: vec![vec![String::from("sleep"), String::from("0.55")]],
+            heartbeat_interval: Some(Duration::from_millis(100)),
+            ..Default::default()
+        });
+        assert!((4..=6).contains(&HEARTBEATS.load(Ordering::SeqCst)));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    pub fn test_event_timestamps() {
+        static LAST_ELAPSED: std::sync::Mutex<Duration> = std::sync::Mutex::new(Duration::ZERO);
+        let callback = |_status: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            let mut last_elapsed = LAST_ELAPSED.lock().unwrap();
+            assert!(data.elapsed() >= *last_elapsed);
+            assert!(data.timestamp() <= SystemTime::now());
+            *last_elapsed = data.elapsed();
+            ProcessResult::new()
+        };
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 253,
+            callback: Some(Arc::new(callback)),
+            use_shell: true,
+            cmd_line: vec![vec![String::from("echo a; sleep 0.2; echo b")]],
+            ..Default::default()
+        });
+        assert!(*LAST_ELAPSED.lock().unwrap() >= Duration::from_millis(200));
+        assert!(result.duration.unwrap() >= Duration::from_millis(200));
+    }
+
+    #[test]
+    pub fn test_context() {
+        let counter = Arc::new(AtomicU32::new(0));
+        let callback = |status: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            if let ProcessEvent::IOData = status {
+                data.context::<AtomicU32>()
+                    .unwrap()
+                    .fetch_add(1, Ordering::SeqCst);
+            }
+            assert!(data.context::<String>().is_none());
+            ProcessResult::new()
+        };
+        ProcessRequest::start(ProcessRequest {
+            request_id: 254,
+            callback: Some(Arc::new(callback)),
+            cmd_line: vec![vec![String::from("echo"), String::from("context")]],
+            context: Some(counter.clone()),
+            ..Default::default()
+        });
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    pub fn test_wait() {
+        let start = || {
+            ProcessRequest::start(ProcessRequest {
+                request_id: 255,
+                cmd_line: vec![vec![String::from("sleep"), String::from("0.3")]],
+                non_blocking_mode: true,
+                ..Default::default()
+            })
+        };
+        let pending = match start().wait_timeout(Duration::from_millis(10)) {
+            Err(ProcessError::TimedOut(pending)) => pending,
+            other => panic!("unexpected {:?}", other),
+        };
+        let result = pending.wait_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(result.exit_code, Some(0));
+        assert_eq!(start().wait().unwrap().exit_code, Some(0));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    pub fn test_success_exit_codes() {
+        let grep = |success_exit_codes: Option<Vec<i32>>| {
+            ProcessRequest::start(ProcessRequest {
+                request_id: 256,
+                use_shell: true,
+                cmd_line: vec![vec![String::from("echo a | grep b")]],
+                success_exit_codes,
+                ..Default::default()
+            })
+        };
+        let result = grep(None);
+        assert_eq!(result.exit_code, Some(1));
+        assert!(!result.success.unwrap());
+        assert!(grep(Some(vec![0, 1])).success.unwrap());
+    }
+
+    #[test]
+    pub fn test_cancel_before_start() {
+        static CANCELLED: AtomicBool = AtomicBool::new(false);
+        let callback = |status: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            println!("Event {:?} | {}", status, data.line);
+            if let ProcessEvent::ScheduleCancelled = status {
+                CANCELLED.store(true, Ordering::SeqCst);
+            }
+            ProcessResult::new()
+        };
+        let process_result = ProcessRequest::start(ProcessRequest {
+            request_id: 261,
+            callback: Some(Arc::new(callback)),
+            cmd_line: vec![vec![String::from("echo"), String::from("never")]],
+            non_blocking_mode: true,
+            start_after: Some(Duration::from_secs(10)),
+            ..Default::default()
+        });
+        assert!(process_result.cancel_before_start());
+        let result = process_result.join_handle.unwrap().unwrap().join().unwrap();
+        assert_eq!(result.attempts, 0);
+        assert!(CANCELLED.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    pub fn test_start_jitter() {
+        static DELAY_NANOS: AtomicU64 = AtomicU64::new(u64::MAX);
+        static REPORTED: AtomicBool = AtomicBool::new(false);
+        let jitter = Duration::from_millis(50);
+        let callback = |status: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            if let (ProcessEvent::Starting, Some(delay)) = (status, data.start_delay) {
+                DELAY_NANOS.store(delay.as_nanos() as u64, Ordering::SeqCst);
+                REPORTED.store(data.line.contains(", start jitter: "), Ordering::SeqCst);
+            }
+            ProcessResult::new()
+        };
+        let started = Instant::now();
+        ProcessRequest::start(ProcessRequest {
+            request_id: 324,
+            callback: Some(Arc::new(callback)),
+            cmd_line: vec![vec![String::from("echo"), String::from("jittered")]],
+            start_jitter: Some(jitter),
+            ..Default::default()
+        });
+        let delay = Duration::from_nanos(DELAY_NANOS.load(Ordering::SeqCst));
+        assert!(delay <= jitter);
+        assert!(started.elapsed() >= delay);
+        assert!(REPORTED.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    pub fn test_timeout_env_working_dir() {
+        static TIMED_OUT: AtomicBool = AtomicBool::new(false);
+        let callback = |status: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            match status {
+                ProcessEvent::IOData => assert_eq!(data.line.trim(), "/ value"),
+                ProcessEvent::TimedOut => TIMED_OUT.store(true, Ordering::SeqCst),
+                _ => {}
+            }
+            ProcessResult::new()
+        };
+        let started = Instant::now();
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 362,
+            callback: Some(Arc::new(callback)),
+            use_shell: true,
+            cmd_line: vec![vec![String::from("echo $(pwd) $PES_VAR; exec sleep 10")]],
+            env: [(String::from("PES_VAR"), String::from("value"))].into(),
+            working_dir: Some(std::path::PathBuf::from("/")),
+            timeout: Some(Duration::from_millis(300)),
+            ..Default::default()
+        });
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(TIMED_OUT.load(Ordering::SeqCst));
+        assert_ne!(result.exit_code, Some(0));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    pub fn test_dry_run() {
+        static RESOLVED: std::sync::Mutex<String> = std::sync::Mutex::new(String::new());
+        let callback = |status: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            assert_eq!(*status, ProcessEvent::Starting);
+            RESOLVED.lock().unwrap().push_str(&data.line);
+            ProcessResult::new()
+        };
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 363,
+            callback: Some(Arc::new(callback)),
+            use_shell: true,
+            dry_run: true,
+            cmd_line: vec![
+                vec![String::from("touch /tmp/pes_dry_run")],
+                vec![String::from("cat")],
+            ],
+            env: [(String::from("PES_VAR"), String::from("a b"))].into(),
+            working_dir: Some(std::path::PathBuf::from("/tmp")),
+            ..Default::default()
+        });
+        assert_eq!(
+            *RESOLVED.lock().unwrap(),
+            r#"[cwd: /tmp] [env: PES_VAR="a b"] "/bin/sh" "-c" "touch /tmp/pes_dry_run" | "/bin/sh" "-c" "cat""#
+        );
+        assert!(!std::path::Path::new("/tmp/pes_dry_run").exists());
+        assert_eq!(result.exit_code, None);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    pub fn test_serde_round_trip() {
+        let request: ProcessRequest = serde_json::from_str(
+            r#"{"request_id": 361, "cmd_line": [["echo", "serde"]], "retry": {"max_attempts": 2, "backoff": {"Fixed": {"secs": 0, "nanos": 0}}, "retry_on": "Failure"}}"#,
+        )
+        .unwrap();
+        assert_eq!(request.request_id, 361);
+        assert_eq!(request.retry.as_ref().unwrap().max_attempts, 2);
+        let result = ProcessRequest::start(request);
+        let json = serde_json::to_string(&result).unwrap();
+        let result: ProcessResult = serde_json::from_str(&json).unwrap();
+        assert_eq!(result.exit_code, Some(0));
+        assert_eq!(result.attempts, 1);
+    }
 }
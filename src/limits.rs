@@ -0,0 +1,140 @@
+use crate::status;
+use duct::Expression;
+use std::io;
+
+/// Resource limit (rlimit) applied to the spawned process(es), supported on Unix only
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ResourceLimit {
+    /// RLIMIT_CPU : max CPU time in seconds, the process gets SIGXCPU and later SIGKILL
+    CpuSeconds(u64),
+    /// RLIMIT_AS : max size of the virtual memory (address space) in bytes
+    AddressSpaceBytes(u64),
+    /// RLIMIT_NOFILE : max number of open file descriptors
+    OpenFiles(u64),
+    /// RLIMIT_FSIZE : max size of a file written by the process in bytes, the process gets SIGXFSZ
+    FileSizeBytes(u64),
+    /// RLIMIT_NPROC : max number of processes for the real user id
+    Processes(u64),
+    /// RLIMIT_CORE : max size of a core dump file in bytes
+    CoreFileBytes(u64),
+    /// RLIMIT_STACK : max size of the stack in bytes
+    StackBytes(u64),
+}
+
+/// Apply the resource limits to all the commands of the expression before they are spawned
+#[cfg(unix)]
+pub(crate) fn apply_resource_limits(
+    expression: Expression,
+    limits: &[ResourceLimit],
+) -> io::Result<Expression> {
+    use std::os::unix::process::CommandExt;
+
+    if limits.is_empty() {
+        return Ok(expression);
+    }
+    let limits = limits.to_vec();
+    Ok(expression.before_spawn(move |command| {
+        let limits = limits.clone();
+        unsafe {
+            command.pre_exec(move || {
+                for limit in &limits {
+                    set_resource_limit(limit)?;
+                }
+                Ok(())
+            });
+        }
+        Ok(())
+    }))
+}
+
+/// Resource limits are not available on this platform
+#[cfg(not(unix))]
+pub(crate) fn apply_resource_limits(
+    expression: Expression,
+    limits: &[ResourceLimit],
+) -> io::Result<Expression> {
+    if limits.is_empty() {
+        return Ok(expression);
+    }
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Resource limits are supported on Unix only",
+    ))
+}
+
+/// set soft & hard limit, runs in the child after fork so must not allocate
+#[cfg(unix)]
+fn set_resource_limit(limit: &ResourceLimit) -> io::Result<()> {
+    let (resource, value) = match *limit {
+        ResourceLimit::CpuSeconds(value) => (libc::RLIMIT_CPU, value),
+        ResourceLimit::AddressSpaceBytes(value) => (libc::RLIMIT_AS, value),
+        ResourceLimit::OpenFiles(value) => (libc::RLIMIT_NOFILE, value),
+        ResourceLimit::FileSizeBytes(value) => (libc::RLIMIT_FSIZE, value),
+        ResourceLimit::Processes(value) => (libc::RLIMIT_NPROC, value),
+        ResourceLimit::CoreFileBytes(value) => (libc::RLIMIT_CORE, value),
+        ResourceLimit::StackBytes(value) => (libc::RLIMIT_STACK, value),
+    };
+    let mut rlimit = libc::rlimit {
+        rlim_cur: value as libc::rlim_t,
+        rlim_max: value as libc::rlim_t,
+    };
+    // CPU limit: keep the hard limit a bit higher so the process gets SIGXCPU before SIGKILL
+    if let ResourceLimit::CpuSeconds(_) = limit {
+        rlimit.rlim_max = rlimit.rlim_cur.saturating_add(1);
+    }
+    if unsafe { libc::setrlimit(resource, &rlimit) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Check if the read error is due to a process killed by one of the configured limits,
+/// returns the description of the exceeded limit
+pub(crate) fn exceeded_resource_limit(
+    limits: &[ResourceLimit],
+    error: &io::Error,
+) -> Option<String> {
+    let signal = status::exit_signal(error)?;
+    limits
+        .iter()
+        .find(|limit| is_limit_signal(limit, signal))
+        .map(|limit| {
+            format!(
+                "{:?} exceeded, process terminated by signal {}",
+                limit, signal
+            )
+        })
+}
+
+#[cfg(unix)]
+fn is_limit_signal(limit: &ResourceLimit, signal: i32) -> bool {
+    match limit {
+        ResourceLimit::CpuSeconds(_) => signal == libc::SIGXCPU,
+        ResourceLimit::FileSizeBytes(_) => signal == libc::SIGXFSZ,
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_limit_signal(_limit: &ResourceLimit, _signal: i32) -> bool {
+    false
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use crate::limits::exceeded_resource_limit;
+    use crate::ResourceLimit;
+    use std::io;
+
+    #[test]
+    pub fn test_exceeded_resource_limit() {
+        let killed_by =
+            |signal: i32| io::Error::other(format!("command exited with <signal {}>", signal));
+        let limits = [ResourceLimit::CpuSeconds(1)];
+        assert!(exceeded_resource_limit(&limits, &killed_by(libc::SIGXCPU)).is_some());
+        // e.g. the OOM killer or a timeout, not the CPU limit
+        assert!(exceeded_resource_limit(&limits, &killed_by(libc::SIGKILL)).is_none());
+        assert!(exceeded_resource_limit(&limits, &killed_by(libc::SIGXFSZ)).is_none());
+    }
+}
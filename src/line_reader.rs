@@ -0,0 +1,170 @@
+use std::io::{self, Read};
+
+/// Size of the block read from the process output
+const BLOCK_SIZE: usize = 64 * 1024;
+
+/// What to do with an output line which is not valid UTF-8, see [`crate::ProcessRequest::invalid_utf8`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InvalidUtf8 {
+    /// Stop reading with an [`io::ErrorKind::InvalidData`] error, like [`std::io::BufRead::read_line`]
+    #[default]
+    Error,
+    /// Replace the invalid sequences with `U+FFFD` & continue
+    Lossy,
+}
+
+/// Splits the output into lines by reading big blocks & scanning them for the line feeds, the UTF-8 is validated
+/// once per line as per the [`InvalidUtf8`] policy. Used for the process output, it works with any reader
+pub struct LineReader<R> {
+    inner: R,
+    block: Box<[u8]>,
+    start: usize,
+    end: usize,
+    /// bytes of a line spanning multiple blocks
+    pending: Vec<u8>,
+    invalid_utf8: InvalidUtf8,
+    /// invalid UTF-8 of the last line was replaced
+    replaced: bool,
+}
+
+impl<R: Read> LineReader<R> {
+    /// Read the lines of the reader, invalid UTF-8 is an error
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            block: vec![0; BLOCK_SIZE].into_boxed_slice(),
+            start: 0,
+            end: 0,
+            pending: vec![],
+            invalid_utf8: InvalidUtf8::Error,
+            replaced: false,
+        }
+    }
+
+    /// Handle the invalid UTF-8 as per the policy
+    pub fn invalid_utf8(mut self, invalid_utf8: InvalidUtf8) -> Self {
+        self.invalid_utf8 = invalid_utf8;
+        self
+    }
+
+    /// Append the next line including its line feed to the buffer, returns the number of bytes read (0 at the EOF).
+    /// The bytes of a partial line read before an error are appended as well
+    pub fn read_line(&mut self, line: &mut String) -> io::Result<usize> {
+        self.replaced = false;
+        loop {
+            if self.start == self.end {
+                match self.inner.read(&mut self.block) {
+                    Ok(0) => return self.take_pending(line),
+                    Ok(read) => {
+                        self.start = 0;
+                        self.end = read;
+                    }
+                    Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(error) => {
+                        _ = self.take_pending(line);
+                        return Err(error);
+                    }
+                }
+            }
+            let available = &self.block[self.start..self.end];
+            match memchr::memchr(b'\n', available) {
+                Some(index) if self.pending.is_empty() => {
+                    self.start += index + 1;
+                    return append_utf8(
+                        &available[..=index],
+                        self.invalid_utf8,
+                        line,
+                        &mut self.replaced,
+                    );
+                }
+                Some(index) => {
+                    self.pending.extend_from_slice(&available[..=index]);
+                    self.start += index + 1;
+                    return self.take_pending(line);
+                }
+                None => {
+                    self.pending.extend_from_slice(available);
+                    self.start = self.end;
+                }
+            }
+        }
+    }
+
+    /// Invalid UTF-8 of the last read line was replaced as per the [`InvalidUtf8::Lossy`] policy
+    pub fn replaced_invalid_utf8(&self) -> bool {
+        self.replaced
+    }
+
+    /// append the pending bytes of the line spanning multiple blocks, the buffer is kept for reuse
+    fn take_pending(&mut self, line: &mut String) -> io::Result<usize> {
+        let read = append_utf8(&self.pending, self.invalid_utf8, line, &mut self.replaced);
+        self.pending.clear();
+        read
+    }
+}
+
+/// validate & append the bytes of a line
+fn append_utf8(
+    bytes: &[u8],
+    invalid_utf8: InvalidUtf8,
+    line: &mut String,
+    replaced: &mut bool,
+) -> io::Result<usize> {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => line.push_str(text),
+        Err(_) if invalid_utf8 == InvalidUtf8::Lossy => {
+            *replaced = true;
+            line.push_str(&String::from_utf8_lossy(bytes))
+        }
+        Err(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            ))
+        }
+    }
+    Ok(bytes.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{InvalidUtf8, LineReader};
+
+    fn lines(input: &[u8], invalid_utf8: InvalidUtf8) -> Vec<String> {
+        let mut reader = LineReader::new(input).invalid_utf8(invalid_utf8);
+        let mut lines = vec![];
+        let mut line = String::new();
+        while reader.read_line(&mut line).unwrap() > 0 {
+            lines.push(std::mem::take(&mut line));
+        }
+        lines
+    }
+
+    #[test]
+    pub fn test_line_reader() {
+        let long_line = "x".repeat(200 * 1024);
+        let input = format!("a\r\n\nb\n{}\nlast", long_line);
+        assert_eq!(
+            lines(input.as_bytes(), InvalidUtf8::Error),
+            ["a\r\n", "\n", "b\n", &format!("{}\n", long_line), "last"]
+        );
+        assert_eq!(
+            lines(b"ok\n\xff\n", InvalidUtf8::Lossy),
+            ["ok\n", "\u{fffd}\n"]
+        );
+        let mut reader = LineReader::new(&b"ok\n\xff\n"[..]).invalid_utf8(InvalidUtf8::Lossy);
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert!(!reader.replaced_invalid_utf8());
+        reader.read_line(&mut line).unwrap();
+        assert!(reader.replaced_invalid_utf8());
+        let mut reader = LineReader::new(&b"ok\n\xff\n"[..]);
+        let mut line = String::new();
+        assert_eq!(reader.read_line(&mut line).unwrap(), 3);
+        assert_eq!(
+            reader.read_line(&mut line).unwrap_err().kind(),
+            std::io::ErrorKind::InvalidData
+        );
+    }
+}
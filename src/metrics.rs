@@ -0,0 +1,136 @@
+use crate::{ProcessData, ProcessEvent, ProcessRequest};
+use prometheus::{
+    Counter, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+};
+use std::time::Duration;
+
+/// Prometheus metrics of the process executions, share it between requests using [`std::sync::Arc`],
+/// see [`crate::ProcessRequest::metrics`]
+#[derive(Clone)]
+pub struct ProcessMetrics {
+    started: IntCounter,
+    failed: IntCounter,
+    exit_codes: IntCounterVec,
+    duration: Histogram,
+    lines: IntCounter,
+    bytes_read: IntCounter,
+    throttled: Counter,
+    active: IntGauge,
+}
+
+impl ProcessMetrics {
+    /// Create the metrics, names are prefixed with `pes_`
+    pub fn new() -> prometheus::Result<Self> {
+        Ok(Self {
+            started: IntCounter::new("pes_processes_started_total", "Processes started")?,
+            failed: IntCounter::new(
+                "pes_processes_failed_total",
+                "Processes failed to start or exited with a non-zero exit code",
+            )?,
+            exit_codes: IntCounterVec::new(
+                Opts::new(
+                    "pes_process_exit_codes_total",
+                    "Process exits by the exit code, `none` when killed or terminated by a signal",
+                ),
+                &["code"],
+            )?,
+            duration: Histogram::with_opts(HistogramOpts::new(
+                "pes_process_duration_seconds",
+                "Execution duration of the processes",
+            ))?,
+            lines: IntCounter::new("pes_process_lines_total", "Output lines streamed")?,
+            bytes_read: IntCounter::new("pes_process_bytes_read_total", "Output bytes read")?,
+            throttled: Counter::new(
+                "pes_process_output_throttled_seconds_total",
+                "Time the output reading was paused by the output throttle",
+            )?,
+            active: IntGauge::new("pes_processes_active", "Processes running now")?,
+        })
+    }
+
+    /// Register all the metrics into the registry
+    pub fn register(&self, registry: &Registry) -> prometheus::Result<()> {
+        registry.register(Box::new(self.started.clone()))?;
+        registry.register(Box::new(self.failed.clone()))?;
+        registry.register(Box::new(self.exit_codes.clone()))?;
+        registry.register(Box::new(self.duration.clone()))?;
+        registry.register(Box::new(self.lines.clone()))?;
+        registry.register(Box::new(self.bytes_read.clone()))?;
+        registry.register(Box::new(self.throttled.clone()))?;
+        registry.register(Box::new(self.active.clone()))
+    }
+
+    /// update the metrics as per the process event
+    pub(crate) fn observe_event(&self, event: &ProcessEvent, data: &ProcessData) {
+        match event {
+            ProcessEvent::Started => {
+                self.started.inc();
+                self.active.inc();
+            }
+            ProcessEvent::StartError => self.failed.inc(),
+            ProcessEvent::IOData => {
+                self.lines.inc();
+                self.bytes_read.inc_by(data.line.len() as u64);
+            }
+            _ => {}
+        }
+    }
+
+    /// account the pause of the output reading by the throttle
+    pub(crate) fn observe_throttled(&self, throttled: Duration) {
+        if !throttled.is_zero() {
+            self.throttled.inc_by(throttled.as_secs_f64());
+        }
+    }
+
+    /// update the metrics once a started process is completed
+    pub(crate) fn observe_exit(&self, duration: Duration, exit_code: Option<i32>) {
+        self.active.dec();
+        self.duration.observe(duration.as_secs_f64());
+        let code = exit_code.map_or(String::from("none"), |code| code.to_string());
+        self.exit_codes.with_label_values(&[&code]).inc();
+        if exit_code != Some(0) {
+            self.failed.inc();
+        }
+    }
+}
+
+/// metrics of the request if any
+pub(crate) fn request_metrics(request: &ProcessRequest) -> Option<&ProcessMetrics> {
+    request.metrics.as_deref()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ProcessMetrics, ProcessRequest};
+    use prometheus::Registry;
+    use std::sync::Arc;
+
+    #[test]
+    #[cfg(unix)]
+    pub fn test_prometheus_metrics() {
+        let registry = Registry::new();
+        let metrics = Arc::new(ProcessMetrics::new().unwrap());
+        metrics.register(&registry).unwrap();
+        for (request_id, command) in [(401, "echo one; echo two"), (402, "exit 4")] {
+            ProcessRequest::start(ProcessRequest {
+                request_id,
+                use_shell: true,
+                cmd_line: vec![vec![String::from(command)]],
+                metrics: Some(Arc::clone(&metrics)),
+                ..Default::default()
+            });
+        }
+        let families = registry.gather();
+        let value = |name: &str| {
+            let family = families.iter().find(|family| family.get_name() == name);
+            let metric = &family.unwrap().get_metric()[0];
+            metric.get_counter().get_value() + metric.get_gauge().get_value()
+        };
+        assert_eq!(value("pes_processes_started_total"), 2.0);
+        assert_eq!(value("pes_processes_failed_total"), 1.0);
+        assert_eq!(value("pes_process_lines_total"), 2.0);
+        assert_eq!(value("pes_process_bytes_read_total"), 8.0);
+        assert_eq!(value("pes_processes_active"), 0.0);
+    }
+}
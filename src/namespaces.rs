@@ -0,0 +1,319 @@
+use duct::Expression;
+#[cfg(target_os = "linux")]
+use std::ffi::{CStr, CString};
+use std::io;
+use std::path::PathBuf;
+
+/// Linux namespaces to isolate the process in, it needs the privileges (`CAP_SYS_ADMIN`) to create them
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Namespaces {
+    /// New mount namespace, the mounts of the process are not seen outside. Along with `pid` a fresh `/proc` is
+    /// mounted
+    pub mount: bool,
+    /// New PID namespace, the process is the PID 1 of it & sees only its own descendants
+    pub pid: bool,
+    /// New network namespace with only the loopback interface, no network access
+    pub network: bool,
+    /// Paths bind mounted in the new mount namespace, it's created for them even if `mount` is not set
+    pub bind_mounts: Vec<BindMount>,
+}
+
+/// A path bind mounted in the mount namespace of the process, see [`Namespaces::bind_mounts`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BindMount {
+    /// Existing path to mount
+    pub source: PathBuf,
+    /// Existing path where it's mounted
+    pub target: PathBuf,
+    /// Mount it read-only
+    pub read_only: bool,
+}
+
+impl BindMount {
+    /// Make the path read-only for the process
+    pub fn read_only(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        Self {
+            source: path.clone(),
+            target: path,
+            read_only: true,
+        }
+    }
+
+    /// Mount the source path at the target path, writable by the process
+    pub fn read_write(source: impl Into<PathBuf>, target: impl Into<PathBuf>) -> Self {
+        Self {
+            source: source.into(),
+            target: target.into(),
+            read_only: false,
+        }
+    }
+}
+
+/// namespaces to unshare with the paths converted ahead, as the child can't allocate
+#[cfg(target_os = "linux")]
+#[derive(Clone)]
+struct Unshare {
+    flags: libc::c_int,
+    mount_proc: bool,
+    binds: Vec<(CString, CString, bool)>,
+}
+
+/// Unshare the namespaces for all the commands of the expression before they are executed, before switching the
+/// user as the privileges are needed
+#[cfg(target_os = "linux")]
+pub(crate) fn apply_namespaces(
+    expression: Expression,
+    namespaces: Option<&Namespaces>,
+) -> io::Result<Expression> {
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::process::CommandExt;
+
+    let Some(namespaces) = namespaces else {
+        return Ok(expression);
+    };
+    let c_path = |path: &std::path::Path| {
+        CString::new(path.as_os_str().as_bytes())
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))
+    };
+    let mut binds = vec![];
+    for bind in &namespaces.bind_mounts {
+        binds.push((c_path(&bind.source)?, c_path(&bind.target)?, bind.read_only));
+    }
+    let mount = namespaces.mount || !binds.is_empty();
+    let mut flags = 0;
+    if mount {
+        flags |= libc::CLONE_NEWNS;
+    }
+    if namespaces.pid {
+        flags |= libc::CLONE_NEWPID;
+    }
+    if namespaces.network {
+        flags |= libc::CLONE_NEWNET;
+    }
+    let namespaces = Unshare {
+        flags,
+        mount_proc: mount && namespaces.pid,
+        binds,
+    };
+    Ok(expression.before_spawn(move |command| {
+        let namespaces = namespaces.clone();
+        unsafe {
+            command.pre_exec(move || unshare(&namespaces));
+        }
+        Ok(())
+    }))
+}
+
+/// Namespaces are not available on this platform
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn apply_namespaces(
+    expression: Expression,
+    namespaces: Option<&Namespaces>,
+) -> io::Result<Expression> {
+    if namespaces.is_none() {
+        return Ok(expression);
+    }
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Namespaces are supported on Linux only",
+    ))
+}
+
+/// runs in the child after fork so must not allocate. A new PID namespace applies to the children only, so the
+/// child forks once more: the forked process runs the command as the PID 1 & this one waits to pass on its exit
+/// status, it's killed along with this one
+#[cfg(target_os = "linux")]
+fn unshare(namespaces: &Unshare) -> io::Result<()> {
+    unsafe {
+        if libc::unshare(namespaces.flags) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if namespaces.flags & libc::CLONE_NEWNET != 0 {
+            crate::network::loopback_up()?;
+        }
+        if namespaces.flags & libc::CLONE_NEWNS != 0 {
+            // keep the mounts of the process private
+            mount(None, c"/", None, libc::MS_REC | libc::MS_PRIVATE)?;
+            for (source, target, read_only) in &namespaces.binds {
+                mount(Some(source), target, None, libc::MS_BIND | libc::MS_REC)?;
+                if *read_only {
+                    let flags = libc::MS_BIND | libc::MS_REMOUNT | libc::MS_RDONLY;
+                    mount(None, target, None, flags)?;
+                }
+            }
+        }
+        if namespaces.flags & libc::CLONE_NEWPID == 0 {
+            return Ok(());
+        }
+        match libc::fork() {
+            -1 => Err(io::Error::last_os_error()),
+            0 => {
+                if libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                if namespaces.mount_proc {
+                    let flags = libc::MS_NOSUID | libc::MS_NODEV | libc::MS_NOEXEC;
+                    mount(Some(c"proc"), c"/proc", Some(c"proc"), flags)?;
+                }
+                Ok(())
+            }
+            child => {
+                // the spawn waits for the exec of the command till its error pipe is closed by all
+                close_all_fds();
+                let mut status = 0;
+                while libc::waitpid(child, &mut status, 0) == -1 {
+                    if io::Error::last_os_error().raw_os_error() != Some(libc::EINTR) {
+                        libc::_exit(127);
+                    }
+                }
+                if libc::WIFSIGNALED(status) {
+                    let signal = libc::WTERMSIG(status);
+                    libc::signal(signal, libc::SIG_DFL);
+                    libc::raise(signal);
+                    libc::_exit(128 + signal);
+                }
+                libc::_exit(libc::WEXITSTATUS(status))
+            }
+        }
+    }
+}
+
+/// mount(2) without data
+#[cfg(target_os = "linux")]
+unsafe fn mount(
+    source: Option<&CStr>,
+    target: &CStr,
+    filesystem: Option<&CStr>,
+    flags: libc::c_ulong,
+) -> io::Result<()> {
+    let as_ptr = |text: Option<&CStr>| text.map_or(std::ptr::null(), CStr::as_ptr);
+    let result = libc::mount(
+        as_ptr(source),
+        target.as_ptr(),
+        as_ptr(filesystem),
+        flags,
+        std::ptr::null(),
+    );
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// close all the file descriptors, the waiting process keeps none of the pipes open
+#[cfg(target_os = "linux")]
+unsafe fn close_all_fds() {
+    if libc::syscall(libc::SYS_close_range, 0, u32::MAX, 0) != 0 {
+        for fd in 0..libc::sysconf(libc::_SC_OPEN_MAX).clamp(0, 65536) as libc::c_int {
+            libc::close(fd);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BindMount, Namespaces, ProcessData, ProcessEvent, ProcessRequest, ProcessResult};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    pub fn test_namespaces() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let recorded = Arc::clone(&events);
+        let callback = move |event: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            if matches!(event, ProcessEvent::IOData | ProcessEvent::StartError) {
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push((*event, data.line_to_owned()));
+            }
+            ProcessResult::new()
+        };
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 268,
+            callback: Some(Arc::new(callback)),
+            use_shell: true,
+            cmd_line: vec![vec![String::from(
+                "echo $$; ls /proc | grep -c '^[0-9]'; grep -c : /proc/net/dev; exit 3",
+            )]],
+            namespaces: Some(Namespaces {
+                mount: true,
+                pid: true,
+                network: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        let events = events.lock().unwrap();
+        // creating the namespaces needs the privileges
+        if events[0].0 == ProcessEvent::StartError {
+            assert!(events[0].1.contains("Operation not permitted"));
+            return;
+        }
+        let lines: Vec<_> = events.iter().map(|(_, line)| line.as_str()).collect();
+        // the shell is the PID 1 & sees only itself & its children, only the loopback interface
+        assert_eq!(lines[0], "1");
+        assert!(lines[1].parse::<u32>().unwrap() <= 3);
+        assert_eq!(lines[2], "1");
+        assert_eq!(result.exit_code, Some(3));
+
+        // the command is killed along with the process waiting for it
+        let started = Instant::now();
+        ProcessRequest::start(ProcessRequest {
+            request_id: 269,
+            cmd_line: vec![vec![String::from("sleep"), String::from("10")]],
+            namespaces: Some(Namespaces {
+                pid: true,
+                ..Default::default()
+            }),
+            timeout: Some(Duration::from_millis(200)),
+            ..Default::default()
+        });
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    pub fn test_bind_mounts() {
+        let source = std::env::temp_dir().join("pes_bind_source");
+        let target = std::env::temp_dir().join("pes_bind_target");
+        for path in [&source, &target] {
+            _ = std::fs::remove_dir_all(path);
+            std::fs::create_dir(path).unwrap();
+        }
+        std::fs::write(source.join("input"), "bound\n").unwrap();
+        let output = match crate::run::run_request(ProcessRequest {
+            request_id: 273,
+            use_shell: true,
+            cmd_line: vec![vec![format!(
+                "cat {0}/input; touch {0}/output 2>/dev/null || echo read-only; touch {1}/output && echo written",
+                target.display(),
+                source.display()
+            )]],
+            namespaces: Some(Namespaces {
+                bind_mounts: vec![
+                    BindMount::read_write(&source, &target),
+                    BindMount::read_only(&target),
+                ],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }) {
+            Ok(output) => output,
+            // creating the namespaces needs the privileges
+            Err(error) => {
+                assert!(error.to_string().contains("Operation not permitted"));
+                return;
+            }
+        };
+        assert_eq!(output.lines, ["bound", "read-only", "written"]);
+        // the mounts are not seen outside
+        assert!(std::fs::read_dir(&target).unwrap().next().is_none());
+        assert!(source.join("output").exists());
+    }
+}
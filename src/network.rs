@@ -0,0 +1,143 @@
+use duct::Expression;
+use std::io;
+
+/// Run all the commands of the expression in a new network namespace with only the loopback interface, see
+/// [`crate::ProcessRequest::deny_network`]
+#[cfg(target_os = "linux")]
+pub(crate) fn apply_deny_network(expression: Expression, deny: bool) -> io::Result<Expression> {
+    use std::os::unix::process::CommandExt;
+
+    if !deny {
+        return Ok(expression);
+    }
+    Ok(expression.before_spawn(|command| {
+        unsafe {
+            command.pre_exec(enter_network_namespace);
+        }
+        Ok(())
+    }))
+}
+
+/// Denying the network is not available on this platform
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn apply_deny_network(expression: Expression, deny: bool) -> io::Result<Expression> {
+    if !deny {
+        return Ok(expression);
+    }
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Denying the network is supported on Linux only",
+    ))
+}
+
+/// unshare the network namespace, without the privileges along with a user namespace mapping the current user
+/// (if the user namespaces are enabled). Runs in the child after fork so must not allocate
+#[cfg(target_os = "linux")]
+fn enter_network_namespace() -> io::Result<()> {
+    unsafe {
+        if libc::unshare(libc::CLONE_NEWNET) != 0 {
+            let error = io::Error::last_os_error();
+            if error.raw_os_error() != Some(libc::EPERM) {
+                return Err(error);
+            }
+            let (uid, gid) = (libc::getuid(), libc::getgid());
+            if libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) != 0 {
+                return Err(error);
+            }
+            write_id_map(c"/proc/self/setgroups", b"deny", 0)?;
+            write_id_map(c"/proc/self/uid_map", b"", uid)?;
+            write_id_map(c"/proc/self/gid_map", b"", gid)?;
+        }
+        loopback_up()
+    }
+}
+
+/// write the text or else the identity map of the id (`<id> <id> 1`) into the proc file
+#[cfg(target_os = "linux")]
+unsafe fn write_id_map(path: &std::ffi::CStr, text: &[u8], id: u32) -> io::Result<()> {
+    let mut map = [0u8; 32];
+    let map = if text.is_empty() {
+        let mut digits = [0u8; 10];
+        let mut count = 0;
+        let mut rest = id;
+        loop {
+            digits[count] = b'0' + (rest % 10) as u8;
+            count += 1;
+            rest /= 10;
+            if rest == 0 {
+                break;
+            }
+        }
+        let mut length = 0;
+        for _ in 0..2 {
+            for digit in digits[..count].iter().rev() {
+                map[length] = *digit;
+                length += 1;
+            }
+            map[length] = b' ';
+            length += 1;
+        }
+        map[length] = b'1';
+        &map[..=length]
+    } else {
+        text
+    };
+    let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let written = libc::write(fd, map.as_ptr() as *const libc::c_void, map.len());
+    let error = io::Error::last_os_error();
+    libc::close(fd);
+    if written != map.len() as isize {
+        return Err(error);
+    }
+    Ok(())
+}
+
+/// bring up the loopback interface of the new network namespace, it's down initially
+#[cfg(target_os = "linux")]
+pub(crate) unsafe fn loopback_up() -> io::Result<()> {
+    let socket = libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0);
+    if socket < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut request: libc::ifreq = std::mem::zeroed();
+    for (target, source) in request.ifr_name.iter_mut().zip(b"lo") {
+        *target = *source as libc::c_char;
+    }
+    let mut result = libc::ioctl(socket, libc::SIOCGIFFLAGS, &mut request);
+    if result == 0 {
+        request.ifr_ifru.ifru_flags |= libc::IFF_UP as libc::c_short;
+        result = libc::ioctl(socket, libc::SIOCSIFFLAGS, &request);
+    }
+    let error = io::Error::last_os_error();
+    libc::close(socket);
+    if result != 0 {
+        return Err(error);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::run::run_request;
+    use crate::ProcessRequest;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    pub fn test_deny_network() {
+        // only the loopback interface, which is up & has no routes
+        let output = run_request(ProcessRequest {
+            request_id: 270,
+            use_shell: true,
+            cmd_line: vec![vec![String::from(
+                "grep -c : /proc/net/dev; grep -c . /proc/net/route; grep -q 127.0.0.1 /proc/net/fib_trie && echo up",
+            )]],
+            deny_network: true,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(output.lines, ["1", "1", "up"]);
+    }
+}
@@ -0,0 +1,408 @@
+use crate::{
+    check_and_trigger_callback, ProcessData, ProcessEvent, ProcessRequest, ProcessResult,
+    Termination, WarningKind,
+};
+use std::io;
+use std::sync::Arc;
+use std::thread;
+
+/// Condition of a [`Notification`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NotificationKind {
+    /// A run of the [`crate::Supervisor`] or the [`crate::Scheduler`] failed (after all its retries)
+    Failed,
+    /// The [`crate::Supervisor`] gave up restarting the process, see [`ProcessEvent::RestartLimitReached`]
+    CrashLoop,
+}
+
+/// Failure reported to the [`Notifier`] of the request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    /// Condition being reported
+    pub kind: NotificationKind,
+    /// Id of the request, see [`ProcessRequest::request_id`]
+    pub request_id: u32,
+    /// Run sequence of the request, see [`ProcessRequest::run_sequence`]
+    pub run_sequence: u64,
+    /// Command line of the request
+    pub command: String,
+    /// Exit code of the last run
+    pub exit_code: Option<i32>,
+    /// How the last run terminated
+    pub termination: Option<Termination>,
+    /// Details, e.g. the number of the restarts within the window
+    pub message: String,
+}
+
+impl Notification {
+    /// notification of the last result of the request
+    pub(crate) fn new(
+        kind: NotificationKind,
+        request: &ProcessRequest,
+        result: &ProcessResult,
+        message: String,
+    ) -> Self {
+        Self {
+            kind,
+            request_id: request.request_id,
+            run_sequence: request.run_sequence,
+            command: request
+                .pipeline_stages()
+                .iter()
+                .map(|stage| stage.argv.join(" "))
+                .collect::<Vec<_>>()
+                .join(" | "),
+            exit_code: result.exit_code,
+            termination: result.termination,
+            message,
+        }
+    }
+
+    /// One line summary, e.g. the subject of an email
+    pub fn summary(&self) -> String {
+        let condition = match self.kind {
+            NotificationKind::Failed => "failed",
+            NotificationKind::CrashLoop => "is crash looping",
+        };
+        format!(
+            "Request {} (`{}`) {}, exit code {:?}",
+            self.request_id, self.command, condition, self.exit_code
+        )
+    }
+}
+
+/// Receives the failures of the runs of the [`crate::Supervisor`] & the [`crate::Scheduler`], see
+/// [`ProcessRequest::notifier`]. It's called in a background thread, an error is reported as a
+/// [`WarningKind::SinkWriteFailed`] warning
+pub trait Notifier: Send + Sync {
+    /// Deliver the notification
+    fn notify(&self, notification: &Notification) -> io::Result<()>;
+}
+
+/// deliver the notification to the notifier of the request if any, the caller is not held meanwhile
+pub(crate) fn send_notification(
+    request: &Arc<ProcessRequest>,
+    kind: NotificationKind,
+    result: &ProcessResult,
+    message: String,
+) {
+    let Some(notifier) = request.notifier.clone() else {
+        return;
+    };
+    let notification = Notification::new(kind, request, result, message);
+    let request = Arc::clone(request);
+    _ = thread::Builder::new()
+        .name(String::from("pes-notifier"))
+        .spawn(move || {
+            if notifier.notify(&notification).is_err() {
+                let mut process_data = ProcessData::new();
+                process_data.request = Some(Arc::clone(&request));
+                process_data.line.push_str(&notification.summary());
+                check_and_trigger_callback(
+                    &request,
+                    &ProcessEvent::Warning(WarningKind::SinkWriteFailed),
+                    &process_data,
+                );
+            }
+        });
+}
+
+/// Posts the notifications to a Slack incoming webhook, using the system `curl` CLI
+#[cfg(feature = "webhook")]
+#[derive(Debug, Clone)]
+pub struct SlackNotifier {
+    webhook_url: String,
+    timeout: std::time::Duration,
+}
+
+#[cfg(feature = "webhook")]
+impl SlackNotifier {
+    /// Post to the incoming webhook URL of the Slack channel
+    pub fn new(webhook_url: impl Into<String>) -> Self {
+        Self {
+            webhook_url: webhook_url.into(),
+            timeout: std::time::Duration::from_secs(10),
+        }
+    }
+
+    /// Timeout of a delivery
+    pub fn timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+#[cfg(feature = "webhook")]
+impl Notifier for SlackNotifier {
+    fn notify(&self, notification: &Notification) -> io::Result<()> {
+        let mut text = notification.summary();
+        if !notification.message.is_empty() {
+            text.push('\n');
+            text.push_str(&notification.message);
+        }
+        let payload = format!(
+            "{{\"text\":\"{}\"}}",
+            crate::json_events::escape_json(&text)
+        );
+        crate::webhook::post(&self.webhook_url, &payload, self.timeout)
+    }
+}
+
+/// Emails the notifications through an SMTP server, using the system `curl` CLI
+#[cfg(feature = "webhook")]
+#[derive(Clone)]
+pub struct SmtpNotifier {
+    server_url: String,
+    from: String,
+    to: Vec<String>,
+    credentials: Option<(String, String)>,
+    timeout: std::time::Duration,
+}
+
+#[cfg(feature = "webhook")]
+impl SmtpNotifier {
+    /// Send from the address to the recipients through the server, e.g. `smtp://mail.example.com:587` (STARTTLS is
+    /// used when offered) or `smtps://mail.example.com`
+    pub fn new<S: Into<String>>(
+        server_url: impl Into<String>,
+        from: impl Into<String>,
+        to: impl IntoIterator<Item = S>,
+    ) -> Self {
+        Self {
+            server_url: server_url.into(),
+            from: from.into(),
+            to: to.into_iter().map(Into::into).collect(),
+            credentials: None,
+            timeout: std::time::Duration::from_secs(30),
+        }
+    }
+
+    /// Authenticate to the server with the user name & the password
+    pub fn credentials(mut self, user: impl Into<String>, password: impl Into<String>) -> Self {
+        self.credentials = Some((user.into(), password.into()));
+        self
+    }
+
+    /// Timeout of a delivery
+    pub fn timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// the email with the headers, CRLF line endings as per RFC 5322
+    fn email(&self, notification: &Notification) -> String {
+        let to: Vec<_> = self.to.iter().map(|to| header_value(to)).collect();
+        let mut email = format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n",
+            header_value(&self.from),
+            to.join(", "),
+            header_value(&notification.summary())
+        );
+        email.push_str(&format!(
+            "Request: {}\r\nRun sequence: {}\r\nCommand: {}\r\nExit code: {:?}\r\nTermination: {:?}\r\n",
+            notification.request_id,
+            notification.run_sequence,
+            notification.command,
+            notification.exit_code,
+            notification.termination
+        ));
+        if !notification.message.is_empty() {
+            email.push_str(&format!("\r\n{}\r\n", notification.message));
+        }
+        email
+    }
+
+    /// curl option of the credentials, passed in a config file so the password is not in the command line
+    fn curl_config(user: &str, password: &str) -> String {
+        format!(
+            "user = \"{}\"\n",
+            format!("{}:{}", user, password)
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\r', "\\r")
+                .replace('\n', "\\n")
+        )
+    }
+}
+
+/// value of an email header on a single line, so it can't add headers
+#[cfg(feature = "webhook")]
+fn header_value(value: &str) -> String {
+    value.replace(['\r', '\n'], " ")
+}
+
+/// write the curl config to a new file only readable by the user, as the stdin is taken by the email
+#[cfg(feature = "webhook")]
+fn write_curl_config(config: &str) -> io::Result<std::path::PathBuf> {
+    use std::io::Write;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static NEXT_CONFIG: AtomicU64 = AtomicU64::new(0);
+    let path = std::env::temp_dir().join(format!(
+        "pes_curl_{}_{}.conf",
+        std::process::id(),
+        NEXT_CONFIG.fetch_add(1, Ordering::Relaxed)
+    ));
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(&path)?;
+    if let Err(error) = file.write_all(config.as_bytes()) {
+        _ = std::fs::remove_file(&path);
+        return Err(error);
+    }
+    Ok(path)
+}
+
+#[cfg(feature = "webhook")]
+impl Notifier for SmtpNotifier {
+    fn notify(&self, notification: &Notification) -> io::Result<()> {
+        let mut args = vec![
+            String::from("--silent"),
+            String::from("--show-error"),
+            String::from("--ssl"),
+            String::from("--max-time"),
+            format!("{:.3}", self.timeout.as_secs_f64()),
+            String::from("--url"),
+            self.server_url.clone(),
+            String::from("--mail-from"),
+            self.from.clone(),
+        ];
+        for recipient in &self.to {
+            args.push(String::from("--mail-rcpt"));
+            args.push(recipient.clone());
+        }
+        let config = match self.credentials.as_ref() {
+            Some((user, password)) => {
+                let config = write_curl_config(&Self::curl_config(user, password))?;
+                args.push(String::from("--config"));
+                args.push(config.to_string_lossy().into_owned());
+                Some(config)
+            }
+            None => None,
+        };
+        args.push(String::from("--upload-file"));
+        args.push(String::from("-"));
+        let output = duct::cmd("curl", args)
+            .stdin_bytes(self.email(notification))
+            .stdout_null()
+            .stderr_capture()
+            .unchecked()
+            .run();
+        if let Some(config) = config {
+            _ = std::fs::remove_file(config);
+        }
+        let output = output?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "Email delivery failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Backoff, Notification, NotificationKind, Notifier, ProcessRequest, Supervisor,
+        SupervisorPolicy,
+    };
+    use std::io;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    /// notifier keeping the notifications
+    #[derive(Default)]
+    struct MemoryNotifier(Mutex<Vec<Notification>>);
+
+    impl Notifier for MemoryNotifier {
+        fn notify(&self, notification: &Notification) -> io::Result<()> {
+            self.0.lock().unwrap().push(notification.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    pub fn test_supervisor_notifications() {
+        let notifier = Arc::new(MemoryNotifier::default());
+        let supervisor = Supervisor::start(
+            ProcessRequest {
+                request_id: 287,
+                use_shell: true,
+                cmd_line: vec![vec![String::from("exit 3")]],
+                notifier: Some(notifier.clone()),
+                ..Default::default()
+            },
+            SupervisorPolicy {
+                backoff: Backoff::Fixed(Duration::from_millis(10)),
+                max_restarts: 1,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        supervisor.join().unwrap();
+        // delivered in the background
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while notifier.0.lock().unwrap().len() < 3 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let notifications = notifier.0.lock().unwrap();
+        let kinds: Vec<NotificationKind> = notifications.iter().map(|n| n.kind).collect();
+        assert_eq!(kinds.len(), 3);
+        assert_eq!(
+            kinds
+                .iter()
+                .filter(|kind| **kind == NotificationKind::Failed)
+                .count(),
+            2
+        );
+        let crash_loop = notifications
+            .iter()
+            .find(|notification| notification.kind == NotificationKind::CrashLoop)
+            .unwrap();
+        assert_eq!(crash_loop.exit_code, Some(3));
+        assert_eq!(
+            crash_loop.summary(),
+            "Request 287 (`exit 3`) is crash looping, exit code Some(3)"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "webhook")]
+    pub fn test_smtp_email() {
+        let notifier = crate::SmtpNotifier::new(
+            "smtp://mail.example.com",
+            "ops@example.com\r\nBcc: spam@example.com",
+            ["dev@example.com\nX-Injected: 1"],
+        );
+        let notification = Notification {
+            kind: NotificationKind::Failed,
+            request_id: 512,
+            run_sequence: 1,
+            command: String::from("echo\r\nBcc: spam@example.com"),
+            exit_code: Some(1),
+            termination: None,
+            message: String::new(),
+        };
+        let email = notifier.email(&notification);
+        let (headers, _) = email.split_once("\r\n\r\n").unwrap();
+        assert_eq!(
+            headers.split("\r\n").collect::<Vec<_>>(),
+            [
+                "From: ops@example.com  Bcc: spam@example.com",
+                "To: dev@example.com X-Injected: 1",
+                "Subject: Request 512 (`echo  Bcc: spam@example.com`) failed, exit code Some(1)",
+                "Content-Type: text/plain; charset=utf-8"
+            ]
+        );
+        assert_eq!(
+            crate::SmtpNotifier::curl_config("ops", "p\"a\\ss"),
+            "user = \"ops:p\\\"a\\\\ss\"\n"
+        );
+    }
+}
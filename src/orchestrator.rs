@@ -0,0 +1,413 @@
+use crate::retry::start_process_with_retry;
+use crate::{
+    check_and_trigger_callback, ProcessCallback, ProcessData, ProcessEvent, ProcessRequest,
+    ProcessResult,
+};
+use std::collections::HashMap;
+use std::io;
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// What to do with the remaining tasks once a task fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FailurePolicy {
+    /// Skip only the tasks depending on the failed task, the other tasks keep running
+    #[default]
+    SkipDependents,
+    /// Don't start any new task, the running ones complete and the rest are skipped
+    StopAll,
+}
+
+/// Final status of a task of the [`Orchestrator`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskStatus {
+    /// Process execution was successful, see [`crate::ProcessResult::success`]
+    Succeeded,
+    /// Process failed to start or failed
+    Failed,
+    /// Task was not run because of its dependencies or the failure policy
+    Skipped,
+}
+
+/// Outcome of a single task of the [`Orchestrator`]
+#[derive(Debug)]
+pub struct TaskOutcome {
+    /// Id of the request
+    pub request_id: u32,
+    /// Final status of the task
+    pub status: TaskStatus,
+    /// Result of the process, None if the task was skipped
+    pub result: Option<ProcessResult>,
+    /// Time taken to run the task
+    pub duration: Duration,
+}
+
+/// Outcome of all the tasks in the order they were added
+#[derive(Debug)]
+pub struct OrchestrationSummary {
+    /// Outcome of each task
+    pub tasks: Vec<TaskOutcome>,
+}
+
+impl OrchestrationSummary {
+    /// All the tasks succeeded
+    pub fn all_succeeded(&self) -> bool {
+        self.tasks
+            .iter()
+            .all(|task| task.status == TaskStatus::Succeeded)
+    }
+
+    /// Outcome of the task with the request id
+    pub fn task(&self, request_id: u32) -> Option<&TaskOutcome> {
+        self.tasks.iter().find(|task| task.request_id == request_id)
+    }
+}
+
+/// A request along with its dependencies
+struct Task {
+    request: Option<ProcessRequest>,
+    request_id: u32,
+    /// all of these should succeed
+    all_of: Vec<u32>,
+    /// at least one of these should succeed
+    any_of: Vec<u32>,
+    /// all of these should fail
+    failed_of: Vec<u32>,
+    status: Option<TaskStatus>,
+    running: bool,
+}
+
+impl Task {
+    /// ids of all the tasks this task depends on
+    fn dependencies(&self) -> impl Iterator<Item = &u32> {
+        self.all_of
+            .iter()
+            .chain(&self.any_of)
+            .chain(&self.failed_of)
+    }
+}
+
+/// Builder to declare the dependencies of a task added to the [`Orchestrator`]
+pub struct TaskBuilder<'a> {
+    task: &'a mut Task,
+}
+
+impl TaskBuilder<'_> {
+    /// Run the task only after the task with the request id succeeds
+    pub fn depends_on(self, request_id: u32) -> Self {
+        self.task.all_of.push(request_id);
+        self
+    }
+
+    /// Run the task after any one of the tasks with the request ids succeeds (i.e. A || B)
+    pub fn depends_on_any(self, request_ids: &[u32]) -> Self {
+        self.task.any_of.extend_from_slice(request_ids);
+        self
+    }
+
+    /// Run the task only after the task with the request id fails (like `a || b` in a shell), it's skipped if that task succeeds or
+    /// is skipped. A failure handled this way doesn't stop the other tasks with [`FailurePolicy::StopAll`]
+    pub fn on_failure_of(self, request_id: u32) -> Self {
+        self.task.failed_of.push(request_id);
+        self
+    }
+}
+
+/// Runs the requests as a task graph, where a request starts only once its dependencies succeed.
+/// Independent tasks run in parallel.
+#[derive(Default)]
+pub struct Orchestrator {
+    /// What to do with the remaining tasks once a task fails
+    pub failure_policy: FailurePolicy,
+    /// Max tasks to run in parallel, for no limit use None
+    pub max_parallel: Option<usize>,
+    /// Central callback for the events of all the tasks, it replaces the callbacks of the requests. To use the callback of each request use None
+    pub callback: Option<Arc<ProcessCallback>>,
+    tasks: Vec<Task>,
+}
+
+/// readiness of a pending task
+enum Readiness {
+    Ready,
+    Waiting,
+    Skip(String),
+}
+
+impl Orchestrator {
+    /// Create an empty orchestrator
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the request as a task, the request id should be unique within the orchestrator
+    pub fn add(&mut self, process_request: ProcessRequest) -> TaskBuilder<'_> {
+        self.tasks.push(Task {
+            request_id: process_request.request_id,
+            request: Some(process_request),
+            all_of: vec![],
+            any_of: vec![],
+            failed_of: vec![],
+            status: None,
+            running: false,
+        });
+        TaskBuilder {
+            task: self.tasks.last_mut().unwrap(),
+        }
+    }
+
+    /// Run all the tasks as per their dependencies and wait for the completion.
+    /// Returns an error without running anything if the graph has duplicate or unknown ids or a cycle
+    pub fn run(mut self) -> io::Result<OrchestrationSummary> {
+        self.validate()?;
+        let mut outcomes: HashMap<u32, (Option<ProcessResult>, Duration)> = HashMap::new();
+        let mut stopping = false;
+        let (sender, receiver) = mpsc::channel();
+        thread::scope(|scope| loop {
+            let mut running = self.tasks.iter().filter(|task| task.running).count();
+            // a skipped task can make its dependents skippable, so repeat till nothing changes
+            let mut changed = true;
+            while changed {
+                changed = false;
+                for index in 0..self.tasks.len() {
+                    if self.tasks[index].status.is_some() || self.tasks[index].running {
+                        continue;
+                    }
+                    let readiness = match self.readiness(&self.tasks[index]) {
+                        Readiness::Ready if stopping => {
+                            Readiness::Skip(String::from("A task failed, stopping all"))
+                        }
+                        readiness => readiness,
+                    };
+                    match readiness {
+                        Readiness::Skip(reason) => {
+                            let request = self.take_request(index);
+                            let mut process_data = ProcessData::new();
+                            process_data.request = Some(Arc::clone(&request));
+                            process_data.line.push_str(&reason);
+                            check_and_trigger_callback(
+                                &request,
+                                &ProcessEvent::Skipped,
+                                &process_data,
+                            );
+                            self.tasks[index].status = Some(TaskStatus::Skipped);
+                            changed = true;
+                        }
+                        Readiness::Ready if self.max_parallel.is_none_or(|max| running < max) => {
+                            let request = self.take_request(index);
+                            let sender = sender.clone();
+                            self.tasks[index].running = true;
+                            running += 1;
+                            changed = true;
+                            scope.spawn(move || {
+                                let started = Instant::now();
+                                let result = start_process_with_retry(request, None);
+                                _ = sender.send((index, result, started.elapsed()));
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            if running == 0 {
+                // the graph has no cycle, so nothing running means every task is done or skipped
+                break;
+            }
+            let (index, result, duration): (usize, ProcessResult, Duration) =
+                receiver.recv().unwrap();
+            let request_id = self.tasks[index].request_id;
+            let handled = self
+                .tasks
+                .iter()
+                .any(|task| task.failed_of.contains(&request_id));
+            let task = &mut self.tasks[index];
+            task.running = false;
+            task.status = Some(if result.success.as_ref().is_ok_and(|success| *success) {
+                TaskStatus::Succeeded
+            } else {
+                stopping |= self.failure_policy == FailurePolicy::StopAll && !handled;
+                TaskStatus::Failed
+            });
+            outcomes.insert(task.request_id, (Some(result), duration));
+        });
+        Ok(OrchestrationSummary {
+            tasks: self
+                .tasks
+                .iter()
+                .map(|task| {
+                    let (result, duration) = outcomes.remove(&task.request_id).unwrap_or_default();
+                    TaskOutcome {
+                        request_id: task.request_id,
+                        status: task.status.unwrap_or(TaskStatus::Skipped),
+                        result,
+                        duration,
+                    }
+                })
+                .collect(),
+        })
+    }
+
+    /// move the request out of the task, applying the central callback
+    fn take_request(&mut self, index: usize) -> Arc<ProcessRequest> {
+        let mut request = self.tasks[index].request.take().unwrap();
+        if self.callback.is_some() {
+            request.callback = self.callback.clone();
+        }
+        Arc::new(request)
+    }
+
+    fn status_of(&self, request_id: u32) -> Option<TaskStatus> {
+        self.tasks
+            .iter()
+            .find(|task| task.request_id == request_id)
+            .and_then(|task| task.status)
+    }
+
+    fn readiness(&self, task: &Task) -> Readiness {
+        for request_id in &task.all_of {
+            match self.status_of(*request_id) {
+                Some(TaskStatus::Succeeded) => {}
+                Some(status) => {
+                    return Readiness::Skip(format!("Dependency {} {:?}", request_id, status))
+                }
+                None => return Readiness::Waiting,
+            }
+        }
+        for request_id in &task.failed_of {
+            match self.status_of(*request_id) {
+                Some(TaskStatus::Failed) => {}
+                Some(status) => {
+                    return Readiness::Skip(format!("Dependency {} {:?}", request_id, status))
+                }
+                None => return Readiness::Waiting,
+            }
+        }
+        if task.any_of.is_empty() {
+            return Readiness::Ready;
+        }
+        let statuses: Vec<Option<TaskStatus>> = task
+            .any_of
+            .iter()
+            .map(|request_id| self.status_of(*request_id))
+            .collect();
+        if statuses.contains(&Some(TaskStatus::Succeeded)) {
+            Readiness::Ready
+        } else if statuses.contains(&None) {
+            Readiness::Waiting
+        } else {
+            Readiness::Skip(format!(
+                "None of the dependencies {:?} succeeded",
+                task.any_of
+            ))
+        }
+    }
+
+    /// check for duplicate & unknown ids and cycles
+    fn validate(&self) -> io::Result<()> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+        let mut indexes = HashMap::new();
+        for (index, task) in self.tasks.iter().enumerate() {
+            if indexes.insert(task.request_id, index).is_some() {
+                return Err(invalid(format!("Duplicate request id {}", task.request_id)));
+            }
+        }
+        for task in &self.tasks {
+            for dependency in task.dependencies() {
+                if !indexes.contains_key(dependency) {
+                    return Err(invalid(format!(
+                        "Request {} depends on unknown request {}",
+                        task.request_id, dependency
+                    )));
+                }
+            }
+        }
+        // Kahn's algorithm, whatever remains unresolved is part of a cycle
+        let mut pending: Vec<usize> = self
+            .tasks
+            .iter()
+            .map(|task| task.dependencies().count())
+            .collect();
+        let mut resolved: Vec<usize> = (0..self.tasks.len())
+            .filter(|index| pending[*index] == 0)
+            .collect();
+        let mut next = 0;
+        while next < resolved.len() {
+            let request_id = self.tasks[resolved[next]].request_id;
+            next += 1;
+            for (index, task) in self.tasks.iter().enumerate() {
+                let edges = task
+                    .dependencies()
+                    .filter(|dependency| **dependency == request_id)
+                    .count();
+                if edges > 0 {
+                    pending[index] -= edges;
+                    if pending[index] == 0 {
+                        resolved.push(index);
+                    }
+                }
+            }
+        }
+        if resolved.len() != self.tasks.len() {
+            return Err(invalid(String::from("Dependencies have a cycle")));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FailurePolicy, Orchestrator, ProcessRequest, TaskStatus};
+
+    fn shell_request(request_id: u32, command: &str) -> ProcessRequest {
+        ProcessRequest {
+            request_id,
+            use_shell: true,
+            cmd_line: vec![vec![String::from(command)]],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    pub fn test_orchestrator_dependencies() {
+        let mut orchestrator = Orchestrator::new();
+        orchestrator.add(shell_request(1, "exit 0"));
+        orchestrator.add(shell_request(2, "exit 1")).depends_on(1);
+        orchestrator.add(shell_request(3, "exit 0")).depends_on(2);
+        orchestrator
+            .add(shell_request(4, "exit 0"))
+            .depends_on_any(&[2, 1]);
+        let summary = orchestrator.run().unwrap();
+        assert_eq!(summary.task(1).unwrap().status, TaskStatus::Succeeded);
+        assert_eq!(summary.task(2).unwrap().status, TaskStatus::Failed);
+        assert_eq!(summary.task(3).unwrap().status, TaskStatus::Skipped);
+        assert_eq!(summary.task(4).unwrap().status, TaskStatus::Succeeded);
+
+        let mut cyclic = Orchestrator::new();
+        cyclic.add(shell_request(1, "exit 0")).depends_on(2);
+        cyclic.add(shell_request(2, "exit 0")).depends_on(1);
+        assert!(cyclic.run().is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    pub fn test_orchestrator_chaining() {
+        // (1 && 2) || 3, then 4 only if 3 failed
+        let mut orchestrator = Orchestrator::new();
+        orchestrator.failure_policy = FailurePolicy::StopAll;
+        orchestrator.add(shell_request(1, "exit 0"));
+        orchestrator.add(shell_request(2, "exit 2")).depends_on(1);
+        orchestrator
+            .add(shell_request(3, "exit 0"))
+            .on_failure_of(2);
+        orchestrator
+            .add(shell_request(4, "exit 0"))
+            .on_failure_of(3);
+        orchestrator.add(shell_request(5, "exit 0")).depends_on(3);
+        let summary = orchestrator.run().unwrap();
+        assert_eq!(summary.task(2).unwrap().status, TaskStatus::Failed);
+        assert_eq!(summary.task(3).unwrap().status, TaskStatus::Succeeded);
+        assert_eq!(summary.task(4).unwrap().status, TaskStatus::Skipped);
+        assert_eq!(summary.task(5).unwrap().status, TaskStatus::Succeeded);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::ProcessRequest;
+use opentelemetry::global::{self, BoxedSpan};
+use opentelemetry::trace::{Span, Status, TraceContextExt, Tracer};
+use opentelemetry::{Context, KeyValue};
+
+/// OpenTelemetry spans of a single execution, a `process` span with a `process.stage` child span per pipeline stage.
+/// Spans are created using the global tracer provider
+pub(crate) struct ExecutionSpans {
+    context: Context,
+    stages: Vec<BoxedSpan>,
+    lines: i64,
+    bytes_read: i64,
+}
+
+impl ExecutionSpans {
+    /// start the spans of the execution, as children of the current OTel context
+    pub(crate) fn start(request: &ProcessRequest) -> Self {
+        let tracer = global::tracer("process-events-streaming");
+        let pipeline_stages = request.pipeline_stages();
+        let command_line = pipeline_stages
+            .iter()
+            .map(|stage| stage.argv.join(" "))
+            .collect::<Vec<String>>()
+            .join(" | ");
+        let mut span = tracer.start("process");
+        span.set_attributes([
+            KeyValue::new("process.request_id", request.request_id as i64),
+            KeyValue::new("process.command_line", command_line),
+            KeyValue::new("process.use_shell", request.use_shell),
+            KeyValue::new("process.run_sequence", request.run_sequence as i64),
+        ]);
+        let context = Context::current_with_span(span);
+        let stages = pipeline_stages
+            .iter()
+            .enumerate()
+            .map(|(index, stage)| {
+                let mut span = tracer.start_with_context("process.stage", &context);
+                span.set_attributes([
+                    KeyValue::new("process.stage", index as i64),
+                    KeyValue::new("process.command_line", stage.argv.join(" ")),
+                ]);
+                span
+            })
+            .collect();
+        Self {
+            context,
+            stages,
+            lines: 0,
+            bytes_read: 0,
+        }
+    }
+
+    /// record the pids of the started stages, in the pipeline order
+    pub(crate) fn started(&mut self, pids: &[u32]) {
+        for (span, pid) in self.stages.iter_mut().zip(pids) {
+            span.set_attribute(KeyValue::new("process.pid", *pid as i64));
+        }
+    }
+
+    /// count an output line read from the last stage
+    pub(crate) fn output(&mut self, bytes: usize) {
+        self.lines += 1;
+        self.bytes_read += bytes as i64;
+    }
+
+    /// end all the spans with the exit status, `error` describes a failure to start
+    pub(crate) fn end(mut self, exit_code: Option<i32>, error: Option<String>) {
+        let status = match (&error, exit_code) {
+            (Some(error), _) => Status::error(error.clone()),
+            (None, Some(0)) => Status::Ok,
+            (None, Some(exit_code)) => Status::error(format!("Exited with code {}", exit_code)),
+            (None, None) => Status::error("Killed or terminated by a signal"),
+        };
+        for span in self.stages.iter_mut() {
+            span.set_status(status.clone());
+            span.end();
+        }
+        let span = self.context.span();
+        if let Some(exit_code) = exit_code {
+            span.set_attribute(KeyValue::new("process.exit_code", exit_code as i64));
+        }
+        span.set_attributes([
+            KeyValue::new("process.output.lines", self.lines),
+            KeyValue::new("process.output.bytes", self.bytes_read),
+        ]);
+        span.set_status(status);
+        span.end();
+    }
+}
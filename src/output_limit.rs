@@ -0,0 +1,139 @@
+/// What to do once the output exceeds the [`crate::ProcessRequest::max_output_bytes`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OutputLimitAction {
+    /// Stop delivering the output lines but keep reading the output till the process exits
+    #[default]
+    Drain,
+    /// Deliver the part of the line which fits in the limit, then drain the rest of the output
+    Truncate,
+    /// Kill the process
+    Kill,
+}
+
+/// outcome of accounting an output line in the budget
+pub(crate) enum LineBudget {
+    /// line fits in the limit
+    Within,
+    /// line exceeded the limit, it's truncated to fit as per the [`OutputLimitAction::Truncate`]
+    Exceeded,
+    /// limit was already exceeded by a previous line
+    Over,
+}
+
+/// Remaining output bytes of an execution as per the output limit of the request
+pub(crate) struct OutputBudget {
+    max_bytes: u64,
+    remaining: u64,
+    action: OutputLimitAction,
+    exceeded: bool,
+}
+
+impl OutputBudget {
+    pub(crate) fn new(max_bytes: u64, action: OutputLimitAction) -> Self {
+        Self {
+            max_bytes,
+            remaining: max_bytes,
+            action,
+            exceeded: false,
+        }
+    }
+
+    /// account the output line including its line break
+    pub(crate) fn account(&mut self, line: &mut String) -> LineBudget {
+        if self.exceeded {
+            return LineBudget::Over;
+        }
+        let length = line.len() as u64;
+        if length <= self.remaining {
+            self.remaining -= length;
+            return LineBudget::Within;
+        }
+        self.exceeded = true;
+        if self.action == OutputLimitAction::Truncate {
+            let mut end = self.remaining as usize;
+            while !line.is_char_boundary(end) {
+                end -= 1;
+            }
+            line.truncate(end);
+        }
+        self.remaining = 0;
+        LineBudget::Exceeded
+    }
+
+    /// output exceeded the limit
+    pub(crate) fn exceeded(&self) -> bool {
+        self.exceeded
+    }
+
+    /// details of the exceeded limit for the event
+    pub(crate) fn reason(&self) -> String {
+        format!("Output exceeded {} bytes", self.max_bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{OutputLimitAction, ProcessData, ProcessEvent, ProcessRequest, ProcessResult};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    fn run(
+        request_id: u32,
+        command: &str,
+        action: OutputLimitAction,
+    ) -> (ProcessResult, Vec<String>) {
+        let lines = Arc::new(Mutex::new(vec![]));
+        let captured = Arc::clone(&lines);
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id,
+            use_shell: true,
+            cmd_line: vec![vec![String::from(command)]],
+            max_output_bytes: Some(10),
+            output_limit_action: action,
+            callback: Some(Arc::new(move |event: &ProcessEvent, data: &ProcessData| {
+                match event {
+                    ProcessEvent::IOData => captured.lock().unwrap().push(data.line_to_owned()),
+                    ProcessEvent::OutputLimitExceeded => {
+                        captured.lock().unwrap().push(String::from("limit"))
+                    }
+                    _ => {}
+                }
+                ProcessResult::new()
+            })),
+            ..Default::default()
+        });
+        let lines = lines.lock().unwrap().clone();
+        (result, lines)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_output_limit() {
+        let command = "echo 1234; echo 5678; echo 9abc; echo def";
+        let (result, lines) = run(411, command, OutputLimitAction::Drain);
+        assert_eq!(lines, ["1234", "5678", "limit"]);
+        assert!(result.output_limit_exceeded);
+        assert_eq!(result.exit_code, Some(0));
+
+        let (result, lines) = run(412, command, OutputLimitAction::Truncate);
+        assert_eq!(lines, ["1234", "5678", "limit"]);
+        assert!(result.output_limit_exceeded);
+        let (_, lines) = run(413, "echo 123456; echo 789abc", OutputLimitAction::Truncate);
+        assert_eq!(lines, ["123456", "limit", "789"]);
+
+        let started = Instant::now();
+        let (result, lines) = run(
+            414,
+            "echo 123456789012; exec sleep 10",
+            OutputLimitAction::Kill,
+        );
+        assert_eq!(lines, ["limit"]);
+        assert!(result.output_limit_exceeded);
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let (result, lines) = run(415, "echo 1234", OutputLimitAction::Kill);
+        assert_eq!(lines, ["1234"]);
+        assert!(!result.output_limit_exceeded);
+    }
+}
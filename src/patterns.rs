@@ -0,0 +1,217 @@
+use crate::{ProcessData, ProcessEvent};
+use regex::{Regex, RegexSet};
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// compile the patterns of the request, None if there are none
+pub(crate) fn compile_patterns(patterns: &[String]) -> io::Result<Option<RegexSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    RegexSet::new(patterns)
+        .map(Some)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error.to_string()))
+}
+
+#[derive(Default)]
+struct WaiterState {
+    /// lines not yet consumed by a wait
+    lines: VecDeque<String>,
+    /// the process is not running
+    finished: bool,
+}
+
+/// Expect-style waiting for the output of a process, set it as [`crate::ProcessRequest::pattern_waiter`] & wait from
+/// another thread (e.g. with the non-blocking mode), for example till "Server started" before running the next step.
+/// Each wait consumes the output lines till the matching line, so the successive waits match the output in order
+#[derive(Default)]
+pub struct PatternWaiter {
+    state: Mutex<WaiterState>,
+    changed: Condvar,
+}
+
+impl PatternWaiter {
+    /// Create a waiter with no output
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// feed the event of the process
+    pub(crate) fn observe(&self, event: &ProcessEvent, data: &ProcessData) {
+        let mut state = self.state.lock().unwrap();
+        match event {
+            ProcessEvent::IOData => state.lines.push_back(data.line_to_owned()),
+            ProcessEvent::Started => state.finished = false,
+            ProcessEvent::Exited | ProcessEvent::StartError | ProcessEvent::KillError => {
+                state.finished = true
+            }
+            _ => return,
+        }
+        self.changed.notify_all();
+    }
+
+    /// Wait for an output line matching the regular expression & return it. Fails with [`io::ErrorKind::TimedOut`]
+    /// after the timeout, with [`io::ErrorKind::UnexpectedEof`] once the process is done without a match
+    pub fn wait_for_pattern(&self, pattern: &str, timeout: Duration) -> io::Result<String> {
+        let regex = Regex::new(pattern)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error.to_string()))?;
+        let deadline = Instant::now() + timeout;
+        let mut state = self.state.lock().unwrap();
+        loop {
+            while let Some(line) = state.lines.pop_front() {
+                if regex.is_match(&line) {
+                    return Ok(line);
+                }
+            }
+            if state.finished {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("Process is done without an output matching {}", pattern),
+                ));
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("No output matching {} within {:?}", pattern, timeout),
+                ));
+            }
+            state = self.changed.wait_timeout(state, remaining).unwrap().0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{PatternWaiter, ProcessData, ProcessEvent, ProcessRequest, ProcessResult};
+    use std::io;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_patterns() {
+        let matched = Arc::new(Mutex::new(vec![]));
+        let events = Arc::clone(&matched);
+        let waiter = Arc::new(PatternWaiter::new());
+        let process_result = ProcessRequest::start(ProcessRequest {
+            request_id: 281,
+            use_shell: true,
+            cmd_line: vec![vec![String::from(
+                "echo booting; sleep 0.2; echo 'Server started on 8080'; echo ERROR: disk",
+            )]],
+            non_blocking_mode: true,
+            patterns: vec![String::from("^ERROR"), String::from("started on \\d+")],
+            pattern_waiter: Some(Arc::clone(&waiter)),
+            callback: Some(Arc::new(move |event: &ProcessEvent, data: &ProcessData| {
+                if *event == ProcessEvent::PatternMatched {
+                    events.lock().unwrap().push(data.pattern_index.unwrap());
+                }
+                ProcessResult::new()
+            })),
+            ..Default::default()
+        });
+        assert_eq!(
+            waiter
+                .wait_for_pattern("Server started", Duration::from_secs(5))
+                .unwrap(),
+            "Server started on 8080"
+        );
+        // booting was consumed by the previous wait
+        let error = waiter
+            .wait_for_pattern("booting", Duration::from_secs(5))
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        process_result.join_handle.unwrap().unwrap().join().unwrap();
+        assert_eq!(*matched.lock().unwrap(), [1, 0]);
+
+        let waiter = PatternWaiter::new();
+        let error = waiter
+            .wait_for_pattern("never", Duration::from_millis(50))
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_fail_on_patterns() {
+        let failed = Arc::new(Mutex::new(vec![]));
+        let events = Arc::clone(&failed);
+        let lines = Arc::new(Mutex::new(vec![]));
+        let output = Arc::clone(&lines);
+        let started = Instant::now();
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 326,
+            use_shell: true,
+            cmd_line: vec![vec![String::from(
+                "echo ok; echo 'panic: boom'; echo 'panic: again'; sleep 5; echo after",
+            )]],
+            fail_on_patterns: vec![String::from("OutOfMemory"), String::from("^panic:")],
+            kill_on_fail_pattern: true,
+            callback: Some(Arc::new(move |event: &ProcessEvent, data: &ProcessData| {
+                match event {
+                    ProcessEvent::FailPatternMatched => events
+                        .lock()
+                        .unwrap()
+                        .push((data.pattern_index.unwrap(), data.line_to_owned())),
+                    ProcessEvent::IOData => output.lock().unwrap().push(data.line_to_owned()),
+                    _ => {}
+                }
+                ProcessResult::new()
+            })),
+            ..Default::default()
+        });
+        assert!(started.elapsed() < Duration::from_secs(4));
+        assert!(!result.success.unwrap());
+        assert!(result.expectation_failed);
+        assert_eq!(result.fail_line.as_deref(), Some("panic: boom"));
+        assert_eq!(*failed.lock().unwrap(), [(1, String::from("panic: boom"))]);
+        assert!(!lines.lock().unwrap().contains(&String::from("after")));
+        assert!(
+            !ProcessRequest::start(ProcessRequest {
+                request_id: 505,
+                cmd_line: vec![vec![String::from("true")]],
+                fail_on_patterns: vec![String::from("(")],
+                ..Default::default()
+            })
+            .spawned
+        );
+    }
+    #[cfg(unix)]
+    #[test]
+    pub fn test_succeed_on_patterns() {
+        let matched = Arc::new(Mutex::new(vec![]));
+        let events = Arc::clone(&matched);
+        let started = Instant::now();
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 327,
+            use_shell: true,
+            cmd_line: vec![vec![String::from(
+                "echo compiling; sleep 0.2; echo 'Compilation finished'; sleep 5; exit 3",
+            )]],
+            succeed_on_patterns: vec![String::from("^Compilation finished")],
+            callback: Some(Arc::new(move |event: &ProcessEvent, data: &ProcessData| {
+                if *event == ProcessEvent::SuccessPatternMatched {
+                    events
+                        .lock()
+                        .unwrap()
+                        .push((data.pattern_index.unwrap(), data.line_to_owned()));
+                }
+                ProcessResult::new()
+            })),
+            ..Default::default()
+        });
+        assert!(started.elapsed() < Duration::from_secs(4));
+        assert!(result.success.unwrap());
+        assert_eq!(result.success_line.as_deref(), Some("Compilation finished"));
+        let time_to_success = result.time_to_success.unwrap();
+        assert!(time_to_success >= Duration::from_millis(200));
+        assert!(time_to_success < Duration::from_secs(4));
+        assert_eq!(
+            *matched.lock().unwrap(),
+            [(0, String::from("Compilation finished"))]
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use std::io;
+
+/// freeze (SIGSTOP) or continue (SIGCONT) all the processes
+#[cfg(unix)]
+pub(crate) fn set_paused(pids: &[u32], paused: bool) -> io::Result<()> {
+    let signal = if paused { libc::SIGSTOP } else { libc::SIGCONT };
+    for pid in pids {
+        if unsafe { libc::kill(*pid as libc::pid_t, signal) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// suspend or resume all the threads of the processes
+#[cfg(windows)]
+pub(crate) fn set_paused(pids: &[u32], paused: bool) -> io::Result<()> {
+    for pid in pids {
+        unsafe { set_threads_suspended(*pid, paused) }?;
+    }
+    Ok(())
+}
+
+/// Pausing is not available on this platform
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn set_paused(_pids: &[u32], _paused: bool) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Pausing is supported on Unix & Windows only",
+    ))
+}
+
+/// suspend or resume all the threads of the process
+#[cfg(windows)]
+pub(crate) unsafe fn set_threads_suspended(pid: u32, suspended: bool) -> io::Result<()> {
+    use windows_sys::Win32::Foundation::{CloseHandle, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD, THREADENTRY32,
+    };
+    use windows_sys::Win32::System::Threading::{
+        OpenThread, ResumeThread, SuspendThread, THREAD_SUSPEND_RESUME,
+    };
+
+    let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0);
+    if snapshot == INVALID_HANDLE_VALUE {
+        return Err(io::Error::last_os_error());
+    }
+    let mut entry: THREADENTRY32 = std::mem::zeroed();
+    entry.dwSize = std::mem::size_of::<THREADENTRY32>() as u32;
+    let mut result = Ok(());
+    let mut found = Thread32First(snapshot, &mut entry) != 0;
+    while found && result.is_ok() {
+        if entry.th32OwnerProcessID == pid {
+            let thread = OpenThread(THREAD_SUSPEND_RESUME, 0, entry.th32ThreadID);
+            if thread.is_null() {
+                result = Err(io::Error::last_os_error());
+            } else {
+                let previous_count = if suspended {
+                    SuspendThread(thread)
+                } else {
+                    ResumeThread(thread)
+                };
+                if previous_count == u32::MAX {
+                    result = Err(io::Error::last_os_error());
+                }
+                CloseHandle(thread);
+            }
+        }
+        found = Thread32Next(snapshot, &mut entry) != 0;
+    }
+    CloseHandle(snapshot);
+    result
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use crate::{ProcessData, ProcessEvent, ProcessRequest, ProcessResult};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// state letter of the process in /proc e.g. `T` when stopped
+    fn process_state(pid: u32) -> Option<char> {
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        stat.rsplit_once(") ")?.1.chars().next()
+    }
+
+    #[test]
+    pub fn test_pause_resume() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let recorded = Arc::clone(&events);
+        let states = Arc::new(Mutex::new(vec![]));
+        let observed = Arc::clone(&states);
+        let callback = move |event: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            match event {
+                ProcessEvent::Paused | ProcessEvent::Resumed => {
+                    recorded.lock().unwrap().push(*event)
+                }
+                ProcessEvent::IOData if data.line_str() == "ready" => {
+                    let pid = data.child_pids()[0];
+                    data.pause().unwrap();
+                    std::thread::sleep(Duration::from_millis(100));
+                    observed.lock().unwrap().push(process_state(pid));
+                    data.resume().unwrap();
+                    std::thread::sleep(Duration::from_millis(100));
+                    observed.lock().unwrap().push(process_state(pid));
+                }
+                _ => {}
+            }
+            ProcessResult::new()
+        };
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 329,
+            use_shell: true,
+            cmd_line: vec![vec![String::from("echo ready; sleep 0.5; echo done")]],
+            callback: Some(Arc::new(callback)),
+            ..Default::default()
+        });
+        assert!(result.success.unwrap());
+        assert_eq!(
+            *events.lock().unwrap(),
+            [ProcessEvent::Paused, ProcessEvent::Resumed]
+        );
+        let states = states.lock().unwrap();
+        assert_eq!(states[0], Some('T'));
+        assert_ne!(states[1], Some('T'));
+    }
+}
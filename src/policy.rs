@@ -0,0 +1,217 @@
+use crate::{check_and_trigger_callback, ProcessData, ProcessEvent, ProcessRequest};
+use regex::Regex;
+use std::ffi::OsString;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Policy every request is checked against before it's spawned
+static GLOBAL_POLICY: Mutex<Option<Arc<CommandPolicy>>> = Mutex::new(None);
+
+/// Commands allowed to run, for the services running the requests defined by their users. A command is checked as
+/// it's executed i.e. a shell stage as the shell with its script (`sh -c <script>`) & the remote backends as their
+/// pipeline stages. It's rejected if it's denied, or if there is an allowlist & it's not on it. The executables match
+/// by their full path (with a `/`) or by their file name, the patterns are regular expressions matched against the
+/// command line (the arguments joined with spaces). Set it globally, the requests can't opt out of it
+#[derive(Debug, Clone, Default)]
+pub struct CommandPolicy {
+    allowed_executables: Vec<String>,
+    allowed_patterns: Vec<Regex>,
+    denied_executables: Vec<String>,
+    denied_patterns: Vec<Regex>,
+}
+
+impl CommandPolicy {
+    /// Policy allowing every command
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow the executable, either its full path or its file name
+    pub fn allow_executable(mut self, executable: impl Into<String>) -> Self {
+        self.allowed_executables.push(executable.into());
+        self
+    }
+
+    /// Allow the command lines matching the regular expression, fails if it's not valid
+    pub fn allow_pattern(mut self, pattern: &str) -> io::Result<Self> {
+        self.allowed_patterns.push(compile(pattern)?);
+        Ok(self)
+    }
+
+    /// Deny the executable, either its full path or its file name, even if it's allowed
+    pub fn deny_executable(mut self, executable: impl Into<String>) -> Self {
+        self.denied_executables.push(executable.into());
+        self
+    }
+
+    /// Deny the command lines matching the regular expression even if they're allowed, fails if it's not valid
+    pub fn deny_pattern(mut self, pattern: &str) -> io::Result<Self> {
+        self.denied_patterns.push(compile(pattern)?);
+        Ok(self)
+    }
+
+    /// Set or clear the crate wide policy, enforced for all the requests
+    pub fn set_global(policy: Option<Arc<CommandPolicy>>) {
+        *GLOBAL_POLICY.lock().unwrap() = policy;
+    }
+
+    /// The crate wide policy
+    pub fn global() -> Option<Arc<CommandPolicy>> {
+        GLOBAL_POLICY.lock().unwrap().clone()
+    }
+
+    /// Check the commands of the request, returns the reason if one of them violates the policy
+    pub fn check(&self, request: &ProcessRequest) -> Result<(), String> {
+        let allowlisted = !self.allowed_executables.is_empty() || !self.allowed_patterns.is_empty();
+        for argv in crate::stage_argvs(request) {
+            let argv: Vec<String> = argv
+                .iter()
+                .map(OsString::as_os_str)
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect();
+            let Some(executable) = argv.first() else {
+                continue;
+            };
+            let command_line = argv.join(" ");
+            if is_listed(&self.denied_executables, executable) {
+                return Err(format!("Executable {:?} is denied", executable));
+            }
+            if let Some(pattern) = self
+                .denied_patterns
+                .iter()
+                .find(|pattern| pattern.is_match(&command_line))
+            {
+                return Err(format!(
+                    "Command {:?} matches the denied pattern {:?}",
+                    command_line,
+                    pattern.as_str()
+                ));
+            }
+            if allowlisted
+                && !is_listed(&self.allowed_executables, executable)
+                && !self
+                    .allowed_patterns
+                    .iter()
+                    .any(|pattern| pattern.is_match(&command_line))
+            {
+                return Err(format!("Command {:?} is not allowed", command_line));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// compile the pattern of the policy
+fn compile(pattern: &str) -> io::Result<Regex> {
+    Regex::new(pattern)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error.to_string()))
+}
+
+/// the executable is in the list by its full path or its file name
+fn is_listed(executables: &[String], executable: &str) -> bool {
+    let file_name = Path::new(executable)
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy());
+    executables.iter().any(|listed| {
+        listed == executable
+            || (!listed.contains('/') && file_name.as_deref() == Some(listed.as_str()))
+    })
+}
+
+/// check the request against the global policy, on a violation triggers the policy violation event & fails with
+/// [`io::ErrorKind::PermissionDenied`]
+pub(crate) fn enforce(request: &Arc<ProcessRequest>) -> io::Result<()> {
+    enforce_commands(request, request)
+}
+
+/// check the commands against the global policy, on a violation triggers the policy violation event of the request &
+/// fails with [`io::ErrorKind::PermissionDenied`]
+pub(crate) fn enforce_commands(
+    request: &Arc<ProcessRequest>,
+    commands: &ProcessRequest,
+) -> io::Result<()> {
+    let Some(policy) = CommandPolicy::global() else {
+        return Ok(());
+    };
+    let Err(reason) = policy.check(commands) else {
+        return Ok(());
+    };
+    let mut process_data = ProcessData::new();
+    process_data.request = Some(Arc::clone(request));
+    process_data.line.push_str(&reason);
+    check_and_trigger_callback(request, &ProcessEvent::PolicyViolation, &process_data);
+    Err(io::Error::new(io::ErrorKind::PermissionDenied, reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CommandPolicy, ProcessData, ProcessEvent, ProcessRequest, ProcessResult};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_command_policy_check() {
+        let policy = CommandPolicy::new()
+            .allow_executable("echo")
+            .allow_executable("/usr/bin/git")
+            .allow_pattern(r"^\S*sh -c ls( |$)")
+            .unwrap()
+            .deny_pattern(r"--force")
+            .unwrap()
+            .deny_executable("rm");
+        let request = |argv: &[&str], use_shell: bool| ProcessRequest {
+            cmd_line: vec![argv.iter().map(|arg| arg.to_string()).collect()],
+            use_shell,
+            ..Default::default()
+        };
+        assert!(policy.check(&request(&["echo", "hi"], false)).is_ok());
+        assert!(policy.check(&request(&["/bin/echo", "hi"], false)).is_ok());
+        assert!(policy
+            .check(&request(&["/usr/bin/git", "pull"], false))
+            .is_ok());
+        assert!(policy.check(&request(&["git", "pull"], false)).is_err());
+        assert!(policy.check(&request(&["echo", "--force"], false)).is_err());
+        assert!(policy
+            .check(&request(&["/bin/rm", "-r", "x"], false))
+            .is_err());
+        assert!(policy.check(&request(&["ls -l"], true)).is_ok());
+        assert!(policy.check(&request(&["echo hi"], true)).is_err());
+        assert!(CommandPolicy::new().allow_pattern("(").is_err());
+    }
+
+    #[test]
+    pub fn test_global_policy_violation() {
+        static VIOLATED: AtomicBool = AtomicBool::new(false);
+        static STARTED: AtomicBool = AtomicBool::new(false);
+        let callback = |event: &ProcessEvent, _data: &ProcessData| -> ProcessResult {
+            match event {
+                ProcessEvent::PolicyViolation => VIOLATED.store(true, Ordering::SeqCst),
+                ProcessEvent::Starting => STARTED.store(true, Ordering::SeqCst),
+                _ => {}
+            }
+            ProcessResult::new()
+        };
+        // the other tests run in parallel, so deny only the command of this test
+        CommandPolicy::set_global(Some(Arc::new(
+            CommandPolicy::new()
+                .deny_pattern("policy-test-313")
+                .unwrap(),
+        )));
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 313,
+            cmd_line: vec![vec![String::from("echo"), String::from("policy-test-313")]],
+            callback: Some(Arc::new(callback)),
+            ..Default::default()
+        });
+        CommandPolicy::set_global(None);
+        assert_eq!(
+            result.success.unwrap_err().kind(),
+            std::io::ErrorKind::PermissionDenied
+        );
+        assert!(!result.spawned);
+        assert!(VIOLATED.load(Ordering::SeqCst));
+        assert!(!STARTED.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,311 @@
+use crate::report::{self, ReportCollector};
+use crate::retry::start_process_with_retry;
+use crate::{BatchReport, ProcessRequest, ProcessResult, QuotaRegistry, RateLimiter};
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Pool used for the non-blocking mode requests without their own [`ProcessRequest::worker_pool`]
+static GLOBAL_POOL: Mutex<Option<Arc<ProcessPool>>> = Mutex::new(None);
+
+/// A queued task of the pool along with the channel to send back its result
+struct PoolJob {
+    /// command line of the request, for the report
+    command: String,
+    task: Box<dyn FnOnce() -> ProcessResult + Send>,
+    result_sender: mpsc::SyncSender<ProcessResult>,
+}
+
+/// Counters shared between the pool and its workers
+#[derive(Default)]
+struct PoolCounters {
+    queued: AtomicUsize,
+    active: AtomicUsize,
+    report: ReportCollector,
+}
+
+/// Handle of a request submitted to the [`ProcessPool`]
+#[derive(Debug)]
+pub struct PoolHandle {
+    request_id: u32,
+    receiver: mpsc::Receiver<ProcessResult>,
+}
+
+impl PoolHandle {
+    /// Id of the submitted request
+    pub fn request_id(&self) -> u32 {
+        self.request_id
+    }
+
+    /// Wait for the request to complete and return its result, None if the worker panicked
+    pub fn wait(self) -> Option<ProcessResult> {
+        self.receiver.recv().ok()
+    }
+
+    /// wait till the timeout for the request to complete
+    pub(crate) fn wait_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<ProcessResult, mpsc::RecvTimeoutError> {
+        self.receiver.recv_timeout(timeout)
+    }
+
+    /// Result of the request if it's already completed
+    pub fn try_result(&self) -> Option<ProcessResult> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+/// Runs the submitted requests on a fixed set of worker threads, at most `max_parallel` at a time.
+/// Excess requests are queued in the submission order. [`ProcessRequest::non_blocking_mode`] is ignored for the pool.
+/// Set it as the [`ProcessRequest::worker_pool`] or globally to run the non-blocking mode requests on its workers
+pub struct ProcessPool {
+    sender: Option<mpsc::Sender<PoolJob>>,
+    workers: Vec<JoinHandle<()>>,
+    counters: Arc<PoolCounters>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    quotas: Option<Arc<QuotaRegistry>>,
+}
+
+impl ProcessPool {
+    /// Create a pool with `max_parallel` worker threads, at least one worker is created
+    pub fn new(max_parallel: usize) -> io::Result<Self> {
+        let (sender, receiver) = mpsc::channel::<PoolJob>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let counters = Arc::new(PoolCounters::default());
+        let mut workers = vec![];
+        for index in 0..max_parallel.max(1) {
+            let receiver = Arc::clone(&receiver);
+            let counters = Arc::clone(&counters);
+            workers.push(
+                thread::Builder::new()
+                    .name(format!("pes_pool_{}", index))
+                    .spawn(move || run_worker(&receiver, &counters))?,
+            );
+        }
+        Ok(Self {
+            sender: Some(sender),
+            workers,
+            counters,
+            rate_limiter: None,
+            quotas: None,
+        })
+    }
+
+    /// Limit the spawn rate of the submitted requests which don't have their own rate limiter, for no limit use None
+    pub fn set_rate_limiter(&mut self, rate_limiter: Option<Arc<RateLimiter>>) {
+        self.rate_limiter = rate_limiter;
+    }
+
+    /// Enforce the quotas of the tags of the submitted requests which don't have their own registry, for the global
+    /// registry (if set) use None
+    pub fn set_quotas(&mut self, quotas: Option<Arc<QuotaRegistry>>) {
+        self.quotas = quotas;
+    }
+
+    /// Set or clear the crate wide pool, used for the non-blocking mode requests without their own worker pool
+    pub fn set_global(pool: Option<Arc<ProcessPool>>) {
+        *GLOBAL_POOL.lock().unwrap() = pool;
+    }
+
+    /// The crate wide pool
+    pub fn global() -> Option<Arc<ProcessPool>> {
+        GLOBAL_POOL.lock().unwrap().clone()
+    }
+
+    /// Queue the request, it starts as soon as a worker is free
+    pub fn submit(&self, process_request: ProcessRequest) -> PoolHandle {
+        self.submit_task(process_request, |request| {
+            start_process_with_retry(request, None)
+        })
+    }
+
+    /// queue the task running the request with the rate limiter & the quotas of the pool (unless it has its own), it
+    /// runs as soon as a worker is free
+    pub(crate) fn submit_task(
+        &self,
+        mut process_request: ProcessRequest,
+        task: impl FnOnce(Arc<ProcessRequest>) -> ProcessResult + Send + 'static,
+    ) -> PoolHandle {
+        if process_request.rate_limiter.is_none() {
+            process_request.rate_limiter = self.rate_limiter.clone();
+        }
+        if process_request.quotas.is_none() {
+            process_request.quotas = self.quotas.clone();
+        }
+        let request = Arc::new(process_request);
+        let request_id = request.request_id;
+        let command = report::command_line(&request);
+        let (result_sender, receiver) = mpsc::sync_channel(1);
+        self.counters.queued.fetch_add(1, Ordering::SeqCst);
+        if let Some(sender) = self.sender.as_ref() {
+            _ = sender.send(PoolJob {
+                command,
+                task: Box::new(move || task(request)),
+                result_sender,
+            });
+        }
+        PoolHandle {
+            request_id,
+            receiver,
+        }
+    }
+
+    /// Number of requests waiting for a free worker
+    pub fn queued(&self) -> usize {
+        self.counters.queued.load(Ordering::SeqCst)
+    }
+
+    /// Number of requests running now
+    pub fn active(&self) -> usize {
+        self.counters.active.load(Ordering::SeqCst)
+    }
+
+    /// Execution time percentiles, failure rates by the command & the peak concurrency of the requests completed so far
+    pub fn report(&self) -> BatchReport {
+        self.counters.report.report()
+    }
+
+    /// Max number of requests which can run in parallel
+    pub fn max_parallel(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Stop accepting requests, wait for the queued & running requests to complete
+    pub fn shutdown(mut self) {
+        self.join_workers();
+    }
+
+    fn join_workers(&mut self) {
+        self.sender = None;
+        for worker in self.workers.drain(..) {
+            // the last reference may be dropped by a task running on a worker
+            if worker.thread().id() != thread::current().id() {
+                _ = worker.join();
+            }
+        }
+    }
+}
+
+impl Drop for ProcessPool {
+    fn drop(&mut self) {
+        self.join_workers();
+    }
+}
+
+/// run the queued jobs till the pool is closed
+fn run_worker(receiver: &Mutex<mpsc::Receiver<PoolJob>>, counters: &PoolCounters) {
+    loop {
+        let job = receiver.lock().unwrap().recv();
+        let Ok(job) = job else {
+            return;
+        };
+        counters.queued.fetch_sub(1, Ordering::SeqCst);
+        counters.active.fetch_add(1, Ordering::SeqCst);
+        let started = Instant::now();
+        let running = counters.report.begin();
+        let result = (job.task)();
+        drop(running);
+        counters.active.fetch_sub(1, Ordering::SeqCst);
+        // recorded before the result is sent, so the report includes the awaited requests
+        counters
+            .report
+            .record(&job.command, started.elapsed(), &result);
+        _ = job.result_sender.send(result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ProcessPool, ProcessRequest, QuotaRegistry, TagQuota};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    pub fn test_pool_bounded_concurrency() {
+        let pool = ProcessPool::new(2).unwrap();
+        let handles: Vec<_> = (0..5)
+            .map(|index| {
+                pool.submit(ProcessRequest {
+                    request_id: 331 + index,
+                    cmd_line: vec![vec![String::from("sleep"), String::from("0.2")]],
+                    ..Default::default()
+                })
+            })
+            .collect();
+        assert!(pool.active() <= 2);
+        for handle in handles {
+            assert_eq!(handle.wait().unwrap().exit_code, Some(0));
+        }
+        assert_eq!(pool.queued(), 0);
+        let report = pool.report();
+        assert_eq!(report.max_concurrency, 2);
+        assert_eq!(report.commands["sleep 0.2"].runs, 5);
+        assert_eq!(report.commands["sleep 0.2"].failures, 0);
+        assert!(report.runtimes.unwrap().p50 >= Duration::from_millis(200));
+        pool.shutdown();
+    }
+
+    #[test]
+    pub fn test_non_blocking_on_pool() {
+        let pool = Arc::new(ProcessPool::new(1).unwrap());
+        let results: Vec<_> = (0..3)
+            .map(|index| {
+                ProcessRequest::start(ProcessRequest {
+                    request_id: 336 + index,
+                    cmd_line: vec![vec![String::from("sleep"), String::from("0.1")]],
+                    non_blocking_mode: true,
+                    worker_pool: Some(Arc::clone(&pool)),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        assert!(pool.queued() >= 1);
+        for result in results {
+            assert!(result.join_handle.is_none());
+            let result = result.pool_handle.unwrap().wait().unwrap();
+            assert_eq!(result.exit_code, Some(0));
+        }
+    }
+
+    #[test]
+    pub fn test_non_blocking_on_pool_quotas() {
+        let quotas = Arc::new(QuotaRegistry::new());
+        quotas.set_quota(
+            "pool-tenant",
+            TagQuota {
+                max_concurrent: Some(1),
+                ..Default::default()
+            },
+        );
+        let mut pool = ProcessPool::new(2).unwrap();
+        pool.set_quotas(Some(quotas));
+        let pool = Arc::new(pool);
+        let results: Vec<_> = [506, 507]
+            .into_iter()
+            .map(|request_id| {
+                let result = ProcessRequest::start(ProcessRequest {
+                    request_id,
+                    cmd_line: vec![vec![String::from("sleep"), String::from("0.5")]],
+                    tag: Some(String::from("pool-tenant")),
+                    non_blocking_mode: true,
+                    worker_pool: Some(Arc::clone(&pool)),
+                    ..Default::default()
+                });
+                std::thread::sleep(Duration::from_millis(100));
+                result
+            })
+            .collect();
+        let results: Vec<_> = results
+            .into_iter()
+            .map(|result| result.pool_handle.unwrap().wait().unwrap())
+            .collect();
+        assert_eq!(results[0].exit_code, Some(0));
+        assert_eq!(
+            results[1].success.as_ref().unwrap_err().kind(),
+            std::io::ErrorKind::QuotaExceeded
+        );
+    }
+}
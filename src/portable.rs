@@ -0,0 +1,179 @@
+use crate::{shell_quote, ProcessRequest};
+use std::time::Duration;
+
+/// How a step of a [`PortableCommand`] is chained to the previous ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Chain {
+    /// runs only if the previous step succeeded, `&&`
+    OnSuccess,
+    /// runs irrespective of the previous step, `;` in POSIX sh & `&` in cmd.exe
+    Always,
+}
+
+/// A step of a [`PortableCommand`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    Run(Vec<String>),
+    Sleep(Duration),
+}
+
+/// A sequence of commands written once & rendered for the default shell of the platform (POSIX `sh` or `cmd.exe`),
+/// hiding the common syntax differences: chaining, sleeping, the null device & the quoting of the arguments. Use
+/// [`native_path`] for the path arguments
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PortableCommand {
+    steps: Vec<(Chain, Step, bool)>,
+}
+
+impl PortableCommand {
+    /// Empty command
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run the executable with the arguments if all the previous steps succeeded
+    pub fn run<I, S>(self, argv: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.push(
+            Chain::OnSuccess,
+            Step::Run(argv.into_iter().map(Into::into).collect()),
+        )
+    }
+
+    /// Run the executable with the arguments irrespective of the previous steps, e.g. a cleanup
+    pub fn always<I, S>(self, argv: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.push(
+            Chain::Always,
+            Step::Run(argv.into_iter().map(Into::into).collect()),
+        )
+    }
+
+    /// Sleep for the duration if all the previous steps succeeded: `sleep` on Unix & PowerShell `Start-Sleep` on
+    /// Windows, as `timeout` of cmd.exe fails without a console input
+    pub fn sleep(self, duration: Duration) -> Self {
+        self.push(Chain::OnSuccess, Step::Sleep(duration))
+    }
+
+    /// Discard the output (STDOUT & STDERR) of the last step into the null device, see [`null_device`]
+    pub fn quiet(mut self) -> Self {
+        if let Some(step) = self.steps.last_mut() {
+            step.2 = true;
+        }
+        self
+    }
+
+    /// Command line for the default shell of the platform
+    pub fn to_shell_line(&self) -> String {
+        let mut line = String::new();
+        for (index, (chain, step, quiet)) in self.steps.iter().enumerate() {
+            if index > 0 {
+                line.push_str(match chain {
+                    Chain::OnSuccess => " && ",
+                    Chain::Always if cfg!(windows) => " & ",
+                    Chain::Always => " ; ",
+                });
+            }
+            let argv = match step {
+                Step::Run(argv) => argv.clone(),
+                Step::Sleep(duration) => sleep_argv(*duration),
+            };
+            let args: Vec<String> = argv.iter().map(|arg| shell_quote(arg)).collect();
+            line.push_str(&args.join(" "));
+            if *quiet {
+                line.push_str(&format!(" > {} 2>&1", null_device()));
+            }
+        }
+        line
+    }
+
+    /// Request running the command in the default shell of the platform
+    pub fn into_request(self) -> ProcessRequest {
+        ProcessRequest {
+            use_shell: true,
+            cmd_line: vec![vec![self.to_shell_line()]],
+            ..Default::default()
+        }
+    }
+
+    fn push(mut self, chain: Chain, step: Step) -> Self {
+        self.steps.push((chain, step, false));
+        self
+    }
+}
+
+/// argv sleeping for the duration
+fn sleep_argv(duration: Duration) -> Vec<String> {
+    if cfg!(windows) {
+        vec![
+            String::from("powershell"),
+            String::from("-NoProfile"),
+            String::from("-Command"),
+            format!("Start-Sleep -Milliseconds {}", duration.as_millis()),
+        ]
+    } else {
+        vec![String::from("sleep"), format!("{}", duration.as_secs_f64())]
+    }
+}
+
+/// The null device of the platform, `NUL` on Windows & `/dev/null` on Unix
+pub fn null_device() -> &'static str {
+    if cfg!(windows) {
+        "NUL"
+    } else {
+        "/dev/null"
+    }
+}
+
+/// Convert the `/` separated path to the separators of the platform, e.g. `logs/app.log` is `logs\app.log` on Windows
+pub fn native_path(path: &str) -> String {
+    if cfg!(windows) {
+        path.replace('/', "\\")
+    } else {
+        path.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::run::run_request;
+    use crate::{native_path, PortableCommand};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    pub fn test_portable_command() {
+        let command = PortableCommand::new()
+            .run(["echo", "one"])
+            .sleep(Duration::from_millis(200))
+            .run(["echo", "two words"])
+            .quiet()
+            .run(["cd", "pes_missing_dir"])
+            .quiet()
+            .run(["echo", "skipped"])
+            .always(["echo", "done"]);
+        if cfg!(unix) {
+            assert_eq!(
+                command.to_shell_line(),
+                "echo one && sleep 0.2 && echo 'two words' > /dev/null 2>&1 && cd pes_missing_dir > /dev/null 2>&1 && echo skipped ; echo done"
+            );
+        }
+        let started = Instant::now();
+        let output = run_request(command.into_request()).unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        assert_eq!(output.lines, ["one", "done"]);
+        assert_eq!(
+            native_path("logs/app.log"),
+            if cfg!(windows) {
+                "logs\\app.log"
+            } else {
+                "logs/app.log"
+            }
+        );
+    }
+}
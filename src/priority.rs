@@ -0,0 +1,317 @@
+use crate::ProcessRequest;
+use duct::Expression;
+use std::io;
+
+/// Scheduling priority of the spawned process(es), maps to `nice` on Unix and priority classes on Windows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ProcessPriority {
+    /// Runs only when the system is idle (nice 19 / IDLE_PRIORITY_CLASS)
+    Idle,
+    /// Lower than normal (nice 10 / BELOW_NORMAL_PRIORITY_CLASS)
+    BelowNormal,
+    /// Default priority (nice 0 / NORMAL_PRIORITY_CLASS)
+    Normal,
+    /// Higher than normal, may need privileges (nice -5 / ABOVE_NORMAL_PRIORITY_CLASS)
+    AboveNormal,
+    /// High priority, may need privileges (nice -10 / HIGH_PRIORITY_CLASS)
+    High,
+    /// Explicit nice value from -20 to 19, on Windows mapped to the nearest priority class
+    Nice(i32),
+}
+
+impl ProcessPriority {
+    /// nice value of the priority
+    pub fn nice_value(&self) -> i32 {
+        match *self {
+            ProcessPriority::Idle => 19,
+            ProcessPriority::BelowNormal => 10,
+            ProcessPriority::Normal => 0,
+            ProcessPriority::AboveNormal => -5,
+            ProcessPriority::High => -10,
+            ProcessPriority::Nice(nice) => nice.clamp(-20, 19),
+        }
+    }
+}
+
+/// Priority the process & the threads running its execution adopt, see [`crate::ProcessRequest::inherit_priority`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PriorityInheritance {
+    /// Priority of the thread starting the request, captured at the start
+    CallingThread,
+    /// Priority of a thread captured earlier, see [`ThreadPriority::current`]
+    Thread(ThreadPriority),
+    /// This priority, without a QoS class
+    Explicit(ProcessPriority),
+}
+
+impl PriorityInheritance {
+    /// priority to adopt, the current thread's for the calling thread
+    pub(crate) fn resolve(&self) -> ThreadPriority {
+        match *self {
+            PriorityInheritance::CallingThread => ThreadPriority::current(),
+            PriorityInheritance::Thread(thread_priority) => thread_priority,
+            PriorityInheritance::Explicit(priority) => ThreadPriority {
+                priority,
+                qos_class: None,
+            },
+        }
+    }
+}
+
+/// Scheduling priority of a thread: its nice value on Unix (per thread on Linux) & its thread priority on Windows,
+/// along with its QoS class on macOS
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ThreadPriority {
+    /// Priority of the thread as a process priority
+    pub priority: ProcessPriority,
+    /// QoS class (`qos_class_t`) of the thread on macOS, None elsewhere or for no QoS class
+    pub qos_class: Option<u32>,
+}
+
+impl ThreadPriority {
+    /// Priority of the current thread
+    #[cfg(unix)]
+    pub fn current() -> Self {
+        // the nice value can't be looked up on error, which is not possible for the calling thread itself
+        let nice = unsafe { libc::getpriority(libc::PRIO_PROCESS, 0) };
+        Self {
+            priority: ProcessPriority::Nice(nice),
+            qos_class: current_qos_class(),
+        }
+    }
+
+    /// Priority of the current thread
+    #[cfg(windows)]
+    pub fn current() -> Self {
+        use windows_sys::Win32::System::Threading::{GetCurrentThread, GetThreadPriority};
+
+        let priority = match unsafe { GetThreadPriority(GetCurrentThread()) } {
+            ..=-15 => ProcessPriority::Idle,
+            -14..=-1 => ProcessPriority::BelowNormal,
+            0 => ProcessPriority::Normal,
+            1..=14 => ProcessPriority::AboveNormal,
+            _ => ProcessPriority::High,
+        };
+        Self {
+            priority,
+            qos_class: None,
+        }
+    }
+
+    /// Priority of the current thread
+    #[cfg(not(any(unix, windows)))]
+    pub fn current() -> Self {
+        Self {
+            priority: ProcessPriority::Normal,
+            qos_class: None,
+        }
+    }
+
+    /// adopt the priority in the current thread, a higher priority may need privileges. The nice value is per process
+    /// on the Unix systems other than Linux, so only the QoS class is adopted there
+    pub(crate) fn adopt(&self) -> io::Result<()> {
+        #[cfg(target_os = "linux")]
+        if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, self.priority.nice_value()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        #[cfg(target_os = "macos")]
+        if let Some(qos_class) = self.qos_class.and_then(qos_class) {
+            if unsafe { libc::pthread_set_qos_class_self_np(qos_class, 0) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        #[cfg(windows)]
+        {
+            use windows_sys::Win32::System::Threading::{
+                GetCurrentThread, SetThreadPriority, THREAD_PRIORITY_ABOVE_NORMAL,
+                THREAD_PRIORITY_BELOW_NORMAL, THREAD_PRIORITY_HIGHEST, THREAD_PRIORITY_IDLE,
+                THREAD_PRIORITY_NORMAL,
+            };
+
+            let thread_priority = match self.priority.nice_value() {
+                15..=19 => THREAD_PRIORITY_IDLE,
+                1..=14 => THREAD_PRIORITY_BELOW_NORMAL,
+                0 => THREAD_PRIORITY_NORMAL,
+                -9..=-1 => THREAD_PRIORITY_ABOVE_NORMAL,
+                _ => THREAD_PRIORITY_HIGHEST,
+            };
+            if unsafe { SetThreadPriority(GetCurrentThread(), thread_priority) } == 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// QoS class of the current thread
+#[cfg(target_os = "macos")]
+fn current_qos_class() -> Option<u32> {
+    let mut qos_class = libc::qos_class_t::QOS_CLASS_UNSPECIFIED;
+    let mut relative_priority = 0;
+    let result = unsafe {
+        libc::pthread_get_qos_class_np(libc::pthread_self(), &mut qos_class, &mut relative_priority)
+    };
+    (result == 0 && qos_class as u32 != 0).then_some(qos_class as u32)
+}
+
+/// QoS classes are only available on macOS
+#[cfg(all(unix, not(target_os = "macos")))]
+fn current_qos_class() -> Option<u32> {
+    None
+}
+
+/// QoS class of the value
+#[cfg(target_os = "macos")]
+fn qos_class(value: u32) -> Option<libc::qos_class_t> {
+    use libc::qos_class_t::*;
+
+    [
+        QOS_CLASS_USER_INTERACTIVE,
+        QOS_CLASS_USER_INITIATED,
+        QOS_CLASS_DEFAULT,
+        QOS_CLASS_UTILITY,
+        QOS_CLASS_BACKGROUND,
+    ]
+    .into_iter()
+    .find(|qos_class| *qos_class as u32 == value)
+}
+
+/// priority of the process of the request, its own priority or the inherited one
+pub(crate) fn process_priority(request: &ProcessRequest) -> Option<ProcessPriority> {
+    request.priority.or_else(|| {
+        request
+            .inherit_priority
+            .map(|inheritance| inheritance.resolve().priority)
+    })
+}
+
+/// the request with the priority of the calling thread captured, to be adopted by the threads started for it
+pub(crate) fn capture_calling_thread(mut request: ProcessRequest) -> ProcessRequest {
+    if request.inherit_priority == Some(PriorityInheritance::CallingThread) {
+        request.inherit_priority = Some(PriorityInheritance::Thread(ThreadPriority::current()));
+    }
+    request
+}
+
+/// adopt the inherited priority of the request in the current thread, started for the request
+pub(crate) fn adopt_inherited(inheritance: Option<PriorityInheritance>) {
+    if let Some(inheritance) = inheritance {
+        // best effort, the thread keeps its priority if it may not be changed
+        _ = inheritance.resolve().adopt();
+    }
+}
+
+/// Apply the QoS class of the inherited priority to all the commands of the expression before they are spawned
+#[cfg(target_os = "macos")]
+pub(crate) fn apply_inherited_qos_class(
+    expression: Expression,
+    request: &ProcessRequest,
+) -> io::Result<Expression> {
+    use std::os::unix::process::CommandExt;
+
+    let qos_class = request
+        .inherit_priority
+        .and_then(|inheritance| inheritance.resolve().qos_class)
+        .and_then(qos_class);
+    let Some(qos_class) = qos_class else {
+        return Ok(expression);
+    };
+    Ok(expression.before_spawn(move |command| {
+        unsafe {
+            command.pre_exec(move || {
+                if libc::pthread_set_qos_class_self_np(qos_class, 0) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        Ok(())
+    }))
+}
+
+/// QoS classes are only available on macOS
+#[cfg(not(target_os = "macos"))]
+pub(crate) fn apply_inherited_qos_class(
+    expression: Expression,
+    _request: &ProcessRequest,
+) -> io::Result<Expression> {
+    Ok(expression)
+}
+
+/// Apply the priority to all the commands of the expression before they are spawned
+#[cfg(unix)]
+pub(crate) fn apply_priority(
+    expression: Expression,
+    priority: Option<ProcessPriority>,
+) -> io::Result<Expression> {
+    use std::os::unix::process::CommandExt;
+
+    let Some(priority) = priority else {
+        return Ok(expression);
+    };
+    let nice = priority.nice_value();
+    Ok(expression.before_spawn(move |command| {
+        unsafe {
+            command.pre_exec(move || {
+                if libc::setpriority(libc::PRIO_PROCESS, 0, nice) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        Ok(())
+    }))
+}
+
+/// Apply the priority class to all the commands of the expression before they are spawned
+#[cfg(windows)]
+pub(crate) fn apply_priority(
+    expression: Expression,
+    priority: Option<ProcessPriority>,
+) -> io::Result<Expression> {
+    use std::os::windows::process::CommandExt;
+
+    let Some(priority) = priority else {
+        return Ok(expression);
+    };
+    let priority_class = priority_class(priority);
+    Ok(expression.before_spawn(move |command| {
+        command.creation_flags(priority_class);
+        Ok(())
+    }))
+}
+
+/// Windows priority class of the priority, a process creation flag
+#[cfg(windows)]
+pub(crate) fn priority_class(priority: ProcessPriority) -> u32 {
+    use windows_sys::Win32::System::Threading::{
+        ABOVE_NORMAL_PRIORITY_CLASS, BELOW_NORMAL_PRIORITY_CLASS, HIGH_PRIORITY_CLASS,
+        IDLE_PRIORITY_CLASS, NORMAL_PRIORITY_CLASS,
+    };
+
+    match priority.nice_value() {
+        15..=19 => IDLE_PRIORITY_CLASS,
+        1..=14 => BELOW_NORMAL_PRIORITY_CLASS,
+        0 => NORMAL_PRIORITY_CLASS,
+        -9..=-1 => ABOVE_NORMAL_PRIORITY_CLASS,
+        _ => HIGH_PRIORITY_CLASS,
+    }
+}
+
+/// Process priority is not available on this platform
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn apply_priority(
+    expression: Expression,
+    priority: Option<ProcessPriority>,
+) -> io::Result<Expression> {
+    match priority {
+        None => Ok(expression),
+        Some(_) => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Process priority is not supported on this platform",
+        )),
+    }
+}
@@ -0,0 +1,278 @@
+use crate::{check_and_trigger_callback, ProcessData, ProcessEvent, ProcessRequest, ProcessResult};
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Registry used for the requests without their own [`ProcessRequest::quotas`]
+static GLOBAL_QUOTAS: Mutex<Option<Arc<QuotaRegistry>>> = Mutex::new(None);
+
+/// window of the hourly quota
+const HOUR: Duration = Duration::from_secs(3600);
+
+/// Limits of the executions of a tag, see [`QuotaRegistry::set_quota`]. A limit of None is unlimited
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct TagQuota {
+    /// Max executions running at the same time
+    pub max_concurrent: Option<usize>,
+    /// Max executions started within the last hour (sliding window), the retries of an execution are not counted
+    pub max_per_hour: Option<u32>,
+    /// Max total CPU time of the executions, once used up the tag can't run anymore till its usage is reset. It's
+    /// counted from [`ProcessResult::job_accounting`], which is turned on for the requests of the tag (the CPU time of
+    /// an execution is not counted where it's not available)
+    pub max_cpu_time: Option<Duration>,
+}
+
+/// usage of a tag
+#[derive(Debug, Default)]
+struct TagUsage {
+    running: usize,
+    /// start times within the last hour
+    starts: VecDeque<Instant>,
+    cpu_time: Duration,
+}
+
+/// Per tag (requester, tenant) quotas of the executions for the fairness between the tenants of a service, see
+/// [`ProcessRequest::tag`]. A request over the quota of its tag is rejected with the [`ProcessEvent::QuotaExceeded`]
+/// event & its result fails with [`io::ErrorKind::QuotaExceeded`]. Share it between requests using [`Arc`], set it on
+/// a [`ProcessRequest`], a [`crate::ProcessPool`] or globally. The untagged requests & the tags without a quota are
+/// not limited
+#[derive(Debug, Default)]
+pub struct QuotaRegistry {
+    quotas: Mutex<HashMap<String, TagQuota>>,
+    usage: Mutex<HashMap<String, TagUsage>>,
+}
+
+impl QuotaRegistry {
+    /// Registry without any quota
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set or replace the quota of the tag
+    pub fn set_quota(&self, tag: impl Into<String>, quota: TagQuota) {
+        self.quotas.lock().unwrap().insert(tag.into(), quota);
+    }
+
+    /// Remove the quota of the tag, its usage is kept
+    pub fn remove_quota(&self, tag: &str) {
+        self.quotas.lock().unwrap().remove(tag);
+    }
+
+    /// Quota of the tag
+    pub fn quota(&self, tag: &str) -> Option<TagQuota> {
+        self.quotas.lock().unwrap().get(tag).copied()
+    }
+
+    /// Executions of the tag running now
+    pub fn running(&self, tag: &str) -> usize {
+        self.usage
+            .lock()
+            .unwrap()
+            .get(tag)
+            .map_or(0, |usage| usage.running)
+    }
+
+    /// Total CPU time of the executions of the tag so far
+    pub fn cpu_time(&self, tag: &str) -> Duration {
+        self.usage
+            .lock()
+            .unwrap()
+            .get(tag)
+            .map_or(Duration::ZERO, |usage| usage.cpu_time)
+    }
+
+    /// Forget the CPU time & the hourly starts of the tag e.g. at the start of a billing period
+    pub fn reset_usage(&self, tag: &str) {
+        if let Some(usage) = self.usage.lock().unwrap().get_mut(tag) {
+            usage.starts.clear();
+            usage.cpu_time = Duration::ZERO;
+        }
+    }
+
+    /// Set or clear the crate wide registry, used for the requests without their own registry
+    pub fn set_global(quotas: Option<Arc<QuotaRegistry>>) {
+        *GLOBAL_QUOTAS.lock().unwrap() = quotas;
+    }
+
+    /// The crate wide registry
+    pub fn global() -> Option<Arc<QuotaRegistry>> {
+        GLOBAL_QUOTAS.lock().unwrap().clone()
+    }
+
+    /// count a start of the tag if it's within its quota, otherwise returns the exceeded limit
+    fn acquire(&self, tag: &str) -> Result<(), String> {
+        let quota = self.quota(tag).unwrap_or_default();
+        let mut usage = self.usage.lock().unwrap();
+        let usage = usage.entry(tag.to_string()).or_default();
+        let now = Instant::now();
+        while usage
+            .starts
+            .front()
+            .is_some_and(|start| now.duration_since(*start) >= HOUR)
+        {
+            usage.starts.pop_front();
+        }
+        if let Some(max_concurrent) = quota.max_concurrent.filter(|max| usage.running >= *max) {
+            return Err(format!(
+                "Tag {:?} has {} executions running, the quota is {}",
+                tag, usage.running, max_concurrent
+            ));
+        }
+        if let Some(max_per_hour) = quota
+            .max_per_hour
+            .filter(|max| usage.starts.len() >= *max as usize)
+        {
+            return Err(format!(
+                "Tag {:?} has started {} executions within the last hour, the quota is {}",
+                tag,
+                usage.starts.len(),
+                max_per_hour
+            ));
+        }
+        if let Some(max_cpu_time) = quota.max_cpu_time.filter(|max| usage.cpu_time >= *max) {
+            return Err(format!(
+                "Tag {:?} has used {:?} of CPU time, the quota is {:?}",
+                tag, usage.cpu_time, max_cpu_time
+            ));
+        }
+        usage.running += 1;
+        usage.starts.push_back(now);
+        Ok(())
+    }
+
+    /// count the completed execution of the tag
+    fn release(&self, tag: &str, cpu_time: Duration) {
+        if let Some(usage) = self.usage.lock().unwrap().get_mut(tag) {
+            usage.running = usage.running.saturating_sub(1);
+            usage.cpu_time += cpu_time;
+        }
+    }
+}
+
+/// An execution of a tag counted as running till it's dropped
+pub(crate) struct QuotaSlot {
+    quotas: Arc<QuotaRegistry>,
+    tag: String,
+    cpu_time: Duration,
+}
+
+impl QuotaSlot {
+    /// count the CPU time of the completed execution
+    pub(crate) fn complete(mut self, result: &ProcessResult) {
+        if let Some(accounting) = result.job_accounting.as_ref() {
+            self.cpu_time = accounting.cpu_time;
+        }
+    }
+}
+
+impl Drop for QuotaSlot {
+    fn drop(&mut self) {
+        self.quotas.release(&self.tag, self.cpu_time);
+    }
+}
+
+/// check the request against the quota of its tag in the request's (or the global) registry. Returns the request to
+/// run (with the job accounting turned on for a CPU quota) & its slot, on an exceeded quota triggers the quota
+/// exceeded event & fails with [`io::ErrorKind::QuotaExceeded`]
+pub(crate) fn acquire(
+    request: Arc<ProcessRequest>,
+) -> io::Result<(Arc<ProcessRequest>, Option<QuotaSlot>)> {
+    let (Some(tag), Some(quotas)) = (
+        request.tag.clone(),
+        request.quotas.clone().or_else(QuotaRegistry::global),
+    ) else {
+        return Ok((request, None));
+    };
+    if let Err(reason) = quotas.acquire(&tag) {
+        let mut process_data = ProcessData::new();
+        process_data.request = Some(Arc::clone(&request));
+        process_data.line.push_str(&reason);
+        check_and_trigger_callback(&request, &ProcessEvent::QuotaExceeded, &process_data);
+        return Err(io::Error::new(io::ErrorKind::QuotaExceeded, reason));
+    }
+    let cpu_quota = quotas
+        .quota(&tag)
+        .is_some_and(|quota| quota.max_cpu_time.is_some());
+    let request = if cpu_quota && !request.job_accounting {
+        let mut accounted = (*request).clone();
+        accounted.job_accounting = true;
+        Arc::new(accounted)
+    } else {
+        request
+    };
+    let slot = QuotaSlot {
+        quotas,
+        tag,
+        cpu_time: Duration::ZERO,
+    };
+    Ok((request, Some(slot)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ProcessData, ProcessEvent, ProcessPool, ProcessRequest, ProcessResult, QuotaRegistry,
+        TagQuota,
+    };
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    pub fn test_tag_quotas() {
+        static EXCEEDED: AtomicU32 = AtomicU32::new(0);
+        let callback = |event: &ProcessEvent, _data: &ProcessData| -> ProcessResult {
+            if *event == ProcessEvent::QuotaExceeded {
+                EXCEEDED.fetch_add(1, Ordering::SeqCst);
+            }
+            ProcessResult::new()
+        };
+        let quotas = Arc::new(QuotaRegistry::new());
+        quotas.set_quota(
+            "tenant-a",
+            TagQuota {
+                max_concurrent: Some(1),
+                max_per_hour: Some(3),
+                ..Default::default()
+            },
+        );
+        let mut pool = ProcessPool::new(3).unwrap();
+        pool.set_quotas(Some(Arc::clone(&quotas)));
+        let request = |request_id: u32, tag: &str, seconds: &str| ProcessRequest {
+            request_id,
+            cmd_line: vec![vec![String::from("sleep"), String::from(seconds)]],
+            tag: Some(String::from(tag)),
+            callback: Some(Arc::new(callback)),
+            ..Default::default()
+        };
+
+        let running = pool.submit(request(314, "tenant-a", "0.5"));
+        std::thread::sleep(Duration::from_millis(200));
+        assert_eq!(quotas.running("tenant-a"), 1);
+        let rejected = pool.submit(request(315, "tenant-a", "0")).wait().unwrap();
+        assert_eq!(
+            rejected.success.unwrap_err().kind(),
+            std::io::ErrorKind::QuotaExceeded
+        );
+        let other = pool.submit(request(316, "tenant-b", "0")).wait().unwrap();
+        assert_eq!(other.exit_code, Some(0));
+        assert_eq!(running.wait().unwrap().exit_code, Some(0));
+        assert_eq!(quotas.running("tenant-a"), 0);
+
+        let result = pool.submit(request(317, "tenant-a", "0")).wait().unwrap();
+        assert_eq!(result.exit_code, Some(0));
+        let result = pool.submit(request(318, "tenant-a", "0")).wait().unwrap();
+        assert_eq!(result.exit_code, Some(0));
+        let rejected = pool.submit(request(319, "tenant-a", "0")).wait().unwrap();
+        assert!(rejected.success.is_err());
+        assert_eq!(EXCEEDED.load(Ordering::SeqCst), 2);
+
+        quotas.reset_usage("tenant-a");
+        let result = pool.submit(request(320, "tenant-a", "0")).wait().unwrap();
+        assert_eq!(result.exit_code, Some(0));
+        pool.shutdown();
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{check_and_trigger_callback, ProcessData, ProcessEvent, ProcessRequest};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Rate limiter applied to all the requests without their own [`ProcessRequest::rate_limiter`]
+static GLOBAL_RATE_LIMITER: Mutex<Option<Arc<RateLimiter>>> = Mutex::new(None);
+
+/// Token bucket to limit how many processes are spawned per time period, allowing bursts up to `max_spawns`.
+/// Share it between requests using [`Arc`], set it on a [`ProcessRequest`], a [`crate::ProcessPool`] or globally
+#[derive(Debug)]
+pub struct RateLimiter {
+    max_spawns: u32,
+    period: Duration,
+    /// (available tokens, last refill time)
+    bucket: Mutex<(f64, Instant)>,
+}
+
+impl RateLimiter {
+    /// Allow at most `max_spawns` spawns per `period`
+    pub fn new(max_spawns: u32, period: Duration) -> Self {
+        let max_spawns = max_spawns.max(1);
+        Self {
+            max_spawns,
+            period,
+            bucket: Mutex::new((max_spawns as f64, Instant::now())),
+        }
+    }
+
+    /// Set or clear the crate wide rate limiter, used for the requests without their own rate limiter
+    pub fn set_global(rate_limiter: Option<Arc<RateLimiter>>) {
+        *GLOBAL_RATE_LIMITER.lock().unwrap() = rate_limiter;
+    }
+
+    /// The crate wide rate limiter
+    pub fn global() -> Option<Arc<RateLimiter>> {
+        GLOBAL_RATE_LIMITER.lock().unwrap().clone()
+    }
+
+    /// Take a token if available, otherwise returns how long to wait for the next token
+    pub fn try_acquire(&self) -> Result<(), Duration> {
+        let mut bucket = self.bucket.lock().unwrap();
+        let now = Instant::now();
+        let refill_per_second =
+            self.max_spawns as f64 / self.period.as_secs_f64().max(f64::EPSILON);
+        let tokens = (bucket.0 + now.duration_since(bucket.1).as_secs_f64() * refill_per_second)
+            .min(self.max_spawns as f64);
+        *bucket = (tokens, now);
+        if tokens >= 1.0 {
+            bucket.0 -= 1.0;
+            return Ok(());
+        }
+        Err(Duration::from_secs_f64((1.0 - tokens) / refill_per_second))
+    }
+}
+
+/// wait for a spawn token of the request's (or the global) rate limiter, triggers the queued event when waiting
+pub(crate) fn wait_for_spawn_token(request: &Arc<ProcessRequest>) {
+    let Some(rate_limiter) = request.rate_limiter.clone().or_else(RateLimiter::global) else {
+        return;
+    };
+    let mut queued = false;
+    while let Err(wait) = rate_limiter.try_acquire() {
+        if !queued {
+            queued = true;
+            let mut process_data = ProcessData::new();
+            process_data.request = Some(Arc::clone(request));
+            process_data
+                .line
+                .push_str(format!("Spawn rate limited, waiting {} ms", wait.as_millis()).as_str());
+            check_and_trigger_callback(request, &ProcessEvent::Queued, &process_data);
+        }
+        thread::sleep(wait);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ProcessData, ProcessEvent, ProcessRequest, ProcessResult, RateLimiter};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    pub fn test_rate_limiter_token_bucket() {
+        let rate_limiter = RateLimiter::new(2, Duration::from_secs(1));
+        assert!(rate_limiter.try_acquire().is_ok());
+        assert!(rate_limiter.try_acquire().is_ok());
+        let wait = rate_limiter.try_acquire().unwrap_err();
+        assert!(wait > Duration::from_millis(400) && wait <= Duration::from_millis(500));
+    }
+
+    #[test]
+    pub fn test_rate_limited_spawn_queued() {
+        static QUEUED: AtomicBool = AtomicBool::new(false);
+        let callback = |status: &ProcessEvent, _data: &ProcessData| -> ProcessResult {
+            if let ProcessEvent::Queued = status {
+                QUEUED.store(true, Ordering::SeqCst);
+            }
+            ProcessResult::new()
+        };
+        let request = ProcessRequest {
+            request_id: 351,
+            callback: Some(Arc::new(callback)),
+            cmd_line: vec![vec![String::from("echo"), String::from("spawned")]],
+            rate_limiter: Some(Arc::new(RateLimiter::new(1, Duration::from_millis(300)))),
+            ..Default::default()
+        };
+        let started = Instant::now();
+        ProcessRequest::start(request.clone());
+        assert!(!QUEUED.load(Ordering::SeqCst));
+        ProcessRequest::start(request);
+        assert!(QUEUED.load(Ordering::SeqCst));
+        assert!(started.elapsed() >= Duration::from_millis(250));
+    }
+}
@@ -0,0 +1,215 @@
+use crate::ProcessData;
+use std::sync::Arc;
+
+/// How the fields of a record are separated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FieldDelimiter {
+    /// A single character like `,` or `\t`, a field may be double quoted with `""` as an escaped quote
+    Char(char),
+    /// Runs of whitespace like the aligned columns of `ps` or `df`, with a header the last field keeps the remaining
+    /// text (e.g. the command line of `ps`)
+    Whitespace,
+}
+
+impl Default for FieldDelimiter {
+    fn default() -> Self {
+        FieldDelimiter::Char(',')
+    }
+}
+
+/// Split every output line into the fields of a record, see [`crate::ProcessRequest::record_format`]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RecordFormat {
+    /// Separator of the fields
+    pub delimiter: FieldDelimiter,
+    /// The first line is a header with the field names, see [`ProcessData::record_field`]
+    pub has_header: bool,
+}
+
+impl RecordFormat {
+    /// Comma separated values
+    pub fn csv(has_header: bool) -> Self {
+        Self {
+            delimiter: FieldDelimiter::Char(','),
+            has_header,
+        }
+    }
+
+    /// Tab separated values
+    pub fn tsv(has_header: bool) -> Self {
+        Self {
+            delimiter: FieldDelimiter::Char('\t'),
+            has_header,
+        }
+    }
+
+    /// Whitespace aligned columns
+    pub fn columns(has_header: bool) -> Self {
+        Self {
+            delimiter: FieldDelimiter::Whitespace,
+            has_header,
+        }
+    }
+}
+
+/// Record decoder of an execution, it keeps the header
+pub(crate) struct RecordDecoder<'a> {
+    format: &'a RecordFormat,
+    header: Option<Arc<Vec<String>>>,
+}
+
+impl<'a> RecordDecoder<'a> {
+    pub(crate) fn new(format: &'a RecordFormat) -> Self {
+        Self {
+            format,
+            header: None,
+        }
+    }
+
+    /// split the output line of the data into its record, the header line is kept as the header with no record.
+    /// The field buffers of the previous record are reused
+    pub(crate) fn decode(&mut self, data: &mut ProcessData) {
+        let mut fields = FieldBuffers(data.record.take().unwrap_or_default());
+        let line = data.line_str();
+        let max_fields = self.header.as_ref().map(|header| header.len());
+        let fields = match self.format.delimiter {
+            FieldDelimiter::Char(delimiter) => split_delimited(line, delimiter, &mut fields),
+            FieldDelimiter::Whitespace => split_whitespace(line, max_fields, &mut fields),
+        };
+        if self.format.has_header && self.header.is_none() {
+            self.header = Some(Arc::new(fields));
+            data.record = None;
+        } else {
+            data.record = Some(fields);
+        }
+        data.record_header = self.header.clone();
+    }
+}
+
+/// String buffers of the previous record, reused for the fields of the next one
+struct FieldBuffers(Vec<String>);
+
+impl FieldBuffers {
+    /// an empty field buffer
+    fn next(&mut self) -> String {
+        let mut field = self.0.pop().unwrap_or_default();
+        field.clear();
+        field
+    }
+}
+
+/// split at the delimiter, a double quoted field may contain the delimiter & `""` as an escaped quote
+fn split_delimited(line: &str, delimiter: char, buffers: &mut FieldBuffers) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = buffers.next();
+    let mut quoted = false;
+    let mut characters = line.chars().peekable();
+    while let Some(character) = characters.next() {
+        match character {
+            '"' if quoted && characters.peek() == Some(&'"') => {
+                characters.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            character if character == delimiter && !quoted => {
+                fields.push(std::mem::replace(&mut field, buffers.next()))
+            }
+            character => field.push(character),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// split at the runs of whitespace, the last of the max fields keeps the rest of the line
+fn split_whitespace(
+    line: &str,
+    max_fields: Option<usize>,
+    buffers: &mut FieldBuffers,
+) -> Vec<String> {
+    let mut fields = vec![];
+    let mut rest = line.trim();
+    while !rest.is_empty() {
+        let mut field = buffers.next();
+        if max_fields.is_some_and(|max_fields| fields.len() + 1 >= max_fields) {
+            field.push_str(rest);
+            fields.push(field);
+            break;
+        }
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        field.push_str(&rest[..end]);
+        fields.push(field);
+        rest = rest[end..].trim_start();
+    }
+    fields
+}
+
+impl ProcessData<'_> {
+    /// Field of the record by its name in the header, None if unavailable
+    pub fn record_field(&self, name: &str) -> Option<&str> {
+        let index = self
+            .record_header
+            .as_ref()?
+            .iter()
+            .position(|field| field == name)?;
+        self.record.as_ref()?.get(index).map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::records::{split_delimited, FieldBuffers};
+    use crate::{ProcessData, ProcessEvent, ProcessRequest, ProcessResult, RecordFormat};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    pub fn test_split_delimited() {
+        let mut buffers = FieldBuffers(vec![]);
+        let fields = split_delimited(r#"a,"b, ""c""",,d"#, ',', &mut buffers);
+        assert_eq!(fields, ["a", r#"b, "c""#, "", "d"]);
+        let mut buffers = FieldBuffers(fields);
+        assert_eq!(split_delimited("x\ty", '\t', &mut buffers), ["x", "y"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_records() {
+        let records = Arc::new(Mutex::new(vec![]));
+        let recorded = Arc::clone(&records);
+        ProcessRequest::start(ProcessRequest {
+            request_id: 504,
+            use_shell: true,
+            cmd_line: vec![vec![String::from(
+                "echo '  PID TTY  CMD'; echo '    1 ?    /sbin/init splash'",
+            )]],
+            record_format: Some(RecordFormat::columns(true)),
+            callback: Some(Arc::new(move |event: &ProcessEvent, data: &ProcessData| {
+                if *event == ProcessEvent::IOData {
+                    recorded.lock().unwrap().push((
+                        data.record.clone(),
+                        data.record_field("CMD").map(String::from),
+                    ));
+                }
+                ProcessResult::new()
+            })),
+            ..Default::default()
+        });
+        let records = records.lock().unwrap();
+        assert_eq!(records[0], (None, None));
+        assert_eq!(
+            records[1],
+            (
+                Some(vec![
+                    String::from("1"),
+                    String::from("?"),
+                    String::from("/sbin/init splash")
+                ]),
+                Some(String::from("/sbin/init splash"))
+            )
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use crate::ProcessRequest;
+use duct::Expression;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::PathBuf;
+
+/// Output file of the process, see [`ProcessRequest::stdout_path`] & [`ProcessRequest::stderr_path`]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct FileRedirect {
+    /// Path of the file, it's created if missing
+    pub path: PathBuf,
+    /// Append to the file instead of truncating it
+    pub append: bool,
+}
+
+impl FileRedirect {
+    /// Truncate the file & write the output to it
+    pub fn truncate(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            append: false,
+        }
+    }
+
+    /// Append the output to the file
+    pub fn append(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            append: true,
+        }
+    }
+
+    fn open(&self) -> io::Result<File> {
+        OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(self.append)
+            .truncate(!self.append)
+            .open(&self.path)
+    }
+
+    /// shell like notation of the redirect, e.g. `>> out.log`
+    fn describe(&self, fd: &str) -> String {
+        let operator = if self.append { ">>" } else { ">" };
+        format!("{}{} {:?}", fd, operator, self.path)
+    }
+}
+
+/// Redirect the STDIN, STDOUT & STDERR of the pipeline to the files of the request. A redirected output isn't streamed
+/// as the [`crate::ProcessEvent::IOData`] events
+pub(crate) fn apply_redirects(
+    mut expression: Expression,
+    request: &ProcessRequest,
+) -> io::Result<Expression> {
+    if let Some(stdin_path) = request.stdin_path.as_ref() {
+        expression = expression.stdin_file(File::open(stdin_path)?);
+    }
+    if let Some(stdout_path) = request.stdout_path.as_ref() {
+        expression = expression.stdout_file(stdout_path.open()?);
+    }
+    if let Some(stderr_path) = request.stderr_path.as_ref() {
+        expression = expression.stderr_file(stderr_path.open()?);
+    }
+    Ok(expression)
+}
+
+/// shell like notation of the redirects of the request, empty if none
+pub(crate) fn describe_redirects(request: &ProcessRequest) -> String {
+    let mut redirects = String::new();
+    if let Some(stdin_path) = request.stdin_path.as_ref() {
+        redirects.push_str(&format!(" < {:?}", stdin_path));
+    }
+    if let Some(stdout_path) = request.stdout_path.as_ref() {
+        redirects.push(' ');
+        redirects.push_str(&stdout_path.describe(""));
+    }
+    if let Some(stderr_path) = request.stderr_path.as_ref() {
+        redirects.push(' ');
+        redirects.push_str(&stderr_path.describe("2"));
+    }
+    redirects
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FileRedirect, ProcessData, ProcessEvent, ProcessRequest, ProcessResult};
+    use std::sync::{Arc, Mutex};
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_file_redirects() {
+        let dir = std::env::temp_dir();
+        let stdin_path = dir.join(format!("pes_test_redirect_in_{}", std::process::id()));
+        let stdout_path = dir.join(format!("pes_test_redirect_out_{}", std::process::id()));
+        std::fs::write(&stdin_path, "first\nsecond\n").unwrap();
+        std::fs::write(&stdout_path, "existing\n").unwrap();
+        let events = Arc::new(Mutex::new(vec![]));
+        let recorded = Arc::clone(&events);
+        let request = ProcessRequest {
+            request_id: 201,
+            use_shell: true,
+            cmd_line: vec![vec![String::from("cat; echo error >&2")]],
+            stdin_path: Some(stdin_path.clone()),
+            stdout_path: Some(FileRedirect::append(&stdout_path)),
+            callback: Some(Arc::new(move |event: &ProcessEvent, data: &ProcessData| {
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push((*event, data.line.trim_end().to_string()));
+                ProcessResult::new()
+            })),
+            ..Default::default()
+        };
+        let result = ProcessRequest::start(request);
+        assert_eq!(result.exit_code, Some(0));
+        assert_eq!(
+            std::fs::read_to_string(&stdout_path).unwrap(),
+            "existing\nfirst\nsecond\n"
+        );
+        let events = events.lock().unwrap();
+        // STDERR is still streamed
+        let lines: Vec<&String> = events
+            .iter()
+            .filter(|(event, _)| *event == ProcessEvent::IOData)
+            .map(|(_, line)| line)
+            .collect();
+        assert_eq!(lines, ["error"]);
+        assert_eq!(events.last().unwrap().0, ProcessEvent::Exited);
+        _ = std::fs::remove_file(stdin_path);
+        _ = std::fs::remove_file(stdout_path);
+    }
+}
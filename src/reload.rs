@@ -0,0 +1,97 @@
+use std::io;
+
+/// How a supervised service is asked to reload its configuration without a restart, see [`crate::Supervisor::reload`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ReloadAction {
+    /// Send this signal to all the commands of the running pipeline, Unix only. The default is `SIGHUP`
+    Signal(i32),
+    /// Run this command (argv) to completion, it must succeed. The pids of the running pipeline are passed in the
+    /// `PES_PIDS` environment variable, separated by spaces
+    Command(Vec<String>),
+    /// POST to this URL (e.g. an admin endpoint of the service) using the system `curl` CLI, a HTTP error status is a
+    /// failure
+    Http(String),
+}
+
+impl Default for ReloadAction {
+    fn default() -> Self {
+        // SIGHUP
+        ReloadAction::Signal(1)
+    }
+}
+
+impl ReloadAction {
+    /// perform the action on the running processes
+    pub(crate) fn perform(&self, pids: &[u32]) -> io::Result<()> {
+        if pids.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "The service is not running",
+            ));
+        }
+        match self {
+            ReloadAction::Signal(signal) => send_signal(pids, *signal),
+            ReloadAction::Command(argv) => {
+                let (program, args) = argv.split_first().ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "Empty reload command")
+                })?;
+                let pids: Vec<String> = pids.iter().map(u32::to_string).collect();
+                check_output(
+                    "Reload command",
+                    duct::cmd(program, args).env("PES_PIDS", pids.join(" ")),
+                )
+            }
+            ReloadAction::Http(url) => check_output(
+                "Reload request",
+                duct::cmd!(
+                    "curl",
+                    "--silent",
+                    "--show-error",
+                    "--fail",
+                    "--request",
+                    "POST",
+                    "--max-time",
+                    "10",
+                    url
+                ),
+            ),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn send_signal(pids: &[u32], signal: i32) -> io::Result<()> {
+    for pid in pids {
+        if unsafe { libc::kill(*pid as libc::pid_t, signal) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Signals are not available on this platform
+#[cfg(not(unix))]
+fn send_signal(_pids: &[u32], _signal: i32) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Reload signals are supported on Unix only",
+    ))
+}
+
+/// run the expression to completion, a failure exit status is an error with its STDERR
+fn check_output(what: &str, expression: duct::Expression) -> io::Result<()> {
+    let output = expression
+        .stdout_null()
+        .stderr_capture()
+        .unchecked()
+        .run()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "{} failed: {}",
+            what,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,217 @@
+use crate::delayed_start;
+use crate::latch::Latch;
+use crate::retry::start_process_with_retry;
+use crate::{ProcessData, ProcessEvent, ProcessRequest, ProcessResult};
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Max number of the events kept for the late subscribers per process, the oldest ones are dropped
+const MAX_HISTORY_EVENTS: usize = 10_000;
+/// How long a finished process is kept for its events to be read, it's forgotten afterwards
+const FINISHED_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Latest events of a remotely started process, kept for the late subscribers
+struct EventLog<T> {
+    history: VecDeque<T>,
+    subscribers: Vec<mpsc::Sender<T>>,
+    finished_at: Option<Instant>,
+}
+
+/// A remotely started process
+struct RemoteProcess<T> {
+    stop: Latch,
+    events: Mutex<EventLog<T>>,
+}
+
+impl<T: Clone> RemoteProcess<T> {
+    /// keep the event & forward it to the subscribers
+    fn publish(&self, event: T) {
+        let mut events = self.events.lock().unwrap();
+        events
+            .subscribers
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+        if events.history.len() >= MAX_HISTORY_EVENTS {
+            events.history.pop_front();
+        }
+        events.history.push_back(event);
+    }
+
+    /// mark as finished, the subscribers are dropped which ends their streams
+    fn finish(&self) {
+        let mut events = self.events.lock().unwrap();
+        events.finished_at = Some(Instant::now());
+        events.subscribers.clear();
+    }
+}
+
+/// Registry of the processes started by the remote interfaces (HTTP, gRPC), with the events converted to `T`
+pub(crate) struct RemoteProcesses<T> {
+    processes: Mutex<HashMap<u32, Arc<RemoteProcess<T>>>>,
+    to_event: fn(&ProcessEvent, &ProcessData) -> T,
+    finished_ttl: Duration,
+}
+
+impl<T: Clone + Send + 'static> RemoteProcesses<T> {
+    pub(crate) fn new(to_event: fn(&ProcessEvent, &ProcessData) -> T) -> Self {
+        Self {
+            processes: Mutex::default(),
+            to_event,
+            finished_ttl: FINISHED_TTL,
+        }
+    }
+
+    /// the processes, without the ones finished for longer than the TTL
+    fn processes(&self) -> std::sync::MutexGuard<'_, HashMap<u32, Arc<RemoteProcess<T>>>> {
+        let mut processes = self.processes.lock().unwrap();
+        processes.retain(|_, process| {
+            process
+                .events
+                .lock()
+                .unwrap()
+                .finished_at
+                .is_none_or(|finished_at| finished_at.elapsed() < self.finished_ttl)
+        });
+        processes
+    }
+
+    /// start the request in its own thread, the callback of the request is replaced
+    pub(crate) fn start(&self, mut request: ProcessRequest) -> io::Result<()> {
+        let request_id = request.request_id;
+        let process = Arc::new(RemoteProcess {
+            stop: Latch::new(),
+            events: Mutex::new(EventLog {
+                history: VecDeque::new(),
+                subscribers: vec![],
+                finished_at: None,
+            }),
+        });
+        {
+            let mut processes = self.processes();
+            if processes.contains_key(&request_id) {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("Request id {} is already in use", request_id),
+                ));
+            }
+            processes.insert(request_id, Arc::clone(&process));
+        }
+        let publisher = Arc::clone(&process);
+        let to_event = self.to_event;
+        request.callback = Some(Arc::new(
+            move |event: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+                publisher.publish(to_event(event, data));
+                ProcessResult::new()
+            },
+        ));
+        let request = Arc::new(request);
+        let spawned = thread::Builder::new()
+            .name(format!("pes_remote_rq_{}", request_id))
+            .spawn(move || {
+                let delay = delayed_start::start_delay(&request);
+                if let Some(delay) = delay {
+                    delayed_start::notify_scheduled(&request, delay);
+                }
+                if !delay.is_some_and(|delay| process.stop.wait_timeout(delay)) {
+                    start_process_with_retry(request, Some(&process.stop));
+                }
+                process.finish();
+            });
+        if let Err(error) = spawned {
+            self.processes.lock().unwrap().remove(&request_id);
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    /// the kept events of the process (all of them from the start unless there were too many), the receiver is disconnected once the process completes
+    pub(crate) fn subscribe(&self, request_id: u32) -> Option<mpsc::Receiver<T>> {
+        let process = self.processes().get(&request_id).cloned()?;
+        let (sender, receiver) = mpsc::channel();
+        let mut events = process.events.lock().unwrap();
+        for event in &events.history {
+            _ = sender.send(event.clone());
+        }
+        if events.finished_at.is_none() {
+            events.subscribers.push(sender);
+        }
+        Some(receiver)
+    }
+
+    /// kill the process if running & forget it, false if the request id is unknown
+    pub(crate) fn kill(&self, request_id: u32) -> bool {
+        match self.processes().remove(&request_id) {
+            Some(process) => {
+                process.stop.set();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// ids of the known processes along with their running state, sorted by the id
+    pub(crate) fn list(&self) -> Vec<(u32, bool)> {
+        let mut list: Vec<(u32, bool)> = self
+            .processes()
+            .iter()
+            .map(|(request_id, process)| {
+                let finished = process.events.lock().unwrap().finished_at.is_some();
+                (*request_id, !finished)
+            })
+            .collect();
+        list.sort_unstable();
+        list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::latch::Latch;
+    use crate::remote::{EventLog, RemoteProcess, RemoteProcesses, MAX_HISTORY_EVENTS};
+    use crate::{ProcessData, ProcessEvent, ProcessRequest};
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    pub fn test_remote_history_bound() {
+        let process = RemoteProcess {
+            stop: Latch::new(),
+            events: Mutex::new(EventLog {
+                history: VecDeque::new(),
+                subscribers: vec![],
+                finished_at: None,
+            }),
+        };
+        for event in 0..MAX_HISTORY_EVENTS + 2 {
+            process.publish(event);
+        }
+        let events = process.events.lock().unwrap();
+        assert_eq!(events.history.len(), MAX_HISTORY_EVENTS);
+        assert_eq!(events.history.front(), Some(&2));
+    }
+
+    #[test]
+    pub fn test_remote_finished_eviction() {
+        let processes = RemoteProcesses {
+            finished_ttl: Duration::ZERO,
+            ..RemoteProcesses::new(|event: &ProcessEvent, _: &ProcessData| *event)
+        };
+        processes
+            .start(ProcessRequest {
+                request_id: 515,
+                cmd_line: vec![vec![String::from("true")]],
+                ..Default::default()
+            })
+            .unwrap();
+        let events: Vec<ProcessEvent> = processes.subscribe(515).unwrap().into_iter().collect();
+        assert_eq!(events.last(), Some(&ProcessEvent::Exited));
+        // finished right after the last event is published
+        thread::sleep(Duration::from_millis(50));
+        assert!(processes.list().is_empty());
+        assert!(processes.subscribe(515).is_none());
+    }
+}
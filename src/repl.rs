@@ -0,0 +1,285 @@
+use crate::{check_and_trigger_callback, ProcessData, ProcessEvent, ProcessRequest, Termination};
+use duct::ReaderHandle;
+use regex::Regex;
+use std::io::{self, Read, Write};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// size of the reads of the output
+const CHUNK_SIZE: usize = 4096;
+
+/// Output of a snippet sent to the interpreter, see [`ReplDriver::send`]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ReplResponse {
+    /// Output lines before the next prompt, without the line breaks
+    pub lines: Vec<String>,
+    /// Time from sending the snippet till the prompt
+    pub duration: Duration,
+}
+
+impl ReplResponse {
+    /// Output lines joined with the line feeds
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+}
+
+/// Drives an interactive interpreter (e.g. `python3 -u -i`, `node -i`, `psql`, `sh -i`) started from the request: a
+/// snippet is written to its STDIN & its output (STDOUT & STDERR) is collected till the prompt, a regular expression
+/// matched against the last unfinished line (prompts usually have no line break) or a whole line, shows up again.
+/// Send one complete statement at a time, every input line gets its own prompt. The output lines are also emitted as
+/// the [`ProcessEvent::IOData`] events on the callback of the request, with [`ProcessEvent::Started`] at the start &
+/// [`ProcessEvent::Exited`] once the interpreter ends. Run the interpreter with its output unbuffered, otherwise the
+/// output may come after the prompt
+pub struct ReplDriver {
+    request: Arc<ProcessRequest>,
+    stdin: Option<io::PipeWriter>,
+    handle: Arc<ReaderHandle>,
+    chunks: Receiver<Vec<u8>>,
+    /// output received after the last complete line
+    pending: Vec<u8>,
+    prompt: Regex,
+    timeout: Duration,
+    banner: Vec<String>,
+    line_number: i64,
+    exited: bool,
+}
+
+impl ReplDriver {
+    /// Start the interpreter of the request & wait for its first prompt. Fails if the prompt is not a valid regular
+    /// expression, the interpreter could not be started or it doesn't prompt within the timeout, which also applies
+    /// to every snippet
+    pub fn new(request: ProcessRequest, prompt: &str, timeout: Duration) -> io::Result<Self> {
+        let prompt = Regex::new(prompt)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error.to_string()))?;
+        let request = Arc::new(request);
+        crate::policy::enforce(&request)?;
+        let (stdin_reader, stdin) = io::pipe()?;
+        let expression = crate::handle_pipeline(&request)?;
+        let handle = Arc::new(
+            expression
+                .stdin_file(stdin_reader)
+                .stderr_to_stdout()
+                .unchecked()
+                .reader()?,
+        );
+        let (sender, chunks) = mpsc::channel();
+        let reader = Arc::clone(&handle);
+        // detached, it ends with the output of the interpreter
+        thread::spawn(move || {
+            let _thread = crate::diagnostics::track_thread();
+            let mut buffer = [0; CHUNK_SIZE];
+            while let Ok(read @ 1..) = (&*reader).read(&mut buffer) {
+                if sender.send(buffer[..read].to_vec()).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut driver = Self {
+            request,
+            stdin: Some(stdin),
+            handle,
+            chunks,
+            pending: vec![],
+            prompt,
+            timeout,
+            banner: vec![],
+            line_number: 0,
+            exited: false,
+        };
+        let mut process_data = ProcessData::new();
+        process_data.request = Some(Arc::clone(&driver.request));
+        check_and_trigger_callback(&driver.request, &ProcessEvent::Started, &process_data);
+        driver.banner = driver.read_till_prompt()?;
+        Ok(driver)
+    }
+
+    /// Output of the interpreter before its first prompt
+    pub fn banner(&self) -> &[String] {
+        &self.banner
+    }
+
+    /// Change the time to wait for the prompt after a snippet
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Send the snippet (a line break is appended) & return its output once the prompt is back. Fails with
+    /// [`io::ErrorKind::TimedOut`] if the prompt doesn't show up within the timeout & with
+    /// [`io::ErrorKind::UnexpectedEof`] if the interpreter has ended
+    pub fn send(&mut self, snippet: &str) -> io::Result<ReplResponse> {
+        let start = Instant::now();
+        let stdin = self.stdin.as_mut().ok_or_else(interpreter_ended)?;
+        stdin.write_all(snippet.trim_end_matches('\n').as_bytes())?;
+        stdin.write_all(b"\n")?;
+        stdin.flush()?;
+        let lines = self.read_till_prompt()?;
+        Ok(ReplResponse {
+            lines,
+            duration: start.elapsed(),
+        })
+    }
+
+    /// Close the STDIN of the interpreter & wait for it to end (within the timeout), returns its exit code
+    pub fn close(mut self) -> io::Result<Option<i32>> {
+        self.stdin = None;
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            match self.next_chunk(deadline) {
+                Ok(chunk) => self.pending.extend(chunk),
+                Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(error) => return Err(error),
+            }
+        }
+        let leftover = String::from_utf8_lossy(&std::mem::take(&mut self.pending)).into_owned();
+        for line in leftover.lines() {
+            if !self.prompt.is_match(line) {
+                self.emit_line(line);
+            }
+        }
+        Ok(self.finish())
+    }
+
+    /// collect the output lines till the prompt
+    fn read_till_prompt(&mut self) -> io::Result<Vec<String>> {
+        let deadline = Instant::now() + self.timeout;
+        let mut lines = vec![];
+        loop {
+            while let Some(end) = self.pending.iter().position(|byte| *byte == b'\n') {
+                let line: Vec<u8> = self.pending.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                let line = line.trim_end_matches(['\n', '\r']);
+                if self.prompt.is_match(line) {
+                    return Ok(lines);
+                }
+                self.emit_line(line);
+                lines.push(line.to_string());
+            }
+            if !self.pending.is_empty()
+                && self
+                    .prompt
+                    .is_match(&String::from_utf8_lossy(&self.pending))
+            {
+                self.pending.clear();
+                return Ok(lines);
+            }
+            match self.next_chunk(deadline) {
+                Ok(chunk) => self.pending.extend(chunk),
+                Err(error) => {
+                    if error.kind() == io::ErrorKind::UnexpectedEof {
+                        self.finish();
+                    }
+                    return Err(error);
+                }
+            }
+        }
+    }
+
+    /// next output of the interpreter, waiting till the deadline
+    fn next_chunk(&mut self, deadline: Instant) -> io::Result<Vec<u8>> {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match self.chunks.recv_timeout(remaining) {
+            Ok(chunk) => Ok(chunk),
+            Err(RecvTimeoutError::Timeout) => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("No prompt within {:?}", self.timeout),
+            )),
+            Err(RecvTimeoutError::Disconnected) => {
+                self.stdin = None;
+                Err(interpreter_ended())
+            }
+        }
+    }
+
+    /// emit the output line
+    fn emit_line(&mut self, line: &str) {
+        self.line_number += 1;
+        let mut process_data = ProcessData::new();
+        process_data.request = Some(Arc::clone(&self.request));
+        process_data.line_number = self.line_number;
+        process_data.line = format!("{}\n", line);
+        check_and_trigger_callback(&self.request, &ProcessEvent::IOData, &process_data);
+    }
+
+    /// emit the exit of the interpreter once its output has ended, returns its exit code
+    fn finish(&mut self) -> Option<i32> {
+        let exit_code = match self.handle.try_wait() {
+            Ok(Some(output)) => output.status.code(),
+            _ => None,
+        };
+        if !self.exited {
+            self.exited = true;
+            let mut process_data = ProcessData::new();
+            process_data.request = Some(Arc::clone(&self.request));
+            process_data.line_number = self.line_number;
+            process_data.termination = exit_code.map(|code| Termination::Exited { code });
+            check_and_trigger_callback(&self.request, &ProcessEvent::Exited, &process_data);
+        }
+        exit_code
+    }
+}
+
+impl Drop for ReplDriver {
+    fn drop(&mut self) {
+        if !self.exited {
+            _ = self.handle.kill();
+        }
+    }
+}
+
+/// error of a snippet after the interpreter has ended
+fn interpreter_ended() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "Interpreter has ended")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ProcessData, ProcessEvent, ProcessRequest, ProcessResult, ReplDriver};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_repl_driver() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let recorded = Arc::clone(&events);
+        let callback = move |event: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            recorded
+                .lock()
+                .unwrap()
+                .push((*event, data.line_to_owned()));
+            ProcessResult::new()
+        };
+        let mut repl = ReplDriver::new(
+            ProcessRequest {
+                request_id: 280,
+                cmd_line: vec![vec![String::from("sh"), String::from("-i")]],
+                env: [(String::from("PS1"), String::from("repl> "))].into(),
+                callback: Some(Arc::new(callback)),
+                ..Default::default()
+            },
+            "^repl> $",
+            Duration::from_secs(10),
+        )
+        .unwrap();
+
+        let response = repl.send("echo one; echo two").unwrap();
+        assert_eq!(response.lines, ["one", "two"]);
+        assert_eq!(repl.send("x=5").unwrap().lines, Vec::<String>::new());
+        assert_eq!(repl.send("echo $((x * 2))").unwrap().text(), "10");
+        repl.set_timeout(Duration::from_millis(200));
+        assert_eq!(
+            repl.send("sleep 2").unwrap_err().kind(),
+            std::io::ErrorKind::TimedOut
+        );
+        repl.set_timeout(Duration::from_secs(10));
+        assert_eq!(repl.close().unwrap(), Some(0));
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.first().unwrap().0, ProcessEvent::Started);
+        assert!(events.contains(&(ProcessEvent::IOData, String::from("10"))));
+        assert_eq!(events.last().unwrap().0, ProcessEvent::Exited);
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{ProcessRequest, ProcessResult};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Distribution of the execution times (including the retries), nearest-rank percentiles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuntimePercentiles {
+    /// Number of the executions
+    pub count: usize,
+    /// Shortest execution
+    pub min: Duration,
+    /// Median execution time
+    pub p50: Duration,
+    /// 90th percentile of the execution times
+    pub p90: Duration,
+    /// 99th percentile of the execution times
+    pub p99: Duration,
+    /// Longest execution
+    pub max: Duration,
+}
+
+impl RuntimePercentiles {
+    /// percentiles of the execution times, None if there are none
+    fn of(mut durations: Vec<Duration>) -> Option<Self> {
+        durations.sort();
+        let percentile = |percent: usize| {
+            let rank = (percent * durations.len()).div_ceil(100).max(1);
+            durations[rank - 1]
+        };
+        Some(Self {
+            count: durations.len(),
+            min: *durations.first()?,
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max: *durations.last()?,
+        })
+    }
+}
+
+/// Executions & failures of a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CommandStats {
+    /// Number of the executions
+    pub runs: u64,
+    /// Number of the executions which failed to start or failed
+    pub failures: u64,
+}
+
+impl CommandStats {
+    /// Share of the failed executions from 0 to 1
+    pub fn failure_rate(&self) -> f64 {
+        if self.runs == 0 {
+            return 0.0;
+        }
+        self.failures as f64 / self.runs as f64
+    }
+}
+
+/// Capacity planning report of a batch ([`crate::BatchSummary::report`]) or a pool ([`crate::ProcessPool::report`])
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BatchReport {
+    /// Distribution of the execution times, None if nothing ran
+    pub runtimes: Option<RuntimePercentiles>,
+    /// Executions & failures by the command line (the pipeline commands joined with ` | `)
+    pub commands: BTreeMap<String, CommandStats>,
+    /// Most executions running at the same time
+    pub max_concurrency: usize,
+}
+
+/// Collects the executions of a batch or a pool for its report
+#[derive(Default)]
+pub(crate) struct ReportCollector {
+    durations: Mutex<Vec<Duration>>,
+    commands: Mutex<BTreeMap<String, CommandStats>>,
+    running: AtomicUsize,
+    max_running: AtomicUsize,
+}
+
+/// An execution counted as running till it's dropped
+pub(crate) struct RunningExecution<'a>(&'a ReportCollector);
+
+impl Drop for RunningExecution<'_> {
+    fn drop(&mut self) {
+        self.0.running.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl ReportCollector {
+    /// count an execution as running till the returned guard is dropped
+    pub(crate) fn begin(&self) -> RunningExecution<'_> {
+        let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_running.fetch_max(running, Ordering::SeqCst);
+        RunningExecution(self)
+    }
+
+    /// record the completed execution of the command
+    pub(crate) fn record(&self, command: &str, duration: Duration, result: &ProcessResult) {
+        self.durations.lock().unwrap().push(duration);
+        let mut commands = self.commands.lock().unwrap();
+        let stats = commands.entry(command.to_string()).or_default();
+        stats.runs += 1;
+        if !result.success.as_ref().is_ok_and(|success| *success) {
+            stats.failures += 1;
+        }
+    }
+
+    /// report of the executions recorded so far
+    pub(crate) fn report(&self) -> BatchReport {
+        BatchReport {
+            runtimes: RuntimePercentiles::of(self.durations.lock().unwrap().clone()),
+            commands: self.commands.lock().unwrap().clone(),
+            max_concurrency: self.max_running.load(Ordering::SeqCst),
+        }
+    }
+}
+
+/// command line of the request to group its executions by
+pub(crate) fn command_line(request: &ProcessRequest) -> String {
+    request
+        .pipeline_stages()
+        .iter()
+        .map(|stage| stage.argv.join(" "))
+        .collect::<Vec<String>>()
+        .join(" | ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::report::RuntimePercentiles;
+    use std::time::Duration;
+
+    #[test]
+    pub fn test_runtime_percentiles() {
+        assert_eq!(RuntimePercentiles::of(vec![]), None);
+        let durations = (1..=200).rev().map(Duration::from_millis).collect();
+        let percentiles = RuntimePercentiles::of(durations).unwrap();
+        assert_eq!(percentiles.count, 200);
+        assert_eq!(percentiles.min, Duration::from_millis(1));
+        assert_eq!(percentiles.p50, Duration::from_millis(100));
+        assert_eq!(percentiles.p90, Duration::from_millis(180));
+        assert_eq!(percentiles.p99, Duration::from_millis(198));
+        assert_eq!(percentiles.max, Duration::from_millis(200));
+    }
+}
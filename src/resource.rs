@@ -0,0 +1,173 @@
+use crate::latch::Latch;
+use crate::termination::KillRecord;
+use crate::{check_and_trigger_callback, ProcessData, ProcessEvent, ProcessRequest};
+use duct::ReaderHandle;
+use std::collections::HashMap;
+use std::io;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Resource usage of the process, for a pipeline it's the sum of all the running commands
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ResourceUsage {
+    /// CPU usage in percentage of a single core since the previous sample
+    pub cpu_percent: f64,
+    /// Resident (physical) memory in bytes
+    pub rss_bytes: u64,
+}
+
+impl ResourceUsage {
+    /// keep the max of each field
+    fn max(self, other: ResourceUsage) -> ResourceUsage {
+        ResourceUsage {
+            cpu_percent: self.cpu_percent.max(other.cpu_percent),
+            rss_bytes: self.rss_bytes.max(other.rss_bytes),
+        }
+    }
+}
+
+/// Keeps the previous cpu times to calculate the CPU% between two samples
+struct ResourceSampler {
+    cpu_times: HashMap<u32, Duration>,
+    sampled_at: Instant,
+}
+
+impl ResourceSampler {
+    fn new() -> Self {
+        Self {
+            cpu_times: HashMap::new(),
+            sampled_at: Instant::now(),
+        }
+    }
+
+    /// sample all the pids, pids which are already gone are skipped
+    fn sample(&mut self, pids: &[u32]) -> io::Result<ResourceUsage> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.sampled_at).as_secs_f64();
+        let mut usage = ResourceUsage::default();
+        let mut cpu_times = HashMap::new();
+        let mut last_error = None;
+        for pid in pids {
+            match read_process_usage(*pid) {
+                Ok((cpu_time, rss_bytes)) => {
+                    let previous = self.cpu_times.get(pid).copied().unwrap_or_default();
+                    if elapsed > 0.0 {
+                        usage.cpu_percent +=
+                            cpu_time.saturating_sub(previous).as_secs_f64() / elapsed * 100.0;
+                    }
+                    usage.rss_bytes += rss_bytes;
+                    cpu_times.insert(*pid, cpu_time);
+                }
+                Err(error) => last_error = Some(error),
+            }
+        }
+        self.cpu_times = cpu_times;
+        self.sampled_at = now;
+        match last_error {
+            Some(error) if self.cpu_times.is_empty() => Err(error),
+            _ => Ok(usage),
+        }
+    }
+}
+
+/// Sample the process at the given interval until the execution is done, returns the peak values
+pub(crate) fn run_sampler(
+    request: &Arc<ProcessRequest>,
+    reader: &ReaderHandle,
+    kill_record: &KillRecord,
+    started: Instant,
+    interval: Duration,
+    done: &Latch,
+) -> Option<ResourceUsage> {
+    let mut sampler = ResourceSampler::new();
+    let mut peak: Option<ResourceUsage> = None;
+    let mut process_data = ProcessData::for_execution(request, reader, kill_record, started);
+    while !done.wait_timeout(interval) {
+        if let Ok(usage) = sampler.sample(&reader.pids()) {
+            peak = Some(peak.map_or(usage, |peak| peak.max(usage)));
+            process_data.resource_usage = Some(usage);
+            check_and_trigger_callback(request, &ProcessEvent::ResourceSample, &process_data);
+        }
+    }
+    peak
+}
+
+/// read the total cpu time & rss of a process using procfs
+#[cfg(target_os = "linux")]
+fn read_process_usage(pid: u32) -> io::Result<(Duration, u64)> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid))?;
+    // skip the pid & command name, the command name may contain spaces & brackets
+    let fields: Vec<&str> = stat
+        .rsplit_once(')')
+        .map(|(_, fields)| fields.split_whitespace().collect())
+        .unwrap_or_default();
+    let field = |index: usize| -> io::Result<u64> {
+        fields
+            .get(index)
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid /proc stat data"))
+    };
+    // fields are 1 based in proc(5) and we start from the 3rd one(state)
+    let cpu_ticks = field(11)? + field(12)?;
+    let rss_pages = field(21)?;
+    let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) }.max(1) as f64;
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.max(1) as u64;
+    Ok((
+        Duration::from_secs_f64(cpu_ticks as f64 / ticks_per_second),
+        rss_pages * page_size,
+    ))
+}
+
+/// read the total cpu time & working set of a process
+#[cfg(windows)]
+fn read_process_usage(pid: u32) -> io::Result<(Duration, u64)> {
+    use windows_sys::Win32::Foundation::{CloseHandle, FILETIME};
+    use windows_sys::Win32::System::ProcessStatus::{
+        GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS,
+    };
+    use windows_sys::Win32::System::Threading::{
+        GetProcessTimes, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_VM_READ,
+    };
+
+    let filetime_to_duration = |time: &FILETIME| {
+        let ticks = ((time.dwHighDateTime as u64) << 32) | time.dwLowDateTime as u64;
+        // FILETIME is in 100 nanoseconds unit
+        Duration::from_nanos(ticks * 100)
+    };
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION | PROCESS_VM_READ, 0, pid);
+        if handle.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        let empty = FILETIME {
+            dwLowDateTime: 0,
+            dwHighDateTime: 0,
+        };
+        let (mut creation, mut exit, mut kernel, mut user) = (empty, empty, empty, empty);
+        let mut counters: PROCESS_MEMORY_COUNTERS = std::mem::zeroed();
+        counters.cb = std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+        let result = if GetProcessTimes(handle, &mut creation, &mut exit, &mut kernel, &mut user)
+            == 0
+            || GetProcessMemoryInfo(handle, &mut counters, counters.cb) == 0
+        {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok((
+                filetime_to_duration(&kernel) + filetime_to_duration(&user),
+                counters.WorkingSetSize as u64,
+            ))
+        };
+        CloseHandle(handle);
+        result
+    }
+}
+
+/// resource sampling is not available on this platform
+#[cfg(not(any(target_os = "linux", windows)))]
+fn read_process_usage(_pid: u32) -> io::Result<(Duration, u64)> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Resource sampling is not supported on this platform",
+    ))
+}
@@ -0,0 +1,191 @@
+use crate::cancel;
+use crate::latch::Latch;
+use crate::{
+    check_and_trigger_callback, start_process, ExecutionRecord, ProcessData, ProcessEvent,
+    ProcessRequest, ProcessResult, WarningKind,
+};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Delay strategy between two attempts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Backoff {
+    /// Same delay before every attempt
+    Fixed(Duration),
+    /// Delay doubles after every attempt starting from `initial`, capped at `max`
+    Exponential {
+        /// Delay before the first retry
+        initial: Duration,
+        /// Upper bound of the delay
+        max: Duration,
+    },
+}
+
+impl Backoff {
+    /// Delay before the given retry, 1 for the first retry
+    pub fn delay(&self, retry: u32) -> Duration {
+        match *self {
+            Backoff::Fixed(delay) => delay,
+            Backoff::Exponential { initial, max } => {
+                let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+                initial.saturating_mul(factor).min(max)
+            }
+        }
+    }
+}
+
+/// Which failures should be retried
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RetryOn {
+    /// Only when the process could not be spawned
+    StartError,
+    /// When the process could not be started or failed, see [`ProcessResult::success`]
+    Failure,
+    /// When the process could not be started or exited with one of these exit codes
+    ExitCodes(Vec<i32>),
+}
+
+/// Retry policy of a request, see [`ProcessRequest::retry`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RetryPolicy {
+    /// Max number of attempts including the first one
+    pub max_attempts: u32,
+    /// Delay between the attempts
+    pub backoff: Backoff,
+    /// Failures to retry on
+    pub retry_on: RetryOn,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            backoff: Backoff::Fixed(Duration::from_secs(1)),
+            retry_on: RetryOn::Failure,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// check if the result of an attempt is a failure to retry on
+    fn should_retry(&self, result: &ProcessResult) -> bool {
+        if result.spawn_failed {
+            return true;
+        }
+        // a dry run or an invalid request fails the same way on every attempt
+        if !result.spawned {
+            return false;
+        }
+        match (&self.retry_on, result.exit_code) {
+            (RetryOn::StartError, _) => false,
+            (RetryOn::Failure, _) => result.success.as_ref().is_ok_and(|success| !success),
+            (RetryOn::ExitCodes(exit_codes), Some(exit_code)) => exit_codes.contains(&exit_code),
+            (RetryOn::ExitCodes(_), None) => false,
+        }
+    }
+}
+
+/// Run the process and retry it as per the retry policy of the request, till the stop latch is set. The execution is
+/// stored in the history of the request
+pub(crate) fn start_process_with_retry(
+    request: Arc<ProcessRequest>,
+    stop: Option<&Latch>,
+) -> ProcessResult {
+    let started_at = SystemTime::now();
+    let request = crate::trace_context::resolve(request);
+    let admitted =
+        crate::policy::enforce(&request).and_then(|()| crate::quota::acquire(Arc::clone(&request)));
+    let result = match admitted {
+        Ok((request, quota_slot)) => {
+            let result = run_attempts(&request, stop);
+            if let Some(quota_slot) = quota_slot {
+                quota_slot.complete(&result);
+            }
+            result
+        }
+        Err(error) => {
+            let mut result = ProcessResult::new();
+            result.success = Err(error);
+            result
+        }
+    };
+    if let Some(history) = request.history.as_ref() {
+        if let Err(error) = history.record(ExecutionRecord::new(&request, started_at, &result)) {
+            let mut process_data = ProcessData::new();
+            process_data.request = Some(Arc::clone(&request));
+            process_data.line.push_str(&error.to_string());
+            check_and_trigger_callback(
+                &request,
+                &ProcessEvent::Warning(WarningKind::SinkWriteFailed),
+                &process_data,
+            );
+        }
+    }
+    result
+}
+
+/// run the attempts of the process as per the retry policy
+fn run_attempts(request: &Arc<ProcessRequest>, stop: Option<&Latch>) -> ProcessResult {
+    let started = Instant::now();
+    if cancel::is_cancelled(request) {
+        return cancel::cancelled_before_spawn(request);
+    }
+    let mut attempt = 1;
+    loop {
+        let mut result = start_process(Arc::clone(request), stop);
+        result.attempts = attempt;
+        result.duration = Some(started.elapsed());
+        if result.expectation_failed {
+            result.success = Ok(false);
+        } else if result.success_line.is_some() {
+            result.success = Ok(true);
+        } else if result.should_exit.is_none() {
+            result.success = Ok(request.is_success_exit_code(result.exit_code));
+        }
+        result.exit_category = request
+            .exit_category(result.termination.as_ref())
+            .map(String::from);
+        let policy = match &request.retry {
+            Some(policy)
+                if attempt < policy.max_attempts
+                    && policy.should_retry(&result)
+                    && !stop.is_some_and(Latch::is_set)
+                    && !cancel::is_cancelled(request) =>
+            {
+                policy
+            }
+            _ => return result,
+        };
+        let delay = policy.backoff.delay(attempt);
+        attempt += 1;
+        let mut process_data = ProcessData::new();
+        process_data.request = Some(Arc::clone(request));
+        process_data.line.push_str(
+            format!(
+                "Attempt {} of {} in {} ms",
+                attempt,
+                policy.max_attempts,
+                delay.as_millis()
+            )
+            .as_str(),
+        );
+        check_and_trigger_callback(request, &ProcessEvent::RetryScheduled, &process_data);
+        match stop {
+            Some(stop) if stop.wait_timeout(delay) => return result,
+            Some(_) => {}
+            None => thread::sleep(delay),
+        }
+        if cancel::is_cancelled(request) {
+            return result;
+        }
+        process_data.line.clear();
+        process_data
+            .line
+            .push_str(format!("Attempt {} of {}", attempt, policy.max_attempts).as_str());
+        check_and_trigger_callback(request, &ProcessEvent::RetryStarted, &process_data);
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{ProcessData, ProcessEvent, ProcessRequest, ProcessResult, Termination};
+use std::io;
+use std::sync::{Arc, Mutex};
+
+/// Captured output & exit status of a one-shot command, see [`run_shell`] & [`run_cmd`]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CommandOutput {
+    /// Output lines of the STDOUT & STDERR without the line breaks
+    pub lines: Vec<String>,
+    /// Exit code of the process, None if it was terminated by a signal
+    pub exit_code: Option<i32>,
+    /// How the process terminated
+    pub termination: Option<Termination>,
+}
+
+impl CommandOutput {
+    /// Process exited with exit code 0
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+
+    /// Output lines joined with the line feeds
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+}
+
+/// Run the command line in the shell till it completes & capture its output, e.g. `run_shell("du -sh /var | sort -h")`.
+/// Fails if the process could not be started
+pub fn run_shell(command_line: &str) -> io::Result<CommandOutput> {
+    run_request(ProcessRequest {
+        use_shell: true,
+        cmd_line: vec![vec![command_line.to_string()]],
+        ..Default::default()
+    })
+}
+
+/// Run the executable with the arguments till it completes & capture its output, e.g. `run_cmd(["git", "status"])`.
+/// Fails if the process could not be started
+pub fn run_cmd<I, S>(argv: I) -> io::Result<CommandOutput>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    run_request(ProcessRequest {
+        cmd_line: vec![argv.into_iter().map(Into::into).collect()],
+        ..Default::default()
+    })
+}
+
+/// run the request in the blocking mode capturing the output lines
+pub(crate) fn run_request(mut request: ProcessRequest) -> io::Result<CommandOutput> {
+    let lines = Arc::new(Mutex::new(vec![]));
+    let start_error = Arc::new(Mutex::new(None));
+    let (captured, failed) = (Arc::clone(&lines), Arc::clone(&start_error));
+    request.callback = Some(Arc::new(
+        move |event: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            match event {
+                ProcessEvent::IOData => captured.lock().unwrap().push(data.line_to_owned()),
+                ProcessEvent::StartError => *failed.lock().unwrap() = Some(data.line_to_owned()),
+                _ => {}
+            }
+            ProcessResult::new()
+        },
+    ));
+    let result = ProcessRequest::start(request);
+    if let Some(error) = start_error.lock().unwrap().take() {
+        return Err(io::Error::other(error));
+    }
+    let lines = std::mem::take(&mut *lines.lock().unwrap());
+    Ok(CommandOutput {
+        lines,
+        exit_code: result.exit_code,
+        termination: result.termination,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{run_cmd, run_shell};
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_run_shell_and_cmd() {
+        let output = run_shell("printf 'b\\na\\n' | sort; exit 2").unwrap();
+        assert_eq!(output.lines, ["a", "b"]);
+        assert_eq!(output.exit_code, Some(2));
+        assert!(!output.success());
+        let output = run_cmd(["echo", "one", "two"]).unwrap();
+        assert!(output.success());
+        assert_eq!(output.text(), "one two");
+        assert!(run_cmd(["pes-missing-executable"]).is_err());
+    }
+}
@@ -0,0 +1,201 @@
+use crate::latch::Latch;
+use crate::notifier::send_notification;
+use crate::retry::start_process_with_retry;
+use crate::{CronSchedule, NotificationKind, ProcessRequest};
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+/// When the [`Scheduler`] should run the request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schedule {
+    /// Run at a fixed rate, the first run starts immediately. A run which overruns the interval delays the next one
+    Interval(Duration),
+    /// Run at the times matching the cron expression
+    Cron(CronSchedule),
+}
+
+impl Schedule {
+    /// delay till the next run, None if there are no more runs
+    fn next_delay(&self, last_run: Option<Instant>) -> Option<Duration> {
+        match self {
+            Schedule::Interval(interval) => Some(last_run.map_or(Duration::ZERO, |last_run| {
+                interval.saturating_sub(last_run.elapsed())
+            })),
+            Schedule::Cron(cron) => {
+                let now = SystemTime::now();
+                let next = cron.next_after(now)?;
+                Some(next.duration_since(now).unwrap_or_default())
+            }
+        }
+    }
+}
+
+/// Shared state between the [`Scheduler`] and its thread
+struct SchedulerState {
+    schedule: Mutex<(Schedule, bool)>,
+    condvar: Condvar,
+    /// kills the running process on stop
+    stop: Latch,
+    runs: Arc<AtomicU64>,
+}
+
+/// Runs a request repeatedly as per the [`Schedule`], one run at a time in its own thread.
+/// Events of every run are delivered through the callback of the request, see [`ProcessRequest::run_sequence`]
+pub struct Scheduler {
+    request: ProcessRequest,
+    schedule: Schedule,
+    runs: Arc<AtomicU64>,
+    state: Option<Arc<SchedulerState>>,
+    join_handle: Option<JoinHandle<()>>,
+}
+
+impl Scheduler {
+    /// Create a scheduler for the request, call [`Scheduler::start`] to start the runs
+    pub fn new(process_request: ProcessRequest, schedule: Schedule) -> Self {
+        Self {
+            request: process_request,
+            schedule,
+            runs: Arc::new(AtomicU64::new(0)),
+            state: None,
+            join_handle: None,
+        }
+    }
+
+    /// Start running the request as per the schedule, no-op if it's already started
+    pub fn start(&mut self) -> io::Result<()> {
+        if self.state.is_some() {
+            return Ok(());
+        }
+        let state = Arc::new(SchedulerState {
+            schedule: Mutex::new((self.schedule.clone(), false)),
+            condvar: Condvar::new(),
+            stop: Latch::new(),
+            runs: Arc::clone(&self.runs),
+        });
+        let request = self.request.clone();
+        let thread_state = Arc::clone(&state);
+        self.join_handle = Some(
+            thread::Builder::new()
+                .name(format!("pes_sc_rq_{}", request.request_id))
+                .spawn(move || run_schedule(request, &thread_state))?,
+        );
+        self.state = Some(state);
+        Ok(())
+    }
+
+    /// Stop the runs, kills the running process if any and waits for the scheduler thread to finish
+    pub fn stop(&mut self) {
+        if let Some(state) = self.state.take() {
+            state.schedule.lock().unwrap().1 = true;
+            state.stop.set();
+            state.condvar.notify_all();
+        }
+        if let Some(join_handle) = self.join_handle.take() {
+            _ = join_handle.join();
+        }
+    }
+
+    /// Change the schedule, the running process (if any) is not affected
+    pub fn reschedule(&mut self, schedule: Schedule) {
+        if let Some(state) = self.state.as_ref() {
+            state.schedule.lock().unwrap().0 = schedule.clone();
+            state.condvar.notify_all();
+        }
+        self.schedule = schedule;
+    }
+
+    /// Number of runs started so far
+    pub fn runs(&self) -> u64 {
+        self.runs.load(Ordering::SeqCst)
+    }
+
+    /// Scheduler is started and not yet stopped
+    pub fn is_running(&self) -> bool {
+        self.join_handle
+            .as_ref()
+            .is_some_and(|join_handle| !join_handle.is_finished())
+    }
+}
+
+impl Drop for Scheduler {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// wait for the next run as per the current schedule & run the request, till stopped
+fn run_schedule(request: ProcessRequest, state: &SchedulerState) {
+    let mut last_run = None;
+    loop {
+        let mut schedule = state.schedule.lock().unwrap();
+        // recompute the delay whenever it's rescheduled
+        loop {
+            if schedule.1 {
+                return;
+            }
+            let Some(delay) = schedule.0.next_delay(last_run) else {
+                return;
+            };
+            if delay.is_zero() {
+                break;
+            }
+            let (guard, timeout) = state.condvar.wait_timeout(schedule, delay).unwrap();
+            schedule = guard;
+            if timeout.timed_out() && !schedule.1 {
+                break;
+            }
+        }
+        drop(schedule);
+        last_run = Some(Instant::now());
+        let mut run_request = request.clone();
+        run_request.run_sequence = state.runs.fetch_add(1, Ordering::SeqCst) + 1;
+        let run_request = Arc::new(run_request);
+        let result = start_process_with_retry(Arc::clone(&run_request), Some(&state.stop));
+        if !state.stop.is_set() && !result.success.as_ref().is_ok_and(|success| *success) {
+            send_notification(
+                &run_request,
+                NotificationKind::Failed,
+                &result,
+                String::new(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ProcessData, ProcessEvent, ProcessRequest, ProcessResult, Schedule, Scheduler};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    pub fn test_scheduler_interval() {
+        static LAST_RUN: AtomicU64 = AtomicU64::new(0);
+        let callback = |status: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            if let ProcessEvent::Exited = status {
+                let run_sequence = data.request.as_ref().unwrap().run_sequence;
+                LAST_RUN.store(run_sequence, Ordering::SeqCst);
+            }
+            ProcessResult::new()
+        };
+        let mut scheduler = Scheduler::new(
+            ProcessRequest {
+                request_id: 321,
+                callback: Some(Arc::new(callback)),
+                cmd_line: vec![vec![String::from("echo"), String::from("tick")]],
+                ..Default::default()
+            },
+            Schedule::Interval(Duration::from_millis(50)),
+        );
+        scheduler.start().unwrap();
+        std::thread::sleep(Duration::from_millis(400));
+        scheduler.stop();
+        assert!(!scheduler.is_running());
+        assert!(scheduler.runs() >= 3);
+        assert_eq!(LAST_RUN.load(Ordering::SeqCst), scheduler.runs());
+    }
+}
@@ -0,0 +1,354 @@
+use duct::Expression;
+use std::io;
+
+/// Syscalls denied by the baseline of the [`SyscallFilter`]: debugging other processes, mounts, kernel modules &
+/// other system administration
+pub const BASELINE_DENIED_SYSCALLS: &[&str] = &[
+    "ptrace",
+    "process_vm_readv",
+    "process_vm_writev",
+    "mount",
+    "umount2",
+    "pivot_root",
+    "chroot",
+    "unshare",
+    "setns",
+    "init_module",
+    "finit_module",
+    "delete_module",
+    "kexec_load",
+    "kexec_file_load",
+    "reboot",
+    "swapon",
+    "swapoff",
+    "acct",
+    "settimeofday",
+    "clock_settime",
+    "sethostname",
+    "setdomainname",
+    "bpf",
+    "perf_event_open",
+    "userfaultfd",
+    "open_by_handle_at",
+    "keyctl",
+    "add_key",
+    "request_key",
+    "quotactl",
+    "syslog",
+];
+
+/// Seccomp filter installed in the process before exec, the denied syscalls fail with `EPERM`. Linux only (x86_64 &
+/// aarch64). It sets `no_new_privs`, so setuid executables (e.g. `sudo`) don't gain the privileges
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct SyscallFilter {
+    /// Deny the [`BASELINE_DENIED_SYSCALLS`]
+    pub baseline: bool,
+    /// More syscalls to deny by name, e.g. `socket`
+    pub deny: Vec<String>,
+    /// Syscalls of the baseline to allow anyway, e.g. `ptrace` for a debugger
+    pub allow: Vec<String>,
+}
+
+impl Default for SyscallFilter {
+    fn default() -> Self {
+        Self {
+            baseline: true,
+            deny: vec![],
+            allow: vec![],
+        }
+    }
+}
+
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+mod bpf {
+    use super::{SyscallFilter, BASELINE_DENIED_SYSCALLS};
+    use libc::{sock_filter, BPF_ABS, BPF_JEQ, BPF_JGE, BPF_JMP, BPF_K, BPF_LD, BPF_RET, BPF_W};
+    use std::io;
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: u32 = 0xC000_003E;
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: u32 = 0xC000_00B7;
+    /// syscalls of the x32 ABI on x86_64, denied as they would bypass the filter
+    const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+    /// offsets in the `seccomp_data`
+    const NR_OFFSET: u32 = 0;
+    const ARCH_OFFSET: u32 = 4;
+
+    /// syscalls which can be denied by name
+    const SYSCALLS: &[(&str, libc::c_long)] = &[
+        ("ptrace", libc::SYS_ptrace),
+        ("process_vm_readv", libc::SYS_process_vm_readv),
+        ("process_vm_writev", libc::SYS_process_vm_writev),
+        ("mount", libc::SYS_mount),
+        ("umount2", libc::SYS_umount2),
+        ("pivot_root", libc::SYS_pivot_root),
+        ("chroot", libc::SYS_chroot),
+        ("unshare", libc::SYS_unshare),
+        ("setns", libc::SYS_setns),
+        ("init_module", libc::SYS_init_module),
+        ("finit_module", libc::SYS_finit_module),
+        ("delete_module", libc::SYS_delete_module),
+        ("kexec_load", libc::SYS_kexec_load),
+        ("kexec_file_load", libc::SYS_kexec_file_load),
+        ("reboot", libc::SYS_reboot),
+        ("swapon", libc::SYS_swapon),
+        ("swapoff", libc::SYS_swapoff),
+        ("acct", libc::SYS_acct),
+        ("settimeofday", libc::SYS_settimeofday),
+        ("clock_settime", libc::SYS_clock_settime),
+        ("sethostname", libc::SYS_sethostname),
+        ("setdomainname", libc::SYS_setdomainname),
+        ("bpf", libc::SYS_bpf),
+        ("perf_event_open", libc::SYS_perf_event_open),
+        ("userfaultfd", libc::SYS_userfaultfd),
+        ("open_by_handle_at", libc::SYS_open_by_handle_at),
+        ("keyctl", libc::SYS_keyctl),
+        ("add_key", libc::SYS_add_key),
+        ("request_key", libc::SYS_request_key),
+        ("quotactl", libc::SYS_quotactl),
+        ("syslog", libc::SYS_syslog),
+        ("socket", libc::SYS_socket),
+        ("socketpair", libc::SYS_socketpair),
+        ("connect", libc::SYS_connect),
+        ("bind", libc::SYS_bind),
+        ("listen", libc::SYS_listen),
+        ("accept4", libc::SYS_accept4),
+        ("execve", libc::SYS_execve),
+        ("execveat", libc::SYS_execveat),
+        ("clone", libc::SYS_clone),
+        ("clone3", libc::SYS_clone3),
+        ("kill", libc::SYS_kill),
+        ("tkill", libc::SYS_tkill),
+        ("tgkill", libc::SYS_tgkill),
+        ("setuid", libc::SYS_setuid),
+        ("setgid", libc::SYS_setgid),
+        ("setgroups", libc::SYS_setgroups),
+        ("fchown", libc::SYS_fchown),
+        ("fchownat", libc::SYS_fchownat),
+        ("fchmod", libc::SYS_fchmod),
+        ("fchmodat", libc::SYS_fchmodat),
+        ("unlinkat", libc::SYS_unlinkat),
+        ("renameat", libc::SYS_renameat),
+        ("mkdirat", libc::SYS_mkdirat),
+        ("truncate", libc::SYS_truncate),
+        ("ftruncate", libc::SYS_ftruncate),
+        ("memfd_create", libc::SYS_memfd_create),
+        ("prctl", libc::SYS_prctl),
+        ("ioctl", libc::SYS_ioctl),
+        ("uname", libc::SYS_uname),
+    ];
+
+    fn syscall_number(name: &str) -> io::Result<u32> {
+        SYSCALLS
+            .iter()
+            .find(|(syscall, _)| *syscall == name)
+            .map(|(_, number)| *number as u32)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Unknown syscall in the filter: {}", name),
+                )
+            })
+    }
+
+    fn statement(code: u32, k: u32) -> sock_filter {
+        sock_filter {
+            code: code as u16,
+            jt: 0,
+            jf: 0,
+            k,
+        }
+    }
+
+    fn jump(code: u32, k: u32, jt: u8, jf: u8) -> sock_filter {
+        sock_filter {
+            code: code as u16,
+            jt,
+            jf,
+            k,
+        }
+    }
+
+    /// compile the filter into a BPF program, the syscalls of another architecture kill the process
+    pub(super) fn compile(filter: &SyscallFilter) -> io::Result<Vec<sock_filter>> {
+        let mut denied = vec![];
+        let baseline = if filter.baseline {
+            BASELINE_DENIED_SYSCALLS
+        } else {
+            &[]
+        };
+        let names = baseline
+            .iter()
+            .copied()
+            .filter(|name| !filter.allow.iter().any(|allowed| allowed == name))
+            .chain(filter.deny.iter().map(String::as_str));
+        for name in names {
+            denied.push(syscall_number(name)?);
+        }
+        for name in &filter.allow {
+            syscall_number(name)?;
+        }
+        denied.sort_unstable();
+        denied.dedup();
+        let deny = statement(
+            BPF_RET | BPF_K,
+            libc::SECCOMP_RET_ERRNO | libc::EPERM as u32,
+        );
+        let mut program = vec![
+            statement(BPF_LD | BPF_W | BPF_ABS, ARCH_OFFSET),
+            jump(BPF_JMP | BPF_JEQ | BPF_K, AUDIT_ARCH, 1, 0),
+            statement(BPF_RET | BPF_K, libc::SECCOMP_RET_KILL_PROCESS),
+            statement(BPF_LD | BPF_W | BPF_ABS, NR_OFFSET),
+        ];
+        if cfg!(target_arch = "x86_64") {
+            program.push(jump(BPF_JMP | BPF_JGE | BPF_K, X32_SYSCALL_BIT, 0, 1));
+            program.push(deny);
+        }
+        for number in denied {
+            program.push(jump(BPF_JMP | BPF_JEQ | BPF_K, number, 0, 1));
+            program.push(deny);
+        }
+        program.push(statement(BPF_RET | BPF_K, libc::SECCOMP_RET_ALLOW));
+        Ok(program)
+    }
+
+    /// install the program, runs in the child after fork so must not allocate
+    pub(super) fn install(program: &[sock_filter]) -> io::Result<()> {
+        let program = libc::sock_fprog {
+            len: program.len() as u16,
+            filter: program.as_ptr() as *mut sock_filter,
+        };
+        unsafe {
+            if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0
+                || libc::prctl(
+                    libc::PR_SET_SECCOMP,
+                    libc::SECCOMP_MODE_FILTER,
+                    &program as *const libc::sock_fprog,
+                ) != 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Install the filter in all the commands of the expression right before exec, it's applied to the innermost expression
+/// so its hook runs after the ones of all the other spawn options
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+pub(crate) fn apply_syscall_filter(
+    expression: Expression,
+    filter: Option<&SyscallFilter>,
+) -> io::Result<Expression> {
+    use std::os::unix::process::CommandExt;
+
+    let Some(filter) = filter else {
+        return Ok(expression);
+    };
+    let program = std::sync::Arc::new(bpf::compile(filter)?);
+    Ok(expression.before_spawn(move |command| {
+        let program = std::sync::Arc::clone(&program);
+        unsafe {
+            command.pre_exec(move || bpf::install(&program));
+        }
+        Ok(())
+    }))
+}
+
+/// Seccomp is not available on this platform
+#[cfg(not(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+)))]
+pub(crate) fn apply_syscall_filter(
+    expression: Expression,
+    filter: Option<&SyscallFilter>,
+) -> io::Result<Expression> {
+    if filter.is_none() {
+        return Ok(expression);
+    }
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Syscall filter is supported on Linux x86_64 & aarch64 only",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::run::run_request;
+    use crate::{ProcessRequest, SyscallFilter};
+
+    fn run(request_id: u32, command: &str, filter: SyscallFilter) -> std::io::Result<Vec<String>> {
+        run_request(ProcessRequest {
+            request_id,
+            use_shell: true,
+            cmd_line: vec![vec![String::from(command)]],
+            syscall_filter: Some(filter),
+            ..Default::default()
+        })
+        .map(|output| output.lines)
+    }
+
+    #[cfg(all(
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    #[test]
+    pub fn test_syscall_filter() {
+        let lines = run(
+            274,
+            "grep Seccomp: /proc/self/status; uname >/dev/null 2>&1 || echo denied",
+            SyscallFilter {
+                deny: vec![String::from("uname")],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(lines, ["Seccomp:\t2", "denied"]);
+        let unknown = SyscallFilter {
+            allow: vec![String::from("no_such_syscall")],
+            ..Default::default()
+        };
+        assert!(run(275, "true", unknown).is_err());
+    }
+
+    #[cfg(all(
+        feature = "namespaces",
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    #[test]
+    pub fn test_syscall_filter_with_namespaces() {
+        let request = |request_id, syscall_filter| ProcessRequest {
+            request_id,
+            use_shell: true,
+            cmd_line: vec![vec![String::from(
+                "grep Seccomp: /proc/self/status; grep -c : /proc/net/dev",
+            )]],
+            namespaces: Some(crate::Namespaces {
+                mount: true,
+                pid: true,
+                ..Default::default()
+            }),
+            deny_network: true,
+            syscall_filter,
+            ..Default::default()
+        };
+        // creating the namespaces needs the privileges or the user namespaces
+        if run_request(request(510, None)).is_err() {
+            return;
+        }
+        // the baseline denies unshare, so the filter must be installed after the namespaces are created
+        let output = run_request(request(511, Some(SyscallFilter::default()))).unwrap();
+        assert_eq!(output.lines, ["Seccomp:\t2", "1"]);
+    }
+}
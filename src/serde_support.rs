@@ -0,0 +1,23 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::io;
+
+/// (de)serialize `Result<bool, io::Error>` as `Result<bool, String>`, the error kind is not preserved
+pub(crate) mod io_result {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        result: &Result<bool, io::Error>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        result
+            .as_ref()
+            .map_err(|error| error.to_string())
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Result<bool, io::Error>, D::Error> {
+        Ok(Result::<bool, String>::deserialize(deserializer)?.map_err(io::Error::other))
+    }
+}
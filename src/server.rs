@@ -0,0 +1,291 @@
+use crate::json_events::{escape_json, event_to_json};
+use crate::remote::RemoteProcesses;
+use crate::ProcessRequest;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::thread;
+use tungstenite::handshake::server::{Request, Response};
+use tungstenite::Message;
+
+/// Max size of the HTTP request headers
+const MAX_HEADER_BYTES: usize = 16 * 1024;
+/// Max size of the HTTP request body, a larger one is answered with `413 Payload Too Large`
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+type Processes = Arc<RemoteProcesses<String>>;
+
+/// Small HTTP + WebSocket server to run the processes remotely, events are JSON objects as per [`crate::JsonEventSink`].
+/// - `POST /processes` with a JSON [`ProcessRequest`] body starts the process, the callback is not used
+/// - `GET /processes` lists the ids of the known processes
+/// - `GET /processes/{request_id}/events` as a WebSocket streams all the events (from the start, the latest 10000 of a
+///   long one) till the process completes
+/// - `DELETE /processes/{request_id}` kills the process if running & forgets it, a completed process is forgotten
+///   10 minutes after it completes
+pub struct ProcessServer {
+    listener: TcpListener,
+    processes: Processes,
+}
+
+impl ProcessServer {
+    /// Bind the server to the address, use port 0 for any free port
+    pub fn bind(address: impl ToSocketAddrs) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(address)?,
+            processes: Arc::new(RemoteProcesses::new(event_to_json)),
+        })
+    }
+
+    /// Address the server is bound to
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accept & serve the connections, each in its own thread. Blocks till accepting fails
+    pub fn run(&self) -> io::Result<()> {
+        loop {
+            let (stream, _) = self.listener.accept()?;
+            let processes = Arc::clone(&self.processes);
+            thread::Builder::new()
+                .name(String::from("pes_server_conn"))
+                .spawn(move || {
+                    _ = handle_connection(stream, &processes);
+                })?;
+        }
+    }
+}
+
+/// a parsed HTTP request
+struct HttpRequest {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+}
+
+fn handle_connection(mut stream: TcpStream, processes: &Processes) -> io::Result<()> {
+    if is_websocket_upgrade(&stream)? {
+        return stream_events(stream, processes);
+    }
+    let request = match read_http_request(&mut stream) {
+        Ok(request) => request,
+        Err(error) if error.kind() == io::ErrorKind::FileTooLarge => {
+            return respond(
+                &mut stream,
+                "413 Payload Too Large",
+                &error_json(&error.to_string()),
+            );
+        }
+        Err(error) => return Err(error),
+    };
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+    let (status, body) = match (request.method.as_str(), segments.as_slice()) {
+        ("POST", ["processes"]) => start(&request.body, processes),
+        ("GET", ["processes"]) => {
+            let ids: Vec<u32> = processes.list().iter().map(|(id, _)| *id).collect();
+            ("200 OK", format!("{:?}", ids))
+        }
+        ("DELETE", ["processes", request_id]) => {
+            if request_id.parse().is_ok_and(|id| processes.kill(id)) {
+                ("202 Accepted", String::from("{}"))
+            } else {
+                ("404 Not Found", error_json("Unknown request id"))
+            }
+        }
+        _ => ("404 Not Found", error_json("Unknown path")),
+    };
+    respond(&mut stream, status, &body)
+}
+
+/// write the JSON response & close the connection
+fn respond(stream: &mut TcpStream, status: &str, body: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+fn error_json(message: &str) -> String {
+    format!("{{\"error\":\"{}\"}}", escape_json(message))
+}
+
+/// start the posted request
+fn start(body: &[u8], processes: &Processes) -> (&'static str, String) {
+    let request: ProcessRequest = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(error) => return ("400 Bad Request", error_json(&error.to_string())),
+    };
+    let request_id = request.request_id;
+    match processes.start(request) {
+        Ok(()) => ("201 Created", format!("{{\"request_id\":{}}}", request_id)),
+        Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {
+            ("409 Conflict", error_json(&error.to_string()))
+        }
+        Err(error) => ("500 Internal Server Error", error_json(&error.to_string())),
+    }
+}
+
+/// peek the request headers, without consuming them, for the WebSocket upgrade
+fn is_websocket_upgrade(stream: &TcpStream) -> io::Result<bool> {
+    let mut buffer = vec![0; MAX_HEADER_BYTES];
+    loop {
+        let peeked = stream.peek(&mut buffer)?;
+        let headers = String::from_utf8_lossy(&buffer[..peeked]).to_ascii_lowercase();
+        if headers.contains("\r\n\r\n") || peeked == 0 || peeked == buffer.len() {
+            return Ok(headers.contains("upgrade: websocket"));
+        }
+        thread::sleep(std::time::Duration::from_millis(5));
+    }
+}
+
+fn read_http_request(stream: &mut TcpStream) -> io::Result<HttpRequest> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+    let mut content_length = 0;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidData, "Invalid Content-Length")
+                })?;
+            }
+        }
+    }
+    if content_length > MAX_BODY_BYTES {
+        return Err(io::Error::new(
+            io::ErrorKind::FileTooLarge,
+            format!("Request body over {} bytes", MAX_BODY_BYTES),
+        ));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    Ok(HttpRequest { method, path, body })
+}
+
+/// stream the events of the process over the WebSocket till it completes
+#[allow(clippy::result_large_err)] // the handshake callback signature is defined by tungstenite
+fn stream_events(stream: TcpStream, processes: &Processes) -> io::Result<()> {
+    let mut path = String::new();
+    let mut socket = tungstenite::accept_hdr(stream, |request: &Request, response: Response| {
+        path = request.uri().path().to_string();
+        Ok(response)
+    })
+    .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))?;
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let receiver = match segments.as_slice() {
+        ["processes", request_id, "events"] => request_id
+            .parse()
+            .ok()
+            .and_then(|request_id| processes.subscribe(request_id)),
+        _ => None,
+    };
+    let Some(receiver) = receiver else {
+        _ = socket.send(Message::text(error_json("Unknown request id")));
+        _ = socket.close(None);
+        return Ok(());
+    };
+    for json in receiver {
+        if socket.send(Message::text(json)).is_err() {
+            return Ok(());
+        }
+    }
+    _ = socket.close(None);
+    // wait for the close acknowledgement
+    while socket.read().is_ok() {}
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ProcessServer;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::thread;
+
+    fn http(address: &str, method: &str, path: &str, body: &str) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            address,
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    pub fn test_server_start_stream_kill() {
+        let server = ProcessServer::bind("127.0.0.1:0").unwrap();
+        let address = server.local_addr().unwrap().to_string();
+        thread::spawn(move || server.run());
+        let response = http(
+            &address,
+            "POST",
+            "/processes",
+            r#"{"request_id": 441, "cmd_line": [["echo", "remote"]]}"#,
+        );
+        assert!(response.starts_with("HTTP/1.1 201"));
+        let (mut socket, _) =
+            tungstenite::connect(format!("ws://{}/processes/441/events", address)).unwrap();
+        let mut events = vec![];
+        while let Ok(message) = socket.read() {
+            if message.is_text() {
+                events.push(message.into_text().unwrap().to_string());
+            }
+        }
+        assert!(events[0].starts_with("{\"event\":\"Starting\",\"request_id\":441"));
+        assert!(events
+            .iter()
+            .any(|event| event.contains("\"event\":\"IOData\"") && event.contains("remote")));
+        assert!(events.last().unwrap().contains("\"event\":\"Exited\""));
+
+        let response = http(
+            &address,
+            "POST",
+            "/processes",
+            r#"{"request_id": 442, "cmd_line": [["sleep", "10"]]}"#,
+        );
+        assert!(response.starts_with("HTTP/1.1 201"));
+        assert!(http(&address, "DELETE", "/processes/442", "").starts_with("HTTP/1.1 202"));
+        assert!(http(&address, "DELETE", "/processes/442", "").starts_with("HTTP/1.1 404"));
+        assert!(http(&address, "GET", "/processes", "").ends_with("[441]"));
+    }
+
+    #[test]
+    pub fn test_server_limits() {
+        let server = ProcessServer::bind("127.0.0.1:0").unwrap();
+        let address = server.local_addr().unwrap().to_string();
+        thread::spawn(move || server.run());
+        let mut stream = TcpStream::connect(&address).unwrap();
+        write!(
+            stream,
+            "POST /processes HTTP/1.1\r\nHost: {}\r\nContent-Length: 1000000000000\r\n\r\n",
+            address
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 413"));
+        // valid JSON whatever the message
+        let error = super::error_json("bad \u{1b} 'value' \"é\"");
+        let json: serde_json::Value = serde_json::from_str(&error).unwrap();
+        assert_eq!(json["error"], "bad \u{1b} 'value' \"é\"");
+    }
+}
@@ -0,0 +1,154 @@
+use crate::{ProcessRequest, ProcessResult, Supervisor, SupervisorPolicy};
+use std::collections::BTreeMap;
+use std::io;
+use std::thread;
+
+/// Manages long running services by name, each one kept running by its own [`Supervisor`]
+#[derive(Default)]
+pub struct ServiceManager {
+    services: BTreeMap<String, Supervisor>,
+}
+
+impl ServiceManager {
+    /// Manager without services
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start supervising the service as per the policy, fails if a service of the name is already managed
+    pub fn start(
+        &mut self,
+        name: impl Into<String>,
+        process_request: ProcessRequest,
+        policy: SupervisorPolicy,
+    ) -> io::Result<()> {
+        let name = name.into();
+        if self.services.contains_key(&name) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("Service {} is already managed", name),
+            ));
+        }
+        let supervisor = Supervisor::start(process_request, policy)?;
+        self.services.insert(name, supervisor);
+        Ok(())
+    }
+
+    /// Ask the running service to reload as per the [`SupervisorPolicy::reload_action`] instead of restarting it, see
+    /// [`Supervisor::reload`]
+    pub fn reload(&self, name: &str) -> io::Result<()> {
+        self.service(name)?.reload()
+    }
+
+    /// Stop the service and remove it, returns the result of its last run
+    pub fn stop(&mut self, name: &str) -> io::Result<thread::Result<ProcessResult>> {
+        self.service(name)?;
+        let supervisor = self.services.remove(name).unwrap();
+        Ok(supervisor.stop())
+    }
+
+    /// Supervisor of the service
+    pub fn supervisor(&self, name: &str) -> Option<&Supervisor> {
+        self.services.get(name)
+    }
+
+    /// Names of the managed services, sorted
+    pub fn names(&self) -> Vec<&str> {
+        self.services.keys().map(String::as_str).collect()
+    }
+
+    fn service(&self, name: &str) -> io::Result<&Supervisor> {
+        self.services.get(name).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Service {} is not managed", name),
+            )
+        })
+    }
+}
+
+/// all the services are stopped
+impl Drop for ServiceManager {
+    fn drop(&mut self) {
+        for (_, supervisor) in std::mem::take(&mut self.services) {
+            _ = supervisor.stop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ProcessData, ProcessEvent, ProcessRequest, ProcessResult, ReloadAction, ServiceManager,
+        SupervisorPolicy,
+    };
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_service_reload() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let recorded = Arc::clone(&events);
+        let callback = move |event: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            recorded
+                .lock()
+                .unwrap()
+                .push((*event, data.line_to_owned()));
+            ProcessResult::new()
+        };
+        let mut manager = ServiceManager::new();
+        // the service reports the reload on SIGHUP
+        manager
+            .start(
+                "daemon",
+                ProcessRequest {
+                    request_id: 288,
+                    callback: Some(Arc::new(callback)),
+                    use_shell: true,
+                    cmd_line: vec![vec![String::from(
+                        "trap 'echo reloaded' HUP; echo ready; while true; do sleep 0.05; done",
+                    )]],
+                    ..Default::default()
+                },
+                SupervisorPolicy::default(),
+            )
+            .unwrap();
+        assert!(manager.reload("missing").is_err());
+        let wait_for = |line: &str| {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while Instant::now() < deadline {
+                if events.lock().unwrap().iter().any(|(_, data)| data == line) {
+                    return true;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            false
+        };
+        assert!(wait_for("ready"));
+        manager.reload("daemon").unwrap();
+        assert!(wait_for("reloaded"));
+        let supervisor = manager.supervisor("daemon").unwrap();
+        assert_eq!(supervisor.restarts(), 0);
+        assert_eq!(supervisor.reloads(), 1);
+        manager.stop("daemon").unwrap().unwrap();
+        assert!(manager.names().is_empty());
+
+        let events = events.lock().unwrap();
+        let reload_events: Vec<ProcessEvent> = events
+            .iter()
+            .map(|(event, _)| *event)
+            .filter(|event| {
+                matches!(
+                    event,
+                    ProcessEvent::ReloadRequested | ProcessEvent::Reloaded
+                )
+            })
+            .collect();
+        assert_eq!(
+            reload_events,
+            [ProcessEvent::ReloadRequested, ProcessEvent::Reloaded]
+        );
+        assert_eq!(ReloadAction::default(), ReloadAction::Signal(libc::SIGHUP));
+    }
+}
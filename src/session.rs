@@ -0,0 +1,273 @@
+use crate::{ProcessData, ProcessEvent, ProcessRequest, ProcessResult, WarningKind};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Records the full event stream of the requests to a file, see [`ProcessRequest::recorder`] & [`SessionReplayer`].
+/// Each event is a line of tab separated fields: elapsed microseconds, request id, run sequence, event, line number & line
+pub struct SessionRecorder {
+    started: Instant,
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl SessionRecorder {
+    /// Create (or truncate) the recording file, share the recorder between requests using [`Arc`]
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self {
+            started: Instant::now(),
+            writer: Mutex::new(BufWriter::new(File::create(path)?)),
+        })
+    }
+
+    /// write the event to the recording & flush, a write error is reported as a warning so the process is not affected
+    pub(crate) fn record(&self, event: &ProcessEvent, data: &ProcessData) -> io::Result<()> {
+        let (request_id, run_sequence) = data
+            .request
+            .as_ref()
+            .map_or((0, 0), |request| (request.request_id, request.run_sequence));
+        let mut writer = self.writer.lock().unwrap();
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{:?}\t{}\t{}",
+            self.started.elapsed().as_micros(),
+            request_id,
+            run_sequence,
+            event,
+            data.line_number,
+            escape(&data.line)
+        )
+        .and_then(|_| writer.flush())
+    }
+}
+
+/// A recorded event
+struct RecordedEvent {
+    elapsed: Duration,
+    request_id: u32,
+    run_sequence: u64,
+    event: ProcessEvent,
+    line_number: i64,
+    line: String,
+}
+
+/// Re-emits the events of a recording made by the [`SessionRecorder`] to a callback, without running the commands
+pub struct SessionReplayer {
+    events: Vec<RecordedEvent>,
+    speed: f64,
+}
+
+impl SessionReplayer {
+    /// Load the recording file
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut events = vec![];
+        for (index, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+            let line = line?;
+            if !line.is_empty() {
+                events.push(parse_record(&line).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Invalid recording at line {}", index + 1),
+                    )
+                })?);
+            }
+        }
+        Ok(Self { events, speed: 1.0 })
+    }
+
+    /// Replay faster (e.g. 10.0) or slower (e.g. 0.5) than the original timing, for no delays use [`f64::INFINITY`]
+    pub fn speed(mut self, speed: f64) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Number of the recorded events
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// The recording has no events
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Re-emit all the events to the callback with the recorded timing, blocks till the replay is done.
+    /// `data.request` has the recorded request id & run sequence, the process can't be killed & has no pids
+    pub fn replay(&self, callback: impl Fn(&ProcessEvent, &ProcessData) -> ProcessResult) {
+        let started = Instant::now();
+        let mut requests: Vec<Arc<ProcessRequest>> = vec![];
+        for recorded in &self.events {
+            let due = recorded.elapsed.div_f64(self.speed.max(f64::MIN_POSITIVE));
+            if let Some(wait) = due.checked_sub(started.elapsed()) {
+                thread::sleep(wait);
+            }
+            let request = match requests.iter().find(|request| {
+                request.request_id == recorded.request_id
+                    && request.run_sequence == recorded.run_sequence
+            }) {
+                Some(request) => Arc::clone(request),
+                None => {
+                    let request = Arc::new(ProcessRequest {
+                        request_id: recorded.request_id,
+                        run_sequence: recorded.run_sequence,
+                        ..Default::default()
+                    });
+                    requests.push(Arc::clone(&request));
+                    request
+                }
+            };
+            let mut process_data = ProcessData::new();
+            process_data.request = Some(request);
+            process_data.line_number = recorded.line_number;
+            process_data.line.push_str(&recorded.line);
+            callback(&recorded.event, &process_data);
+        }
+    }
+}
+
+fn parse_record(line: &str) -> Option<RecordedEvent> {
+    let mut fields = line.splitn(6, '\t');
+    Some(RecordedEvent {
+        elapsed: Duration::from_micros(fields.next()?.parse().ok()?),
+        request_id: fields.next()?.parse().ok()?,
+        run_sequence: fields.next()?.parse().ok()?,
+        event: fields.next()?.parse().ok()?,
+        line_number: fields.next()?.parse().ok()?,
+        line: unescape(fields.next()?),
+    })
+}
+
+/// escape the tab, line breaks & backslash to keep an event per line
+fn escape(input: &str) -> String {
+    input
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn unescape(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut characters = input.chars();
+    while let Some(character) = characters.next() {
+        if character != '\\' {
+            output.push(character);
+            continue;
+        }
+        match characters.next() {
+            Some('t') => output.push('\t'),
+            Some('n') => output.push('\n'),
+            Some('r') => output.push('\r'),
+            Some(other) => output.push(other),
+            None => output.push('\\'),
+        }
+    }
+    output
+}
+
+impl FromStr for ProcessEvent {
+    type Err = io::Error;
+
+    /// Parse the event from its name, as formatted by [`Debug`]
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Ok(match name {
+            "_Unknown" => ProcessEvent::_Unknown,
+            "Starting" => ProcessEvent::Starting,
+            "Started" => ProcessEvent::Started,
+            "StartError" => ProcessEvent::StartError,
+            "IOError" => ProcessEvent::IOError,
+            "IOEof" => ProcessEvent::IOEof,
+            "IOData" => ProcessEvent::IOData,
+            "ExitRequested" => ProcessEvent::ExitRequested,
+            "KillRequested" => ProcessEvent::KillRequested,
+            "Paused" => ProcessEvent::Paused,
+            "Resumed" => ProcessEvent::Resumed,
+            "Exited" => ProcessEvent::Exited,
+            "KillError" => ProcessEvent::KillError,
+            "ResourceSample" => ProcessEvent::ResourceSample,
+            "ResourceLimitExceeded" => ProcessEvent::ResourceLimitExceeded,
+            "RetryScheduled" => ProcessEvent::RetryScheduled,
+            "RetryStarted" => ProcessEvent::RetryStarted,
+            "Restarted" => ProcessEvent::Restarted,
+            "RestartLimitReached" => ProcessEvent::RestartLimitReached,
+            "FileRotated" => ProcessEvent::FileRotated,
+            "ReloadRequested" => ProcessEvent::ReloadRequested,
+            "Reloaded" => ProcessEvent::Reloaded,
+            "HostFailover" => ProcessEvent::HostFailover,
+            "GoldenMismatch" => ProcessEvent::GoldenMismatch,
+            "RepeatedOutput" => ProcessEvent::RepeatedOutput,
+            "PolicyViolation" => ProcessEvent::PolicyViolation,
+            "QuotaExceeded" => ProcessEvent::QuotaExceeded,
+            "EnvironmentDiff" => ProcessEvent::EnvironmentDiff,
+            "IdleTimeout" => ProcessEvent::IdleTimeout,
+            "Scheduled" => ProcessEvent::Scheduled,
+            "ScheduleCancelled" => ProcessEvent::ScheduleCancelled,
+            "Skipped" => ProcessEvent::Skipped,
+            "Queued" => ProcessEvent::Queued,
+            "TimedOut" => ProcessEvent::TimedOut,
+            "StageStarted" => ProcessEvent::StageStarted,
+            "StageExited" => ProcessEvent::StageExited,
+            "Heartbeat" => ProcessEvent::Heartbeat,
+            "PatternMatched" => ProcessEvent::PatternMatched,
+            "FailPatternMatched" => ProcessEvent::FailPatternMatched,
+            "SuccessPatternMatched" => ProcessEvent::SuccessPatternMatched,
+            "JsonParseError" => ProcessEvent::JsonParseError,
+            "ExpectationFailed" => ProcessEvent::ExpectationFailed,
+            "ReadStalled" => ProcessEvent::ReadStalled,
+            "OutputLimitExceeded" => ProcessEvent::OutputLimitExceeded,
+            "Warning(LineTruncated)" => ProcessEvent::Warning(WarningKind::LineTruncated),
+            "Warning(InvalidUtf8Replaced)" => {
+                ProcessEvent::Warning(WarningKind::InvalidUtf8Replaced)
+            }
+            "Warning(EventsDropped)" => ProcessEvent::Warning(WarningKind::EventsDropped),
+            "Warning(SinkWriteFailed)" => ProcessEvent::Warning(WarningKind::SinkWriteFailed),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unknown event {}", name),
+                ))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ProcessData, ProcessEvent, ProcessRequest, ProcessResult, SessionRecorder, SessionReplayer,
+    };
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    pub fn test_record_and_replay() {
+        let path = std::env::temp_dir().join("pes_test_session.rec");
+        let recorder = Arc::new(SessionRecorder::create(&path).unwrap());
+        ProcessRequest::start(ProcessRequest {
+            request_id: 421,
+            cmd_line: vec![vec![String::from("echo"), String::from("a\tb \\ c")]],
+            recorder: Some(recorder),
+            ..Default::default()
+        });
+        let replayer = SessionReplayer::open(&path).unwrap().speed(f64::INFINITY);
+        let replayed = Mutex::new(vec![]);
+        let started = Instant::now();
+        replayer.replay(
+            |event: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+                assert_eq!(data.request.as_ref().unwrap().request_id, 421);
+                replayed.lock().unwrap().push((*event, data.line.clone()));
+                ProcessResult::new()
+            },
+        );
+        assert!(started.elapsed() < Duration::from_secs(1));
+        let replayed = replayed.into_inner().unwrap();
+        assert_eq!(replayed.len(), replayer.len());
+        assert_eq!(replayed.first().unwrap().0, ProcessEvent::Starting);
+        assert!(replayed.contains(&(ProcessEvent::IOData, String::from("a\tb \\ c\n"))));
+        assert_eq!(replayed.last().unwrap().0, ProcessEvent::Exited);
+        _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,306 @@
+use std::ffi::OsString;
+
+/// Shell to run the shell mode commands with, see [`crate::ProcessRequest::shell`]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ShellKind {
+    /// `/bin/sh -c` on Unix & `%COMSPEC% /C` on Windows
+    #[default]
+    Default,
+    /// `sh -c`
+    Sh,
+    /// `bash -c`
+    Bash,
+    /// `zsh -c`
+    Zsh,
+    /// `fish -c`
+    Fish,
+    /// `cmd.exe /C`
+    Cmd,
+    /// Windows PowerShell, `powershell -NoProfile -NonInteractive -Command`
+    PowerShell,
+    /// PowerShell (Core) on any platform, `pwsh -NoProfile -NonInteractive -Command`
+    Pwsh,
+    /// Custom argv template, an argument `{}` is replaced by the command line (its parts joined with spaces),
+    /// without the placeholder the command line parts are appended e.g. `["nu", "-c"]`
+    Custom(Vec<String>),
+}
+
+/// argument of the custom template replaced by the command line
+const COMMAND_PLACEHOLDER: &str = "{}";
+
+/// characters which never need quoting in the POSIX shells, fish & cmd.exe
+const SAFE_CHARACTERS: &str = "-_./=:,+@";
+
+/// characters which never need quoting in zsh, a leading `=` expands a command to its path e.g. `=ls`
+const ZSH_SAFE_CHARACTERS: &str = "-_./:,+@";
+
+/// characters which never need quoting in PowerShell, `@` splats a variable & `,` makes an array
+const POWERSHELL_SAFE_CHARACTERS: &str = "-_./=:+";
+
+/// characters with a special meaning for cmd.exe, escaped with `^`
+const CMD_META_CHARACTERS: &str = "^&|<>()%!\"";
+
+/// Quote the argument for the default shell of the platform (POSIX `sh` or `cmd.exe`), so that it's passed as is
+pub fn shell_quote(arg: &str) -> String {
+    ShellKind::Default.quote(arg)
+}
+
+/// Build a command line for the default shell of the platform, every argument is quoted as per [`shell_quote`]
+pub fn build_shell_line(args: &[&str]) -> String {
+    ShellKind::Default.build_line(args)
+}
+
+impl ShellKind {
+    /// argv of the shell & its arguments, before the command line
+    fn prefix(&self) -> Vec<OsString> {
+        let prefix: &[&str] = match self {
+            ShellKind::Default => return default_prefix(),
+            ShellKind::Sh => &["sh", "-c"],
+            ShellKind::Bash => &["bash", "-c"],
+            ShellKind::Zsh => &["zsh", "-c"],
+            ShellKind::Fish => &["fish", "-c"],
+            ShellKind::Cmd => &["cmd.exe", "/C"],
+            ShellKind::PowerShell => &["powershell", "-NoProfile", "-NonInteractive", "-Command"],
+            ShellKind::Pwsh => &["pwsh", "-NoProfile", "-NonInteractive", "-Command"],
+            ShellKind::Custom(template) => return template.iter().map(OsString::from).collect(),
+        };
+        prefix.iter().map(OsString::from).collect()
+    }
+
+    /// characters of the arguments left unquoted in this shell
+    fn safe_characters(&self) -> &'static str {
+        match self {
+            ShellKind::Zsh => ZSH_SAFE_CHARACTERS,
+            ShellKind::PowerShell | ShellKind::Pwsh => POWERSHELL_SAFE_CHARACTERS,
+            _ => SAFE_CHARACTERS,
+        }
+    }
+
+    /// Quote the argument for this shell, so that it's passed as is. Custom shells use the POSIX quoting
+    pub fn quote(&self, arg: &str) -> String {
+        let safe_characters = self.safe_characters();
+        if !arg.is_empty()
+            && arg.chars().all(|character| {
+                character.is_ascii_alphanumeric() || safe_characters.contains(character)
+            })
+        {
+            return arg.to_string();
+        }
+        match self {
+            #[cfg(windows)]
+            ShellKind::Default => cmd_quote(arg),
+            ShellKind::Cmd => cmd_quote(arg),
+            ShellKind::PowerShell | ShellKind::Pwsh => {
+                // PowerShell also treats the typographic single quotes as quotes
+                let mut quoted = String::from("'");
+                for character in arg.chars() {
+                    if matches!(character, '\'' | '\u{2018}' | '\u{2019}') {
+                        quoted.push(character);
+                    }
+                    quoted.push(character);
+                }
+                quoted.push('\'');
+                quoted
+            }
+            ShellKind::Fish => format!("'{}'", arg.replace('\\', "\\\\").replace('\'', "\\'")),
+            _ => posix_quote(arg),
+        }
+    }
+
+    /// Build a command line for this shell, every argument is quoted as per [`ShellKind::quote`]
+    pub fn build_line(&self, args: &[&str]) -> String {
+        args.iter()
+            .map(|arg| self.quote(arg))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    /// argv to run the command line in the shell
+    pub(crate) fn argv(&self, command: &[String]) -> Vec<OsString> {
+        let mut argv = self.prefix();
+        match self {
+            ShellKind::Custom(template)
+                if template.iter().any(|arg| arg == COMMAND_PLACEHOLDER) =>
+            {
+                let command_line = OsString::from(command.join(" "));
+                for arg in argv.iter_mut().filter(|arg| *arg == COMMAND_PLACEHOLDER) {
+                    *arg = command_line.clone();
+                }
+            }
+            _ => argv.extend(command.iter().map(OsString::from)),
+        }
+        argv
+    }
+}
+
+/// quote the argument for a POSIX shell
+fn posix_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// quote the argument as per the Windows argv parsing rules & escape the cmd.exe meta characters,
+/// the quotes are escaped too so that cmd.exe never enters its quoted mode
+fn cmd_quote(arg: &str) -> String {
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for character in arg.chars() {
+        match character {
+            '\\' => backslashes += 1,
+            '"' => {
+                quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+                backslashes = 0;
+            }
+            _ => {
+                quoted.push_str(&"\\".repeat(backslashes));
+                backslashes = 0;
+            }
+        }
+        if character != '\\' {
+            quoted.push(character);
+        }
+    }
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+        .chars()
+        .fold(String::new(), |mut escaped, character| {
+            if CMD_META_CHARACTERS.contains(character) {
+                escaped.push('^');
+            }
+            escaped.push(character);
+            escaped
+        })
+}
+
+#[cfg(unix)]
+fn default_prefix() -> Vec<OsString> {
+    vec!["/bin/sh".into(), "-c".into()]
+}
+
+#[cfg(windows)]
+fn default_prefix() -> Vec<OsString> {
+    let comspec = std::env::var_os("COMSPEC").unwrap_or_else(|| "cmd.exe".into());
+    vec![comspec, "/C".into()]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        build_shell_line, ProcessData, ProcessEvent, ProcessRequest, ProcessResult, ShellKind,
+    };
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    pub fn test_shell_argv() {
+        let command = [String::from("echo $0")];
+        assert_eq!(
+            ShellKind::Pwsh.argv(&command),
+            [
+                "pwsh",
+                "-NoProfile",
+                "-NonInteractive",
+                "-Command",
+                "echo $0"
+            ]
+        );
+        let custom = ShellKind::Custom(vec![
+            String::from("docker"),
+            String::from("exec"),
+            String::from("web"),
+            String::from("sh"),
+            String::from("-c"),
+            String::from("{}"),
+            String::from("pes"),
+        ]);
+        assert_eq!(
+            custom.argv(&[String::from("echo"), String::from("$0")]),
+            ["docker", "exec", "web", "sh", "-c", "echo $0", "pes"]
+        );
+    }
+
+    #[test]
+    pub fn test_shell_quote() {
+        let args = ["grep", "it's $HOME", "a\\b\"c"];
+        assert_eq!(
+            ShellKind::Bash.build_line(&args),
+            r#"grep 'it'\''s $HOME' 'a\b"c'"#
+        );
+        assert_eq!(
+            ShellKind::Pwsh.build_line(&args),
+            r#"grep 'it''s $HOME' 'a\b"c'"#
+        );
+        assert_eq!(ShellKind::Fish.quote("it's a\\b"), r"'it\'s a\\b'");
+        assert_eq!(
+            ShellKind::Cmd.build_line(&["echo", "50% & more", "a\\\"b"]),
+            r#"echo ^"50^% ^& more^" ^"a\\\^"b^""#
+        );
+    }
+
+    #[test]
+    pub fn test_shell_safe_characters() {
+        assert_eq!(ShellKind::Bash.quote("=ls"), "=ls");
+        assert_eq!(ShellKind::Zsh.quote("=ls"), "'=ls'");
+        assert_eq!(ShellKind::Zsh.quote("user@host:a,b"), "user@host:a,b");
+        for shell in [ShellKind::PowerShell, ShellKind::Pwsh] {
+            assert_eq!(shell.quote("@name"), "'@name'");
+            assert_eq!(shell.quote("a,b"), "'a,b'");
+            assert_eq!(shell.quote("key=./value"), "key=./value");
+        }
+        assert_eq!(ShellKind::Sh.quote("a,b@c"), "a,b@c");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_build_shell_line() {
+        let lines = Arc::new(Mutex::new(vec![]));
+        let output = Arc::clone(&lines);
+        let request = ProcessRequest {
+            request_id: 221,
+            use_shell: true,
+            cmd_line: vec![vec![build_shell_line(&["echo", "$HOME; it's `id`"])]],
+            callback: Some(Arc::new(move |event: &ProcessEvent, data: &ProcessData| {
+                if *event == ProcessEvent::IOData {
+                    output
+                        .lock()
+                        .unwrap()
+                        .push(data.line.trim_end().to_string());
+                }
+                ProcessResult::new()
+            })),
+            ..Default::default()
+        };
+        assert_eq!(ProcessRequest::start(request).exit_code, Some(0));
+        assert_eq!(*lines.lock().unwrap(), ["$HOME; it's `id`"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_custom_shell() {
+        let lines = Arc::new(Mutex::new(vec![]));
+        let output = Arc::clone(&lines);
+        let request = ProcessRequest {
+            request_id: 211,
+            use_shell: true,
+            shell: ShellKind::Custom(vec![
+                String::from("/bin/sh"),
+                String::from("-c"),
+                String::from("{}"),
+                String::from("custom"),
+            ]),
+            cmd_line: vec![vec![String::from("echo"), String::from("$0")]],
+            callback: Some(Arc::new(move |event: &ProcessEvent, data: &ProcessData| {
+                if *event == ProcessEvent::IOData {
+                    output
+                        .lock()
+                        .unwrap()
+                        .push(data.line.trim_end().to_string());
+                }
+                ProcessResult::new()
+            })),
+            ..Default::default()
+        };
+        assert_eq!(ProcessRequest::start(request).exit_code, Some(0));
+        assert_eq!(*lines.lock().unwrap(), ["custom"]);
+    }
+}
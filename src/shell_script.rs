@@ -0,0 +1,46 @@
+use crate::{ProcessRequest, ShellKind};
+#[cfg(any(feature = "ssh", feature = "kubernetes"))]
+use std::io;
+
+/// quote the string for a POSIX shell
+pub(crate) fn quote(input: &str) -> String {
+    ShellKind::Sh.quote(input)
+}
+
+/// pipeline of the request as a POSIX shell command line, shell stages are used as is & direct stages are quoted
+pub(crate) fn pipeline(request: &ProcessRequest) -> String {
+    let stages: Vec<String> = request
+        .pipeline_stages()
+        .iter()
+        .map(|stage| {
+            if stage.shell {
+                stage.argv.join(" ")
+            } else {
+                stage
+                    .argv
+                    .iter()
+                    .map(|arg| quote(arg))
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            }
+        })
+        .collect();
+    stages.join(" | ")
+}
+
+/// command line to run in a POSIX shell of another host or pod, including the env & the working directory
+#[cfg(any(feature = "ssh", feature = "kubernetes"))]
+pub(crate) fn command_line(request: &ProcessRequest) -> io::Result<String> {
+    let mut command_line = String::new();
+    let mut env: Vec<(&String, &String)> = request.env.iter().collect();
+    env.sort();
+    for (name, value) in env {
+        crate::env::check_name(name)?;
+        command_line.push_str(&format!("export {}={}; ", name, quote(value)));
+    }
+    if let Some(working_dir) = request.working_dir.as_ref() {
+        command_line.push_str(&format!("cd {} && ", quote(&working_dir.to_string_lossy())));
+    }
+    command_line.push_str(&pipeline(request));
+    Ok(command_line)
+}
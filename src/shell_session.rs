@@ -0,0 +1,208 @@
+use crate::{
+    check_and_trigger_callback, CommandOutput, ProcessData, ProcessEvent, ProcessRequest,
+    ShellKind, Termination,
+};
+use duct::ReaderHandle;
+use std::io::{self, BufRead, BufReader, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// sessions started by this process, part of the sentinel
+static SESSION_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// One long-lived POSIX shell (`sh`, `bash` or `zsh` as per [`ProcessRequest::shell`]) running many commands one after
+/// another, saving the start of a new shell per command. The state of the shell (working directory, variables,
+/// functions) carries over between the commands. Every command gets its own [`ProcessEvent::Started`] (the line is
+/// the command), [`ProcessEvent::IOData`] (numbered from 1 per command) & [`ProcessEvent::Exited`] events on the
+/// callback of the request, its end is detected with a unique sentinel line printed by the shell after it. The env &
+/// working directory of the request apply to the shell. The commands read no STDIN, a command exiting the shell ends
+/// the session
+pub struct ShellSession {
+    request: Arc<ProcessRequest>,
+    stdin: Option<io::PipeWriter>,
+    output: BufReader<ReaderHandle>,
+    sentinel: String,
+    commands: u64,
+}
+
+impl ShellSession {
+    /// Start the shell of the request, fails if it's not a POSIX shell or could not be started
+    pub fn new(request: ProcessRequest) -> io::Result<Self> {
+        let shell = match &request.shell {
+            ShellKind::Default if cfg!(unix) => "/bin/sh",
+            ShellKind::Sh => "sh",
+            ShellKind::Bash => "bash",
+            ShellKind::Zsh => "zsh",
+            other => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Shell session needs a POSIX shell, not {:?}", other),
+                ))
+            }
+        };
+        let (stdin_reader, stdin) = io::pipe()?;
+        let mut expression = duct::cmd(shell, ["-s"]).stdin_file(stdin_reader);
+        if let Some(working_dir) = request.working_dir.as_ref() {
+            expression = expression.dir(working_dir);
+        }
+        let expression = crate::env::apply_env(expression, &request)?;
+        let output = expression.stderr_to_stdout().unchecked().reader()?;
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.subsec_nanos());
+        Ok(Self {
+            request: Arc::new(request),
+            stdin: Some(stdin),
+            output: BufReader::new(output),
+            sentinel: format!(
+                "__pes_session_{}_{}_{:x}__",
+                std::process::id(),
+                SESSION_COUNTER.fetch_add(1, Ordering::Relaxed),
+                nanos
+            ),
+            commands: 0,
+        })
+    }
+
+    /// Run the command line in the shell till it completes & return its output, fails if the shell has ended
+    pub fn run(&mut self, command_line: &str) -> io::Result<CommandOutput> {
+        crate::policy::enforce_commands(
+            &self.request,
+            &ProcessRequest {
+                cmd_line: vec![vec![command_line.to_string()]],
+                use_shell: true,
+                shell: self.request.shell.clone(),
+                ..Default::default()
+            },
+        )?;
+        self.commands += 1;
+        let marker = format!("{}_{}", self.sentinel, self.commands);
+        let stdin = self.stdin.as_mut().ok_or_else(session_ended)?;
+        // the braces keep the command in the current shell, so that its state carries over
+        write!(
+            stdin,
+            "{{ {}\n}} < /dev/null\nprintf '%s %d\\n' '{}' \"$?\"\n",
+            command_line, marker
+        )?;
+        stdin.flush()?;
+
+        let mut process_data = ProcessData::new();
+        process_data.request = Some(Arc::clone(&self.request));
+        process_data.line = command_line.to_string();
+        check_and_trigger_callback(&self.request, &ProcessEvent::Started, &process_data);
+
+        let mut output = CommandOutput::default();
+        let mut buffer = vec![];
+        loop {
+            buffer.clear();
+            if self.output.read_until(b'\n', &mut buffer)? == 0 {
+                self.stdin = None;
+                return Err(session_ended());
+            }
+            let line = String::from_utf8_lossy(&buffer);
+            let line = line.trim_end_matches(['\n', '\r']);
+            // the sentinel follows the last line directly if the command didn't end it
+            let (text, exit_code) = match line.find(&marker) {
+                Some(index) => (&line[..index], Some(&line[index + marker.len()..])),
+                None => (line, None),
+            };
+            if exit_code.is_none() || !text.is_empty() {
+                output.lines.push(text.to_string());
+                process_data.line_number += 1;
+                process_data.line = format!("{}\n", text);
+                check_and_trigger_callback(&self.request, &ProcessEvent::IOData, &process_data);
+            }
+            if let Some(exit_code) = exit_code {
+                output.exit_code = exit_code.trim().parse().ok();
+                break;
+            }
+        }
+        output.termination = output.exit_code.map(|code| Termination::Exited { code });
+        process_data.line.clear();
+        process_data.termination = output.termination;
+        check_and_trigger_callback(&self.request, &ProcessEvent::Exited, &process_data);
+        Ok(output)
+    }
+
+    /// Number of commands run in the session
+    pub fn commands(&self) -> u64 {
+        self.commands
+    }
+
+    /// Exit the shell & wait for it to end
+    pub fn close(mut self) -> io::Result<()> {
+        self.stdin = None;
+        io::copy(&mut self.output, &mut io::sink())?;
+        Ok(())
+    }
+}
+
+impl Drop for ShellSession {
+    fn drop(&mut self) {
+        if self.stdin.take().is_some() {
+            _ = self.output.get_ref().kill();
+        }
+    }
+}
+
+/// error of a command after the shell has ended
+fn session_ended() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "Shell session has ended")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ProcessData, ProcessEvent, ProcessRequest, ProcessResult, ShellSession};
+    use std::sync::{Arc, Mutex};
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_shell_session() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let recorded = Arc::clone(&events);
+        let callback = move |event: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            recorded
+                .lock()
+                .unwrap()
+                .push((*event, data.line_number, data.line_to_owned()));
+            ProcessResult::new()
+        };
+        let mut session = ShellSession::new(ProcessRequest {
+            request_id: 299,
+            env: [(String::from("GREETING"), String::from("hello"))].into(),
+            working_dir: Some(std::env::temp_dir()),
+            callback: Some(Arc::new(callback)),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let output = session.run("echo $GREETING; NAME=world").unwrap();
+        assert_eq!(output.lines, ["hello"]);
+        assert!(output.success());
+        let output = session.run("printf \"$GREETING $NAME\"").unwrap();
+        assert_eq!(output.lines, ["hello world"]);
+        let output = session.run("echo oops >&2; (exit 3)").unwrap();
+        assert_eq!(output.lines, ["oops"]);
+        assert_eq!(output.exit_code, Some(3));
+        assert_eq!(session.commands(), 3);
+        {
+            let events = events.lock().unwrap();
+            assert_eq!(events.len(), 9);
+            assert_eq!(
+                events[6],
+                (
+                    ProcessEvent::Started,
+                    0,
+                    String::from("echo oops >&2; (exit 3)")
+                )
+            );
+            assert_eq!(events[7], (ProcessEvent::IOData, 1, String::from("oops")));
+            assert_eq!(events[8].0, ProcessEvent::Exited);
+        }
+
+        assert!(session.run("exit 1").is_err());
+        assert!(session.run("true").is_err());
+        session.close().unwrap();
+    }
+}
@@ -0,0 +1,231 @@
+#[cfg(feature = "kubernetes")]
+use crate::latch::Latch;
+use crate::termination::KillRecord;
+use duct::ReaderHandle;
+use std::cell::Cell;
+use std::io;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+/// Running executions of the process, to terminate them all on shutdown
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    executions: vec![],
+    next_id: 0,
+    shutting_down: false,
+});
+/// Notified when an execution completes
+static COMPLETED: Condvar = Condvar::new();
+
+thread_local! {
+    /// Whether the thread is running a process callback, the execution of the callback can't complete meanwhile
+    static IN_CALLBACK: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Marks the thread as running a process callback till dropped
+pub(crate) struct CallbackGuard {
+    previous: bool,
+}
+
+impl Drop for CallbackGuard {
+    fn drop(&mut self) {
+        IN_CALLBACK.with(|in_callback| in_callback.set(self.previous));
+    }
+}
+
+/// mark the current thread as running a process callback
+pub(crate) fn enter_callback() -> CallbackGuard {
+    CallbackGuard {
+        previous: IN_CALLBACK.with(|in_callback| in_callback.replace(true)),
+    }
+}
+
+struct Registry {
+    executions: Vec<Execution>,
+    next_id: u64,
+    shutting_down: bool,
+}
+
+struct Execution {
+    id: u64,
+    target: Target,
+}
+
+/// What is terminated to end an execution
+enum Target {
+    /// Local processes of the reader, the kill is recorded as requested
+    Process {
+        reader: Arc<ReaderHandle>,
+        kill_record: Arc<KillRecord>,
+    },
+    /// Execution which ends itself once the latch is set, e.g. in a pod
+    #[cfg(feature = "kubernetes")]
+    Latch(Arc<Latch>),
+}
+
+impl Target {
+    /// ask the execution to terminate
+    fn terminate(&self) {
+        match self {
+            Target::Process {
+                reader,
+                kill_record,
+            } => {
+                kill_record.record(true);
+                terminate(reader);
+            }
+            #[cfg(feature = "kubernetes")]
+            Target::Latch(latch) => latch.set(),
+        }
+    }
+
+    /// kill the execution
+    fn kill(&self) {
+        match self {
+            Target::Process {
+                reader,
+                kill_record,
+            } => {
+                kill_record.record(true);
+                _ = reader.kill();
+            }
+            #[cfg(feature = "kubernetes")]
+            Target::Latch(latch) => latch.set(),
+        }
+    }
+}
+
+/// Registration of a running execution, removed from the registry when dropped
+pub(crate) struct Registration {
+    id: u64,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let mut registry = REGISTRY.lock().unwrap();
+        registry
+            .executions
+            .retain(|execution| execution.id != self.id);
+        COMPLETED.notify_all();
+    }
+}
+
+/// register the running execution till the registration is dropped, it's killed right away during a shutdown
+pub(crate) fn register(reader: &Arc<ReaderHandle>, kill_record: &Arc<KillRecord>) -> Registration {
+    register_target(Target::Process {
+        reader: Arc::clone(reader),
+        kill_record: Arc::clone(kill_record),
+    })
+}
+
+/// register the running execution which ends once the latch is set, till the registration is dropped
+#[cfg(feature = "kubernetes")]
+pub(crate) fn register_latch(latch: &Arc<Latch>) -> Registration {
+    register_target(Target::Latch(Arc::clone(latch)))
+}
+
+fn register_target(target: Target) -> Registration {
+    let mut registry = REGISTRY.lock().unwrap();
+    if registry.shutting_down {
+        target.kill();
+    }
+    registry.next_id += 1;
+    let id = registry.next_id;
+    registry.executions.push(Execution { id, target });
+    Registration { id }
+}
+
+/// Terminate all the running processes started by this library, e.g. on application exit.
+/// The processes are asked to terminate gracefully (`SIGTERM`, on Windows they are killed right away), the ones still
+/// running after the grace period are killed. Blocks till all the executions are completed or the `timeout` elapses
+/// after the kill, the executions starting meanwhile are killed right away.
+///
+/// It's not async-signal-safe, to shut down on a signal call it from a dedicated signal-handling thread. It can't be
+/// called from a process callback either, the execution of the callback can't complete while it waits: it fails with
+/// [`io::ErrorKind::Deadlock`] without terminating anything, call it from another thread instead
+pub fn shutdown_all(grace: Duration, timeout: Option<Duration>) -> io::Result<()> {
+    if IN_CALLBACK.with(Cell::get) {
+        return Err(io::Error::new(
+            io::ErrorKind::Deadlock,
+            "shutdown_all can't wait for the executions from a process callback",
+        ));
+    }
+    let mut registry = REGISTRY.lock().unwrap();
+    registry.shutting_down = true;
+    for execution in &registry.executions {
+        execution.target.terminate();
+    }
+    (registry, _) = COMPLETED
+        .wait_timeout_while(registry, grace, |registry| !registry.executions.is_empty())
+        .unwrap();
+    for execution in &registry.executions {
+        execution.target.kill();
+    }
+    registry = match timeout {
+        Some(timeout) => {
+            COMPLETED
+                .wait_timeout_while(registry, timeout, |registry| {
+                    !registry.executions.is_empty()
+                })
+                .unwrap()
+                .0
+        }
+        None => COMPLETED
+            .wait_while(registry, |registry| !registry.executions.is_empty())
+            .unwrap(),
+    };
+    let running = registry.executions.len();
+    registry.shutting_down = false;
+    if running == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!("{} executions still running after the timeout", running),
+        ))
+    }
+}
+
+/// ask the processes of the execution to terminate
+#[cfg(unix)]
+fn terminate(reader: &ReaderHandle) {
+    for pid in reader.pids() {
+        unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) };
+    }
+}
+
+/// no graceful termination on Windows, the processes are killed
+#[cfg(not(unix))]
+fn terminate(reader: &ReaderHandle) {
+    _ = reader.kill();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ProcessData, ProcessEvent, ProcessRequest, ProcessResult};
+    use std::io;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[test]
+    pub fn test_shutdown_from_callback() {
+        let errors = Arc::new(Mutex::new(vec![]));
+        let recorded = Arc::clone(&errors);
+        let callback = move |event: &ProcessEvent, _: &ProcessData| -> ProcessResult {
+            if *event == ProcessEvent::IOData {
+                if let Err(error) = super::shutdown_all(Duration::ZERO, None) {
+                    recorded.lock().unwrap().push(error.kind());
+                }
+            }
+            ProcessResult::new()
+        };
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 508,
+            use_shell: true,
+            cmd_line: vec![vec![String::from("echo ready")]],
+            callback: Some(Arc::new(callback)),
+            ..Default::default()
+        });
+        assert!(result.success.unwrap());
+        assert_eq!(*errors.lock().unwrap(), [io::ErrorKind::Deadlock]);
+    }
+}
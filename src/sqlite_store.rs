@@ -0,0 +1,212 @@
+use crate::{ExecutionRecord, HistoryQuery, HistoryStore, ProcessRequest};
+use rusqlite::{params, params_from_iter, Connection};
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Tables of the store: the queued requests & the completed executions, both stored as JSON along with the columns to
+/// query them
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS jobs (
+    job_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    request_id INTEGER NOT NULL,
+    queued_at INTEGER NOT NULL,
+    request TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS executions (
+    execution_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    request_id INTEGER NOT NULL,
+    started_at INTEGER NOT NULL,
+    success INTEGER NOT NULL,
+    record TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS executions_request_id ON executions (request_id, started_at);
+";
+
+/// SQLite database persisting the queued requests & the history of the executions, so a supervisor can resume its
+/// jobs after a restart. Share it between requests using [`std::sync::Arc`]
+pub struct SqliteStore {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    /// Open the database, it's created along with the tables if missing
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let connection = Connection::open(path).map_err(sql_error)?;
+        connection.execute_batch(SCHEMA).map_err(sql_error)?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// Persist a queued request, returns its job id. The callback, sinks & other runtime fields are not persisted
+    pub fn enqueue(&self, request: &ProcessRequest) -> io::Result<i64> {
+        let request_json = serde_json::to_string(request)?;
+        let connection = self.connection.lock().unwrap();
+        connection
+            .execute(
+                "INSERT INTO jobs (request_id, queued_at, request) VALUES (?1, ?2, ?3)",
+                params![
+                    request.request_id,
+                    micros_since_epoch(SystemTime::now()),
+                    request_json
+                ],
+            )
+            .map_err(sql_error)?;
+        Ok(connection.last_insert_rowid())
+    }
+
+    /// The queued requests not completed yet along with their job ids, oldest first, e.g. to start them again after a
+    /// restart
+    pub fn pending_jobs(&self) -> io::Result<Vec<(i64, ProcessRequest)>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection
+            .prepare("SELECT job_id, request FROM jobs ORDER BY job_id")
+            .map_err(sql_error)?;
+        let rows = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get::<_, String>(1)?)))
+            .map_err(sql_error)?;
+        rows.map(|row| {
+            let (job_id, request) = row.map_err(sql_error)?;
+            Ok((job_id, serde_json::from_str(&request)?))
+        })
+        .collect()
+    }
+
+    /// Remove the job of a completed request
+    pub fn complete(&self, job_id: i64) -> io::Result<()> {
+        self.connection
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM jobs WHERE job_id = ?1", [job_id])
+            .map(|_| ())
+            .map_err(sql_error)
+    }
+
+    /// Store the record of a completed execution
+    pub fn insert_record(&self, record: &ExecutionRecord) -> io::Result<()> {
+        let record_json = serde_json::to_string(record)?;
+        self.connection
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT INTO executions (request_id, started_at, success, record) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    record.request_id,
+                    micros_since_epoch(record.started_at),
+                    record.success,
+                    record_json
+                ],
+            )
+            .map(|_| ())
+            .map_err(sql_error)
+    }
+
+    /// Records of the executions matching the query, oldest first
+    pub fn query_records(&self, query: &HistoryQuery) -> io::Result<Vec<ExecutionRecord>> {
+        let mut conditions = vec!["1"];
+        let mut values: Vec<i64> = vec![];
+        if let Some(request_id) = query.request_id {
+            conditions.push("request_id = ?");
+            values.push(request_id.into());
+        }
+        if let Some(started_after) = query.started_after {
+            conditions.push("started_at >= ?");
+            values.push(micros_since_epoch(started_after));
+        }
+        if let Some(started_before) = query.started_before {
+            conditions.push("started_at < ?");
+            values.push(micros_since_epoch(started_before));
+        }
+        if let Some(success) = query.success {
+            conditions.push("success = ?");
+            values.push(success.into());
+        }
+        values.push(query.limit.map_or(-1, |limit| limit as i64));
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection
+            .prepare(&format!(
+                "SELECT record FROM executions WHERE {} ORDER BY execution_id DESC LIMIT ?",
+                conditions.join(" AND ")
+            ))
+            .map_err(sql_error)?;
+        let rows = statement
+            .query_map(params_from_iter(values), |row| row.get::<_, String>(0))
+            .map_err(sql_error)?;
+        let mut records = rows
+            .map(|row| Ok(serde_json::from_str(&row.map_err(sql_error)?)?))
+            .collect::<io::Result<Vec<ExecutionRecord>>>()?;
+        records.reverse();
+        Ok(records)
+    }
+}
+
+impl HistoryStore for SqliteStore {
+    fn record(&self, record: ExecutionRecord) -> io::Result<()> {
+        self.insert_record(&record)
+    }
+
+    /// an error of the query is ignored, use [`SqliteStore::query_records`] to handle it
+    fn query(&self, query: &HistoryQuery) -> Vec<ExecutionRecord> {
+        self.query_records(query).unwrap_or_default()
+    }
+}
+
+fn sql_error(error: rusqlite::Error) -> io::Error {
+    io::Error::other(error)
+}
+
+fn micros_since_epoch(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_micros() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{HistoryQuery, HistoryStore, ProcessRequest, SqliteStore};
+    use std::sync::Arc;
+
+    #[test]
+    pub fn test_sqlite_store() {
+        let path = std::env::temp_dir().join("pes_test_store.db");
+        _ = std::fs::remove_file(&path);
+        let store = Arc::new(SqliteStore::open(&path).unwrap());
+        let request = ProcessRequest {
+            request_id: 284,
+            cmd_line: vec![vec![String::from("echo"), String::from("it's queued")]],
+            history: Some(store.clone()),
+            ..Default::default()
+        };
+        let job_id = store.enqueue(&request).unwrap();
+
+        // the pending job survives a restart
+        let store = Arc::new(SqliteStore::open(&path).unwrap());
+        let pending = store.pending_jobs().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].0, job_id);
+        assert_eq!(pending[0].1.cmd_line, request.cmd_line);
+        let mut resumed = pending[0].1.clone();
+        resumed.history = Some(store.clone());
+        ProcessRequest::start(resumed);
+        ProcessRequest::start(ProcessRequest {
+            request_id: 285,
+            cmd_line: vec![vec![String::from("false")]],
+            history: Some(store.clone()),
+            ..Default::default()
+        });
+        store.complete(job_id).unwrap();
+        assert!(store.pending_jobs().unwrap().is_empty());
+
+        let records = store.query(&HistoryQuery::new());
+        let ids: Vec<u32> = records.iter().map(|record| record.request_id).collect();
+        assert_eq!(ids, [284, 285]);
+        let failed = store.query(&HistoryQuery::new().success(false));
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].exit_code, Some(1));
+        let latest = store.query(&HistoryQuery::new().limit(1));
+        assert_eq!(latest[0].request_id, 285);
+        _ = std::fs::remove_file(&path);
+    }
+}
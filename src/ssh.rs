@@ -0,0 +1,168 @@
+use crate::shell_script;
+use crate::ProcessRequest;
+use std::ffi::OsString;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// How to authenticate with the SSH server
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SshAuth {
+    /// Keys of the SSH agent or the default identity files
+    #[default]
+    Agent,
+    /// This private key file
+    KeyFile(PathBuf),
+}
+
+/// Host to run the request on over SSH using the system `ssh` client, see [`ProcessRequest::remote`].
+/// The command line (including the pipeline, env & working directory) runs in the remote user's POSIX shell,
+/// the STDOUT & STDERR of the remote command are streamed as the [`crate::ProcessEvent::IOData`] events
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RemoteTarget {
+    /// Host name or address
+    pub host: String,
+    /// SSH port, for the default (22 or as per the ssh config) use None
+    pub port: Option<u16>,
+    /// Remote user, for the default (current user or as per the ssh config) use None
+    pub user: Option<String>,
+    /// Authentication method, password authentication is not supported as it's interactive
+    pub auth: SshAuth,
+    /// Hosts tried in order once the host is unreachable, by the [`crate::SshConnectionPool`] executor. Without the
+    /// pool only the host is used
+    pub failover_hosts: Vec<String>,
+    /// Share one connection to the host between the requests, set by the [`crate::SshConnectionPool`]. For a new
+    /// connection per request use None
+    pub multiplex: Option<SshMultiplex>,
+}
+
+/// OpenSSH connection multiplexing: the first request opens a master connection listening on the control socket,
+/// the following ones to the same host, port & user run over it without a new handshake. Unix only
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct SshMultiplex {
+    /// Path of the control socket, the `ssh_config` tokens are expanded e.g. `%C` (hash of the connection)
+    pub control_path: PathBuf,
+    /// Keep the idle master connection open this long after the last request
+    pub persist: Duration,
+}
+
+/// options of the ssh client to reach the target, without the host
+pub(crate) fn connection_options(remote: &RemoteTarget) -> Vec<OsString> {
+    let mut options: Vec<OsString> = vec![
+        // never prompt, fail instead
+        "-o".into(),
+        "BatchMode=yes".into(),
+    ];
+    if let Some(multiplex) = remote.multiplex.as_ref() {
+        let mut control_path = OsString::from("ControlPath=");
+        control_path.push(&multiplex.control_path);
+        options.extend([
+            "-o".into(),
+            "ControlMaster=auto".into(),
+            "-o".into(),
+            control_path,
+            "-o".into(),
+            format!("ControlPersist={}", multiplex.persist.as_secs().max(1)).into(),
+        ]);
+    }
+    if let Some(port) = remote.port {
+        options.push("-p".into());
+        options.push(port.to_string().into());
+    }
+    if let SshAuth::KeyFile(key_file) = &remote.auth {
+        options.push("-i".into());
+        options.push(key_file.into());
+    }
+    options
+}
+
+/// destination of the ssh client, `user@host` or `host`
+pub(crate) fn destination(remote: &RemoteTarget) -> String {
+    match remote.user.as_ref() {
+        Some(user) => format!("{}@{}", user, remote.host),
+        None => remote.host.clone(),
+    }
+}
+
+/// argv of the local ssh client to run the request on the remote target
+pub(crate) fn ssh_argv(
+    remote: &RemoteTarget,
+    request: &ProcessRequest,
+) -> io::Result<Vec<OsString>> {
+    let mut argv: Vec<OsString> = vec!["ssh".into()];
+    argv.extend(connection_options(remote));
+    // the end of the options, so a destination starting with `-` can't inject an option
+    argv.push("--".into());
+    argv.push(destination(remote).into());
+    argv.push(shell_script::command_line(request)?.into());
+    Ok(argv)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ssh::ssh_argv;
+    use crate::{ProcessRequest, RemoteTarget, SshAuth};
+    use std::io;
+
+    #[test]
+    pub fn test_ssh_argv() {
+        let request = ProcessRequest {
+            cmd_line: vec![
+                vec![String::from("grep"), String::from("it's here")],
+                vec![String::from("wc"), String::from("-l")],
+            ],
+            env: [(String::from("LANG"), String::from("C"))].into(),
+            working_dir: Some("/var/log".into()),
+            ..Default::default()
+        };
+        let remote = RemoteTarget {
+            host: String::from("example.com"),
+            port: Some(2222),
+            user: Some(String::from("ops")),
+            auth: SshAuth::KeyFile("/keys/id_ed25519".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            ssh_argv(&remote, &request).unwrap(),
+            [
+                "ssh",
+                "-o",
+                "BatchMode=yes",
+                "-p",
+                "2222",
+                "-i",
+                "/keys/id_ed25519",
+                "--",
+                "ops@example.com",
+                r"export LANG=C; cd /var/log && grep 'it'\''s here' | wc -l"
+            ]
+        );
+
+        let remote = RemoteTarget {
+            host: String::from("-oProxyCommand=touch /tmp/injected"),
+            ..Default::default()
+        };
+        let argv = ssh_argv(&remote, &request).unwrap();
+        let destination = argv
+            .iter()
+            .position(|arg| arg == "-oProxyCommand=touch /tmp/injected")
+            .unwrap();
+        assert_eq!(argv[destination - 1], "--");
+        assert!(!argv[..destination - 1].contains(&"--".into()));
+
+        let request = ProcessRequest {
+            cmd_line: vec![vec![String::from("uptime")]],
+            env: [(String::from("X=1; rm -rf ~;Y"), String::from("1"))].into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            ssh_argv(&remote, &request).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use crate::ssh::{connection_options, destination};
+use crate::{
+    check_and_trigger_callback, ProcessData, ProcessEvent, ProcessExecutor, ProcessRequest,
+    ProcessResult, RemoteTarget, SshMultiplex,
+};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Executor running the SSH requests (see [`ProcessRequest::remote`]) over pooled connections: one master connection
+/// per host, port & user is opened on the first request & reused by the following ones (OpenSSH multiplexing, Unix
+/// only), so a request doesn't pay for a new handshake. Before a request its host is checked, an unreachable host
+/// fails over to the next of the [`RemoteTarget::failover_hosts`] with the [`ProcessEvent::HostFailover`] event.
+/// Share it between the requests as their [`ProcessRequest::executor`], the open connections are closed once it's
+/// dropped. The requests without the remote target run locally
+#[derive(Debug)]
+pub struct SshConnectionPool {
+    control_dir: PathBuf,
+    persist: Duration,
+    connect_timeout: Duration,
+    /// targets the pool has connected to, by the destination & port
+    connected: Mutex<BTreeMap<String, RemoteTarget>>,
+}
+
+impl SshConnectionPool {
+    /// Keep the control sockets in the directory (it's created if missing), an idle connection stays open for 10
+    /// minutes & a host is unreachable if it doesn't connect within 10 seconds
+    pub fn new(control_dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let control_dir = control_dir.into();
+        fs::create_dir_all(&control_dir)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            // the sockets give access to the connections, only for the current user
+            fs::set_permissions(&control_dir, fs::Permissions::from_mode(0o700))?;
+        }
+        Ok(Self {
+            control_dir,
+            persist: Duration::from_secs(600),
+            connect_timeout: Duration::from_secs(10),
+            connected: Mutex::new(BTreeMap::new()),
+        })
+    }
+
+    /// Keep an idle connection open this long after its last request
+    pub fn persist(mut self, persist: Duration) -> Self {
+        self.persist = persist;
+        self
+    }
+
+    /// Consider a host unreachable if it doesn't connect within this duration
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+
+    /// Destinations (`user@host:port`) the pool has connected to
+    pub fn connected_hosts(&self) -> Vec<String> {
+        self.connected.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// Close the open connections, the next requests connect again
+    pub fn close_all(&self) {
+        let connected = std::mem::take(&mut *self.connected.lock().unwrap());
+        for target in connected.values() {
+            _ = ssh(target, &["-O", "exit"], &[]);
+        }
+    }
+
+    /// target with the connection sharing of the pool
+    fn pooled(&self, remote: &RemoteTarget, host: &str) -> RemoteTarget {
+        RemoteTarget {
+            host: host.to_string(),
+            failover_hosts: vec![],
+            multiplex: cfg!(unix).then(|| SshMultiplex {
+                control_path: self.control_dir.join("%C"),
+                persist: self.persist,
+            }),
+            ..remote.clone()
+        }
+    }
+
+    /// check the host is reachable, opening the shared connection unless it's open
+    fn connect(&self, target: &RemoteTarget) -> io::Result<()> {
+        if target.multiplex.is_some() && ssh(target, &["-O", "check"], &[]).is_ok() {
+            return Ok(());
+        }
+        let timeout = format!("ConnectTimeout={}", self.connect_timeout.as_secs().max(1));
+        ssh(target, &["-o", &timeout], &["true"])?;
+        let key = match target.port {
+            Some(port) => format!("{}:{}", destination(target), port),
+            None => destination(target),
+        };
+        self.connected.lock().unwrap().insert(key, target.clone());
+        Ok(())
+    }
+}
+
+impl ProcessExecutor for SshConnectionPool {
+    fn execute(&self, request: Arc<ProcessRequest>) -> ProcessResult {
+        let Some(remote) = request.remote.as_ref() else {
+            return crate::spawn_process(request, None);
+        };
+        let hosts: Vec<&String> = std::iter::once(&remote.host)
+            .chain(&remote.failover_hosts)
+            .collect();
+        let mut process_data = ProcessData::new();
+        process_data.request = Some(Arc::clone(&request));
+        let mut errors = vec![];
+        for (index, host) in hosts.iter().enumerate() {
+            let target = self.pooled(remote, host);
+            match self.connect(&target) {
+                Ok(()) => {
+                    let attempt = ProcessRequest {
+                        remote: Some(target),
+                        ..(*request).clone()
+                    };
+                    return crate::spawn_process(Arc::new(attempt), None);
+                }
+                Err(error) => {
+                    errors.push(format!("{}: {}", host, error));
+                    if let Some(next) = hosts.get(index + 1) {
+                        process_data.line = format!(
+                            "{} is unreachable ({}), failing over to {}",
+                            host, error, next
+                        );
+                        check_and_trigger_callback(
+                            &request,
+                            &ProcessEvent::HostFailover,
+                            &process_data,
+                        );
+                    }
+                }
+            }
+        }
+        process_data.line = format!("None of the hosts is reachable: {}", errors.join("; "));
+        check_and_trigger_callback(&request, &ProcessEvent::StartError, &process_data)
+    }
+}
+
+impl Drop for SshConnectionPool {
+    fn drop(&mut self) {
+        self.close_all();
+    }
+}
+
+/// run the ssh client with the options & the remote command, a failure is an error with its STDERR
+fn ssh(target: &RemoteTarget, options: &[&str], command: &[&str]) -> io::Result<()> {
+    let mut argv = connection_options(target);
+    argv.extend(options.iter().map(Into::into));
+    // the end of the options, so a destination starting with `-` can't inject an option
+    argv.push("--".into());
+    argv.push(destination(target).into());
+    argv.extend(command.iter().map(Into::into));
+    let output = duct::cmd("ssh", argv)
+        .stdin_null()
+        .stdout_null()
+        .stderr_capture()
+        .unchecked()
+        .run()?;
+    if !output.status.success() {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ssh::ssh_argv;
+    use crate::{
+        ProcessData, ProcessEvent, ProcessRequest, ProcessResult, RemoteTarget, SshConnectionPool,
+    };
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[test]
+    pub fn test_ssh_connection_pool() {
+        let dir = std::env::temp_dir().join("pes_test_ssh_pool");
+        _ = std::fs::remove_dir_all(&dir);
+        let pool = Arc::new(
+            SshConnectionPool::new(&dir)
+                .unwrap()
+                .connect_timeout(Duration::from_secs(2)),
+        );
+        let remote = RemoteTarget {
+            host: String::from("127.0.0.1"),
+            // nothing listens on the port, the hosts are unreachable
+            port: Some(1),
+            failover_hosts: vec![String::from("localhost")],
+            ..Default::default()
+        };
+        if cfg!(unix) {
+            let argv = ssh_argv(
+                &pool.pooled(&remote, "localhost"),
+                &ProcessRequest::default(),
+            )
+            .unwrap();
+            let control_path = format!("ControlPath={}", dir.join("%C").display());
+            assert_eq!(
+                argv[1..9],
+                [
+                    "-o",
+                    "BatchMode=yes",
+                    "-o",
+                    "ControlMaster=auto",
+                    "-o",
+                    control_path.as_str(),
+                    "-o",
+                    "ControlPersist=600"
+                ]
+            );
+        }
+
+        let events = Arc::new(Mutex::new(vec![]));
+        let recorded = Arc::clone(&events);
+        let callback = move |event: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            recorded
+                .lock()
+                .unwrap()
+                .push((*event, data.line_to_owned()));
+            ProcessResult::new()
+        };
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 295,
+            cmd_line: vec![vec![String::from("uptime")]],
+            remote: Some(remote),
+            executor: Some(pool.clone()),
+            callback: Some(Arc::new(callback)),
+            ..Default::default()
+        });
+        assert!(!result.spawned);
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].0, ProcessEvent::HostFailover);
+        assert!(events[0].1.starts_with("127.0.0.1 is unreachable"));
+        assert!(events[0].1.ends_with("failing over to localhost"));
+        assert_eq!(events[1].0, ProcessEvent::StartError);
+        assert!(pool.connected_hosts().is_empty());
+        _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,154 @@
+use std::ffi::OsString;
+use std::io;
+
+/// A command of the pipeline with its own shell mode, see [`crate::ProcessRequest::stages`]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct PipelineStage {
+    /// Command & its arguments, or the shell command line parts if `shell` is set
+    pub argv: Vec<String>,
+    /// Run the command in the shell
+    pub shell: bool,
+}
+
+impl PipelineStage {
+    /// Stage running the executable directly
+    pub fn direct(argv: &[&str]) -> Self {
+        Self {
+            argv: argv.iter().map(|arg| arg.to_string()).collect(),
+            shell: false,
+        }
+    }
+
+    /// Stage running the command line in the shell
+    pub fn shell(command_line: &str) -> Self {
+        Self {
+            argv: vec![command_line.to_string()],
+            shell: true,
+        }
+    }
+}
+
+/// Details of a single command of a multi command pipeline, available with the
+/// [`crate::ProcessEvent::StageStarted`] & [`crate::ProcessEvent::StageExited`] events
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct StageInfo {
+    /// Position of the command in the pipeline, starting from 0
+    pub index: usize,
+    /// Pid of the command
+    pub pid: Option<u32>,
+    /// Exit code of the command, None if unknown (e.g. killed, or left of a failed command)
+    pub exit_code: Option<i32>,
+}
+
+/// Exit codes of the pipeline stages. duct checks every stage & reports the right most failed one, so on success all
+/// the stages exited with 0 & on failure the stages right of the failed one exited with 0
+pub(crate) fn stage_exit_codes(
+    stage_argvs: &[Vec<OsString>],
+    result: Result<(), &io::Error>,
+) -> Vec<Option<i32>> {
+    let error = match result {
+        Ok(()) => return vec![Some(0); stage_argvs.len()],
+        Err(error) => error,
+    };
+    let mut exit_codes = vec![None; stage_argvs.len()];
+    let message = error.to_string();
+    let failed = stage_argvs
+        .iter()
+        .rposition(|argv| message.starts_with(&format!("command {:?} exited with code ", argv)));
+    if let Some(failed) = failed {
+        exit_codes[failed] = crate::status::exit_code(error);
+        for exit_code in exit_codes.iter_mut().skip(failed + 1) {
+            *exit_code = Some(0);
+        }
+    }
+    exit_codes
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        PipelineStage, ProcessData, ProcessEvent, ProcessRequest, ProcessResult, StageInfo,
+    };
+    use std::sync::{Arc, Mutex};
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_stage_events() {
+        let stages = Arc::new(Mutex::new(vec![]));
+        let events = Arc::clone(&stages);
+        let request = ProcessRequest {
+            request_id: 171,
+            cmd_line: vec![
+                vec![String::from("echo"), String::from("stage")],
+                vec![String::from("false")],
+                vec![String::from("cat")],
+            ],
+            callback: Some(Arc::new(move |event: &ProcessEvent, data: &ProcessData| {
+                if let Some(stage) = data.stage.as_ref() {
+                    events.lock().unwrap().push((*event, stage.clone()));
+                }
+                ProcessResult::new()
+            })),
+            ..Default::default()
+        };
+        let result = ProcessRequest::start(request);
+        assert_eq!(result.exit_code, Some(1));
+        let stages = stages.lock().unwrap();
+        assert_eq!(stages.len(), 6);
+        for (index, (event, stage)) in stages[..3].iter().enumerate() {
+            assert_eq!(*event, ProcessEvent::StageStarted);
+            assert_eq!(stage.index, index);
+            assert!(stage.pid.is_some());
+        }
+        let exit_codes: Vec<(ProcessEvent, Option<i32>)> = stages[3..]
+            .iter()
+            .map(|(event, stage)| (*event, stage.exit_code))
+            .collect();
+        assert_eq!(
+            exit_codes,
+            [
+                (ProcessEvent::StageExited, None),
+                (ProcessEvent::StageExited, Some(1)),
+                (ProcessEvent::StageExited, Some(0))
+            ]
+        );
+        assert_eq!(
+            stages[4].1,
+            StageInfo {
+                index: 1,
+                pid: stages[1].1.pid,
+                exit_code: Some(1)
+            }
+        );
+    }
+
+    #[test]
+    pub fn test_mixed_stages() {
+        let lines = Arc::new(Mutex::new(vec![]));
+        let output = Arc::clone(&lines);
+        let request = ProcessRequest {
+            request_id: 181,
+            use_shell: true,
+            cmd_line: vec![vec![String::from("ignored")]],
+            stages: vec![
+                PipelineStage::direct(&["echo", "a $HOME b"]),
+                PipelineStage::shell("tr a-z A-Z"),
+            ],
+            callback: Some(Arc::new(move |event: &ProcessEvent, data: &ProcessData| {
+                if *event == ProcessEvent::IOData {
+                    output
+                        .lock()
+                        .unwrap()
+                        .push(data.line.trim_end().to_string());
+                }
+                ProcessResult::new()
+            })),
+            ..Default::default()
+        };
+        let result = ProcessRequest::start(request);
+        assert_eq!(result.exit_code, Some(0));
+        assert_eq!(*lines.lock().unwrap(), ["A $HOME B"]);
+    }
+}
@@ -0,0 +1,61 @@
+use std::io;
+
+/// duct reports a non-zero exit as "command ... exited with code N" or "... exited with code <signal N>"
+const EXIT_CODE_PREFIX: &str = "exited with code ";
+
+/// Exit code of the process from the checked read error of duct
+pub(crate) fn exit_code(error: &io::Error) -> Option<i32> {
+    let message = error.to_string();
+    let start = message.rfind(EXIT_CODE_PREFIX)? + EXIT_CODE_PREFIX.len();
+    message[start..].trim().parse().ok()
+}
+
+/// Signal which terminated the process from the checked read error of duct
+pub(crate) fn exit_signal(error: &io::Error) -> Option<i32> {
+    let message = error.to_string();
+    let start = message.rfind("<signal ")? + "<signal ".len();
+    let end = start + message[start..].find('>')?;
+    message[start..end].parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ProcessData, ProcessEvent, ProcessRequest, ProcessResult};
+    use std::sync::{Arc, Mutex};
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_unchecked_exit_status() {
+        let run = |request_id: u32, unchecked_exit_status: bool| {
+            let events = Arc::new(Mutex::new(vec![]));
+            let recorded = Arc::clone(&events);
+            let callback = move |event: &ProcessEvent, _data: &ProcessData| -> ProcessResult {
+                if matches!(
+                    event,
+                    ProcessEvent::IOError | ProcessEvent::IOEof | ProcessEvent::Exited
+                ) {
+                    recorded.lock().unwrap().push(*event);
+                }
+                ProcessResult::new()
+            };
+            let result = ProcessRequest::start(ProcessRequest {
+                request_id,
+                use_shell: true,
+                cmd_line: vec![vec![String::from("echo no match; exit 1")]],
+                success_exit_codes: Some(vec![0, 1]),
+                unchecked_exit_status,
+                callback: Some(Arc::new(callback)),
+                ..Default::default()
+            });
+            assert_eq!(result.exit_code, Some(1));
+            assert!(result.success.unwrap());
+            let events = events.lock().unwrap().clone();
+            events
+        };
+        assert_eq!(
+            run(305, false),
+            [ProcessEvent::IOError, ProcessEvent::Exited]
+        );
+        assert_eq!(run(306, true), [ProcessEvent::IOEof, ProcessEvent::Exited]);
+    }
+}
@@ -0,0 +1,223 @@
+use crate::PriorityInheritance;
+use duct::ReaderHandle;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Read};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+/// Output stream of the process an output line was written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OutputStream {
+    /// STDOUT of the process
+    Stdout,
+    /// STDERR of the process
+    Stderr,
+}
+
+/// Origin of an output line in the [`crate::ProcessRequest::tag_output_streams`] mode, see
+/// [`crate::ProcessData::origin`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StreamOrigin {
+    /// Stream the line was written to
+    pub stream: OutputStream,
+    /// Position of the line among the lines of both the streams (starting from 1), in the order they were read off
+    /// the pipes. The lines written close together to different streams may be read in another order than written,
+    /// a gap between the sequences of consecutive lines of a stream shows where the other stream interleaved
+    pub sequence: u64,
+    /// Line number within its own stream, starting from 1
+    pub stream_line_number: u64,
+}
+
+/// message of a stream reading thread
+enum Chunk {
+    Line(Vec<u8>, StreamOrigin),
+    Closed(OutputStream, io::Result<()>),
+}
+
+/// numbers the lines of both the streams in the order they are read
+struct Sequencer {
+    sender: mpsc::Sender<Chunk>,
+    sequence: u64,
+}
+
+/// Merges the lines of the STDOUT & STDERR read by two threads into one output, keeping the origin of every line in
+/// the shared tags. It ends once both the streams are closed, with the result of the STDOUT reader which has waited
+/// for the process
+pub(crate) struct StreamMerger {
+    receiver: mpsc::Receiver<Chunk>,
+    tags: StreamTags,
+    chunk: Vec<u8>,
+    position: usize,
+    open_streams: usize,
+    stdout_result: Option<io::Result<()>>,
+}
+
+/// Origins of the lines delivered by the [`StreamMerger`] & not taken yet, in the order of the lines
+#[derive(Clone, Default)]
+pub(crate) struct StreamTags(Arc<Mutex<VecDeque<StreamOrigin>>>);
+
+impl StreamTags {
+    /// origin of the next line read from the merged output
+    pub(crate) fn next(&self) -> Option<StreamOrigin> {
+        self.0.lock().unwrap().pop_front()
+    }
+}
+
+impl StreamMerger {
+    /// read the STDOUT from the reader & the STDERR from the pipe in the background, the threads adopt the inherited
+    /// priority & stop once this merger is dropped & their next read returns
+    pub(crate) fn start(
+        stdout: Arc<ReaderHandle>,
+        stderr: io::PipeReader,
+        inherit_priority: Option<PriorityInheritance>,
+    ) -> (Self, StreamTags) {
+        let (sender, receiver) = mpsc::channel();
+        let sequencer = Arc::new(Mutex::new(Sequencer {
+            sender,
+            sequence: 0,
+        }));
+        let stdout_sequencer = Arc::clone(&sequencer);
+        // detached, they end with the output of the process
+        thread::spawn(move || {
+            let _thread = crate::diagnostics::track_thread();
+            crate::priority::adopt_inherited(inherit_priority);
+            read_stream(&*stdout, OutputStream::Stdout, &stdout_sequencer);
+        });
+        thread::spawn(move || {
+            let _thread = crate::diagnostics::track_thread();
+            crate::priority::adopt_inherited(inherit_priority);
+            read_stream(stderr, OutputStream::Stderr, &sequencer);
+        });
+        let tags = StreamTags::default();
+        let merger = Self {
+            receiver,
+            tags: tags.clone(),
+            chunk: vec![],
+            position: 0,
+            open_streams: 2,
+            stdout_result: None,
+        };
+        (merger, tags)
+    }
+}
+
+impl Read for StreamMerger {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.chunk.len() {
+            if self.open_streams == 0 {
+                return match self.stdout_result.take() {
+                    Some(Err(error)) => Err(error),
+                    _ => Ok(0),
+                };
+            }
+            match self.receiver.recv() {
+                Ok(Chunk::Line(line, origin)) => {
+                    self.tags.0.lock().unwrap().push_back(origin);
+                    self.chunk = line;
+                    self.position = 0;
+                }
+                Ok(Chunk::Closed(stream, result)) => {
+                    self.open_streams -= 1;
+                    if stream == OutputStream::Stdout {
+                        self.stdout_result = Some(result);
+                    }
+                }
+                Err(_) => self.open_streams = 0,
+            }
+        }
+        let count = buf.len().min(self.chunk.len() - self.position);
+        buf[..count].copy_from_slice(&self.chunk[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+/// send the lines of the stream till it's closed, a last line without the line break gets one so every line has a
+/// single tag
+fn read_stream(stream: impl Read, origin: OutputStream, sequencer: &Mutex<Sequencer>) {
+    let mut reader = BufReader::new(stream);
+    let mut stream_line_number = 0;
+    let result = loop {
+        let mut line = vec![];
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) => break Ok(()),
+            Ok(_) => {
+                if !line.ends_with(b"\n") {
+                    line.push(b'\n');
+                }
+                stream_line_number += 1;
+                // the sequence is taken & the line sent under the lock, so the merged order follows the sequence
+                let mut sequencer = sequencer.lock().unwrap();
+                sequencer.sequence += 1;
+                let origin = StreamOrigin {
+                    stream: origin,
+                    sequence: sequencer.sequence,
+                    stream_line_number,
+                };
+                if sequencer.sender.send(Chunk::Line(line, origin)).is_err() {
+                    return;
+                }
+            }
+            Err(error) => break Err(error),
+        }
+    };
+    let sequencer = sequencer.lock().unwrap();
+    _ = sequencer.sender.send(Chunk::Closed(origin, result));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{OutputStream, ProcessData, ProcessEvent, ProcessRequest, ProcessResult};
+    use std::sync::{Arc, Mutex};
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_tag_output_streams() {
+        let lines = Arc::new(Mutex::new(vec![]));
+        let captured = Arc::clone(&lines);
+        let callback = move |event: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            if *event == ProcessEvent::IOData {
+                captured
+                    .lock()
+                    .unwrap()
+                    .push((data.line_to_owned(), data.origin.unwrap()));
+            }
+            ProcessResult::new()
+        };
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 293,
+            use_shell: true,
+            cmd_line: vec![vec![String::from(
+                "echo out1; sleep 0.1; echo err1 >&2; sleep 0.1; echo out2; sleep 0.1; printf err2 >&2; exit 3",
+            )]],
+            tag_output_streams: true,
+            callback: Some(Arc::new(callback)),
+            ..Default::default()
+        });
+        assert_eq!(result.exit_code, Some(3));
+
+        let lines = lines.lock().unwrap();
+        let tagged: Vec<(&str, OutputStream, u64, u64)> = lines
+            .iter()
+            .map(|(line, origin)| {
+                (
+                    line.as_str(),
+                    origin.stream,
+                    origin.sequence,
+                    origin.stream_line_number,
+                )
+            })
+            .collect();
+        assert_eq!(
+            tagged,
+            [
+                ("out1", OutputStream::Stdout, 1, 1),
+                ("err1", OutputStream::Stderr, 2, 1),
+                ("out2", OutputStream::Stdout, 3, 2),
+                ("err2", OutputStream::Stderr, 4, 2),
+            ]
+        );
+    }
+}
@@ -0,0 +1,409 @@
+use crate::latch::Latch;
+use crate::notifier::send_notification;
+use crate::retry::start_process_with_retry;
+use crate::{
+    check_and_trigger_callback, Backoff, LineTransform, NotificationKind, ProcessData,
+    ProcessEvent, ProcessRequest, ProcessResult, ReloadAction,
+};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// max distinct output lines remembered for the duplicate suppression
+const MAX_DEDUPE_LINES: usize = 10_000;
+
+/// Restart policy of a [`Supervisor`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SupervisorPolicy {
+    /// Delay before a restart, for the exponential backoff the consecutive restarts within the window are counted
+    pub backoff: Backoff,
+    /// Max restarts allowed within the `restart_window`, once exceeded the process is not restarted anymore
+    pub max_restarts: u32,
+    /// Sliding time window to count the restarts
+    pub restart_window: Duration,
+    /// Restart the process even when it exited successfully, see [`crate::ProcessRequest::success_exit_codes`]
+    pub restart_on_success: bool,
+    /// How the running process is asked to reload, see [`Supervisor::reload`]
+    pub reload_action: ReloadAction,
+    /// Suppress the output lines repeated from the earlier runs, to keep a crash-looping process from flooding the
+    /// logs with the same startup output & errors. The lines are compared after the
+    /// [`crate::ProcessRequest::line_transforms`], so strip the timestamps there. Once the supervisor finishes, a
+    /// [`ProcessEvent::RepeatedOutput`] event with the count is emitted for every suppressed line & every
+    /// [`ProcessEvent::Restarted`] event tells how many lines the last run suppressed
+    pub dedupe_output: bool,
+}
+
+impl Default for SupervisorPolicy {
+    fn default() -> Self {
+        Self {
+            backoff: Backoff::Exponential {
+                initial: Duration::from_millis(100),
+                max: Duration::from_secs(30),
+            },
+            max_restarts: 5,
+            restart_window: Duration::from_secs(60),
+            restart_on_success: true,
+            reload_action: ReloadAction::default(),
+            dedupe_output: false,
+        }
+    }
+}
+
+/// Keeps a long running process(e.g. sidecar daemon) running by restarting it on exit.
+/// The process runs in its own thread irrespective of [`ProcessRequest::non_blocking_mode`].
+pub struct Supervisor {
+    request: Arc<ProcessRequest>,
+    reload_action: ReloadAction,
+    /// pids of the running pipeline, empty in between the runs
+    pids: Arc<Mutex<Vec<u32>>>,
+    stop: Arc<Latch>,
+    restarts: Arc<AtomicU32>,
+    reloads: AtomicU32,
+    join_handle: JoinHandle<ProcessResult>,
+}
+
+impl Supervisor {
+    /// Start supervising the process of the request as per the policy
+    pub fn start(process_request: ProcessRequest, policy: SupervisorPolicy) -> io::Result<Self> {
+        let pids = Arc::new(Mutex::new(vec![]));
+        let mut process_request = track_pids(process_request, Arc::clone(&pids));
+        let deduper = policy.dedupe_output.then(|| {
+            let deduper = Arc::new(OutputDeduper::default());
+            let admitted = Arc::clone(&deduper);
+            process_request
+                .line_transforms
+                .push(LineTransform::Map(Arc::new(move |line: &str| {
+                    admitted.admit(line)
+                })));
+            deduper
+        });
+        let request = Arc::new(process_request);
+        let reload_action = policy.reload_action.clone();
+        let stop = Arc::new(Latch::new());
+        let restarts = Arc::new(AtomicU32::new(0));
+        let join_handle = {
+            let request = Arc::clone(&request);
+            let stop = Arc::clone(&stop);
+            let restarts = Arc::clone(&restarts);
+            thread::Builder::new()
+                .name(format!("pes_sv_rq_{}", request.request_id))
+                .spawn(move || {
+                    let result = supervise(&request, policy, &stop, &restarts, deduper.as_deref());
+                    if let Some(deduper) = deduper {
+                        deduper.emit_repeated(&request);
+                    }
+                    result
+                })?
+        };
+        Ok(Self {
+            request,
+            reload_action,
+            pids,
+            stop,
+            restarts,
+            reloads: AtomicU32::new(0),
+            join_handle,
+        })
+    }
+
+    /// Ask the running process to reload (e.g. its configuration) as per the [`SupervisorPolicy::reload_action`]
+    /// instead of restarting it. Emits [`ProcessEvent::ReloadRequested`], then [`ProcessEvent::Reloaded`] once the
+    /// action succeeded. Fails if the process is not running at the moment
+    pub fn reload(&self) -> io::Result<()> {
+        let pids = self.pids.lock().unwrap().clone();
+        let reload = self.reloads.load(Ordering::SeqCst) + 1;
+        let mut process_data = ProcessData::new();
+        process_data.request = Some(Arc::clone(&self.request));
+        process_data
+            .line
+            .push_str(format!("Reload #{} by {:?}", reload, self.reload_action).as_str());
+        check_and_trigger_callback(&self.request, &ProcessEvent::ReloadRequested, &process_data);
+        self.reload_action.perform(&pids)?;
+        self.reloads.fetch_add(1, Ordering::SeqCst);
+        check_and_trigger_callback(&self.request, &ProcessEvent::Reloaded, &process_data);
+        Ok(())
+    }
+
+    /// Total number of successful reloads so far
+    pub fn reloads(&self) -> u32 {
+        self.reloads.load(Ordering::SeqCst)
+    }
+
+    /// Total number of restarts so far
+    pub fn restarts(&self) -> u32 {
+        self.restarts.load(Ordering::SeqCst)
+    }
+
+    /// Supervisor is over, either stopped or restart limit reached
+    pub fn is_finished(&self) -> bool {
+        self.join_handle.is_finished()
+    }
+
+    /// Kill the running process, stop restarting it and wait for the supervisor to finish.
+    /// Returns the result of the last run
+    pub fn stop(self) -> thread::Result<ProcessResult> {
+        self.stop.set();
+        self.join_handle.join()
+    }
+
+    /// Wait for the supervisor to finish (restart limit reached), returns the result of the last run
+    pub fn join(self) -> thread::Result<ProcessResult> {
+        self.join_handle.join()
+    }
+}
+
+/// keep the pids of the running pipeline up to date, ahead of the callback of the request
+// the wrapped callback is not Send & Sync, same as any callback of the request
+#[allow(clippy::arc_with_non_send_sync)]
+fn track_pids(mut process_request: ProcessRequest, pids: Arc<Mutex<Vec<u32>>>) -> ProcessRequest {
+    let callback = process_request.callback.take();
+    process_request.callback = Some(Arc::new(
+        move |event: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            match event {
+                ProcessEvent::Started => *pids.lock().unwrap() = data.child_pids(),
+                ProcessEvent::Exited => pids.lock().unwrap().clear(),
+                _ => {}
+            }
+            match callback.as_ref() {
+                Some(callback) => callback(event, data),
+                None => ProcessResult::new(),
+            }
+        },
+    ));
+    process_request
+}
+
+/// Output lines seen in the runs of a supervised process, to suppress the ones repeated from the earlier runs
+#[derive(Default)]
+struct OutputDeduper {
+    state: Mutex<DedupeState>,
+}
+
+#[derive(Default)]
+struct DedupeState {
+    /// distinct lines of the earlier runs
+    seen: HashSet<String>,
+    /// distinct lines of the current run
+    current: HashSet<String>,
+    /// suppressed lines in the order of their first suppression
+    suppressed: Vec<String>,
+    counts: HashMap<String, u64>,
+    /// lines suppressed in the current run
+    run_suppressed: u64,
+}
+
+impl OutputDeduper {
+    /// the line if it's not repeated from an earlier run, a line may repeat within the same run
+    fn admit(&self, line: &str) -> Option<String> {
+        let mut state = self.state.lock().unwrap();
+        if state.seen.contains(line) {
+            state.run_suppressed += 1;
+            match state.counts.get_mut(line) {
+                Some(count) => *count += 1,
+                None => {
+                    state.suppressed.push(line.to_string());
+                    state.counts.insert(line.to_string(), 1);
+                }
+            }
+            return None;
+        }
+        if state.seen.len() + state.current.len() < MAX_DEDUPE_LINES {
+            state.current.insert(line.to_string());
+        }
+        Some(line.to_string())
+    }
+
+    /// the lines of the finished run become the earlier lines, returns the number of lines it suppressed
+    fn end_run(&self) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        let current = std::mem::take(&mut state.current);
+        state.seen.extend(current);
+        std::mem::take(&mut state.run_suppressed)
+    }
+
+    /// emit a repeated output event for every suppressed line with its count
+    fn emit_repeated(&self, request: &Arc<ProcessRequest>) {
+        let mut state = self.state.lock().unwrap();
+        let suppressed = std::mem::take(&mut state.suppressed);
+        for line in suppressed {
+            let mut process_data = ProcessData::new();
+            process_data.request = Some(Arc::clone(request));
+            process_data.repeat_count = state.counts.get(&line).copied();
+            process_data.line = line;
+            check_and_trigger_callback(request, &ProcessEvent::RepeatedOutput, &process_data);
+        }
+    }
+}
+
+/// run & restart the process till stopped or the restart limit is reached
+fn supervise(
+    request: &Arc<ProcessRequest>,
+    policy: SupervisorPolicy,
+    stop: &Latch,
+    restarts: &AtomicU32,
+    deduper: Option<&OutputDeduper>,
+) -> ProcessResult {
+    let mut restarted_at: Vec<Instant> = vec![];
+    loop {
+        let result = start_process_with_retry(Arc::clone(request), Some(stop));
+        let suppressed = deduper.map(OutputDeduper::end_run).unwrap_or_default();
+        let succeeded = result.success.as_ref().is_ok_and(|success| *success);
+        if stop.is_set() || (!policy.restart_on_success && succeeded) {
+            return result;
+        }
+        if !succeeded {
+            send_notification(request, NotificationKind::Failed, &result, String::new());
+        }
+        let now = Instant::now();
+        restarted_at.retain(|time| now.duration_since(*time) < policy.restart_window);
+        let mut process_data = ProcessData::new();
+        process_data.request = Some(Arc::clone(request));
+        if restarted_at.len() >= policy.max_restarts as usize {
+            process_data.line.push_str(
+                format!(
+                    "{} restarts within {:?}, giving up",
+                    restarted_at.len(),
+                    policy.restart_window
+                )
+                .as_str(),
+            );
+            check_and_trigger_callback(request, &ProcessEvent::RestartLimitReached, &process_data);
+            send_notification(
+                request,
+                NotificationKind::CrashLoop,
+                &result,
+                process_data.line,
+            );
+            return result;
+        }
+        restarted_at.push(now);
+        let delay = policy.backoff.delay(restarted_at.len() as u32);
+        if stop.wait_timeout(delay) {
+            return result;
+        }
+        let restart = restarts.fetch_add(1, Ordering::SeqCst) + 1;
+        process_data.line.push_str(
+            format!(
+                "Restart #{} after exit code {:?}",
+                restart, result.exit_code
+            )
+            .as_str(),
+        );
+        if suppressed > 0 {
+            process_data
+                .line
+                .push_str(format!(", {} repeated output lines suppressed", suppressed).as_str());
+        }
+        check_and_trigger_callback(request, &ProcessEvent::Restarted, &process_data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Backoff, ProcessData, ProcessEvent, ProcessRequest, ProcessResult, Supervisor,
+        SupervisorPolicy,
+    };
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[test]
+    #[cfg(unix)]
+    pub fn test_supervisor_restart_limit() {
+        let supervisor = Supervisor::start(
+            ProcessRequest {
+                request_id: 301,
+                use_shell: true,
+                cmd_line: vec![vec![String::from("exit 1")]],
+                ..Default::default()
+            },
+            SupervisorPolicy {
+                backoff: Backoff::Fixed(Duration::from_millis(10)),
+                max_restarts: 2,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let result = supervisor.join().unwrap();
+        assert_eq!(result.exit_code, Some(1));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    pub fn test_supervisor_stop() {
+        let supervisor = Supervisor::start(
+            ProcessRequest {
+                request_id: 311,
+                cmd_line: vec![vec![String::from("sleep"), String::from("10")]],
+                ..Default::default()
+            },
+            SupervisorPolicy::default(),
+        )
+        .unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(!supervisor.is_finished());
+        supervisor.stop().unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    pub fn test_supervisor_dedupe_output() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let recorded = Arc::clone(&events);
+        let callback = move |event: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            recorded
+                .lock()
+                .unwrap()
+                .push((*event, data.line_to_owned(), data.repeat_count));
+            ProcessResult::new()
+        };
+        let supervisor = Supervisor::start(
+            ProcessRequest {
+                request_id: 312,
+                use_shell: true,
+                cmd_line: vec![vec![String::from("echo starting; echo run $$; exit 1")]],
+                callback: Some(Arc::new(callback)),
+                ..Default::default()
+            },
+            SupervisorPolicy {
+                backoff: Backoff::Fixed(Duration::from_millis(10)),
+                max_restarts: 2,
+                dedupe_output: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        supervisor.join().unwrap();
+        let events = events.lock().unwrap();
+        let output = |line: &str| {
+            events
+                .iter()
+                .filter(|(event, data_line, _)| *event == ProcessEvent::IOData && data_line == line)
+                .count()
+        };
+        assert_eq!(output("starting"), 1);
+        let runs = events
+            .iter()
+            .filter(|(event, line, _)| *event == ProcessEvent::IOData && line.starts_with("run "))
+            .count();
+        assert_eq!(runs, 3);
+        let restarted: Vec<&String> = events
+            .iter()
+            .filter(|(event, _, _)| *event == ProcessEvent::Restarted)
+            .map(|(_, line, _)| line)
+            .collect();
+        assert_eq!(restarted.len(), 2);
+        assert!(restarted[1].ends_with(", 1 repeated output lines suppressed"));
+        assert_eq!(
+            events.last().unwrap(),
+            &(
+                ProcessEvent::RepeatedOutput,
+                String::from("starting"),
+                Some(2)
+            )
+        );
+    }
+}
@@ -0,0 +1,243 @@
+use crate::latch::Latch;
+use crate::{
+    check_and_trigger_callback, ProcessData, ProcessEvent, ProcessExecutor, ProcessRequest,
+    ProcessResult, Termination,
+};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Request following the file like `tail -F`: the lines appended from now on are delivered as the
+/// [`ProcessEvent::IOData`] events, a rotation as the [`ProcessEvent::FileRotated`] event. It runs till the callback
+/// requests the exit, use a [`TailExecutor`] directly to stop it from elsewhere
+pub fn tail_file(path: impl AsRef<Path>) -> ProcessRequest {
+    let path = path.as_ref();
+    ProcessRequest {
+        cmd_line: vec![vec![
+            String::from("tail"),
+            String::from("-F"),
+            path.to_string_lossy().into_owned(),
+        ]],
+        executor: Some(Arc::new(TailExecutor::new(path))),
+        ..Default::default()
+    }
+}
+
+/// Executor following a file natively instead of spawning a process, see [`tail_file`]. The file is polled, it may be
+/// missing at the start. A rotation is detected once the path refers to another file (on Unix) or the file shrinks,
+/// the rest of the old file is delivered before the new one is followed from its start
+#[derive(Debug)]
+pub struct TailExecutor {
+    path: PathBuf,
+    from_start: bool,
+    poll_interval: Duration,
+    stop: Latch,
+}
+
+impl TailExecutor {
+    /// Follow the file from its current end, polling it every 100 ms
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            from_start: false,
+            poll_interval: Duration::from_millis(100),
+            stop: Latch::new(),
+        }
+    }
+
+    /// Deliver the existing lines of the file too
+    pub fn from_start(mut self) -> Self {
+        self.from_start = true;
+        self
+    }
+
+    /// Check the file for the new lines & rotations at this interval
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Stop following the file, the execution exits as if killed by request
+    pub fn stop(&self) {
+        self.stop.set();
+    }
+
+    /// open the file, positioned at its start or end
+    fn open(&self, at_end: bool) -> Option<(BufReader<File>, FileIdentity)> {
+        let mut file = File::open(&self.path).ok()?;
+        let identity = FileIdentity::of(&file.metadata().ok()?);
+        if at_end {
+            file.seek(SeekFrom::End(0)).ok()?;
+        }
+        Some((BufReader::new(file), identity))
+    }
+
+    /// why the followed file is not the one at the path anymore, None if it still is
+    fn rotation(&self, reader: &mut BufReader<File>, identity: &FileIdentity) -> Option<&str> {
+        let metadata = fs::metadata(&self.path).ok()?;
+        if FileIdentity::of(&metadata) != *identity {
+            return Some("replaced");
+        }
+        let position = reader.stream_position().ok()?;
+        (metadata.len() < position).then_some("truncated")
+    }
+}
+
+impl ProcessExecutor for TailExecutor {
+    fn execute(&self, request: Arc<ProcessRequest>) -> ProcessResult {
+        let mut process_data = ProcessData::new();
+        process_data.request = Some(Arc::clone(&request));
+        process_data
+            .line
+            .push_str(&format!("Following {}", self.path.display()));
+        check_and_trigger_callback(&request, &ProcessEvent::Started, &process_data);
+        let mut process_result = ProcessResult::new();
+        let mut opened = self.open(!self.from_start);
+        let mut line = String::new();
+        'follow: while !self.stop.is_set() {
+            let Some((reader, identity)) = opened.as_mut() else {
+                // the file is missing, it's followed once created
+                if self.stop.wait_timeout(self.poll_interval) {
+                    break;
+                }
+                opened = self.open(false);
+                continue;
+            };
+            // a partial last line is kept till its line break is written
+            match reader.read_line(&mut line) {
+                Ok(_) if line.ends_with('\n') => {
+                    process_data.line_number += 1;
+                    process_data.line = std::mem::take(&mut line);
+                    process_result =
+                        check_and_trigger_callback(&request, &ProcessEvent::IOData, &process_data);
+                    if process_result.should_exit == Some(true) {
+                        check_and_trigger_callback(
+                            &request,
+                            &ProcessEvent::ExitRequested,
+                            &process_data,
+                        );
+                        break 'follow;
+                    }
+                    continue;
+                }
+                Ok(_) => {}
+                Err(error) => {
+                    process_data.line = format!("{:?}", error);
+                    check_and_trigger_callback(&request, &ProcessEvent::IOError, &process_data);
+                    line.clear();
+                }
+            }
+            if let Some(reason) = self.rotation(reader, identity) {
+                line.clear();
+                process_data.line = format!("{} was {}", self.path.display(), reason);
+                check_and_trigger_callback(&request, &ProcessEvent::FileRotated, &process_data);
+                opened = self.open(false);
+                continue;
+            }
+            if self.stop.wait_timeout(self.poll_interval) {
+                break;
+            }
+        }
+        process_data.line.clear();
+        process_data.termination = Some(Termination::Killed { by_request: true });
+        check_and_trigger_callback(&request, &ProcessEvent::Exited, &process_data);
+        process_result.set_exited(None);
+        process_result.termination = process_data.termination;
+        process_result
+    }
+}
+
+/// identity of a file to tell a replaced file apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileIdentity {
+    #[cfg(unix)]
+    device: u64,
+    #[cfg(unix)]
+    inode: u64,
+    #[cfg(not(unix))]
+    created: Option<std::time::SystemTime>,
+}
+
+impl FileIdentity {
+    #[cfg(unix)]
+    fn of(metadata: &fs::Metadata) -> Self {
+        use std::os::unix::fs::MetadataExt;
+
+        Self {
+            device: metadata.dev(),
+            inode: metadata.ino(),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn of(metadata: &fs::Metadata) -> Self {
+        Self {
+            created: metadata.created().ok(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tail_file, ProcessData, ProcessEvent, ProcessRequest, ProcessResult};
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_tail_file() {
+        let dir = std::env::temp_dir().join("pes_test_tail");
+        _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("app.log");
+        std::fs::write(&path, "old\n").unwrap();
+
+        let events = Arc::new(Mutex::new(vec![]));
+        let recorded = Arc::clone(&events);
+        let callback = move |event: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            let mut result = ProcessResult::new();
+            if matches!(event, ProcessEvent::IOData | ProcessEvent::FileRotated) {
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push((*event, data.line_to_owned()));
+            }
+            if data.line_str() == "stop" {
+                result.set_exit_flag_and_success(true, Ok(true));
+            }
+            result
+        };
+        let request = ProcessRequest {
+            request_id: 292,
+            callback: Some(Arc::new(callback)),
+            non_blocking_mode: true,
+            ..tail_file(&path)
+        };
+        let result = ProcessRequest::start(request);
+        std::thread::sleep(Duration::from_millis(300));
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(b"first\nsec").unwrap();
+        std::thread::sleep(Duration::from_millis(300));
+        file.write_all(b"ond\n").unwrap();
+        std::thread::sleep(Duration::from_millis(300));
+        // rotate, the new file is followed from its start
+        std::fs::rename(&path, dir.join("app.log.1")).unwrap();
+        file.write_all(b"last of old\n").unwrap();
+        std::fs::write(&path, "new\nstop\n").unwrap();
+        let result = result.wait().unwrap();
+        assert_eq!(result.exit_code, None);
+
+        let events = events.lock().unwrap();
+        let lines: Vec<&str> = events.iter().map(|(_, line)| line.as_str()).collect();
+        assert_eq!(lines[..3], ["first", "second", "last of old"]);
+        assert_eq!(events[3].0, ProcessEvent::FileRotated);
+        assert_eq!(lines[4..], ["new", "stop"]);
+        _ = std::fs::remove_dir_all(&dir);
+    }
+}
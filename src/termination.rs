@@ -0,0 +1,159 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// How the process terminated, available with the [`crate::ProcessEvent::Exited`] event as
+/// [`crate::ProcessData::termination`] & in [`crate::ProcessResult::termination`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Termination {
+    /// Process exited by itself with the exit code
+    Exited {
+        /// Exit code of the process (of the right most failed command for a pipeline)
+        code: i32,
+    },
+    /// Process was terminated by a signal which wasn't sent by this library (e.g. a resource limit or another process)
+    Signaled {
+        /// Signal number
+        signal: i32,
+    },
+    /// Process was killed by this library
+    Killed {
+        /// Killed as requested by the API consumer ([`crate::ProcessData::kill`], an exit request or a stop),
+        /// otherwise by a watchdog like the timeout
+        by_request: bool,
+    },
+}
+
+const NOT_KILLED: u8 = 0;
+const KILLED_BY_REQUEST: u8 = 1;
+const KILLED_BY_WATCHDOG: u8 = 2;
+
+/// Who killed the process of an execution, the first kill wins
+pub(crate) struct KillRecord {
+    killed: AtomicU8,
+}
+
+impl KillRecord {
+    pub(crate) fn new() -> Self {
+        Self {
+            killed: AtomicU8::new(NOT_KILLED),
+        }
+    }
+
+    /// record the kill, unless already recorded
+    pub(crate) fn record(&self, by_request: bool) {
+        let killed = if by_request {
+            KILLED_BY_REQUEST
+        } else {
+            KILLED_BY_WATCHDOG
+        };
+        _ = self
+            .killed
+            .compare_exchange(NOT_KILLED, killed, Ordering::SeqCst, Ordering::SeqCst);
+    }
+
+    /// the termination if the process was killed
+    pub(crate) fn termination(&self) -> Option<Termination> {
+        match self.killed.load(Ordering::SeqCst) {
+            NOT_KILLED => None,
+            killed => Some(Termination::Killed {
+                by_request: killed == KILLED_BY_REQUEST,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ProcessData, ProcessEvent, ProcessRequest, ProcessResult, Termination};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    fn run(
+        request_id: u32,
+        command: &str,
+        configure: impl Fn(&mut ProcessRequest),
+    ) -> ProcessResult {
+        let mut request = ProcessRequest {
+            request_id,
+            use_shell: true,
+            cmd_line: vec![vec![String::from(command)]],
+            ..Default::default()
+        };
+        configure(&mut request);
+        ProcessRequest::start(request)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_termination() {
+        let result = run(241, "exit 3", |_| {});
+        assert_eq!(result.termination, Some(Termination::Exited { code: 3 }));
+        let result = run(242, "kill -TERM $$", |_| {});
+        assert_eq!(
+            result.termination,
+            Some(Termination::Signaled { signal: 15 })
+        );
+        // killing the shell doesn't kill its children holding the output, so run sleep directly
+        let result = run(243, "", |request| {
+            request.use_shell = false;
+            request.cmd_line = vec![vec![String::from("sleep"), String::from("10")]];
+            request.timeout = Some(Duration::from_millis(100));
+        });
+        assert_eq!(
+            result.termination,
+            Some(Termination::Killed { by_request: false })
+        );
+        let result = run(244, "echo a; exec sleep 10", |request| {
+            request.callback = Some(Arc::new(|event: &ProcessEvent, _data: &ProcessData| {
+                let mut result = ProcessResult::new();
+                if *event == ProcessEvent::IOData {
+                    result.set_exit_flag_and_success(true, Ok(true));
+                }
+                result
+            }))
+        });
+        assert_eq!(
+            result.termination,
+            Some(Termination::Killed { by_request: true })
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_exit_categories() {
+        let categories = Arc::new(Mutex::new(vec![]));
+        let configure = |request: &mut ProcessRequest| {
+            request.exit_categories = [
+                (2, String::from("usage error")),
+                (137, String::from("OOM killed")),
+            ]
+            .into();
+            let recorded = Arc::clone(&categories);
+            request.callback = Some(Arc::new(
+                move |event: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+                    if *event == ProcessEvent::Exited {
+                        recorded
+                            .lock()
+                            .unwrap()
+                            .push(data.exit_category().map(String::from));
+                    }
+                    ProcessResult::new()
+                },
+            ));
+        };
+        let result = run(302, "exit 2", configure);
+        assert_eq!(result.exit_category.as_deref(), Some("usage error"));
+        let result = run(303, "kill -KILL $$", configure);
+        assert_eq!(result.exit_category.as_deref(), Some("OOM killed"));
+        let result = run(304, "exit 1", configure);
+        assert_eq!(result.exit_category, None);
+        assert_eq!(
+            *categories.lock().unwrap(),
+            [
+                Some(String::from("usage error")),
+                Some(String::from("OOM killed")),
+                None
+            ]
+        );
+    }
+}
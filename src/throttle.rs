@@ -0,0 +1,105 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Cap on how fast the output is consumed, see [`crate::ProcessRequest::output_throttle`]. The reading is paused once
+/// the output gets ahead of the rate, so the pipe fills up & the process blocks on its writes (backpressure), e.g. to
+/// pace a log generator during a load test
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct OutputThrottle {
+    /// Max output lines per second, for no line rate use None
+    pub max_lines_per_second: Option<u32>,
+    /// Max output bytes (including the line breaks) per second, for no byte rate use None
+    pub max_bytes_per_second: Option<u64>,
+}
+
+impl OutputThrottle {
+    /// Consume at most this many lines per second
+    pub fn lines_per_second(max_lines_per_second: u32) -> Self {
+        Self {
+            max_lines_per_second: Some(max_lines_per_second),
+            max_bytes_per_second: None,
+        }
+    }
+
+    /// Consume at most this many bytes per second
+    pub fn bytes_per_second(max_bytes_per_second: u64) -> Self {
+        Self {
+            max_lines_per_second: None,
+            max_bytes_per_second: Some(max_bytes_per_second),
+        }
+    }
+
+    /// time the line takes as per the slower of the rates
+    fn cost(&self, line_bytes: usize) -> Duration {
+        let line_cost = self
+            .max_lines_per_second
+            .map_or(0.0, |rate| 1.0 / rate.max(1) as f64);
+        let byte_cost = self
+            .max_bytes_per_second
+            .map_or(0.0, |rate| line_bytes as f64 / rate.max(1) as f64);
+        Duration::from_secs_f64(line_cost.max(byte_cost))
+    }
+}
+
+/// Paces the output reading of an execution as per its throttle
+pub(crate) struct OutputPacer {
+    throttle: OutputThrottle,
+    /// earliest time to consume the next line
+    due: Option<Instant>,
+    throttled: Duration,
+}
+
+impl OutputPacer {
+    pub(crate) fn new(throttle: OutputThrottle) -> Self {
+        Self {
+            throttle,
+            due: None,
+            throttled: Duration::ZERO,
+        }
+    }
+
+    /// wait till the line read just now may be consumed, returns how long it waited
+    pub(crate) fn pace(&mut self, line_bytes: usize) -> Duration {
+        let now = Instant::now();
+        let wait = self.due.map_or(Duration::ZERO, |due| due - now.min(due));
+        if !wait.is_zero() {
+            thread::sleep(wait);
+            self.throttled += wait;
+        }
+        self.due = Some(now + wait + self.throttle.cost(line_bytes));
+        wait
+    }
+
+    /// total time the reading was paused
+    pub(crate) fn throttled(&self) -> Duration {
+        self.throttled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{OutputThrottle, ProcessRequest};
+    use std::time::{Duration, Instant};
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_output_throttle() {
+        let started = Instant::now();
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 289,
+            use_shell: true,
+            cmd_line: vec![vec![String::from("for i in 1 2 3 4 5 6; do echo $i; done")]],
+            output_throttle: Some(OutputThrottle::lines_per_second(20)),
+            ..Default::default()
+        });
+        // 5 pauses of 50 ms between the 6 lines
+        assert!(started.elapsed() >= Duration::from_millis(250));
+        assert!(result.output_throttled >= Duration::from_millis(200));
+        assert_eq!(result.exit_code, Some(0));
+
+        let throttle = OutputThrottle::bytes_per_second(1000);
+        assert_eq!(throttle.cost(100), Duration::from_millis(100));
+    }
+}
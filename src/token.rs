@@ -0,0 +1,286 @@
+use crate::ProcessPriority;
+use duct::Expression;
+use std::fmt;
+use std::io;
+
+/// Mandatory integrity level of the process token, a lower one can't write to the objects (files, registry keys,
+/// processes) of a higher one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IntegrityLevel {
+    /// Low integrity, as of the sandboxed browser processes, it can write to the low integrity locations only (e.g.
+    /// `%USERPROFILE%\AppData\LocalLow`)
+    #[default]
+    Low,
+    /// Medium integrity, as of the standard user processes, e.g. to drop the elevation of an administrator
+    Medium,
+}
+
+/// Restricted token to run the process with, to contain untrusted commands. Windows only
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RestrictedToken {
+    /// Integrity level of the token, it can only be lowered
+    pub integrity_level: IntegrityLevel,
+    /// Remove all the privileges from the token (e.g. `SeShutdownPrivilege`, `SeChangeNotifyPrivilege`)
+    pub remove_privileges: bool,
+}
+
+/// Failure of restricting the token of the process, told apart from the other start errors
+#[derive(Debug)]
+struct TokenError(io::Error);
+
+impl fmt::Display for TokenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to restrict the process token: {}", self.0)
+    }
+}
+
+impl std::error::Error for TokenError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+fn token_error(error: io::Error) -> io::Error {
+    io::Error::new(error.kind(), TokenError(error))
+}
+
+/// Detail of the start error if it's a failure of restricting the process token
+pub(crate) fn start_error_detail(error: &io::Error) -> Option<String> {
+    let token_error = error.get_ref()?.downcast_ref::<TokenError>()?;
+    Some(format!("{}: {:?}", token_error, token_error.0))
+}
+
+/// Spawn all the commands of the expression suspended, their token is restricted once they are spawned, see
+/// [`restrict_spawned_processes`]. The priority class is a creation flag too, so it's set along as the creation
+/// flags replace each other
+#[cfg(windows)]
+pub(crate) fn apply_restricted_token(
+    expression: Expression,
+    token: Option<&RestrictedToken>,
+    priority: Option<ProcessPriority>,
+) -> io::Result<Expression> {
+    use std::os::windows::process::CommandExt;
+    use windows_sys::Win32::System::Threading::CREATE_SUSPENDED;
+
+    if token.is_none() {
+        return Ok(expression);
+    }
+    let flags = CREATE_SUSPENDED | priority.map_or(0, crate::priority::priority_class);
+    Ok(expression.before_spawn(move |command| {
+        command.creation_flags(flags);
+        Ok(())
+    }))
+}
+
+/// Restricted tokens are not available on this platform
+#[cfg(not(windows))]
+pub(crate) fn apply_restricted_token(
+    expression: Expression,
+    token: Option<&RestrictedToken>,
+    _priority: Option<ProcessPriority>,
+) -> io::Result<Expression> {
+    if token.is_none() {
+        return Ok(expression);
+    }
+    Err(token_error(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Restricted tokens are supported on Windows only",
+    )))
+}
+
+/// Restrict the token of the suspended processes, then resume them. On failure they are left suspended to be killed
+#[cfg(windows)]
+pub(crate) fn restrict_spawned_processes(
+    pids: &[u32],
+    token: Option<&RestrictedToken>,
+) -> io::Result<()> {
+    let Some(token) = token else {
+        return Ok(());
+    };
+    for pid in pids {
+        unsafe { restrict_process(*pid, token) }.map_err(token_error)?;
+    }
+    for pid in pids {
+        unsafe { crate::pause::set_threads_suspended(*pid, false) }?;
+    }
+    Ok(())
+}
+
+/// Token is already restricted on this platform
+#[cfg(not(windows))]
+pub(crate) fn restrict_spawned_processes(
+    _pids: &[u32],
+    _token: Option<&RestrictedToken>,
+) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(windows)]
+unsafe fn restrict_process(pid: u32, token: &RestrictedToken) -> io::Result<()> {
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::Security::{
+        TOKEN_ADJUST_DEFAULT, TOKEN_ADJUST_PRIVILEGES, TOKEN_QUERY,
+    };
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, OpenProcessToken, PROCESS_QUERY_INFORMATION,
+    };
+
+    let process = OpenProcess(PROCESS_QUERY_INFORMATION, 0, pid);
+    if process.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+    let mut handle = std::ptr::null_mut();
+    let access = TOKEN_ADJUST_DEFAULT | TOKEN_ADJUST_PRIVILEGES | TOKEN_QUERY;
+    let opened = OpenProcessToken(process, access, &mut handle);
+    let error = io::Error::last_os_error();
+    CloseHandle(process);
+    if opened == 0 {
+        return Err(error);
+    }
+    let mut result = set_integrity_level(handle, token.integrity_level);
+    if result.is_ok() && token.remove_privileges {
+        result = remove_privileges(handle);
+    }
+    CloseHandle(handle);
+    result
+}
+
+#[cfg(windows)]
+unsafe fn set_integrity_level(
+    token: windows_sys::Win32::Foundation::HANDLE,
+    level: IntegrityLevel,
+) -> io::Result<()> {
+    use windows_sys::Win32::Security::{
+        AllocateAndInitializeSid, FreeSid, GetLengthSid, SetTokenInformation, TokenIntegrityLevel,
+        SECURITY_MANDATORY_LABEL_AUTHORITY, SID_AND_ATTRIBUTES, TOKEN_MANDATORY_LABEL,
+    };
+    use windows_sys::Win32::System::SystemServices::{
+        SECURITY_MANDATORY_LOW_RID, SECURITY_MANDATORY_MEDIUM_RID, SE_GROUP_INTEGRITY,
+    };
+
+    let rid = match level {
+        IntegrityLevel::Low => SECURITY_MANDATORY_LOW_RID,
+        IntegrityLevel::Medium => SECURITY_MANDATORY_MEDIUM_RID,
+    };
+    let mut sid = std::ptr::null_mut();
+    if AllocateAndInitializeSid(
+        &SECURITY_MANDATORY_LABEL_AUTHORITY,
+        1,
+        rid as u32,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        &mut sid,
+    ) == 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    let label = TOKEN_MANDATORY_LABEL {
+        Label: SID_AND_ATTRIBUTES {
+            Sid: sid,
+            Attributes: SE_GROUP_INTEGRITY as u32,
+        },
+    };
+    let size = std::mem::size_of::<TOKEN_MANDATORY_LABEL>() as u32 + GetLengthSid(sid);
+    let result = SetTokenInformation(
+        token,
+        TokenIntegrityLevel,
+        &label as *const TOKEN_MANDATORY_LABEL as *const std::ffi::c_void,
+        size,
+    );
+    let error = io::Error::last_os_error();
+    FreeSid(sid);
+    if result == 0 {
+        return Err(error);
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+unsafe fn remove_privileges(token: windows_sys::Win32::Foundation::HANDLE) -> io::Result<()> {
+    use windows_sys::Win32::Foundation::{GetLastError, ERROR_NOT_ALL_ASSIGNED};
+    use windows_sys::Win32::Security::{
+        AdjustTokenPrivileges, GetTokenInformation, TokenPrivileges, LUID_AND_ATTRIBUTES,
+        SE_PRIVILEGE_REMOVED, TOKEN_PRIVILEGES,
+    };
+
+    let mut size = 0;
+    GetTokenInformation(token, TokenPrivileges, std::ptr::null_mut(), 0, &mut size);
+    // u64 for the alignment of the privileges
+    let mut buffer = vec![0u64; (size as usize).div_ceil(8)];
+    if GetTokenInformation(
+        token,
+        TokenPrivileges,
+        buffer.as_mut_ptr() as *mut std::ffi::c_void,
+        size,
+        &mut size,
+    ) == 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    let privileges = buffer.as_mut_ptr() as *mut TOKEN_PRIVILEGES;
+    let entries = std::slice::from_raw_parts_mut(
+        std::ptr::addr_of_mut!((*privileges).Privileges) as *mut LUID_AND_ATTRIBUTES,
+        (*privileges).PrivilegeCount as usize,
+    );
+    for entry in entries {
+        entry.Attributes = SE_PRIVILEGE_REMOVED;
+    }
+    let adjusted = AdjustTokenPrivileges(
+        token,
+        0,
+        privileges,
+        0,
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+    );
+    if adjusted == 0 || GetLastError() == ERROR_NOT_ALL_ASSIGNED {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ProcessData, ProcessEvent, ProcessRequest, ProcessResult, RestrictedToken};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    pub fn test_restricted_token() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let recorded = Arc::clone(&events);
+        let callback = move |event: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            if matches!(event, ProcessEvent::IOData | ProcessEvent::StartError) {
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push((*event, data.line_to_owned()));
+            }
+            ProcessResult::new()
+        };
+        ProcessRequest::start(ProcessRequest {
+            request_id: 276,
+            callback: Some(Arc::new(callback)),
+            use_shell: true,
+            cmd_line: vec![vec![String::from("whoami /groups | findstr Mandatory")]],
+            restricted_token: Some(RestrictedToken::default()),
+            ..Default::default()
+        });
+        let events = events.lock().unwrap();
+        if cfg!(windows) {
+            assert!(events
+                .iter()
+                .any(|(_, line)| line.contains("Low Mandatory Level")));
+        } else {
+            assert_eq!(events[0].0, ProcessEvent::StartError);
+            assert!(events[0].1.contains("Failed to restrict the process token"));
+        }
+    }
+}
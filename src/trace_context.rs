@@ -0,0 +1,162 @@
+use crate::ProcessRequest;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// environment variable of the W3C trace context
+const TRACEPARENT: &str = "TRACEPARENT";
+
+/// ids generated by this process, keeps them unique within the process
+static GENERATED: AtomicU64 = AtomicU64::new(0);
+
+/// Correlation id of a request as a W3C trace context (`traceparent`), see [`ProcessRequest::trace_context`]. It's set
+/// in the environment of the process (also on the remote backends) so that the work of the child joins the trace &
+/// it's available on every event with [`crate::ProcessData::traceparent`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct TraceContext {
+    /// `traceparent` value (`00-<trace id>-<parent id>-<flags>`) of the caller, for a new trace generated at every start
+    /// use None
+    pub traceparent: Option<String>,
+    /// Environment variable of the process set to the traceparent
+    pub env_var: String,
+}
+
+impl Default for TraceContext {
+    fn default() -> Self {
+        Self {
+            traceparent: None,
+            env_var: String::from(TRACEPARENT),
+        }
+    }
+}
+
+impl TraceContext {
+    /// Generate a new trace for every start, set as `TRACEPARENT`
+    pub fn generate() -> Self {
+        Self::default()
+    }
+
+    /// Continue the trace of the caller, set as `TRACEPARENT`
+    pub fn from_traceparent(traceparent: impl Into<String>) -> Self {
+        Self {
+            traceparent: Some(traceparent.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Set the traceparent as this environment variable of the process instead
+    pub fn env_var(mut self, env_var: impl Into<String>) -> Self {
+        self.env_var = env_var.into();
+        self
+    }
+
+    /// Trace id part of the traceparent, None if it's not generated yet or not a traceparent
+    pub fn trace_id(&self) -> Option<&str> {
+        let mut parts = self.traceparent.as_deref()?.split('-');
+        parts.next()?;
+        parts.next().filter(|trace_id| !trace_id.is_empty())
+    }
+}
+
+/// new traceparent with a random trace id & parent id, sampled
+pub(crate) fn generate_traceparent() -> String {
+    let random = || {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(GENERATED.fetch_add(1, Ordering::Relaxed));
+        hasher.write_u128(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos(),
+        );
+        hasher.finish()
+    };
+    format!("00-{:016x}{:016x}-{:016x}-01", random(), random(), random())
+}
+
+/// the request with the traceparent resolved & set in its env, the same request if it has no trace context or the
+/// traceparent is set already
+pub(crate) fn resolve(request: Arc<ProcessRequest>) -> Arc<ProcessRequest> {
+    let Some(trace_context) = request.trace_context.as_ref() else {
+        return request;
+    };
+    let traceparent = match trace_context.traceparent.as_ref() {
+        Some(traceparent) if request.env.get(&trace_context.env_var) == Some(traceparent) => {
+            return request;
+        }
+        Some(traceparent) => traceparent.clone(),
+        None => generate_traceparent(),
+    };
+    let mut resolved = (*request).clone();
+    resolved
+        .env
+        .insert(trace_context.env_var.clone(), traceparent.clone());
+    resolved.trace_context = Some(TraceContext {
+        traceparent: Some(traceparent),
+        env_var: trace_context.env_var.clone(),
+    });
+    Arc::new(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ProcessData, ProcessEvent, ProcessRequest, ProcessResult, TraceContext};
+    use std::sync::{Arc, Mutex};
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_trace_context() {
+        let run = |request_id: u32, trace_context: TraceContext| {
+            let events = Arc::new(Mutex::new(vec![]));
+            let recorded = Arc::clone(&events);
+            let callback = move |event: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+                recorded.lock().unwrap().push((
+                    *event,
+                    data.line_to_owned(),
+                    data.traceparent().map(String::from),
+                ));
+                ProcessResult::new()
+            };
+            let result = ProcessRequest::start(ProcessRequest {
+                request_id,
+                use_shell: true,
+                cmd_line: vec![vec![String::from("echo \"$TRACEPARENT$CORRELATION_ID\"")]],
+                trace_context: Some(trace_context),
+                callback: Some(Arc::new(callback)),
+                ..Default::default()
+            });
+            assert!(result.success.unwrap());
+            let events = events.lock().unwrap().clone();
+            events
+        };
+
+        let traceparent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let events = run(
+            300,
+            TraceContext::from_traceparent(traceparent).env_var("CORRELATION_ID"),
+        );
+        assert!(events.len() >= 3);
+        assert!(events
+            .iter()
+            .all(|(_, _, event_traceparent)| event_traceparent.as_deref() == Some(traceparent)));
+        assert!(events.contains(&(
+            ProcessEvent::IOData,
+            String::from(traceparent),
+            Some(String::from(traceparent))
+        )));
+
+        let events = run(310, TraceContext::generate());
+        let (_, line, generated) = events
+            .iter()
+            .find(|(event, _, _)| *event == ProcessEvent::IOData)
+            .unwrap();
+        assert_eq!(generated.as_ref(), Some(line));
+        let context = TraceContext::from_traceparent(line.as_str());
+        assert_eq!(context.trace_id().unwrap().len(), 32);
+        assert_ne!(line, traceparent);
+    }
+}
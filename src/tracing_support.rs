@@ -0,0 +1,65 @@
+use crate::{ProcessData, ProcessEvent, ProcessRequest};
+use tracing::field::Empty;
+use tracing::{debug, error, info, trace, warn, Level, Span};
+
+/// span of a single process execution, the pid field is recorded once the process is started
+pub(crate) fn execution_span(request: &ProcessRequest) -> Span {
+    tracing::span!(
+        Level::INFO,
+        "process",
+        request_id = request.request_id,
+        cmd = ?request.pipeline_stages(),
+        run_sequence = request.run_sequence,
+        traceparent = request
+            .trace_context
+            .as_ref()
+            .and_then(|trace_context| trace_context.traceparent.as_deref()),
+        pid = Empty,
+    )
+}
+
+/// record the pids of the started process(es) on the current span
+pub(crate) fn record_pids(pids: &[u32]) {
+    let span = Span::current();
+    match pids {
+        [pid] => span.record("pid", pid),
+        pids => span.record("pid", tracing::field::debug(pids)),
+    };
+}
+
+/// emit a tracing event for the process event, output lines are at trace level & failures at warn/error level
+pub(crate) fn trace_event(event: &ProcessEvent, data: &ProcessData) {
+    let request_id = data.request.as_ref().map(|request| request.request_id);
+    let line = data.line_str();
+    match event {
+        ProcessEvent::IOData => {
+            trace!(?request_id, line_number = data.line_number, line, "output")
+        }
+        ProcessEvent::ResourceSample => {
+            trace!(?request_id, usage = ?data.resource_usage, "resource sample")
+        }
+        ProcessEvent::Heartbeat => {
+            trace!(?request_id, heartbeat = ?data.heartbeat, "heartbeat")
+        }
+        ProcessEvent::StartError | ProcessEvent::IOError | ProcessEvent::KillError => {
+            error!(?request_id, event = ?event, line)
+        }
+        ProcessEvent::ResourceLimitExceeded
+        | ProcessEvent::IdleTimeout
+        | ProcessEvent::ReadStalled
+        | ProcessEvent::OutputLimitExceeded
+        | ProcessEvent::TimedOut
+        | ProcessEvent::JsonParseError
+        | ProcessEvent::ExpectationFailed
+        | ProcessEvent::FailPatternMatched
+        | ProcessEvent::GoldenMismatch
+        | ProcessEvent::Warning(_)
+        | ProcessEvent::RestartLimitReached
+        | ProcessEvent::HostFailover
+        | ProcessEvent::PolicyViolation
+        | ProcessEvent::QuotaExceeded
+        | ProcessEvent::RetryScheduled => warn!(?request_id, event = ?event, line),
+        ProcessEvent::Started | ProcessEvent::Exited => info!(?request_id, event = ?event, line),
+        event => debug!(?request_id, event = ?event, line),
+    }
+}
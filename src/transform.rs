@@ -0,0 +1,218 @@
+use regex::Regex;
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+
+/// Custom line transformation, returns the new line or None to drop the line
+pub type LineMapper = dyn Fn(&str) -> Option<String> + Send + Sync;
+
+/// A stage of the transformations applied to every output line before it's delivered, see
+/// [`crate::ProcessRequest::line_transforms`]. The stages work on the line without its line break
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LineTransform {
+    /// Remove the leading & trailing whitespace
+    Trim,
+    /// Remove the prefix if the line starts with it
+    StripPrefix(String),
+    /// Keep only the lines matching the regular expression, the others are dropped
+    Filter(String),
+    /// Replace the matches of the regular expression with the replacement, which may refer to the groups e.g.
+    /// `(password=)\S+` with `${1}***`
+    Redact {
+        /// Regular expression of the sensitive text
+        pattern: String,
+        /// Replacement of the matches
+        replacement: String,
+    },
+    /// Replace a JSON object line with the value of its field, a dotted path for the nested objects e.g.
+    /// `request.path`. A string value is delivered without the quotes, a line which is not JSON or has no such field
+    /// is dropped
+    #[cfg(feature = "json")]
+    JsonField(String),
+    /// Custom transformation
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Map(Arc<LineMapper>),
+}
+
+impl fmt::Debug for LineTransform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LineTransform::Trim => write!(f, "Trim"),
+            LineTransform::StripPrefix(prefix) => {
+                f.debug_tuple("StripPrefix").field(prefix).finish()
+            }
+            LineTransform::Filter(pattern) => f.debug_tuple("Filter").field(pattern).finish(),
+            LineTransform::Redact {
+                pattern,
+                replacement,
+            } => f
+                .debug_struct("Redact")
+                .field("pattern", pattern)
+                .field("replacement", replacement)
+                .finish(),
+            #[cfg(feature = "json")]
+            LineTransform::JsonField(path) => f.debug_tuple("JsonField").field(path).finish(),
+            LineTransform::Map(_) => write!(f, "Map(..)"),
+        }
+    }
+}
+
+/// compiled transformation stage
+enum Stage {
+    Trim,
+    StripPrefix(String),
+    Filter(Regex),
+    Redact(Regex, String),
+    #[cfg(feature = "json")]
+    JsonField(Vec<String>),
+    Map(Arc<LineMapper>),
+}
+
+/// Applies the line transformations of the request to the output lines
+pub(crate) struct LineTransformer {
+    stages: Vec<Stage>,
+}
+
+impl LineTransformer {
+    /// compile the transformations of the request, None if there are none
+    pub(crate) fn new(transforms: &[LineTransform]) -> io::Result<Option<Self>> {
+        if transforms.is_empty() {
+            return Ok(None);
+        }
+        let regex = |pattern: &str| {
+            Regex::new(pattern)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error.to_string()))
+        };
+        let stages = transforms
+            .iter()
+            .map(|transform| {
+                Ok(match transform {
+                    LineTransform::Trim => Stage::Trim,
+                    LineTransform::StripPrefix(prefix) => Stage::StripPrefix(prefix.clone()),
+                    LineTransform::Filter(pattern) => Stage::Filter(regex(pattern)?),
+                    LineTransform::Redact {
+                        pattern,
+                        replacement,
+                    } => Stage::Redact(regex(pattern)?, replacement.clone()),
+                    #[cfg(feature = "json")]
+                    LineTransform::JsonField(path) => {
+                        Stage::JsonField(path.split('.').map(String::from).collect())
+                    }
+                    LineTransform::Map(mapper) => Stage::Map(Arc::clone(mapper)),
+                })
+            })
+            .collect::<io::Result<Vec<Stage>>>()?;
+        Ok(Some(Self { stages }))
+    }
+
+    /// transform the line in place keeping its line break, false if the line is dropped
+    pub(crate) fn apply(&self, line: &mut String) -> bool {
+        let content_len = line.trim_end_matches(['\r', '\n']).len();
+        let line_break = line.split_off(content_len);
+        let mut content = std::mem::take(line);
+        for stage in &self.stages {
+            content = match stage {
+                Stage::Trim => content.trim().to_string(),
+                Stage::StripPrefix(prefix) => match content.strip_prefix(prefix.as_str()) {
+                    Some(rest) => rest.to_string(),
+                    None => content,
+                },
+                Stage::Filter(regex) if regex.is_match(&content) => content,
+                Stage::Filter(_) => return false,
+                Stage::Redact(regex, replacement) => regex
+                    .replace_all(&content, replacement.as_str())
+                    .into_owned(),
+                #[cfg(feature = "json")]
+                Stage::JsonField(path) => match json_field(&content, path) {
+                    Some(value) => value,
+                    None => return false,
+                },
+                Stage::Map(mapper) => match mapper(&content) {
+                    Some(content) => content,
+                    None => return false,
+                },
+            };
+        }
+        *line = content;
+        line.push_str(&line_break);
+        true
+    }
+}
+
+/// value of the field at the path of the JSON object line
+#[cfg(feature = "json")]
+fn json_field(line: &str, path: &[String]) -> Option<String> {
+    let mut value: serde_json::Value = serde_json::from_str(line).ok()?;
+    for field in path {
+        value = value.get_mut(field.as_str())?.take();
+    }
+    Some(match value {
+        serde_json::Value::String(text) => text,
+        value => value.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{LineTransform, ProcessData, ProcessEvent, ProcessRequest, ProcessResult};
+    use std::sync::{Arc, Mutex};
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_line_transforms() {
+        let lines = Arc::new(Mutex::new(vec![]));
+        let captured = Arc::clone(&lines);
+        let callback = move |event: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            if *event == ProcessEvent::IOData {
+                captured.lock().unwrap().push(data.line_to_owned());
+            }
+            ProcessResult::new()
+        };
+        let result = ProcessRequest::start(ProcessRequest {
+            request_id: 308,
+            use_shell: true,
+            cmd_line: vec![vec![String::from(
+                "printf '  [app] login password=hunter2  \\n[app] debug\\n  [app] done\\n'",
+            )]],
+            line_transforms: vec![
+                LineTransform::Trim,
+                LineTransform::StripPrefix(String::from("[app] ")),
+                LineTransform::Filter(String::from("^(login|done)")),
+                LineTransform::Redact {
+                    pattern: String::from(r"(password=)\S+"),
+                    replacement: String::from("${1}***"),
+                },
+                LineTransform::Map(Arc::new(|line: &str| Some(line.to_uppercase()))),
+            ],
+            callback: Some(Arc::new(callback)),
+            ..Default::default()
+        });
+        assert!(result.success.unwrap());
+        assert_eq!(*lines.lock().unwrap(), ["LOGIN PASSWORD=***", "DONE"]);
+    }
+
+    #[cfg(all(unix, feature = "json"))]
+    #[test]
+    pub fn test_json_field_transform() {
+        let lines = Arc::new(Mutex::new(vec![]));
+        let captured = Arc::clone(&lines);
+        let callback = move |event: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            if *event == ProcessEvent::IOData {
+                captured.lock().unwrap().push(data.line_to_owned());
+            }
+            ProcessResult::new()
+        };
+        ProcessRequest::start(ProcessRequest {
+            request_id: 309,
+            use_shell: true,
+            cmd_line: vec![vec![String::from(
+                r#"printf '{"request":{"path":"/a","status":200}}\nplain\n{"request":{"status":404}}\n'"#,
+            )]],
+            line_transforms: vec![LineTransform::JsonField(String::from("request.path"))],
+            callback: Some(Arc::new(callback)),
+            ..Default::default()
+        });
+        assert_eq!(*lines.lock().unwrap(), ["/a"]);
+    }
+}
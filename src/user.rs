@@ -0,0 +1,163 @@
+use duct::Expression;
+use std::io;
+
+/// User & groups to run the process as, e.g. for a privileged supervisor to drop the privileges. Unix only
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UserSpec {
+    /// User id
+    pub uid: u32,
+    /// Primary group id
+    pub gid: u32,
+    /// Supplementary group ids, the inherited ones are dropped
+    pub supplementary_groups: Vec<u32>,
+}
+
+/// Switch all the commands of the expression to the user before they are executed, after the other spawn options
+/// which may need the privileges (e.g. a higher priority)
+#[cfg(unix)]
+pub(crate) fn apply_run_as(
+    expression: Expression,
+    run_as: Option<&UserSpec>,
+) -> io::Result<Expression> {
+    use std::os::unix::process::CommandExt;
+
+    let Some(user) = run_as.cloned() else {
+        return Ok(expression);
+    };
+    Ok(expression.before_spawn(move |command| {
+        let user = user.clone();
+        unsafe {
+            command.pre_exec(move || switch_user(&user));
+        }
+        Ok(())
+    }))
+}
+
+/// Running as another user is not available on this platform
+#[cfg(not(unix))]
+pub(crate) fn apply_run_as(
+    expression: Expression,
+    run_as: Option<&UserSpec>,
+) -> io::Result<Expression> {
+    if run_as.is_none() {
+        return Ok(expression);
+    }
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Running as another user is supported on Unix only",
+    ))
+}
+
+/// set the groups first as they can't be changed once the user is switched, runs in the child after fork so must
+/// not allocate
+#[cfg(unix)]
+fn switch_user(user: &UserSpec) -> io::Result<()> {
+    let groups = &user.supplementary_groups;
+    unsafe {
+        if libc::setgroups(groups.len() as _, groups.as_ptr() as *const libc::gid_t) != 0
+            || libc::setgid(user.gid as libc::gid_t) != 0
+            || libc::setuid(user.uid as libc::uid_t) != 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Detail of the start error, a permission error of switching the user is told apart
+pub(crate) fn start_error_detail(run_as: Option<&UserSpec>, error: &io::Error) -> String {
+    match run_as {
+        Some(user) if error.kind() == io::ErrorKind::PermissionDenied => format!(
+            "Permission denied to run as uid {} gid {}: {:?}",
+            user.uid, user.gid, error
+        ),
+        _ => format!("{:?}", error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ProcessData, ProcessEvent, ProcessPriority, ProcessRequest, ProcessResult, UserSpec,
+    };
+    use std::sync::{Arc, Mutex};
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_run_as() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let recorded = Arc::clone(&events);
+        let callback = move |event: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            if matches!(event, ProcessEvent::IOData | ProcessEvent::StartError) {
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push((*event, data.line_to_owned()));
+            }
+            ProcessResult::new()
+        };
+        ProcessRequest::start(ProcessRequest {
+            request_id: 267,
+            callback: Some(Arc::new(callback)),
+            use_shell: true,
+            cmd_line: vec![vec![String::from("id -u; id -g; id -G")]],
+            run_as: Some(UserSpec {
+                uid: 65534,
+                gid: 65534,
+                supplementary_groups: vec![65534],
+            }),
+            ..Default::default()
+        });
+        let events = events.lock().unwrap();
+        if unsafe { libc::geteuid() } == 0 {
+            let lines: Vec<_> = events.iter().map(|(_, line)| line.as_str()).collect();
+            assert_eq!(lines, ["65534", "65534", "65534"]);
+        } else {
+            assert_eq!(events[0].0, ProcessEvent::StartError);
+            assert!(events[0]
+                .1
+                .contains("Permission denied to run as uid 65534"));
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    pub fn test_run_as_with_priority() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let recorded = Arc::clone(&events);
+        let callback = move |event: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            if matches!(event, ProcessEvent::IOData | ProcessEvent::StartError) {
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push((*event, data.line_to_owned()));
+            }
+            ProcessResult::new()
+        };
+        ProcessRequest::start(ProcessRequest {
+            request_id: 509,
+            callback: Some(Arc::new(callback)),
+            use_shell: true,
+            // the nice value is the 19th field of the stat
+            cmd_line: vec![vec![String::from(
+                "id -u; cut -d ' ' -f 19 /proc/self/stat",
+            )]],
+            run_as: Some(UserSpec {
+                uid: 65534,
+                gid: 65534,
+                supplementary_groups: vec![],
+            }),
+            priority: Some(ProcessPriority::High),
+            ..Default::default()
+        });
+        let events = events.lock().unwrap();
+        // the priority is raised before the privileges are dropped
+        if unsafe { libc::geteuid() } == 0 {
+            let lines: Vec<_> = events.iter().map(|(_, line)| line.as_str()).collect();
+            assert_eq!(lines, ["65534", "-10"]);
+        } else {
+            assert_eq!(events[0].0, ProcessEvent::StartError);
+        }
+    }
+}
@@ -0,0 +1,84 @@
+/// Non-fatal internal condition reported by the [`crate::ProcessEvent::Warning`] event, the data of the event is the
+/// one of the affected line or event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WarningKind {
+    /// The output line was truncated to fit the [`crate::ProcessRequest::max_output_bytes`]
+    LineTruncated,
+    /// Invalid UTF-8 of the output line was replaced with `U+FFFD` as per the [`crate::InvalidUtf8::Lossy`] policy
+    InvalidUtf8Replaced,
+    /// The event was dropped by the full [`crate::EventQueue`] as per its [`crate::OverflowPolicy`]
+    EventsDropped,
+    /// Writing the event to the [`crate::SessionRecorder`] or the [`crate::JsonEventSink`] failed, or the
+    /// [`crate::Notifier`] failed to deliver a notification, or the [`crate::Checkpointer`] failed to save a checkpoint,
+    /// or the [`crate::HistoryStore`] failed to store the record of an execution
+    SinkWriteFailed,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        InvalidUtf8, JsonEventSink, OutputLimitAction, ProcessData, ProcessEvent, ProcessRequest,
+        ProcessResult, WarningKind,
+    };
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    /// writer which always fails
+    struct FailingWriter;
+
+    impl io::Write for FailingWriter {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_warnings() {
+        let warnings = Arc::new(Mutex::new(vec![]));
+        let recorded = Arc::clone(&warnings);
+        let callback = move |event: &ProcessEvent, data: &ProcessData| -> ProcessResult {
+            if let ProcessEvent::Warning(kind) = event {
+                recorded.lock().unwrap().push((*kind, data.line_to_owned()));
+            }
+            ProcessResult::new()
+        };
+        let callback = Arc::new(callback);
+        ProcessRequest::start(ProcessRequest {
+            request_id: 278,
+            callback: Some(callback.clone()),
+            use_shell: true,
+            cmd_line: vec![vec![String::from("printf 'a\\377\\nlong line\\n'")]],
+            invalid_utf8: InvalidUtf8::Lossy,
+            max_output_bytes: Some(8),
+            output_limit_action: OutputLimitAction::Truncate,
+            ..Default::default()
+        });
+        assert_eq!(
+            *warnings.lock().unwrap(),
+            [
+                (WarningKind::InvalidUtf8Replaced, String::from("a\u{fffd}")),
+                (WarningKind::LineTruncated, String::from("lon")),
+            ]
+        );
+
+        warnings.lock().unwrap().clear();
+        ProcessRequest::start(ProcessRequest {
+            request_id: 279,
+            callback: Some(callback),
+            cmd_line: vec![vec![String::from("true")]],
+            json_events: Some(Arc::new(JsonEventSink::new(FailingWriter))),
+            ..Default::default()
+        });
+        let warnings = warnings.lock().unwrap();
+        assert!(!warnings.is_empty());
+        assert!(warnings
+            .iter()
+            .all(|(kind, _)| *kind == WarningKind::SinkWriteFailed));
+    }
+}
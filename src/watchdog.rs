@@ -0,0 +1,255 @@
+use crate::latch::Latch;
+use crate::termination::KillRecord;
+use crate::{
+    check_and_trigger_callback, CancellationToken, ProcessData, ProcessEvent, ProcessRequest,
+};
+use duct::ReaderHandle;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the stop latch of an execution is checked
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Liveness details of a running process, available with the [`ProcessEvent::Heartbeat`] event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Heartbeat {
+    /// Time elapsed since the process started
+    pub elapsed: Duration,
+    /// Time of the last output line, or of the start if there is no output yet
+    pub last_activity: SystemTime,
+}
+
+/// Time of the last output activity of a running process
+pub(crate) struct Activity {
+    started: Instant,
+    last_millis: AtomicU64,
+}
+
+impl Activity {
+    pub(crate) fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            last_millis: AtomicU64::new(0),
+        }
+    }
+
+    /// record the output activity now
+    pub(crate) fn touch(&self) {
+        self.last_millis
+            .store(self.started.elapsed().as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// time elapsed since the start
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// time elapsed since the last activity
+    pub(crate) fn idle_for(&self) -> Duration {
+        self.started.elapsed().saturating_sub(Duration::from_millis(
+            self.last_millis.load(Ordering::Relaxed),
+        ))
+    }
+}
+
+/// Pending blocking read of the output, to detect a stalled pipe
+pub(crate) struct ReadTracker {
+    /// number of the reads so far & start of the pending read, None if not reading
+    state: Mutex<(u64, Option<Instant>)>,
+}
+
+impl ReadTracker {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: Mutex::new((0, None)),
+        }
+    }
+
+    /// record the start of a read
+    pub(crate) fn begin(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.0 += 1;
+        state.1 = Some(Instant::now());
+    }
+
+    /// record the end of the pending read
+    pub(crate) fn end(&self) {
+        self.state.lock().unwrap().1 = None;
+    }
+
+    /// number of the pending read & how long it's blocked
+    fn blocked_for(&self) -> Option<(u64, Duration)> {
+        let state = self.state.lock().unwrap();
+        state.1.map(|started| (state.0, started.elapsed()))
+    }
+}
+
+/// kill the process and trigger the event with the reason
+fn kill_with_event(
+    request: &Arc<ProcessRequest>,
+    reader: &ReaderHandle,
+    kill_record: &KillRecord,
+    started: Instant,
+    event: &ProcessEvent,
+    reason: String,
+) {
+    let mut process_data = ProcessData::for_execution(request, reader, kill_record, started);
+    process_data.line = reason;
+    check_and_trigger_callback(request, event, &process_data);
+    kill_record.record(false);
+    _ = process_data.kill();
+}
+
+/// kill the process once the stop latch is set, till the execution is done
+pub(crate) fn watch_stop(
+    request: &Arc<ProcessRequest>,
+    reader: &ReaderHandle,
+    kill_record: &KillRecord,
+    started: Instant,
+    stop: &Latch,
+    done: &Latch,
+) {
+    while !done.wait_timeout(STOP_POLL_INTERVAL) {
+        if stop.is_set() {
+            let process_data = ProcessData::for_execution(request, reader, kill_record, started);
+            _ = process_data.kill();
+            break;
+        }
+    }
+}
+
+/// kill the process once the cancellation token is cancelled, till the execution is done
+pub(crate) fn watch_cancellation(
+    request: &Arc<ProcessRequest>,
+    reader: &ReaderHandle,
+    kill_record: &KillRecord,
+    started: Instant,
+    token: &CancellationToken,
+    done: &Latch,
+) {
+    while !done.wait_timeout(STOP_POLL_INTERVAL) {
+        if token.is_cancelled() {
+            let process_data = ProcessData::for_execution(request, reader, kill_record, started);
+            check_and_trigger_callback(request, &ProcessEvent::ExitRequested, &process_data);
+            kill_record.record(true);
+            _ = reader.kill();
+            break;
+        }
+    }
+}
+
+/// kill the process if it's still running after the timeout
+pub(crate) fn watch_timeout(
+    request: &Arc<ProcessRequest>,
+    reader: &ReaderHandle,
+    kill_record: &KillRecord,
+    started: Instant,
+    timeout: Duration,
+    done: &Latch,
+) {
+    if !done.wait_timeout(timeout) {
+        let reason = format!("Not completed within {} ms", timeout.as_millis());
+        kill_with_event(
+            request,
+            reader,
+            kill_record,
+            started,
+            &ProcessEvent::TimedOut,
+            reason,
+        );
+    }
+}
+
+/// kill the process if there is no output activity within the idle timeout, till the execution is done
+pub(crate) fn watch_idle(
+    request: &Arc<ProcessRequest>,
+    reader: &ReaderHandle,
+    kill_record: &KillRecord,
+    started: Instant,
+    idle_timeout: Duration,
+    activity: &Activity,
+    done: &Latch,
+) {
+    let mut wait = idle_timeout;
+    while !done.wait_timeout(wait) {
+        let idle_for = activity.idle_for();
+        if idle_for >= idle_timeout {
+            let reason = format!("No output for {} ms", idle_for.as_millis());
+            kill_with_event(
+                request,
+                reader,
+                kill_record,
+                started,
+                &ProcessEvent::IdleTimeout,
+                reason,
+            );
+            break;
+        }
+        wait = idle_timeout - idle_for;
+    }
+}
+
+/// emit the read stalled event once per read blocked longer than the read timeout & optionally kill the process,
+/// till the execution is done
+pub(crate) fn watch_reads(
+    request: &Arc<ProcessRequest>,
+    reader: &ReaderHandle,
+    kill_record: &KillRecord,
+    started: Instant,
+    read_timeout: Duration,
+    tracker: &ReadTracker,
+    done: &Latch,
+) {
+    let mut reported = None;
+    let mut wait = read_timeout;
+    while !done.wait_timeout(wait) {
+        wait = read_timeout;
+        let Some((read, blocked_for)) = tracker.blocked_for() else {
+            continue;
+        };
+        if blocked_for < read_timeout {
+            wait = read_timeout - blocked_for;
+            continue;
+        }
+        if reported == Some(read) {
+            continue;
+        }
+        reported = Some(read);
+        let reason = format!("Read blocked for {} ms", blocked_for.as_millis());
+        if request.kill_on_read_stall {
+            kill_with_event(
+                request,
+                reader,
+                kill_record,
+                started,
+                &ProcessEvent::ReadStalled,
+                reason,
+            );
+            break;
+        }
+        let mut process_data = ProcessData::for_execution(request, reader, kill_record, started);
+        process_data.line = reason;
+        check_and_trigger_callback(request, &ProcessEvent::ReadStalled, &process_data);
+    }
+}
+
+/// emit a heartbeat at the interval, till the execution is done
+pub(crate) fn run_heartbeat(
+    request: &Arc<ProcessRequest>,
+    reader: &ReaderHandle,
+    kill_record: &KillRecord,
+    started: Instant,
+    interval: Duration,
+    activity: &Activity,
+    done: &Latch,
+) {
+    let mut process_data = ProcessData::for_execution(request, reader, kill_record, started);
+    while !done.wait_timeout(interval) {
+        process_data.heartbeat = Some(Heartbeat {
+            elapsed: activity.elapsed(),
+            last_activity: SystemTime::now() - activity.idle_for(),
+        });
+        check_and_trigger_callback(request, &ProcessEvent::Heartbeat, &process_data);
+    }
+}
@@ -0,0 +1,303 @@
+use crate::json_events::{escape_json, event_to_json};
+use crate::{
+    check_and_trigger_callback, Backoff, ProcessData, ProcessEvent, Termination, WarningKind,
+};
+use std::io;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Lifecycle transition which triggers the [`WebhookNotifier`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WebhookTrigger {
+    /// Process exited with a success exit code, see [`crate::ProcessRequest::success_exit_codes`]
+    Exited,
+    /// Process exited with a failure exit code or was terminated by a signal which wasn't sent by this library
+    Crashed,
+    /// Process was killed by the [`crate::ProcessRequest::timeout`] or the [`crate::ProcessRequest::idle_timeout`]
+    TimedOut,
+    /// The output failed one of the [`crate::ProcessRequest::expectations`] or a read of the output stalled, see
+    /// [`crate::ProcessRequest::read_timeout`]
+    HealthCheckFailed,
+    /// Any other event
+    Event(ProcessEvent),
+}
+
+impl WebhookTrigger {
+    /// the trigger matches the event
+    fn matches(&self, event: &ProcessEvent, data: &ProcessData) -> bool {
+        let success = || {
+            data.request
+                .as_ref()
+                .is_some_and(|request| match data.termination {
+                    Some(Termination::Exited { code }) => request.is_success_exit_code(Some(code)),
+                    _ => false,
+                })
+        };
+        match self {
+            WebhookTrigger::Exited => *event == ProcessEvent::Exited && success(),
+            WebhookTrigger::Crashed => {
+                *event == ProcessEvent::Exited
+                    && matches!(
+                        data.termination,
+                        Some(Termination::Exited { .. } | Termination::Signaled { .. })
+                    )
+                    && !success()
+            }
+            WebhookTrigger::TimedOut => {
+                matches!(event, ProcessEvent::TimedOut | ProcessEvent::IdleTimeout)
+            }
+            WebhookTrigger::HealthCheckFailed => {
+                matches!(
+                    event,
+                    ProcessEvent::ExpectationFailed | ProcessEvent::ReadStalled
+                )
+            }
+            WebhookTrigger::Event(trigger_event) => trigger_event == event,
+        }
+    }
+}
+
+/// POSTs a JSON payload to the URLs on the selected lifecycle transitions of the process, see
+/// [`crate::ProcessRequest::webhook`]. The payload has the `trigger`, the `termination` & the `event` (as written by
+/// the [`crate::JsonEventSink`]). It uses the system `curl` CLI in a background thread, a failed delivery is retried
+/// with a backoff
+#[derive(Debug, Clone)]
+pub struct WebhookNotifier {
+    urls: Vec<String>,
+    triggers: Vec<WebhookTrigger>,
+    max_attempts: u32,
+    backoff: Backoff,
+    timeout: Duration,
+}
+
+impl WebhookNotifier {
+    /// Notify the URLs on the failures: [`WebhookTrigger::Crashed`], [`WebhookTrigger::TimedOut`] &
+    /// [`WebhookTrigger::HealthCheckFailed`]. A delivery is attempted 3 times by default
+    pub fn new<S: Into<String>>(urls: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            urls: urls.into_iter().map(Into::into).collect(),
+            triggers: vec![
+                WebhookTrigger::Crashed,
+                WebhookTrigger::TimedOut,
+                WebhookTrigger::HealthCheckFailed,
+            ],
+            max_attempts: 3,
+            backoff: Backoff::Exponential {
+                initial: Duration::from_secs(1),
+                max: Duration::from_secs(30),
+            },
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// Notify on these transitions instead
+    pub fn triggers(mut self, triggers: impl IntoIterator<Item = WebhookTrigger>) -> Self {
+        self.triggers = triggers.into_iter().collect();
+        self
+    }
+
+    /// Attempt a delivery at most `max_attempts` times (at least once) with the backoff between the attempts
+    pub fn retry(mut self, max_attempts: u32, backoff: Backoff) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self.backoff = backoff;
+        self
+    }
+
+    /// Timeout of a single delivery attempt
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// post the payload of the event to all the URLs if it matches a trigger, the process is not held meanwhile. A
+    /// failed delivery is reported as the [`WarningKind::SinkWriteFailed`] warning
+    pub(crate) fn notify(&self, event: &ProcessEvent, data: &ProcessData) {
+        let Some(trigger) = self
+            .triggers
+            .iter()
+            .find(|trigger| trigger.matches(event, data))
+        else {
+            return;
+        };
+        let termination = data
+            .termination
+            .map_or(String::from("null"), |termination| {
+                format!("\"{}\"", escape_json(&format!("{:?}", termination)))
+            });
+        let payload = format!(
+            "{{\"trigger\":\"{}\",\"termination\":{},\"event\":{}}}",
+            escape_json(&format!("{:?}", trigger)),
+            termination,
+            event_to_json(event, data)
+        );
+        let notifier = self.clone();
+        // the failure of the warning's own delivery isn't reported, so it can't trigger itself again
+        let request = data
+            .request
+            .clone()
+            .filter(|_| *event != ProcessEvent::Warning(WarningKind::SinkWriteFailed));
+        _ = thread::Builder::new()
+            .name(String::from("pes-webhook"))
+            .spawn(move || {
+                for url in &notifier.urls {
+                    let Err(error) = notifier.deliver(url, &payload) else {
+                        continue;
+                    };
+                    if let Some(request) = request.as_ref() {
+                        let mut process_data = ProcessData::new();
+                        process_data.request = Some(Arc::clone(request));
+                        process_data.line.push_str(&error.to_string());
+                        check_and_trigger_callback(
+                            request,
+                            &ProcessEvent::Warning(WarningKind::SinkWriteFailed),
+                            &process_data,
+                        );
+                    }
+                }
+            });
+    }
+
+    /// post the payload to the URL, retried as per the policy
+    fn deliver(&self, url: &str, payload: &str) -> io::Result<()> {
+        let mut attempt = 1;
+        loop {
+            let result = post(url, payload, self.timeout);
+            if result.is_ok() || attempt >= self.max_attempts {
+                return result;
+            }
+            thread::sleep(self.backoff.delay(attempt));
+            attempt += 1;
+        }
+    }
+}
+
+/// POST the JSON payload, a HTTP error status is a failure
+pub(crate) fn post(url: &str, payload: &str, timeout: Duration) -> io::Result<()> {
+    let output = duct::cmd!(
+        "curl",
+        "--silent",
+        "--show-error",
+        "--fail",
+        "--max-time",
+        format!("{:.3}", timeout.as_secs_f64()),
+        "--header",
+        "Content-Type: application/json",
+        "--data-binary",
+        "@-",
+        url
+    )
+    .stdin_bytes(payload)
+    .stdout_null()
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "Webhook delivery failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Backoff, ProcessData, ProcessEvent, ProcessRequest, ProcessResult, WarningKind,
+        WebhookNotifier, WebhookTrigger,
+    };
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{mpsc, Arc, Mutex};
+    use std::time::Duration;
+
+    /// read a request & its body, then reply with the status
+    fn serve(listener: &TcpListener, status: &str) -> String {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut content_length = 0;
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).unwrap();
+            if header.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            status
+        );
+        reader.get_mut().write_all(response.as_bytes()).unwrap();
+        String::from_utf8(body).unwrap()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_webhook() {
+        // the curl CLI is needed
+        if duct::cmd!("curl", "--version").stdout_null().run().is_err() {
+            return;
+        }
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let notifier = WebhookNotifier::new([url])
+            .triggers([WebhookTrigger::Crashed, WebhookTrigger::Exited])
+            .retry(2, Backoff::Fixed(Duration::from_millis(10)));
+        ProcessRequest::start(ProcessRequest {
+            request_id: 286,
+            cmd_line: vec![vec![String::from("false")]],
+            webhook: Some(Arc::new(notifier)),
+            ..Default::default()
+        });
+        // the failed delivery is retried
+        let first = serve(&listener, "500 Internal Server Error");
+        let second = serve(&listener, "200 OK");
+        assert_eq!(first, second);
+        assert!(
+            second.starts_with("{\"trigger\":\"Crashed\",\"termination\":\"Exited { code: 1 }\",")
+        );
+        assert!(second.contains("\"event\":{\"event\":\"Exited\",\"request_id\":286,"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_webhook_delivery_failure() {
+        // the curl CLI is needed
+        if duct::cmd!("curl", "--version").stdout_null().run().is_err() {
+            return;
+        }
+        // nothing listens on the port once the listener is dropped
+        let address = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let notifier = WebhookNotifier::new([format!("http://{}/hook", address)])
+            .triggers([WebhookTrigger::Crashed])
+            .retry(1, Backoff::Fixed(Duration::from_millis(10)));
+        let (sender, warnings) = mpsc::channel();
+        let sender = Mutex::new(sender);
+        ProcessRequest::start(ProcessRequest {
+            request_id: 516,
+            cmd_line: vec![vec![String::from("false")]],
+            webhook: Some(Arc::new(notifier)),
+            callback: Some(Arc::new(move |event: &ProcessEvent, data: &ProcessData| {
+                if *event == ProcessEvent::Warning(WarningKind::SinkWriteFailed) {
+                    _ = sender.lock().unwrap().send(data.line.clone());
+                }
+                ProcessResult::new()
+            })),
+            ..Default::default()
+        });
+        let warning = warnings.recv_timeout(Duration::from_secs(30)).unwrap();
+        assert!(!warning.is_empty());
+    }
+}
@@ -0,0 +1,182 @@
+use crate::{ProcessRequest, ShellKind};
+use std::ffi::OsString;
+use std::io;
+
+/// How to authenticate with the WinRM service, the credentials of the current user are used as entering a password
+/// is interactive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WinRmAuth {
+    /// Default of the PowerShell remoting, Kerberos in a domain
+    #[default]
+    Default,
+    /// Kerberos or NTLM as negotiated
+    Negotiate,
+    /// Kerberos only
+    Kerberos,
+    /// CredSSP, delegating the credentials to the remote host (e.g. for a second hop)
+    CredSsp,
+}
+
+impl WinRmAuth {
+    fn parameter(&self) -> &'static str {
+        match self {
+            WinRmAuth::Default => "Default",
+            WinRmAuth::Negotiate => "Negotiate",
+            WinRmAuth::Kerberos => "Kerberos",
+            WinRmAuth::CredSsp => "CredSSP",
+        }
+    }
+}
+
+/// Windows host to run the request on over WinRM using PowerShell remoting (`powershell` on Windows & `pwsh`
+/// elsewhere), see [`ProcessRequest::winrm`]. The command line (including the pipeline, env & working directory) runs
+/// in the remote PowerShell, a direct command is invoked with `&` & a shell command is used as is. The output of the
+/// remote command is streamed as the [`crate::ProcessEvent::IOData`] events & its exit code is the exit code of the
+/// request
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct WinRmTarget {
+    /// Host name or address
+    pub host: String,
+    /// WinRM port, for the default (5985 or 5986 with SSL) use None
+    pub port: Option<u16>,
+    /// Connect over HTTPS
+    pub use_ssl: bool,
+    /// Authentication method
+    pub auth: WinRmAuth,
+}
+
+/// remote PowerShell pipeline of the request, including the env & the working directory
+fn remote_script(request: &ProcessRequest) -> io::Result<String> {
+    let quote = |arg: &str| ShellKind::PowerShell.quote(arg);
+    let mut script = String::new();
+    let mut env: Vec<(&String, &String)> = request.env.iter().collect();
+    env.sort();
+    for (name, value) in env {
+        crate::env::check_name(name)?;
+        script.push_str(&format!("${{env:{}}} = {}; ", name, string_literal(value)));
+    }
+    if let Some(working_dir) = request.working_dir.as_ref() {
+        script.push_str(&format!(
+            "Set-Location -LiteralPath {}; ",
+            quote(&working_dir.to_string_lossy())
+        ));
+    }
+    let stages: Vec<String> = request
+        .pipeline_stages()
+        .iter()
+        .map(|stage| {
+            if stage.shell {
+                stage.argv.join(" ")
+            } else {
+                let args: Vec<String> = stage.argv.iter().map(|arg| quote(arg)).collect();
+                format!("& {}", args.join(" "))
+            }
+        })
+        .collect();
+    script.push_str(&stages.join(" | "));
+    Ok(script)
+}
+
+/// PowerShell string literal of the value, quoted even if it needs no quoting as an argument
+fn string_literal(value: &str) -> String {
+    let mut literal = String::from("'");
+    for character in value.chars() {
+        if matches!(character, '\'' | '\u{2018}' | '\u{2019}') {
+            literal.push(character);
+        }
+        literal.push(character);
+    }
+    literal.push('\'');
+    literal
+}
+
+/// argv of the local PowerShell to run the request on the remote target. The exit code of the remote command is
+/// fetched from the session after the command, as `Invoke-Command` doesn't pass it on
+pub(crate) fn winrm_argv(
+    target: &WinRmTarget,
+    request: &ProcessRequest,
+) -> io::Result<Vec<OsString>> {
+    let mut session = format!(
+        "New-PSSession -ComputerName {} -Authentication {}",
+        ShellKind::PowerShell.quote(&target.host),
+        target.auth.parameter()
+    );
+    if let Some(port) = target.port {
+        session.push_str(&format!(" -Port {}", port));
+    }
+    if target.use_ssl {
+        session.push_str(" -UseSSL");
+    }
+    let script = format!(
+        "$session = {} -ErrorAction Stop; try {{ Invoke-Command -Session $session -ScriptBlock {{ {} }} 2>&1; \
+         $code = Invoke-Command -Session $session -ScriptBlock {{ $LASTEXITCODE }} }} \
+         finally {{ Remove-PSSession $session }}; exit $code",
+        session,
+        remote_script(request)?
+    );
+    Ok(vec![
+        if cfg!(windows) { "powershell" } else { "pwsh" }.into(),
+        "-NoProfile".into(),
+        "-NonInteractive".into(),
+        "-Command".into(),
+        script.into(),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::winrm::winrm_argv;
+    use crate::{PipelineStage, ProcessRequest, WinRmAuth, WinRmTarget};
+    use std::io;
+
+    #[test]
+    pub fn test_winrm_argv() {
+        let request = ProcessRequest {
+            stages: vec![
+                PipelineStage {
+                    argv: vec![String::from("findstr"), String::from("it's here")],
+                    shell: false,
+                },
+                PipelineStage {
+                    argv: vec![String::from("Measure-Object -Line")],
+                    shell: true,
+                },
+            ],
+            env: [(String::from("LANG"), String::from("C"))].into(),
+            working_dir: Some("C:\\logs".into()),
+            ..Default::default()
+        };
+        let target = WinRmTarget {
+            host: String::from("win01.example.com"),
+            port: Some(5986),
+            use_ssl: true,
+            auth: WinRmAuth::Kerberos,
+        };
+        let argv = winrm_argv(&target, &request).unwrap();
+        assert_eq!(argv[1..4], ["-NoProfile", "-NonInteractive", "-Command"]);
+        assert_eq!(
+            argv[4],
+            "$session = New-PSSession -ComputerName win01.example.com -Authentication Kerberos -Port 5986 -UseSSL \
+             -ErrorAction Stop; try { Invoke-Command -Session $session -ScriptBlock { ${env:LANG} = 'C'; \
+             Set-Location -LiteralPath 'C:\\logs'; & findstr 'it''s here' | Measure-Object -Line } 2>&1; \
+             $code = Invoke-Command -Session $session -ScriptBlock { $LASTEXITCODE } } \
+             finally { Remove-PSSession $session }; exit $code"
+        );
+
+        let request = ProcessRequest {
+            env: [(
+                String::from("X} = 1; Remove-Item -Recurse ~; ${Y"),
+                String::from("1"),
+            )]
+            .into(),
+            ..request
+        };
+        assert_eq!(
+            winrm_argv(&target, &request).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+}
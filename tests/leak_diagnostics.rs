@@ -0,0 +1,29 @@
+//! Runs in its own test binary, as the leak check & the count of the active threads are global to the process
+#![cfg(unix)]
+
+use process_events_streaming::{active_threads, leaked_children, set_leak_check, ProcessRequest};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_leak_diagnostics() {
+    set_leak_check(true);
+    let result = ProcessRequest::start(ProcessRequest {
+        request_id: 307,
+        use_shell: true,
+        cmd_line: vec![vec![String::from("echo a; echo b >&2")]],
+        tag_output_streams: true,
+        heartbeat_interval: Some(Duration::from_millis(10)),
+        non_blocking_mode: true,
+        ..Default::default()
+    });
+    let result = result.join_handle.unwrap().unwrap().join().unwrap();
+    assert_eq!(result.exit_code, Some(0));
+    assert!(leaked_children().is_empty());
+    // the detached output readers end right after the output of the process
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while active_threads() > 0 && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(active_threads(), 0);
+}
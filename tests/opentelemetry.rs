@@ -0,0 +1,69 @@
+//! Runs in its own test binary, as the OpenTelemetry tracer provider is global
+#![cfg(feature = "opentelemetry")]
+
+use opentelemetry::global;
+use opentelemetry::trace::Status;
+use opentelemetry_sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+use opentelemetry_sdk::trace::TracerProvider;
+use process_events_streaming::ProcessRequest;
+use std::future::{self, Future};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+/// keeps the exported spans for the assertions
+#[derive(Debug, Clone, Default)]
+struct MemoryExporter(Arc<Mutex<Vec<SpanData>>>);
+
+impl SpanExporter for MemoryExporter {
+    fn export(
+        &mut self,
+        batch: Vec<SpanData>,
+    ) -> Pin<Box<dyn Future<Output = ExportResult> + Send + 'static>> {
+        self.0.lock().unwrap().extend(batch);
+        Box::pin(future::ready(Ok(())))
+    }
+}
+
+#[test]
+#[cfg(unix)]
+pub fn test_opentelemetry_spans() {
+    let exporter = MemoryExporter::default();
+    global::set_tracer_provider(
+        TracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build(),
+    );
+    ProcessRequest::start(ProcessRequest {
+        request_id: 411,
+        cmd_line: vec![
+            vec![String::from("printf"), String::from("a\\nbb\\n")],
+            vec![String::from("cat")],
+        ],
+        ..Default::default()
+    });
+    let spans = exporter.0.lock().unwrap();
+    let stages: Vec<&SpanData> = spans
+        .iter()
+        .filter(|span| span.name == "process.stage")
+        .collect();
+    let process = spans.iter().find(|span| span.name == "process").unwrap();
+    assert_eq!(stages.len(), 2);
+    assert!(stages
+        .iter()
+        .all(|stage| stage.parent_span_id == process.span_context.span_id()));
+    assert_eq!(process.status, Status::Ok);
+    let attribute = |key: &str| {
+        process
+            .attributes
+            .iter()
+            .find(|attribute| attribute.key.as_str() == key)
+            .map(|attribute| attribute.value.to_string())
+    };
+    assert_eq!(attribute("process.exit_code").as_deref(), Some("0"));
+    assert_eq!(attribute("process.output.lines").as_deref(), Some("2"));
+    assert_eq!(attribute("process.output.bytes").as_deref(), Some("5"));
+    assert_eq!(
+        attribute("process.command_line").as_deref(),
+        Some("printf a\\nbb\\n | cat")
+    );
+}
@@ -0,0 +1,35 @@
+//! Runs in its own test binary, as the shutdown terminates every execution of the process
+#![cfg(unix)]
+
+use process_events_streaming::{shutdown_all, ProcessRequest, Termination};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn start(request_id: u32, command: &str) -> process_events_streaming::ProcessResult {
+    ProcessRequest::start(ProcessRequest {
+        request_id,
+        use_shell: true,
+        cmd_line: vec![vec![String::from(command)]],
+        non_blocking_mode: true,
+        ..Default::default()
+    })
+}
+
+#[test]
+fn test_shutdown_all() {
+    let graceful = start(271, "exec sleep 10");
+    // ignores SIGTERM, so it's killed after the grace period
+    let stubborn = start(272, "trap '' TERM; exec sleep 10");
+    thread::sleep(Duration::from_millis(300));
+    let started = Instant::now();
+    shutdown_all(Duration::from_millis(500), Some(Duration::from_secs(5))).unwrap();
+    let elapsed = started.elapsed();
+    assert!(elapsed >= Duration::from_millis(500));
+    assert!(elapsed < Duration::from_secs(5));
+    for result in [graceful, stubborn] {
+        assert_eq!(
+            result.wait().unwrap().termination,
+            Some(Termination::Killed { by_request: true })
+        );
+    }
+}
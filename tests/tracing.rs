@@ -0,0 +1,64 @@
+//! Runs in its own test binary, as the tracing callsite interest is cached process wide
+#![cfg(feature = "tracing")]
+
+use process_events_streaming::ProcessRequest;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+/// counts the spans & the events emitted inside a span
+#[derive(Default)]
+struct CountingSubscriber {
+    next_id: AtomicU64,
+    spans: Mutex<Vec<String>>,
+    events_in_span: AtomicUsize,
+    entered: AtomicUsize,
+}
+
+impl Subscriber for &'static CountingSubscriber {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        self.spans
+            .lock()
+            .unwrap()
+            .push(span.metadata().name().to_string());
+        Id::from_u64(self.next_id.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event<'_>) {
+        if self.entered.load(Ordering::SeqCst) > 0 {
+            self.events_in_span.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn enter(&self, _span: &Id) {
+        self.entered.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn exit(&self, _span: &Id) {
+        self.entered.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+pub fn test_tracing_span_and_events() {
+    let subscriber: &'static CountingSubscriber = Box::leak(Box::default());
+    tracing::subscriber::with_default(subscriber, || {
+        ProcessRequest::start(ProcessRequest {
+            request_id: 391,
+            cmd_line: vec![vec![String::from("echo"), String::from("traced")]],
+            ..Default::default()
+        });
+    });
+    assert_eq!(*subscriber.spans.lock().unwrap(), vec!["process"]);
+    // Starting, Started, IOData, IOEof, Exited
+    assert!(subscriber.events_in_span.load(Ordering::SeqCst) >= 5);
+}